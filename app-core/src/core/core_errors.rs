@@ -1,9 +1,22 @@
+use crate::vault::vault_error::VaultError;
+
 #[derive(Debug)]
 pub enum CoreError {
     InternalError(String),
     VaultManagerError(String),
     CryptographyError(String),
-    PasswordGeneratorError(String)
+    PasswordGeneratorError(String),
+    UsernameGeneratorError(String),
+    KeyFileError(String),
+    PublicKeyChanged(String),
+    InvalidCredentials,
+    UserExists,
+    SessionExpired,
+    Conflict,
+    VaultRolledBack,
+    SavedOffline,
+    RateLimited(Option<u64>),
+    ServerUnavailable(String)
 }
 
 impl std::fmt::Display for CoreError {
@@ -13,11 +26,45 @@ impl std::fmt::Display for CoreError {
             CoreError::InternalError(message) => write!(formatter, "Internal error on core: {}", message),
             CoreError::VaultManagerError(message) => write!(formatter, "Error in the vault manager adapter: {}", message),
             CoreError::CryptographyError(message) => write!(formatter, "Error in the cryptography adapter: {}", message),
-            CoreError::PasswordGeneratorError(message) => write!(formatter, "Error in the password generator: {}", message)
+            CoreError::PasswordGeneratorError(message) => write!(formatter, "Error in the password generator: {}", message),
+            CoreError::UsernameGeneratorError(message) => write!(formatter, "Error in the username generator: {}", message),
+            CoreError::KeyFileError(message) => write!(formatter, "Error with the key file: {}", message),
+            CoreError::PublicKeyChanged(username) => {
+                write!(formatter, "The public key of {} changed, compare the fingerprints before trusting it.", username)
+            }
+            CoreError::InvalidCredentials => write!(formatter, "Wrong username or password."),
+            CoreError::UserExists => write!(formatter, "This username is already taken."),
+            CoreError::SessionExpired => write!(formatter, "The session expired, log in again."),
+            CoreError::Conflict => write!(formatter, "The vault was changed on another device, merge its changes before saving."),
+            CoreError::VaultRolledBack => {
+                write!(formatter, "The server sent an older version of the vault than the one last seen on this device.")
+            }
+            CoreError::SavedOffline => {
+                write!(formatter, "The server can't be reached, the changes are kept on this device until the next save.")
+            }
+            CoreError::RateLimited(Some(seconds)) => write!(formatter, "Too many attempts, retry in {} seconds.", seconds),
+            CoreError::RateLimited(None) => write!(formatter, "Too many attempts, retry later."),
+            CoreError::ServerUnavailable(message) => write!(formatter, "The server is unavailable: {}", message),
         }
     }
 }
 
 impl std::error::Error for CoreError {}
 
+/// Keeps the errors the user can act on, the others only carry their message
+impl From<VaultError> for CoreError {
+    fn from(error: VaultError) -> Self {
+        match error {
+            VaultError::InvalidCredentials => CoreError::InvalidCredentials,
+            VaultError::UserExists => CoreError::UserExists,
+            VaultError::SessionExpired => CoreError::SessionExpired,
+            VaultError::Conflict => CoreError::Conflict,
+            VaultError::SavedOffline => CoreError::SavedOffline,
+            VaultError::RateLimited(retry_after) => CoreError::RateLimited(retry_after),
+            VaultError::ServerUnavailable(message) => CoreError::ServerUnavailable(message),
+            error => CoreError::VaultManagerError(error.to_string()),
+        }
+    }
+}
+
 pub type Result<T> = std::result::Result<T, CoreError>;
\ No newline at end of file
@@ -1,70 +1,168 @@
-use std::marker::PhantomData;
+use std::{cell::RefCell, collections::HashMap, marker::PhantomData};
 
 use cli_clipboard::{ClipboardContext, ClipboardProvider};
+use zeroize::Zeroizing;
 
 use crate::{
     core::core_errors::*,
-    password::password_restriction::PasswordRestriction,
+    cryptography::{
+        recovery_key::RecoveryKey,
+        secret_share::SecretShare,
+        vault_manifest::{MANIFEST_RECORD, VaultManifest, record_aad},
+    },
+    password::{derivation_profile::DerivationProfile, password_restriction::PasswordRestriction},
     ports::{
         cryptography::{Cryptography, NoKeyCipher},
+        key_management::KeyManagement,
         password_generator::PasswordGenerator,
+        username_generator::UsernameGenerator,
         vault_manager::VaultManager,
     },
-    uncrypted_vault::{Entry, UncryptedVault},
+    uncrypted_vault::{DerivedEntry, Entry, EntryConflict, MergeSide, UncryptedRecord, UncryptedVault},
+    username::{email_alias::EmailAliasKind, username_restriction::UsernameRestriction},
+    vault::{
+        crypted_record::CryptedRecord,
+        crypted_vault::CryptedVault,
+        share::{KeyStatus, ReceivedShare, RecipientKey, SharedEntry},
+        vault_error::VaultError,
+    },
 };
 
-pub trait Core<VM: VaultManager, PG: PasswordGenerator, NKC: NoKeyCipher> {
-    type LoggedType: LoggedCore<VM, PG, NKC::Crypto>;
+const EMPTY_NEW_PASSWORD: &str = "The new password cannot be empty.";
+const WRONG_KEY_FILE: &str = "the key file doesn't match this vault.";
+const MAYBE_MISSING_KEY_FILE: &str = "The vault may need its key file.";
+const SHARING_NOT_ENABLED: &str = "Sharing isn't enabled for this vault.";
+const NO_REMOTE_VAULT: &str = "There is no vault from another device to merge.";
+const MISSING_RECORD: &str = "the server is missing a record of the vault";
+
+pub trait Core<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, NKC: NoKeyCipher> {
+    type LoggedType: LoggedCore<VM, PG, UG, NKC::Crypto>;
 
     fn create_account(self, username: &str, password: &str) -> Result<Self::LoggedType>;
     fn logging_in(self, username: &str, password: &str) -> Result<Self::LoggedType>;
+    fn recover_account(self, username: &str, recovery_key: &str, new_password: &str) -> Result<Self::LoggedType>;
+    fn combine_recovery_shares(&self, shares: &[String]) -> Result<RecoveryKey>;
+    /// Mixed into the encryption key when the account is created, logged in or recovered
+    fn set_key_file(&mut self, key_file: Option<Vec<u8>>);
+    /// Server key pinned for this vault, the login and the recovery fail if the server proves another one
+    fn set_server_key(&mut self, server_key: Option<Vec<u8>>);
+    /// The vault already logged in with its identifiers bound on this device, the default ones are refused
+    fn set_identifiers_bound(&mut self, identifiers_bound: bool);
+    /// Generation of the vault last seen on this device, the login fails if the server hands out an older one
+    fn set_min_generation(&mut self, generation: u64);
 }
 
-pub trait LoggedCore<VM: VaultManager, PG: PasswordGenerator, C: Cryptography> {
+pub trait LoggedCore<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, C: Cryptography> {
     fn get_entries(&self) -> &[Entry];
     fn add_entry(&mut self, title: String, username: String, password: String);
     fn update_entry(&mut self, entry: Entry);
+    fn get_derived_entries(&self) -> &[DerivedEntry];
+    fn add_derived_entry(&mut self, profile: DerivationProfile);
+    fn update_derived_entry(&mut self, entry: DerivedEntry);
+    fn derive_password(&self, profile: &DerivationProfile) -> Result<Vec<u8>>;
     fn generate_password(&self, restrictions: &PasswordRestriction) -> Result<Vec<u8>>;
-    fn save_vault(&self) -> Result<()>;
+    fn password_entropy(&self, restrictions: &PasswordRestriction) -> f64;
+    fn get_password_restriction(&self) -> Option<&PasswordRestriction>;
+    /// Key the server proved at the login, to pin for the next ones
+    fn get_server_key(&self) -> &[u8];
+    /// The login used the identifiers bound to the account, to refuse the default ones at the next logins
+    fn get_identifiers_bound(&self) -> bool;
+    /// Generation of the vault last read from or saved to the server, to refuse an older one at the next login
+    fn get_generation(&self) -> u64;
+    /// Failure of the upload or the upgrade run after the login, the vault is open anyway
+    fn take_pending_error(&mut self) -> Option<CoreError>;
+    fn set_password_restriction(&mut self, restrictions: PasswordRestriction);
+    fn generate_username(&self, restrictions: &UsernameRestriction) -> Result<String>;
+    fn generate_email_alias(&self, base_address: &str, alias_kind: &EmailAliasKind) -> Result<String>;
+    /// Refused with `CoreError::Conflict` if another device saved first, its vault is then kept to be merged
+    fn save_vault(&mut self) -> Result<()>;
+    /// Merges the vault kept at the last conflict into this one, the next save uploads the result
+    ///
+    /// Fields changed differently on both devices keep the local value until resolved.
+    fn merge_remote_vault(&mut self) -> Result<Vec<EntryConflict>>;
+    fn resolve_conflict(&mut self, conflict: &EntryConflict, side: MergeSide);
+    /// Logs in again after the session expired, the vault stays open with its unsaved changes
+    fn reauthenticate(&mut self, username: &str, password: &str) -> Result<()>;
+    fn logout(&mut self) -> Result<()>;
+    fn change_password(&mut self, username: &str, old_password: &str, new_password: &str) -> Result<()>;
+    fn create_recovery_key(&self) -> Result<RecoveryKey>;
+    fn split_recovery_key(&self, recovery_key: &RecoveryKey, threshold: u8, share_count: u8) -> Result<Vec<SecretShare>>;
+    fn generate_key_file(&self) -> Vec<u8>;
+    /// Wraps the data key again with or without a key file, the new key file must be saved before
+    fn set_key_file(&mut self, key_file: Option<Vec<u8>>) -> Result<()>;
+    /// Creates the sharing key pair on first use and publishes its public key
+    fn enable_sharing(&mut self) -> Result<()>;
+    /// Fingerprint of the own sharing public key, `None` until sharing is enabled
+    fn get_sharing_fingerprint(&self) -> Option<String>;
+    /// Asks the server for the key of the user and compares it with the pinned one
+    fn lookup_public_key(&self, username: &str) -> Result<RecipientKey>;
+    /// Trusts the key from now on, only once its fingerprint was compared with the recipient
+    fn pin_public_key(&mut self, recipient_key: &RecipientKey) -> Result<()>;
+    /// Pins the key of a new recipient, refuses a key that changed since it was pinned
+    fn share_entry(&mut self, entry: &Entry, recipient: &str) -> Result<()>;
+    /// Shares that can't be opened are left out, they stay in the inbox of the server
+    fn get_received_shares(&self) -> Result<Vec<ReceivedShare>>;
+    /// Removes the share from the inbox, then adds the entry to the vault
+    fn accept_share(&mut self, share: &ReceivedShare) -> Result<()>;
+    fn decline_share(&self, id: u64) -> Result<()>;
     fn copy_to_clipboard(&self, content: String) -> Result<()>;
 }
 
 #[derive(Debug, Default)]
-pub struct CoreService<VM: VaultManager, PG: PasswordGenerator, NKC: NoKeyCipher> {
+pub struct CoreService<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, NKC: NoKeyCipher> {
     vault_manager: VM,
+    key_file: Option<Vec<u8>>,
+    min_generation: u64,
     _phantom_pg: PhantomData<PG>,
+    _phantom_ug: PhantomData<UG>,
     _phantom_nkc: PhantomData<NKC>,
 }
 
-impl<VM: VaultManager, PG: PasswordGenerator, NKC: NoKeyCipher> CoreService<VM, PG, NKC> {
-    pub fn new(vault_manager: VM) -> CoreService<VM, PG, NKC> {
+impl<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, NKC: NoKeyCipher> CoreService<VM, PG, UG, NKC> {
+    pub fn new(vault_manager: VM) -> CoreService<VM, PG, UG, NKC> {
         Self {
             vault_manager,
+            key_file: None,
+            min_generation: 0,
             _phantom_pg: std::marker::PhantomData,
+            _phantom_ug: std::marker::PhantomData,
             _phantom_nkc: std::marker::PhantomData,
         }
     }
 }
 
-impl<VM: VaultManager, PG: PasswordGenerator, NKC: NoKeyCipher> Core<VM, PG, NKC>
-    for CoreService<VM, PG, NKC>
+impl<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, NKC: NoKeyCipher> Core<VM, PG, UG, NKC>
+    for CoreService<VM, PG, UG, NKC>
 {
-    type LoggedType = LoggedCoreService<VM, PG, NKC::Crypto>;
+    type LoggedType = LoggedCoreService<VM, PG, UG, NKC>;
 
     fn create_account(mut self, username: &str, password: &str) -> Result<Self::LoggedType> {
         let crypted_vault = self
             .vault_manager
             .create(username, password)
-            .map_err(|error| CoreError::VaultManagerError(error.to_string()))?;
+            .map_err(CoreError::from)?;
 
-        let cryptography = NKC::create_cipher_from_key(crypted_vault.encryption_key.as_bytes())
-            .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+        let encryption_key = crypted_vault.encryption_key.as_bytes();
+        let wrapping_key = wrapping_key::<NKC>(encryption_key, self.key_file.as_deref())?;
+
+        let (cryptography, data_key) = create_data_key::<VM, NKC>(&self.vault_manager, &wrapping_key)?;
 
         Ok(LoggedCoreService {
             vault_manager: self.vault_manager,
             _phantom: std::marker::PhantomData,
+            _phantom_ug: std::marker::PhantomData,
+            _phantom_nkc: std::marker::PhantomData,
             cryptography,
             vault: UncryptedVault::new(),
+            derivation_key: derivation_key::<NKC>(&data_key)?,
+            data_key,
+            encryption_key: Zeroizing::new(encryption_key.to_vec()),
+            server_key: crypted_vault.server_key,
+            key_file: self.key_file,
+            saved_records: SavedRecords::default(),
+            revision: crypted_vault.revision,
+            remote_vault: None,
+            pending_error: None,
         })
     }
 
@@ -72,37 +170,364 @@ impl<VM: VaultManager, PG: PasswordGenerator, NKC: NoKeyCipher> Core<VM, PG, NKC
         let crypted_vault = self
             .vault_manager
             .retrieve(username, password)
-            .map_err(|error| CoreError::VaultManagerError(error.to_string()))?;
+            .map_err(CoreError::from)?;
 
-        let cryptography = NKC::create_cipher_from_key(crypted_vault.encryption_key.as_bytes())
-            .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+        let key_file = self.key_file.as_deref();
 
-        let uncrypted_vault = match &crypted_vault.content.is_empty() {
-            true => UncryptedVault::new(),
-            false => cryptography
-                .decrypt(&crypted_vault.content)
-                .map_err(|error| CoreError::CryptographyError(error.to_string()))?,
+        let (cryptography, data_key, uncrypted_vault, saved_records) = match crypted_vault.wrapped_key.is_empty() {
+            true => migrate_to_data_key::<VM, NKC>(&self.vault_manager, &crypted_vault, key_file)?,
+            false => open_with_data_key::<VM, NKC>(&crypted_vault, key_file)?,
         };
 
-        Ok(LoggedCoreService {
+        // The manifest is replaced as a whole, only the generation kept on this device shows it is an older one
+        if saved_records.manifest.get_generation() < self.min_generation {
+            return Err(CoreError::VaultRolledBack);
+        }
+
+        let mut logged_core_service = LoggedCoreService {
             vault_manager: self.vault_manager,
             _phantom: std::marker::PhantomData,
+            _phantom_ug: std::marker::PhantomData,
+            _phantom_nkc: std::marker::PhantomData,
             cryptography,
             vault: uncrypted_vault,
+            derivation_key: derivation_key::<NKC>(&data_key)?,
+            data_key,
+            encryption_key: Zeroizing::new(crypted_vault.encryption_key.as_bytes().to_vec()),
+            server_key: crypted_vault.server_key,
+            key_file: self.key_file,
+            saved_records,
+            revision: crypted_vault.revision.clone(),
+            remote_vault: None,
+            pending_error: None,
+        };
+
+        // Vaults saved as one blob are split into records once, the blob is emptied after the records are uploaded
+        if crypted_vault.records.is_empty() && !crypted_vault.content.is_empty() {
+            logged_core_service.save_vault()?;
+
+            logged_core_service.revision = logged_core_service
+                .vault_manager
+                .save(vec![], logged_core_service.revision.as_deref())
+                .map_err(CoreError::from)?;
+        }
+
+        // Changes saved offline are uploaded once the server is reachable, a failed upload is retried by the next save
+        if !crypted_vault.pending_records.is_empty() {
+            logged_core_service.pending_error = logged_core_service.save_vault().err();
+        }
+
+        // Registering the same password again upgrades the key stretching, a failed upgrade is retried on the next login
+        if logged_core_service.vault_manager.needs_reregistration()
+            && let Err(error) = logged_core_service.change_password(username, password, password)
+        {
+            logged_core_service.pending_error.get_or_insert(error);
+        }
+
+        Ok(logged_core_service)
+    }
+
+    /// Registers the new password with the data key unwrapped by the recovery key, then logs in with it
+    fn recover_account(mut self, username: &str, recovery_key: &str, new_password: &str) -> Result<Self::LoggedType> {
+        if new_password.is_empty() {
+            return Err(CoreError::InternalError(EMPTY_NEW_PASSWORD.to_string()));
+        }
+
+        let recovery_key =
+            RecoveryKey::parse(recovery_key).map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+        let recovery_token = NKC::Keys::recovery_token(recovery_key.as_bytes())
+            .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+        let key_file = self.key_file.as_deref();
+
+        let rewrap_data_key = |recovery_wrapped_key: &[u8], encryption_key: &[u8]| {
+            let data_key = NKC::unwrap_data_key(recovery_key.as_bytes(), recovery_wrapped_key)
+                .map(Zeroizing::new)
+                .map_err(|error| VaultError::Internal(error.to_string()))?;
+
+            let wrapping_key = wrapping_key::<NKC>(encryption_key, key_file)
+                .map_err(|error| VaultError::Internal(error.to_string()))?;
+
+            NKC::wrap_data_key(&wrapping_key, &data_key).map_err(|error| VaultError::Internal(error.to_string()))
+        };
+
+        self.vault_manager
+            .recover(username, &recovery_token, new_password, &rewrap_data_key)
+            .map_err(CoreError::from)?;
+
+        self.logging_in(username, new_password)
+    }
+
+    /// Shares only rebuild the recovery key, the recovery itself stays the same
+    fn combine_recovery_shares(&self, shares: &[String]) -> Result<RecoveryKey> {
+        let recovery_key =
+            NKC::Keys::combine_shares(shares).map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+        Ok(RecoveryKey::new(recovery_key))
+    }
+
+    fn set_key_file(&mut self, key_file: Option<Vec<u8>>) {
+        self.key_file = key_file;
+    }
+
+    fn set_server_key(&mut self, server_key: Option<Vec<u8>>) {
+        self.vault_manager.pin_server_key(server_key);
+    }
+
+    fn set_identifiers_bound(&mut self, identifiers_bound: bool) {
+        self.vault_manager.require_bound_identifiers(identifiers_bound);
+    }
+
+    fn set_min_generation(&mut self, generation: u64) {
+        self.min_generation = generation;
+    }
+}
+
+/// Key the data key is wrapped with, the encryption key itself when the vault has no key file
+fn wrapping_key<NKC: NoKeyCipher>(encryption_key: &[u8], key_file: Option<&[u8]>) -> Result<Zeroizing<Vec<u8>>> {
+    match key_file {
+        Some(key_file) => NKC::Keys::mix_key_file(encryption_key, key_file)
+            .map(Zeroizing::new)
+            .map_err(|error| CoreError::KeyFileError(error.to_string())),
+        None => Ok(Zeroizing::new(encryption_key.to_vec())),
+    }
+}
+
+/// Derived passwords come from the data key, changing or recovering the master password keeps them
+fn derivation_key<NKC: NoKeyCipher>(data_key: &[u8]) -> Result<Zeroizing<Vec<u8>>> {
+    NKC::Keys::derivation_key(data_key)
+        .map(Zeroizing::new)
+        .map_err(|error| CoreError::CryptographyError(error.to_string()))
+}
+
+/// Generates the data key of the vault and uploads it wrapped with the encryption key
+fn create_data_key<VM: VaultManager, NKC: NoKeyCipher>(vault_manager: &VM, encryption_key: &[u8]) -> Result<(NKC::Crypto, Zeroizing<Vec<u8>>)> {
+    let data_key = Zeroizing::new(NKC::generate_data_key());
+
+    let wrapped_key = NKC::wrap_data_key(encryption_key, &data_key)
+        .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+    vault_manager
+        .save_wrapped_key(wrapped_key)
+        .map_err(CoreError::from)?;
+
+    let cryptography = NKC::create_cipher_from_key(&data_key)
+        .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+    Ok((cryptography, data_key))
+}
+
+fn open_with_data_key<VM: VaultManager, NKC: NoKeyCipher>(
+    crypted_vault: &CryptedVault<VM::KeySize>,
+    key_file: Option<&[u8]>,
+) -> Result<OpenedVault<NKC::Crypto>> {
+    let encryption_key = crypted_vault.encryption_key.as_bytes();
+    let wrapping_key = wrapping_key::<NKC>(encryption_key, key_file)?;
+
+    // The password was already checked by the login, a failure here comes from the key file
+    let data_key = NKC::unwrap_data_key(&wrapping_key, &crypted_vault.wrapped_key).map(Zeroizing::new).map_err(|error| match key_file {
+        Some(_) => CoreError::KeyFileError(WRONG_KEY_FILE.to_string()),
+        None => CoreError::CryptographyError(format!("{error} {MAYBE_MISSING_KEY_FILE}")),
+    })?;
+
+    let cryptography = NKC::create_cipher_from_key(&data_key)
+        .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+    if !crypted_vault.records.is_empty() || !crypted_vault.pending_records.is_empty() {
+        let (uncrypted_vault, saved_records) =
+            open_synced_records(&cryptography, &crypted_vault.records, &crypted_vault.pending_records)?;
+
+        return Ok((cryptography, data_key, uncrypted_vault, saved_records));
+    }
+
+    if crypted_vault.content.is_empty() {
+        return Ok((cryptography, data_key, UncryptedVault::new(), SavedRecords::default()));
+    }
+
+    let uncrypted_vault = match cryptography.decrypt(&crypted_vault.content) {
+        Ok(uncrypted_vault) => uncrypted_vault,
+        // The wrapped key is uploaded first, a migration interrupted before the vault was saved leaves it on the old key
+        Err(error) => NKC::create_cipher_from_key(encryption_key)
+            .and_then(|legacy_cryptography| legacy_cryptography.decrypt(&crypted_vault.content))
+            .map_err(|_| CoreError::CryptographyError(error.to_string()))?,
+    };
+
+    Ok((cryptography, data_key, uncrypted_vault, SavedRecords::default()))
+}
+
+/// Every record is decrypted on its own, with the version the manifest gives it
+fn open_records<C: Cryptography>(cryptography: &C, records: &[CryptedRecord]) -> Result<(UncryptedVault, SavedRecords)> {
+    let find_content = |id: &str| {
+        records
+            .iter()
+            .find(|record| record.id == id)
+            .map(|record| record.content.as_slice())
+            .ok_or_else(|| CoreError::CryptographyError(format!("{MISSING_RECORD}: {id}.")))
+    };
+
+    let manifest = cryptography
+        .decrypt_record(find_content(MANIFEST_RECORD)?, &record_aad(MANIFEST_RECORD, 0))
+        .and_then(|manifest| VaultManifest::decode(&manifest))
+        .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+    let uncrypted_records = manifest
+        .get_records()
+        .iter()
+        .map(|record_version| {
+            let aad = record_aad(&record_version.id, record_version.version);
+
+            let data = cryptography
+                .decrypt_record(find_content(&record_version.id)?, &aad)
+                .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+            Ok(UncryptedRecord::new(record_version.id.clone(), data))
         })
+        .collect::<Result<Vec<_>>>()?;
+
+    let uncrypted_vault = UncryptedVault::from_records(&uncrypted_records)
+        .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+    Ok((uncrypted_vault, SavedRecords::new(manifest, uncrypted_records)))
+}
+
+/// Records saved offline are opened on top of the synced ones, which stay the base of the next save
+fn open_synced_records<C: Cryptography>(
+    cryptography: &C,
+    records: &[CryptedRecord],
+    pending_records: &[CryptedRecord],
+) -> Result<(UncryptedVault, SavedRecords)> {
+    let (uncrypted_vault, saved_records) = match records.is_empty() {
+        true => (UncryptedVault::new(), SavedRecords::default()),
+        false => open_records(cryptography, records)?,
+    };
+
+    if pending_records.is_empty() {
+        return Ok((uncrypted_vault, saved_records));
     }
+
+    let mut local_records: Vec<CryptedRecord> = records
+        .iter()
+        .filter(|record| !pending_records.iter().any(|pending_record| pending_record.id == record.id))
+        .cloned()
+        .collect();
+    local_records.extend(pending_records.iter().filter(|record| !record.is_deleted()).cloned());
+
+    let (local_vault, _) = open_records(cryptography, &local_records)?;
+
+    Ok((local_vault, saved_records))
 }
 
+/// Vaults created before envelope encryption are encrypted with the encryption key itself,
+/// they are saved again with the data key when split into records
+fn migrate_to_data_key<VM: VaultManager, NKC: NoKeyCipher>(
+    vault_manager: &VM,
+    crypted_vault: &CryptedVault<VM::KeySize>,
+    key_file: Option<&[u8]>,
+) -> Result<OpenedVault<NKC::Crypto>> {
+    let encryption_key = crypted_vault.encryption_key.as_bytes();
+
+    let uncrypted_vault = match crypted_vault.content.is_empty() {
+        true => UncryptedVault::new(),
+        false => NKC::create_cipher_from_key(encryption_key)
+            .and_then(|legacy_cryptography| legacy_cryptography.decrypt(&crypted_vault.content))
+            .map_err(|error| CoreError::CryptographyError(error.to_string()))?,
+    };
+
+    let wrapping_key = wrapping_key::<NKC>(encryption_key, key_file)?;
+    let (cryptography, data_key) = create_data_key::<VM, NKC>(vault_manager, &wrapping_key)?;
+
+    Ok((cryptography, data_key, uncrypted_vault, SavedRecords::default()))
+}
+
+/// Cipher, data key, content and records of a vault just opened
+type OpenedVault<C> = (C, Zeroizing<Vec<u8>>, UncryptedVault, SavedRecords);
+
+/// Records as last uploaded, a save only encrypts and uploads the ones that changed since
 #[derive(Debug, Default)]
-pub struct LoggedCoreService<VM: VaultManager, PG: PasswordGenerator, C: Cryptography> {
+struct SavedRecords {
+    manifest: VaultManifest,
+    contents: HashMap<String, Vec<u8>>,
+}
+
+impl SavedRecords {
+    fn new(manifest: VaultManifest, records: Vec<UncryptedRecord>) -> Self {
+        Self {
+            manifest,
+            contents: records.into_iter().map(|record| (record.id, record.data)).collect(),
+        }
+    }
+
+    /// Vault as last saved, its records in the order of the manifest
+    fn open(&self) -> Result<UncryptedVault> {
+        let records: Vec<UncryptedRecord> = self
+            .manifest
+            .get_records()
+            .iter()
+            .filter_map(|record_version| {
+                let data = self.contents.get(&record_version.id)?;
+
+                Some(UncryptedRecord::new(record_version.id.clone(), data.clone()))
+            })
+            .collect();
+
+        UncryptedVault::from_records(&records).map_err(|error| CoreError::CryptographyError(error.to_string()))
+    }
+}
+
+/// Vault found on the server after a conflict, decrypted but not merged yet
+#[derive(Debug)]
+struct RemoteVault {
+    saved_records: SavedRecords,
+    revision: Option<String>,
+}
+
+/// Refuses a vault older than the one it conflicts with, another device only saves on top of it
+fn fetch_remote_vault<VM: VaultManager, C: Cryptography>(
+    vault_manager: &VM,
+    cryptography: &C,
+    min_generation: u64,
+) -> Result<RemoteVault> {
+    let (records, revision) = vault_manager.fetch_records().map_err(CoreError::from)?;
+
+    // Only the blob of an older version is left, nothing saved as records to merge
+    let saved_records = match records.is_empty() {
+        true => SavedRecords::default(),
+        false => open_records(cryptography, &records)?.1,
+    };
+
+    if saved_records.manifest.get_generation() < min_generation {
+        return Err(CoreError::VaultRolledBack);
+    }
+
+    Ok(RemoteVault { saved_records, revision })
+}
+
+#[derive(Debug)]
+pub struct LoggedCoreService<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, NKC: NoKeyCipher> {
     vault_manager: VM,
     _phantom: std::marker::PhantomData<PG>,
-    cryptography: C,
+    _phantom_ug: std::marker::PhantomData<UG>,
+    _phantom_nkc: std::marker::PhantomData<NKC>,
+    cryptography: NKC::Crypto,
     vault: UncryptedVault,
+    // Kept in memory for derived entries, whose passwords are recomputed on demand, cleared when the session is dropped
+    derivation_key: Zeroizing<Vec<u8>>,
+    data_key: Zeroizing<Vec<u8>>,
+    // OPAQUE export key, kept to wrap the data key again when the key file changes
+    encryption_key: Zeroizing<Vec<u8>>,
+    server_key: Vec<u8>,
+    key_file: Option<Vec<u8>>,
+    saved_records: SavedRecords,
+    // Revision of the vault on the server the next save is based on
+    revision: Option<String>,
+    remote_vault: Option<RemoteVault>,
+    // Left by the login, the vault opened anyway
+    pending_error: Option<CoreError>,
 }
 
-impl<VM: VaultManager, PG: PasswordGenerator, C: Cryptography> LoggedCore<VM, PG, C>
-    for LoggedCoreService<VM, PG, C>
+impl<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, NKC: NoKeyCipher> LoggedCore<VM, PG, UG, NKC::Crypto>
+    for LoggedCoreService<VM, PG, UG, NKC>
 {
     fn get_entries(&self) -> &[Entry] {
         self.vault.get_entries()
@@ -116,20 +541,349 @@ impl<VM: VaultManager, PG: PasswordGenerator, C: Cryptography> LoggedCore<VM, PG
         self.vault.update_entry(entry);
     }
 
+    fn get_derived_entries(&self) -> &[DerivedEntry] {
+        self.vault.get_derived_entries()
+    }
+
+    fn add_derived_entry(&mut self, profile: DerivationProfile) {
+        self.vault.add_derived_entry(profile);
+    }
+
+    fn update_derived_entry(&mut self, entry: DerivedEntry) {
+        self.vault.update_derived_entry(entry);
+    }
+
+    fn derive_password(&self, profile: &DerivationProfile) -> Result<Vec<u8>> {
+        PG::derive_password(&self.derivation_key, profile)
+            .map_err(|error| CoreError::PasswordGeneratorError(error.to_string()))
+    }
+
     fn generate_password(&self, restrictions: &PasswordRestriction) -> Result<Vec<u8>> {
         PG::generate_password(restrictions)
             .map_err(|error| CoreError::PasswordGeneratorError(error.to_string()))
     }
 
-    fn save_vault(&self) -> Result<()> {
-        let crypted_vault = self
-            .cryptography
-            .encrypt(&self.vault)
+    fn password_entropy(&self, restrictions: &PasswordRestriction) -> f64 {
+        PG::entropy(restrictions)
+    }
+
+    fn get_password_restriction(&self) -> Option<&PasswordRestriction> {
+        self.vault.get_password_restriction()
+    }
+
+    fn get_server_key(&self) -> &[u8] {
+        &self.server_key
+    }
+
+    fn get_identifiers_bound(&self) -> bool {
+        self.vault_manager.identifiers_bound()
+    }
+
+    fn take_pending_error(&mut self) -> Option<CoreError> {
+        self.pending_error.take()
+    }
+
+    fn get_generation(&self) -> u64 {
+        self.saved_records.manifest.get_generation()
+    }
+
+    fn set_password_restriction(&mut self, restrictions: PasswordRestriction) {
+        self.vault.set_password_restriction(restrictions);
+    }
+
+    fn generate_username(&self, restrictions: &UsernameRestriction) -> Result<String> {
+        UG::generate_username(restrictions)
+            .map_err(|error| CoreError::UsernameGeneratorError(error.to_string()))
+    }
+
+    fn generate_email_alias(&self, base_address: &str, alias_kind: &EmailAliasKind) -> Result<String> {
+        UG::generate_email_alias(base_address, alias_kind)
+            .map_err(|error| CoreError::UsernameGeneratorError(error.to_string()))
+    }
+
+    /// Only the records changed since the last save are encrypted again, they are uploaded with the new manifest
+    fn save_vault(&mut self) -> Result<()> {
+        let records = self
+            .vault
+            .records()
+            .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+        let saved_records = &self.saved_records;
+        let generation = saved_records.manifest.get_generation() + 1;
+
+        let mut manifest = VaultManifest::new(generation);
+        let mut changed_records = vec![];
+
+        for record in &records {
+            let unchanged_version = saved_records
+                .manifest
+                .get_version(&record.id)
+                .filter(|_| saved_records.contents.get(&record.id) == Some(&record.data));
+
+            let version = match unchanged_version {
+                Some(version) => version,
+                None => {
+                    let content = self
+                        .cryptography
+                        .encrypt_record(&record.data, &record_aad(&record.id, generation))
+                        .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+                    changed_records.push(CryptedRecord::new(record.id.clone(), content));
+
+                    generation
+                }
+            };
+
+            manifest.add_record(record.id.clone(), version);
+        }
+
+        for id in saved_records.contents.keys() {
+            if manifest.get_version(id).is_none() {
+                changed_records.push(CryptedRecord::deleted(id.clone()));
+            }
+        }
+
+        if changed_records.is_empty() && manifest.get_records() == saved_records.manifest.get_records() {
+            return Ok(());
+        }
+
+        let crypted_manifest = manifest
+            .as_bytes()
+            .and_then(|data| self.cryptography.encrypt_record(&data, &record_aad(MANIFEST_RECORD, 0)))
+            .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+        changed_records.push(CryptedRecord::new(MANIFEST_RECORD.to_string(), crypted_manifest));
+
+        // Another device saved since, its vault is kept aside to be merged instead of being overwritten
+        self.revision = match self.vault_manager.save_records(changed_records, self.revision.as_deref()) {
+            Ok(revision) => revision,
+            Err(VaultError::Conflict) => {
+                let min_generation = self.saved_records.manifest.get_generation();
+                self.remote_vault = Some(fetch_remote_vault(&self.vault_manager, &self.cryptography, min_generation)?);
+
+                return Err(CoreError::Conflict);
+            }
+            Err(error) => return Err(CoreError::from(error)),
+        };
+
+        self.saved_records = SavedRecords::new(manifest, records);
+
+        Ok(())
+    }
+
+    /// The remote records become the base of the next save, which uploads the merged vault on top of them
+    fn merge_remote_vault(&mut self) -> Result<Vec<EntryConflict>> {
+        let Some(remote_vault) = self.remote_vault.take() else {
+            return Err(CoreError::InternalError(NO_REMOTE_VAULT.to_string()));
+        };
+
+        // Both devices changed the vault from the one last saved here
+        let base = self.saved_records.open()?;
+        let remote = remote_vault.saved_records.open()?;
+
+        let (merged_vault, conflicts) = UncryptedVault::merge(&base, &self.vault, &remote);
+
+        self.vault = merged_vault;
+        self.saved_records = remote_vault.saved_records;
+        self.revision = remote_vault.revision;
+
+        Ok(conflicts)
+    }
+
+    fn resolve_conflict(&mut self, conflict: &EntryConflict, side: MergeSide) {
+        self.vault.resolve_conflict(conflict, side);
+    }
+
+    fn reauthenticate(&mut self, username: &str, password: &str) -> Result<()> {
+        self.vault_manager
+            .reauthenticate(username, password)
+            .map_err(CoreError::from)
+    }
+
+    fn logout(&mut self) -> Result<()> {
+        self.vault_manager.logout().map_err(CoreError::from)
+    }
+
+    /// The vault isn't re-encrypted, only its data key is wrapped again for the new password
+    fn change_password(&mut self, username: &str, old_password: &str, new_password: &str) -> Result<()> {
+        if new_password.is_empty() {
+            return Err(CoreError::InternalError(EMPTY_NEW_PASSWORD.to_string()));
+        }
+
+        let (key_file, data_key) = (self.key_file.as_deref(), &self.data_key);
+        // The vault manager only hands the new encryption key to the closure
+        let new_encryption_key = RefCell::new(Zeroizing::new(Vec::new()));
+
+        let wrap_data_key = |encryption_key: &[u8]| {
+            new_encryption_key.replace(Zeroizing::new(encryption_key.to_vec()));
+
+            wrapping_key::<NKC>(encryption_key, key_file)
+                .and_then(|wrapping_key| {
+                    NKC::wrap_data_key(&wrapping_key, data_key).map_err(|error| CoreError::CryptographyError(error.to_string()))
+                })
+                .map_err(|error| VaultError::Internal(error.to_string()))
+        };
+
+        self.vault_manager
+            .change_password(username, old_password, new_password, &wrap_data_key)
+            .map_err(CoreError::from)?;
+
+        self.encryption_key = new_encryption_key.into_inner();
+
+        Ok(())
+    }
+
+    /// Shown to the user once, only its token and the data key it wraps are uploaded
+    fn create_recovery_key(&self) -> Result<RecoveryKey> {
+        let recovery_key = RecoveryKey::new(NKC::Keys::generate_recovery_key());
+
+        let wrapped_key = NKC::wrap_data_key(recovery_key.as_bytes(), &self.data_key)
+            .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+        let recovery_token = NKC::Keys::recovery_token(recovery_key.as_bytes())
+            .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+        self.vault_manager
+            .save_recovery_key(recovery_token, wrapped_key)
+            .map_err(CoreError::from)?;
+
+        Ok(recovery_key)
+    }
+
+    fn split_recovery_key(&self, recovery_key: &RecoveryKey, threshold: u8, share_count: u8) -> Result<Vec<SecretShare>> {
+        NKC::Keys::split_secret(recovery_key.as_bytes(), threshold, share_count)
+            .map_err(|error| CoreError::CryptographyError(error.to_string()))
+    }
+
+    fn generate_key_file(&self) -> Vec<u8> {
+        NKC::Keys::generate_key_file()
+    }
+
+    fn set_key_file(&mut self, key_file: Option<Vec<u8>>) -> Result<()> {
+        let wrapping_key = wrapping_key::<NKC>(&self.encryption_key, key_file.as_deref())?;
+
+        let wrapped_key = NKC::wrap_data_key(&wrapping_key, &self.data_key)
             .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
 
         self.vault_manager
-            .save(crypted_vault)
-            .map_err(|error| CoreError::VaultManagerError(error.to_string()))
+            .save_wrapped_key(wrapped_key)
+            .map_err(CoreError::from)?;
+
+        self.key_file = key_file;
+
+        Ok(())
+    }
+
+    fn enable_sharing(&mut self) -> Result<()> {
+        if self.vault.get_sharing_key_pair().is_none() {
+            self.vault.set_sharing_key_pair(NKC::Keys::generate_sharing_key_pair());
+            self.save_vault()?;
+        }
+
+        let Some(sharing_key_pair) = self.vault.get_sharing_key_pair() else {
+            return Err(CoreError::InternalError(SHARING_NOT_ENABLED.to_string()));
+        };
+
+        // Published again on every call, the server may have lost it
+        self.vault_manager
+            .save_public_key(sharing_key_pair.public_key.clone())
+            .map_err(CoreError::from)
+    }
+
+    fn get_sharing_fingerprint(&self) -> Option<String> {
+        self.vault
+            .get_sharing_key_pair()
+            .map(|sharing_key_pair| NKC::Keys::fingerprint(&sharing_key_pair.public_key))
+    }
+
+    fn lookup_public_key(&self, username: &str) -> Result<RecipientKey> {
+        let public_key = self
+            .vault_manager
+            .get_public_key(username)
+            .map_err(CoreError::from)?;
+
+        let status = match self.vault.get_pinned_key(username) {
+            None => KeyStatus::New,
+            Some(pinned_key) if pinned_key.public_key == public_key => KeyStatus::Pinned,
+            Some(pinned_key) => KeyStatus::Changed {
+                pinned_fingerprint: NKC::Keys::fingerprint(&pinned_key.public_key),
+            },
+        };
+
+        Ok(RecipientKey {
+            username: username.to_string(),
+            fingerprint: NKC::Keys::fingerprint(&public_key),
+            public_key,
+            status,
+        })
+    }
+
+    fn pin_public_key(&mut self, recipient_key: &RecipientKey) -> Result<()> {
+        self.vault
+            .pin_key(recipient_key.username.clone(), recipient_key.public_key.clone());
+
+        self.save_vault()
+    }
+
+    fn share_entry(&mut self, entry: &Entry, recipient: &str) -> Result<()> {
+        let recipient_key = self.lookup_public_key(recipient)?;
+
+        match recipient_key.status {
+            KeyStatus::New => self.pin_public_key(&recipient_key)?,
+            KeyStatus::Pinned => {}
+            KeyStatus::Changed { .. } => return Err(CoreError::PublicKeyChanged(recipient.to_string())),
+        }
+
+        let shared_entry = SharedEntry::from(entry)
+            .as_bytes()
+            .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+        let sealed_entry = NKC::Keys::seal(&recipient_key.public_key, &shared_entry)
+            .map_err(|error| CoreError::CryptographyError(error.to_string()))?;
+
+        self.vault_manager
+            .send_share(recipient, sealed_entry)
+            .map_err(CoreError::from)
+    }
+
+    fn get_received_shares(&self) -> Result<Vec<ReceivedShare>> {
+        let Some(sharing_key_pair) = self.vault.get_sharing_key_pair() else {
+            return Err(CoreError::InternalError(SHARING_NOT_ENABLED.to_string()));
+        };
+
+        let sealed_shares = self
+            .vault_manager
+            .get_shares()
+            .map_err(CoreError::from)?;
+
+        Ok(sealed_shares
+            .into_iter()
+            .filter_map(|sealed_share| {
+                let shared_entry = NKC::Keys::open_sealed(&sharing_key_pair.private_key, &sealed_share.sealed_entry).ok()?;
+
+                Some(ReceivedShare {
+                    id: sealed_share.id,
+                    sender: sealed_share.sender,
+                    entry: SharedEntry::decode(&shared_entry).ok()?,
+                })
+            })
+            .collect())
+    }
+
+    /// The share is removed first, accepting it again after a failed save can't add the entry twice
+    fn accept_share(&mut self, share: &ReceivedShare) -> Result<()> {
+        self.decline_share(share.id)?;
+
+        let entry = share.entry.clone();
+        self.vault.add_entry(entry.title, entry.username, entry.password);
+
+        self.save_vault()
+    }
+
+    fn decline_share(&self, id: u64) -> Result<()> {
+        self.vault_manager
+            .delete_share(id)
+            .map_err(CoreError::from)
     }
 
     fn copy_to_clipboard(&self, content: String) -> Result<()> {
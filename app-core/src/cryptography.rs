@@ -1,2 +1,5 @@
 pub mod cryptography_error;
-pub mod uncrypted_vault;
\ No newline at end of file
+pub mod recovery_key;
+pub mod secret_share;
+pub mod uncrypted_vault;
+pub mod vault_manifest;
@@ -5,7 +5,11 @@ pub enum CryptographyError {
     BinaryEncodingError(String),
     BinaryDecodingError(String),
     EncryptionError(String),
-    DecryptionError(String)
+    DecryptionError(String),
+    HeaderError(String),
+    SecretSharingError(String),
+    KeyFileError(String),
+    SharingError(String)
 }
 
 impl std::fmt::Display for CryptographyError {
@@ -17,7 +21,11 @@ impl std::fmt::Display for CryptographyError {
             CryptographyError::BinaryEncodingError(error) => write!(formatter, "Error couldn't encode struct to binary: {}", error),
             CryptographyError::BinaryDecodingError(error) => write!(formatter, "Error couldn't decode struct to binary: {}", error),
             CryptographyError::EncryptionError(error) => write!(formatter, "Error couldn't encrypt data: {}", error),
-            CryptographyError::DecryptionError(error) => write!(formatter, "Error couldn't decrypt data: {}", error)
+            CryptographyError::DecryptionError(error) => write!(formatter, "Error couldn't decrypt data: {}", error),
+            CryptographyError::HeaderError(error) => write!(formatter, "Error invalid vault header: {}", error),
+            CryptographyError::SecretSharingError(error) => write!(formatter, "Error couldn't split or combine the secret: {}", error),
+            CryptographyError::KeyFileError(error) => write!(formatter, "Error with the key file: {}", error),
+            CryptographyError::SharingError(error) => write!(formatter, "Error couldn't seal or open the share: {}", error)
         }
     }
 }
@@ -0,0 +1,90 @@
+use crate::cryptography::cryptography_error::{CryptographyError, Result};
+
+const BASE32_ALPHABET: &[u8; 32] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ234567";
+const GROUP_LENGTH: usize = 4;
+const INVALID_RECOVERY_KEY: &str = "invalid recovery key.";
+
+/// Recovery key as printed in the emergency kit, base32 in groups of four (`ABCD-EFGH-...`)
+#[derive(Clone, PartialEq)]
+pub struct RecoveryKey {
+    bytes: Vec<u8>,
+}
+
+impl RecoveryKey {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        Self { bytes }
+    }
+
+    /// Case, spaces and dashes are ignored, the key is usually typed back from paper
+    pub fn parse(code: &str) -> Result<Self> {
+        let mut bytes = Vec::new();
+        let mut buffer: u16 = 0;
+        let mut bits = 0;
+
+        for character in code.chars().filter(|character| *character != '-' && !character.is_whitespace()) {
+            let Some(value) = BASE32_ALPHABET
+                .iter()
+                .position(|letter| *letter as char == character.to_ascii_uppercase())
+            else {
+                return Err(CryptographyError::BinaryDecodingError(INVALID_RECOVERY_KEY.to_string()));
+            };
+
+            buffer = (buffer << 5) | value as u16;
+            bits += 5;
+
+            if bits >= 8 {
+                bits -= 8;
+                bytes.push((buffer >> bits) as u8);
+                buffer &= (1 << bits) - 1;
+            }
+        }
+
+        // Leftover bits are padding, a missing or extra character usually leaves some set
+        if bytes.is_empty() || buffer != 0 {
+            return Err(CryptographyError::BinaryDecodingError(INVALID_RECOVERY_KEY.to_string()));
+        }
+
+        Ok(Self { bytes })
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.bytes
+    }
+}
+
+impl std::fmt::Display for RecoveryKey {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut characters = Vec::new();
+        let mut buffer: u16 = 0;
+        let mut bits = 0;
+
+        for byte in &self.bytes {
+            buffer = (buffer << 8) | *byte as u16;
+            bits += 8;
+
+            while bits >= 5 {
+                bits -= 5;
+                characters.push(BASE32_ALPHABET[((buffer >> bits) & 0x1f) as usize] as char);
+            }
+
+            buffer &= (1 << bits) - 1;
+        }
+
+        if bits > 0 {
+            characters.push(BASE32_ALPHABET[((buffer << (5 - bits)) & 0x1f) as usize] as char);
+        }
+
+        let groups: Vec<String> = characters
+            .chunks(GROUP_LENGTH)
+            .map(|group| group.iter().collect())
+            .collect();
+
+        formatter.write_str(&groups.join("-"))
+    }
+}
+
+impl std::fmt::Debug for RecoveryKey {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("RecoveryKey { .. }")
+    }
+}
@@ -0,0 +1,13 @@
+/// One printable share of a secret, both encodings carry the same bytes and checksum
+#[derive(Clone, PartialEq)]
+pub struct SecretShare {
+    pub index: u8,
+    pub mnemonic: String,
+    pub hex: String,
+}
+
+impl std::fmt::Debug for SecretShare {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "SecretShare {{ index: {}, .. }}", self.index)
+    }
+}
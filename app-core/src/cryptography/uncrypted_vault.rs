@@ -1,71 +1,539 @@
 use bincode::{Decode, Encode, config};
+use uuid::Uuid;
 
-use crate::cryptography::cryptography_error::{CryptographyError, Result};
+use crate::{
+    cryptography::cryptography_error::{CryptographyError, Result},
+    password::{derivation_profile::DerivationProfile, password_restriction::PasswordRestriction},
+};
 
-#[derive(Encode, Decode, PartialEq, Debug, Default)]
+// Record ids, a saved vault is one record per entry plus one for everything else
+const SETTINGS_RECORD: &str = "settings";
+const ENTRY_RECORD_PREFIX: &str = "entry/";
+const DERIVED_ENTRY_RECORD_PREFIX: &str = "derived/";
+
+const UNKNOWN_RECORD: &str = "unknown record";
+
+// Namespace of the UUIDs given to the entries saved before the UUIDs, built from their old id
+const LEGACY_NAMESPACE: Uuid = Uuid::from_u128(0x6f1c2a4e_8b3d_4f5a_9c7e_2d1b0a3f4e5c);
+
+// Bincode never starts a varint with 255, a vault encoded before the entry UUIDs can't start with it
+const VAULT_FORMAT_MARKER: u8 = 255;
+
+#[derive(Encode, PartialEq, Debug, Default)]
 pub struct UncryptedVault {
     entries: Vec<Entry>,
+    password_restriction: Option<PasswordRestriction>,
+    derived_entries: Vec<DerivedEntry>,
+    sharing_key_pair: Option<SharingKeyPair>,
+    pinned_keys: Vec<PinnedKey>,
 }
 
 impl UncryptedVault {
     pub fn new() -> Self {
-        Self { entries: vec![] }
+        Self {
+            entries: vec![],
+            password_restriction: None,
+            derived_entries: vec![],
+            sharing_key_pair: None,
+            pinned_keys: vec![],
+        }
     }
 
     pub fn get_entries(&self) -> &[Entry] {
         &self.entries
     }
 
-    pub fn add_entry(&mut self, title: String, username: String, password: String) {
-        let next_id = self
-            .entries
-            .iter()
-            .max_by(|first_entry, second_entry| first_entry.id.cmp(&second_entry.id))
-            .map(|entry| entry.id);
+    pub fn get_derived_entries(&self) -> &[DerivedEntry] {
+        &self.derived_entries
+    }
 
-        let id = match next_id {
-            Some(value) => value + 1,
-            None => 0,
-        };
+    pub fn get_password_restriction(&self) -> Option<&PasswordRestriction> {
+        self.password_restriction.as_ref()
+    }
 
+    pub fn set_password_restriction(&mut self, password_restriction: PasswordRestriction) {
+        self.password_restriction = Some(password_restriction);
+    }
+
+    pub fn get_sharing_key_pair(&self) -> Option<&SharingKeyPair> {
+        self.sharing_key_pair.as_ref()
+    }
+
+    pub fn set_sharing_key_pair(&mut self, sharing_key_pair: SharingKeyPair) {
+        self.sharing_key_pair = Some(sharing_key_pair);
+    }
+
+    pub fn get_pinned_key(&self, username: &str) -> Option<&PinnedKey> {
+        self.pinned_keys.iter().find(|pinned_key| pinned_key.username == username)
+    }
+
+    /// Replaces the key pinned for the user, if any
+    pub fn pin_key(&mut self, username: String, public_key: Vec<u8>) {
+        self.pinned_keys.retain(|pinned_key| pinned_key.username != username);
+        self.pinned_keys.push(PinnedKey { username, public_key });
+    }
+
+    pub fn add_entry(&mut self, title: String, username: String, password: String) {
         let entry = Entry {
-            id,
+            id: Uuid::new_v4().to_string(),
             title,
             username,
             password,
+            clock: 0,
         };
 
         self.entries.push(entry);
     }
 
-    pub fn update_entry(&mut self, entry: Entry) {
-        self.entries.pop_if(|e| e.id == entry.id);
-        self.entries.push(entry);
+    /// The entry keeps its place in the vault, its clock moves on
+    pub fn update_entry(&mut self, mut entry: Entry) {
+        match self.entries.iter_mut().find(|e| e.id == entry.id) {
+            Some(existing) => {
+                entry.clock = existing.clock + 1;
+                *existing = entry;
+            }
+            None => self.entries.push(entry),
+        }
+    }
+
+    pub fn add_derived_entry(&mut self, profile: DerivationProfile) {
+        let id = self
+            .derived_entries
+            .iter()
+            .map(|entry| entry.id + 1)
+            .max()
+            .unwrap_or_default();
+
+        self.derived_entries.push(DerivedEntry { id, profile });
+    }
+
+    pub fn update_derived_entry(&mut self, entry: DerivedEntry) {
+        if let Some(existing) = self.derived_entries.iter_mut().find(|e| e.id == entry.id) {
+            *existing = entry;
+        }
+    }
+
+    /// Three-way merge of two versions of the vault, against the version both were changed from
+    ///
+    /// An entry changed on one side only takes that change, an entry changed on both sides is merged field by field.
+    /// A field changed differently on both sides keeps the local value and is returned as a conflict.
+    /// Deleting an entry only wins over the side that didn't change it.
+    pub fn merge(base: &Self, local: &Self, remote: &Self) -> (Self, Vec<EntryConflict>) {
+        let mut conflicts = vec![];
+
+        let entries = merge_entries(
+            &base.entries,
+            &local.entries,
+            &remote.entries,
+            |entry| &entry.id,
+            |base, local, remote| merge_entry(base, local, remote, &mut conflicts),
+        );
+
+        // Added on both sides under the same id, the remote one is kept under the next free id
+        let mut added_twice = vec![];
+
+        let mut derived_entries = merge_entries(
+            &base.derived_entries,
+            &local.derived_entries,
+            &remote.derived_entries,
+            |entry| &entry.id,
+            |base, local, remote| {
+                if base.is_none() {
+                    added_twice.push(remote.clone());
+                }
+
+                local.clone()
+            },
+        );
+
+        for mut entry in added_twice {
+            entry.id = derived_entries.iter().map(|entry| entry.id + 1).max().unwrap_or_default();
+            derived_entries.push(entry);
+        }
+
+        // Keys are only pinned or replaced, never removed
+        let mut pinned_keys = local.pinned_keys.clone();
+
+        for pinned_key in &remote.pinned_keys {
+            if local.get_pinned_key(&pinned_key.username).is_none() {
+                pinned_keys.push(pinned_key.clone());
+            }
+        }
+
+        let password_restriction =
+            merge_value(&base.password_restriction, &local.password_restriction, &remote.password_restriction);
+
+        let merged_vault = Self {
+            entries,
+            password_restriction,
+            derived_entries,
+            sharing_key_pair: merge_value(&base.sharing_key_pair, &local.sharing_key_pair, &remote.sharing_key_pair),
+            pinned_keys,
+        };
+
+        (merged_vault, conflicts)
+    }
+
+    /// The merged entry holds the local value until the remote one is chosen
+    pub fn resolve_conflict(&mut self, conflict: &EntryConflict, side: MergeSide) {
+        let MergeSide::Remote = side else {
+            return;
+        };
+
+        if let Some(entry) = self.entries.iter_mut().find(|entry| entry.id == conflict.entry_id) {
+            *conflict.field.value_mut(entry) = conflict.remote.clone();
+        }
     }
 
     pub fn as_bytes(&self) -> Result<Vec<u8>> {
-        bincode::encode_to_vec(self, config::standard())
-            .map_err(|error| CryptographyError::BinaryEncodingError(error.to_string()))
+        Ok([vec![VAULT_FORMAT_MARKER], encode(self)?].concat())
+    }
+
+    /// Each record can be encrypted on its own, the entries keep the order of the vault
+    pub fn records(&self) -> Result<Vec<UncryptedRecord>> {
+        let settings = [
+            encode(&self.password_restriction)?,
+            encode(&self.sharing_key_pair)?,
+            encode(&self.pinned_keys)?,
+        ]
+        .concat();
+
+        let mut records = vec![UncryptedRecord::new(SETTINGS_RECORD.to_string(), settings)];
+
+        for entry in &self.entries {
+            records.push(UncryptedRecord::new(format!("{ENTRY_RECORD_PREFIX}{}", entry.id), encode(entry)?));
+        }
+
+        for entry in &self.derived_entries {
+            records.push(UncryptedRecord::new(format!("{DERIVED_ENTRY_RECORD_PREFIX}{}", entry.id), encode(entry)?));
+        }
+
+        Ok(records)
+    }
+
+    pub fn from_records(records: &[UncryptedRecord]) -> Result<Self> {
+        let mut vault = Self::new();
+
+        for record in records {
+            let mut data = record.data.as_slice();
+
+            match record.id.as_str() {
+                SETTINGS_RECORD => {
+                    vault.password_restriction = decode_next(&mut data)?;
+                    vault.sharing_key_pair = decode_next(&mut data)?;
+                    vault.pinned_keys = decode_next(&mut data)?;
+                }
+                id if id.starts_with(ENTRY_RECORD_PREFIX) => vault.entries.push(decode_entry(id, &mut data)?),
+                id if id.starts_with(DERIVED_ENTRY_RECORD_PREFIX) => vault.derived_entries.push(decode_next(&mut data)?),
+                id => return Err(CryptographyError::BinaryDecodingError(format!("{UNKNOWN_RECORD}: {id}."))),
+            }
+        }
+
+        Ok(vault)
     }
 
     pub fn decode(data: &[u8]) -> Result<Self> {
-        let (uncrypted_vault, _) = bincode::decode_from_slice(data, config::standard())
-            .map_err(|error| CryptographyError::BinaryDecodingError(error.to_string()))?;
+        if let Some((&VAULT_FORMAT_MARKER, mut data)) = data.split_first() {
+            return Ok(Self {
+                entries: decode_next(&mut data)?,
+                password_restriction: decode_next(&mut data)?,
+                derived_entries: decode_next(&mut data)?,
+                sharing_key_pair: decode_next(&mut data)?,
+                pinned_keys: decode_next(&mut data)?,
+            });
+        }
+
+        let mut data = data;
 
-        Ok(uncrypted_vault)
+        let entries = decode_next::<Vec<LegacyEntry>>(&mut data)?
+            .into_iter()
+            .map(Entry::from)
+            .collect();
+
+        // Older vaults stop after the entries (no generator settings), the settings (no derived entries)
+        // the derived entries (no sharing key pair) or the sharing key pair (no pinned keys)
+        let password_restriction = match data.is_empty() {
+            true => None,
+            false => decode_next(&mut data)?,
+        };
+
+        let derived_entries = match data.is_empty() {
+            true => vec![],
+            false => decode_next(&mut data)?,
+        };
+
+        let sharing_key_pair = match data.is_empty() {
+            true => None,
+            false => decode_next(&mut data)?,
+        };
+
+        let pinned_keys = match data.is_empty() {
+            true => vec![],
+            false => decode_next(&mut data)?,
+        };
+
+        Ok(Self {
+            entries,
+            password_restriction,
+            derived_entries,
+            sharing_key_pair,
+            pinned_keys,
+        })
     }
 }
 
 #[derive(Encode, Decode, PartialEq, Debug, Default, Clone)]
 pub struct Entry {
-    id: usize,
+    id: String,
     pub title: String,
     pub username: String,
     pub password: String,
+    // Incremented by every update, a merge without a base tells from it which side changed the entry
+    clock: u64,
 }
 
 impl Entry {
+    pub fn get_id(&self) -> &str {
+        &self.id
+    }
+
     pub fn save_password(&mut self, password: &str) {
         self.password = password.to_string();
     }
 }
+
+/// Field of an entry changed differently on both sides of a merge
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EntryField {
+    Title,
+    Username,
+    Password,
+}
+
+impl EntryField {
+    const ALL: [EntryField; 3] = [EntryField::Title, EntryField::Username, EntryField::Password];
+
+    fn value(self, entry: &Entry) -> &String {
+        match self {
+            EntryField::Title => &entry.title,
+            EntryField::Username => &entry.username,
+            EntryField::Password => &entry.password,
+        }
+    }
+
+    fn value_mut(self, entry: &mut Entry) -> &mut String {
+        match self {
+            EntryField::Title => &mut entry.title,
+            EntryField::Username => &mut entry.username,
+            EntryField::Password => &mut entry.password,
+        }
+    }
+}
+
+impl std::fmt::Display for EntryField {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            EntryField::Title => write!(formatter, "Title"),
+            EntryField::Username => write!(formatter, "Username"),
+            EntryField::Password => write!(formatter, "Password"),
+        }
+    }
+}
+
+/// Left by `UncryptedVault::merge`, resolved with `UncryptedVault::resolve_conflict`
+#[derive(Debug, Clone, PartialEq)]
+pub struct EntryConflict {
+    pub entry_id: String,
+    pub title: String,
+    pub field: EntryField,
+    pub local: String,
+    pub remote: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeSide {
+    Local,
+    Remote,
+}
+
+/// Entry of a vault saved before the entries had UUIDs
+#[derive(Decode)]
+struct LegacyEntry {
+    id: usize,
+    title: String,
+    username: String,
+    password: String,
+}
+
+/// The UUID is built from the old id, two devices migrating the same vault give an entry the same one
+impl From<LegacyEntry> for Entry {
+    fn from(legacy_entry: LegacyEntry) -> Self {
+        Self {
+            id: Uuid::new_v5(&LEGACY_NAMESPACE, &(legacy_entry.id as u64).to_be_bytes()).to_string(),
+            title: legacy_entry.title,
+            username: legacy_entry.username,
+            password: legacy_entry.password,
+            clock: 0,
+        }
+    }
+}
+
+/// Entry whose password is never stored, it is recomputed from the master secret and its profile
+#[derive(Encode, Decode, PartialEq, Debug, Default, Clone)]
+pub struct DerivedEntry {
+    id: usize,
+    pub profile: DerivationProfile,
+}
+
+/// Decrypted record of the vault, see `UncryptedVault::records`
+#[derive(PartialEq, Debug, Clone)]
+pub struct UncryptedRecord {
+    pub id: String,
+    pub data: Vec<u8>,
+}
+
+impl UncryptedRecord {
+    pub fn new(id: String, data: Vec<u8>) -> Self {
+        Self { id, data }
+    }
+}
+
+fn encode<T: Encode>(value: &T) -> Result<Vec<u8>> {
+    bincode::encode_to_vec(value, config::standard())
+        .map_err(|error| CryptographyError::BinaryEncodingError(error.to_string()))
+}
+
+/// Entries of the local side in their order, then the ones only found on the remote side
+///
+/// `merge_both` is only called for an entry changed on both sides, the base is `None` if both added it.
+fn merge_entries<T: Clone + PartialEq, I: PartialEq + ?Sized>(
+    base: &[T],
+    local: &[T],
+    remote: &[T],
+    id: impl Fn(&T) -> &I,
+    mut merge_both: impl FnMut(Option<&T>, &T, &T) -> T,
+) -> Vec<T> {
+    let find = |entries: &'_ [T], entry: &T| entries.iter().find(|other| id(other) == id(entry)).cloned();
+
+    let mut merged = vec![];
+
+    for local_entry in local {
+        let base_entry = find(base, local_entry);
+        let changed_locally = base_entry.as_ref() != Some(local_entry);
+
+        match find(remote, local_entry) {
+            Some(remote_entry) if !changed_locally => merged.push(remote_entry),
+            Some(remote_entry) if base_entry.as_ref() == Some(&remote_entry) || remote_entry == *local_entry => {
+                merged.push(local_entry.clone())
+            }
+            Some(remote_entry) => merged.push(merge_both(base_entry.as_ref(), local_entry, &remote_entry)),
+            // Deleted remotely and unchanged here
+            None if base_entry.is_some() && !changed_locally => {}
+            None => merged.push(local_entry.clone()),
+        }
+    }
+
+    for remote_entry in remote {
+        if find(local, remote_entry).is_some() {
+            continue;
+        }
+
+        // Deleted here and unchanged remotely
+        if find(base, remote_entry).as_ref() == Some(remote_entry) {
+            continue;
+        }
+
+        merged.push(remote_entry.clone());
+    }
+
+    merged
+}
+
+/// The clock moves past both sides, the merged entry counts as changed against either of them
+///
+/// Without a base, a side still at clock 0 was never updated since the entry was created, it stands for the base.
+/// Two sides both updated since are concurrent edits, every field that differs is a conflict.
+fn merge_entry(base: Option<&Entry>, local: &Entry, remote: &Entry, conflicts: &mut Vec<EntryConflict>) -> Entry {
+    let base = base.or(match (local.clock, remote.clock) {
+        (0, remote_clock) if remote_clock > 0 => Some(local),
+        (local_clock, 0) if local_clock > 0 => Some(remote),
+        _ => None,
+    });
+
+    let mut merged = Entry {
+        clock: local.clock.max(remote.clock) + 1,
+        ..local.clone()
+    };
+
+    for field in EntryField::ALL {
+        let (local_value, remote_value) = (field.value(local), field.value(remote));
+        let base_value = base.map(|base| field.value(base));
+
+        if local_value == remote_value || base_value == Some(remote_value) {
+            continue;
+        }
+
+        if base_value == Some(local_value) {
+            *field.value_mut(&mut merged) = remote_value.clone();
+            continue;
+        }
+
+        conflicts.push(EntryConflict {
+            entry_id: local.id.clone(),
+            title: local.title.clone(),
+            field,
+            local: local_value.clone(),
+            remote: remote_value.clone(),
+        });
+    }
+
+    merged
+}
+
+/// Settings are merged as a whole, the local value wins when both sides changed it
+fn merge_value<T: Clone + PartialEq>(base: &T, local: &T, remote: &T) -> T {
+    match local == base {
+        true => remote.clone(),
+        false => local.clone(),
+    }
+}
+
+/// Records of the entries saved before the UUIDs are named after their `usize` id
+fn decode_entry(id: &str, data: &mut &[u8]) -> Result<Entry> {
+    match id[ENTRY_RECORD_PREFIX.len()..].parse::<usize>() {
+        Ok(_) => decode_next::<LegacyEntry>(data).map(Entry::from),
+        Err(_) => decode_next(data),
+    }
+}
+
+fn decode_next<T: Decode<()>>(data: &mut &[u8]) -> Result<T> {
+    let (value, read) = bincode::decode_from_slice(data, config::standard())
+        .map_err(|error| CryptographyError::BinaryDecodingError(error.to_string()))?;
+
+    *data = &data[read..];
+
+    Ok(value)
+}
+
+/// X25519 key pair used to receive shared entries, the private key never leaves the vault
+#[derive(Encode, Decode, PartialEq, Clone)]
+pub struct SharingKeyPair {
+    pub private_key: Vec<u8>,
+    pub public_key: Vec<u8>,
+}
+
+impl std::fmt::Debug for SharingKeyPair {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter
+            .debug_struct("SharingKeyPair")
+            .field("public_key", &self.public_key)
+            .finish_non_exhaustive()
+    }
+}
+
+/// Public key of another user, trusted on first use and kept to notice when the server hands out another one
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub struct PinnedKey {
+    pub username: String,
+    pub public_key: Vec<u8>,
+}
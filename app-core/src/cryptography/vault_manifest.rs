@@ -0,0 +1,73 @@
+use bincode::{Decode, Encode, config};
+
+use crate::cryptography::cryptography_error::{CryptographyError, Result};
+
+/// Id the manifest is stored under, it never lists itself
+pub const MANIFEST_RECORD: &str = "manifest";
+
+const RECORD_AAD_LABEL: &[u8] = b"ferris-vault-v1-record";
+
+/// Lists the records of the vault with the version each one was encrypted with
+///
+/// Every save gets the next generation, the records it re-encrypts take it as their version.
+/// A record is authenticated with its id and version, the server can't swap two records or serve an older one
+/// with the current manifest. A whole older manifest replayed with its records is only caught by its generation.
+#[derive(Encode, Decode, PartialEq, Debug, Default, Clone)]
+pub struct VaultManifest {
+    generation: u64,
+    records: Vec<RecordVersion>,
+}
+
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub struct RecordVersion {
+    pub id: String,
+    pub version: u64,
+}
+
+impl VaultManifest {
+    pub fn new(generation: u64) -> Self {
+        Self {
+            generation,
+            records: vec![],
+        }
+    }
+
+    pub fn get_generation(&self) -> u64 {
+        self.generation
+    }
+
+    /// In the order of the vault, the entries are listed back in the same order
+    pub fn get_records(&self) -> &[RecordVersion] {
+        &self.records
+    }
+
+    pub fn get_version(&self, id: &str) -> Option<u64> {
+        self.records
+            .iter()
+            .find(|record| record.id == id)
+            .map(|record| record.version)
+    }
+
+    pub fn add_record(&mut self, id: String, version: u64) {
+        self.records.push(RecordVersion { id, version });
+    }
+
+    pub fn as_bytes(&self) -> Result<Vec<u8>> {
+        bincode::encode_to_vec(self, config::standard())
+            .map_err(|error| CryptographyError::BinaryEncodingError(error.to_string()))
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let (manifest, _) = bincode::decode_from_slice(data, config::standard())
+            .map_err(|error| CryptographyError::BinaryDecodingError(error.to_string()))?;
+
+        Ok(manifest)
+    }
+}
+
+/// Associated data of a record, `label | version (8, big endian) | id`
+///
+/// The manifest is replaced as a whole and always uses version 0.
+pub fn record_aad(id: &str, version: u64) -> Vec<u8> {
+    [RECORD_AAD_LABEL, &version.to_be_bytes(), id.as_bytes()].concat()
+}
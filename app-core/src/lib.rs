@@ -9,6 +9,7 @@ pub mod core;
 pub mod cryptography;
 pub mod password;
 pub mod ports;
+pub mod username;
 pub mod vault;
 
 #[cfg(test)]
@@ -1 +1,2 @@
+pub mod derivation_profile;
 pub mod password_restriction;
\ No newline at end of file
@@ -0,0 +1,13 @@
+use bincode::{Decode, Encode};
+
+use crate::password::password_restriction::PasswordRestriction;
+
+/// Everything needed, with the master secret, to recompute a password instead of storing it
+/// Incrementing the counter rotates the password for the same site and login
+#[derive(Encode, Decode, Clone, Debug, Default, PartialEq)]
+pub struct DerivationProfile {
+    pub site: String,
+    pub login: String,
+    pub counter: u32,
+    pub restrictions: PasswordRestriction
+}
\ No newline at end of file
@@ -1,8 +1,11 @@
-#[derive(Clone, Debug, Default)]
+use bincode::{Decode, Encode};
+
+#[derive(Encode, Decode, Clone, Debug, Default, PartialEq)]
 pub struct PasswordRestriction {
     pub length: usize,
     pub lower_case: bool,
     pub upper_case: bool,
     pub numbers: bool,
-    pub special_characters: bool
+    pub special_characters: bool,
+    pub exclude_ambiguous: bool
 }
\ No newline at end of file
@@ -1,3 +1,5 @@
 pub mod cryptography;
+pub mod key_management;
 pub mod vault_manager;
-pub mod password_generator;
\ No newline at end of file
+pub mod password_generator;
+pub mod username_generator;
\ No newline at end of file
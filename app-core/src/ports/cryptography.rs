@@ -1,13 +1,26 @@
-use crate::cryptography::{cryptography_error::Result, uncrypted_vault::UncryptedVault};
+use crate::{
+    cryptography::{cryptography_error::Result, uncrypted_vault::UncryptedVault},
+    ports::key_management::KeyManagement,
+};
 
 pub trait Cryptography {
     fn encrypt(&self, uncrypted_vault: &UncryptedVault) -> Result<Vec<u8>>;
     fn decrypt(&self, vault: &[u8]) -> Result<UncryptedVault>;   
+    /// One record of the vault, the associated data binds it to its id and version
+    fn encrypt_record(&self, record: &[u8], aad: &[u8]) -> Result<Vec<u8>>;
+    fn decrypt_record(&self, crypted_record: &[u8], aad: &[u8]) -> Result<Vec<u8>>;
 }
 
 pub trait NoKeyCipher {
 
     type Crypto: Cryptography;
+    /// Recovery, key file and sharing keys used alongside this cipher
+    type Keys: KeyManagement;
 
     fn create_cipher_from_key(key: &[u8]) -> Result<Self::Crypto>;
+
+    /// Random key the vault is encrypted with, only its wrapped form leaves the device
+    fn generate_data_key() -> Vec<u8>;
+    fn wrap_data_key(key: &[u8], data_key: &[u8]) -> Result<Vec<u8>>;
+    fn unwrap_data_key(key: &[u8], wrapped_data_key: &[u8]) -> Result<Vec<u8>>;
 }
\ No newline at end of file
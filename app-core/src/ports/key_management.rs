@@ -0,0 +1,28 @@
+use crate::cryptography::{cryptography_error::Result, secret_share::SecretShare, uncrypted_vault::SharingKeyPair};
+
+/// Keys and secrets that don't depend on how the vault is encrypted, every cipher uses the same ones
+pub trait KeyManagement {
+    /// Printed once in the emergency kit, it wraps the data key like the encryption key does
+    fn generate_recovery_key() -> Vec<u8>;
+    fn recovery_token(recovery_key: &[u8]) -> Result<Vec<u8>>;
+    /// Secret the derived passwords are computed from, it only changes with the data key
+    fn derivation_key(data_key: &[u8]) -> Result<Vec<u8>>;
+
+    /// Any `threshold` of the shares give the secret back, fewer reveal nothing about it
+    fn split_secret(secret: &[u8], threshold: u8, share_count: u8) -> Result<Vec<SecretShare>>;
+    /// Shares can be given as mnemonic or hex, in any order
+    fn combine_shares(shares: &[String]) -> Result<Vec<u8>>;
+
+    /// Contents of a new key file, it never leaves the device
+    fn generate_key_file() -> Vec<u8>;
+    /// Mixes the key file into the encryption key, a stolen password alone can't unwrap the data key
+    fn mix_key_file(key: &[u8], key_file: &[u8]) -> Result<Vec<u8>>;
+
+    /// Key pair of the account for sharing, only the public key is sent to the server
+    fn generate_sharing_key_pair() -> SharingKeyPair;
+    /// Anyone can seal data for a public key, only the matching private key opens it
+    fn seal(public_key: &[u8], data: &[u8]) -> Result<Vec<u8>>;
+    fn open_sealed(private_key: &[u8], sealed_data: &[u8]) -> Result<Vec<u8>>;
+    /// Words compared out of band, two users see the same fingerprint for the same public key
+    fn fingerprint(public_key: &[u8]) -> String;
+}
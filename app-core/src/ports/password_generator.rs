@@ -1,6 +1,10 @@
-use crate::password::password_restriction::PasswordRestriction;
+use crate::password::{derivation_profile::DerivationProfile, password_restriction::PasswordRestriction};
 
 pub trait PasswordGenerator {
     fn generate_password(restrictions: &PasswordRestriction) -> Result<Vec<u8>, String>;
+    /// Entropy in bits of a password generated with these restrictions
+    fn entropy(restrictions: &PasswordRestriction) -> f64;
+    /// Always returns the same password for the same master secret and profile
+    fn derive_password(master_secret: &[u8], profile: &DerivationProfile) -> Result<Vec<u8>, String>;
 }
 
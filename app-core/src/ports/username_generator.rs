@@ -0,0 +1,6 @@
+use crate::username::{email_alias::EmailAliasKind, username_restriction::UsernameRestriction};
+
+pub trait UsernameGenerator {
+    fn generate_username(restrictions: &UsernameRestriction) -> Result<String, String>;
+    fn generate_email_alias(base_address: &str, alias_kind: &EmailAliasKind) -> Result<String, String>;
+}
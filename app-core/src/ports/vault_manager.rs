@@ -1,11 +1,106 @@
 use generic_array::ArrayLength;
 
-use crate::{vault::crypted_vault::CryptedVault, vault::vault_error::Result};
+use crate::{
+    vault::crypted_record::CryptedRecord, vault::crypted_vault::CryptedVault, vault::share::SealedShare,
+    vault::vault_error::Result,
+};
+
+/// Receives the data key wrapped with the recovery key and the encryption key of the new password
+pub type RewrapDataKey<'a> = dyn Fn(&[u8], &[u8]) -> Result<Vec<u8>> + 'a;
+/// `RewrapDataKey` that can be shared with the futures of `AsyncVaultManager`
+pub type SyncRewrapDataKey<'a> = dyn Fn(&[u8], &[u8]) -> Result<Vec<u8>> + Sync + 'a;
 
 pub trait VaultManager {
     type KeySize: ArrayLength<u8>;
 
     fn create(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>>;
     fn retrieve(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>>;
-    fn save(&self, vault: Vec<u8>) -> Result<()>;
+    /// Logs in without fetching the vault, once the session expired or after an offline unlock
+    fn reauthenticate(&mut self, username: &str, password: &str) -> Result<()>;
+    /// Invalidates the session on the server, it is dropped on the client even if the server can't be reached
+    fn logout(&mut self) -> Result<()>;
+    /// Key the server must prove at the next logins and recoveries, `None` pins the first key seen
+    fn pin_server_key(&mut self, server_key: Option<Vec<u8>>);
+    /// Refuses the default identifiers of accounts registered before they were bound, once the account logged in with its own
+    fn require_bound_identifiers(&mut self, required: bool);
+    /// The last login used the identifiers bound to the account, the next ones can require them
+    fn identifiers_bound(&self) -> bool;
+    /// The account was registered with weaker key stretching than configured, registering the same password again upgrades it
+    fn needs_reregistration(&self) -> bool;
+    /// Refused with `VaultError::Conflict` if the vault isn't at `revision` anymore, returns the new revision
+    fn save(&self, vault: Vec<u8>, revision: Option<&str>) -> Result<Option<String>>;
+    /// Uploads the changed records in one request, a deleted record has an empty content
+    ///
+    /// Refused with `VaultError::Conflict` if the vault isn't at `revision` anymore, returns the new revision.
+    fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> Result<Option<String>>;
+    /// Records currently on the server with their revision, to merge with after a conflict
+    fn fetch_records(&self) -> Result<(Vec<CryptedRecord>, Option<String>)>;
+    fn save_wrapped_key(&self, wrapped_key: Vec<u8>) -> Result<()>;
+    /// Registers the new password and replaces the wrapped data key in one step,
+    /// `wrap_data_key` receives the encryption key of the new password
+    fn change_password(
+        &mut self,
+        username: &str,
+        old_password: &str,
+        new_password: &str,
+        wrap_data_key: &dyn Fn(&[u8]) -> Result<Vec<u8>>,
+    ) -> Result<()>;
+    /// Replaces the previous recovery key, the server keeps the token to authorize a recovery
+    fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> Result<()>;
+    /// Registers a new password without the old one
+    fn recover(
+        &mut self,
+        username: &str,
+        recovery_token: &[u8],
+        new_password: &str,
+        rewrap_data_key: &RewrapDataKey<'_>,
+    ) -> Result<()>;
+
+    /// Only the public key is published, other users seal their shares with it
+    fn save_public_key(&self, public_key: Vec<u8>) -> Result<()>;
+    fn get_public_key(&self, username: &str) -> Result<Vec<u8>>;
+    fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> Result<()>;
+    fn get_shares(&self) -> Result<Vec<SealedShare>>;
+    fn delete_share(&self, id: u64) -> Result<()>;
+}
+
+/// Same port as `VaultManager` for callers that can't block while the server answers
+///
+/// The futures are `Send`, they can be spawned on a multi-threaded runtime.
+pub trait AsyncVaultManager {
+    type KeySize: ArrayLength<u8>;
+
+    fn create(&mut self, username: &str, password: &str) -> impl Future<Output = Result<CryptedVault<Self::KeySize>>> + Send;
+    fn retrieve(&mut self, username: &str, password: &str) -> impl Future<Output = Result<CryptedVault<Self::KeySize>>> + Send;
+    fn reauthenticate(&mut self, username: &str, password: &str) -> impl Future<Output = Result<()>> + Send;
+    fn logout(&mut self) -> impl Future<Output = Result<()>> + Send;
+    fn pin_server_key(&mut self, server_key: Option<Vec<u8>>);
+    fn require_bound_identifiers(&mut self, required: bool);
+    fn identifiers_bound(&self) -> bool;
+    fn needs_reregistration(&self) -> bool;
+    fn save(&self, vault: Vec<u8>, revision: Option<&str>) -> impl Future<Output = Result<Option<String>>> + Send;
+    fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> impl Future<Output = Result<Option<String>>> + Send;
+    fn fetch_records(&self) -> impl Future<Output = Result<(Vec<CryptedRecord>, Option<String>)>> + Send;
+    fn save_wrapped_key(&self, wrapped_key: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+    fn change_password(
+        &mut self,
+        username: &str,
+        old_password: &str,
+        new_password: &str,
+        wrap_data_key: &(dyn Fn(&[u8]) -> Result<Vec<u8>> + Sync),
+    ) -> impl Future<Output = Result<()>> + Send;
+    fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+    fn recover(
+        &mut self,
+        username: &str,
+        recovery_token: &[u8],
+        new_password: &str,
+        rewrap_data_key: &SyncRewrapDataKey<'_>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn save_public_key(&self, public_key: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+    fn get_public_key(&self, username: &str) -> impl Future<Output = Result<Vec<u8>>> + Send;
+    fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+    fn get_shares(&self) -> impl Future<Output = Result<Vec<SealedShare>>> + Send;
+    fn delete_share(&self, id: u64) -> impl Future<Output = Result<()>> + Send;
 }
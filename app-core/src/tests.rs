@@ -1 +1,3 @@
+mod recovery_key_tests;
+mod uncrypted_vault_tests;
 mod vault_core_tests;
\ No newline at end of file
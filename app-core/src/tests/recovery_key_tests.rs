@@ -0,0 +1,147 @@
+use crate::{
+    cryptography::{cryptography_error::CryptographyError, recovery_key::RecoveryKey, secret_share::SecretShare},
+    vault::emergency_kit::{EmergencyKit, RecoveryShareSheet},
+};
+
+#[test]
+fn should_format_recovery_key_in_groups() {
+    // A-rrange
+
+    let recovery_key = RecoveryKey::new(b"foobar".to_vec());
+
+    // A-ct
+
+    let result = recovery_key.to_string();
+
+    // A-ssert
+
+    // RFC 4648 test vector without its padding
+    assert_eq!(result, "MZXW-6YTB-OI");
+}
+
+#[test]
+fn should_parse_formatted_recovery_key() {
+    // A-rrange
+
+    let bytes: Vec<u8> = (0..20).map(|index| index * 13).collect();
+    let recovery_key = RecoveryKey::new(bytes.clone());
+
+    // A-ct
+
+    let result = RecoveryKey::parse(&recovery_key.to_string());
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().as_bytes(), bytes);
+}
+
+#[test]
+fn should_parse_recovery_key_ignoring_case_and_spaces() {
+    // A-rrange & A-ct
+
+    let result = RecoveryKey::parse(" mzxw 6ytb-oi\n");
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().as_bytes(), b"foobar");
+}
+
+#[test]
+fn should_not_parse_recovery_key_with_invalid_character() {
+    // A-rrange & A-ct
+
+    let result = RecoveryKey::parse("MZXW-6YTB-O1");
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::BinaryDecodingError(error)) => assert_eq!(error, "invalid recovery key."),
+        _ => panic!("Test result should be: 'BinaryDecodingError' error."),
+    }
+}
+
+#[test]
+fn should_not_parse_truncated_recovery_key() {
+    // A-rrange & A-ct
+
+    let result = RecoveryKey::parse("MZXW-6YTB-O");
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_print_recovery_key_in_debug() {
+    // A-rrange
+
+    let recovery_key = RecoveryKey::new(b"foobar".to_vec());
+
+    // A-ct
+
+    let result = format!("{recovery_key:?}");
+
+    // A-ssert
+
+    assert_eq!(result, "RecoveryKey { .. }");
+}
+
+#[test]
+fn should_write_emergency_kit() {
+    // A-rrange
+
+    let emergency_kit = EmergencyKit::new("username", "http://localhost:8080", RecoveryKey::new(b"foobar".to_vec()));
+
+    // A-ct
+
+    let result = emergency_kit.to_text();
+
+    // A-ssert
+
+    assert!(result.contains("Username:     username\n"));
+    assert!(result.contains("Server:       http://localhost:8080\n"));
+    assert!(result.contains("Recovery key: MZXW-6YTB-OI\n"));
+}
+
+#[test]
+fn should_write_recovery_share_sheet() {
+    // A-rrange
+
+    let share = SecretShare {
+        index: 2,
+        mnemonic: "actor agent".to_string(),
+        hex: "0102".to_string(),
+    };
+
+    let sheet = RecoveryShareSheet::new("username", share, 2, 3);
+
+    // A-ct
+
+    let result = sheet.to_text();
+
+    // A-ssert
+
+    assert!(result.starts_with("FERRIS VAULT - RECOVERY KEY SHARE 2 OF 3\n"));
+    assert!(result.contains("Words: actor agent\n"));
+    assert!(result.contains("Hex:   0102\n"));
+    assert!(result.contains("Any 2 shares"));
+}
+
+#[test]
+fn should_not_print_secret_share_in_debug() {
+    // A-rrange
+
+    let share = SecretShare {
+        index: 1,
+        mnemonic: "actor agent".to_string(),
+        hex: "0102".to_string(),
+    };
+
+    // A-ct
+
+    let result = format!("{share:?}");
+
+    // A-ssert
+
+    assert_eq!(result, "SecretShare { index: 1, .. }");
+}
@@ -0,0 +1,468 @@
+use bincode::{Encode, config};
+
+use crate::{
+    password::{derivation_profile::DerivationProfile, password_restriction::PasswordRestriction},
+    cryptography::cryptography_error::CryptographyError,
+    uncrypted_vault::{EntryField, MergeSide, SharingKeyPair, UncryptedRecord, UncryptedVault},
+};
+
+const FIRST_LEGACY_ID: &str = "e4d78262-8aaa-5191-bde2-9f8658999655";
+const SECOND_LEGACY_ID: &str = "922aaeea-fde9-5245-8566-f4f4919d5259";
+
+/// Entries had a `usize` id before the UUIDs
+#[derive(Encode)]
+struct LegacyEntry {
+    id: usize,
+    title: String,
+    username: String,
+    password: String,
+}
+
+impl LegacyEntry {
+    fn new(id: usize, title: &str) -> Self {
+        Self { id, title: title.to_string(), username: "username".to_string(), password: "password".to_string() }
+    }
+}
+
+#[test]
+fn should_decode_vault_with_password_restriction() {
+    // A-rrange
+
+    let mut uncrypted_vault = UncryptedVault::new();
+    uncrypted_vault.add_entry("title".to_string(), "username".to_string(), "password".to_string());
+    uncrypted_vault.set_password_restriction(PasswordRestriction {
+        length: 24,
+        lower_case: true,
+        upper_case: true,
+        numbers: false,
+        special_characters: false,
+        exclude_ambiguous: true,
+    });
+
+    let bytes = uncrypted_vault.as_bytes().unwrap();
+
+    // A-ct
+
+    let result = UncryptedVault::decode(&bytes);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), uncrypted_vault);
+}
+
+#[test]
+fn should_decode_vault_saved_before_password_restriction() {
+    // A-rrange
+
+    // Older vaults were only the encoded entries
+    let bytes = bincode::encode_to_vec(vec![LegacyEntry::new(0, "title")], config::standard()).unwrap();
+
+    // A-ct
+
+    let result = UncryptedVault::decode(&bytes);
+
+    // A-ssert
+    assert!(result.is_ok());
+
+    let result = result.unwrap();
+
+    assert_eq!(result.get_entries()[0].get_id(), FIRST_LEGACY_ID);
+    assert_eq!(result.get_entries()[0].title, "title");
+    assert!(result.get_password_restriction().is_none());
+}
+
+#[test]
+fn should_give_same_uuid_to_entry_record_saved_before_uuids() {
+    // A-rrange
+
+    let first_entry = bincode::encode_to_vec(LegacyEntry::new(0, "first"), config::standard()).unwrap();
+    let second_entry = bincode::encode_to_vec(LegacyEntry::new(1, "second"), config::standard()).unwrap();
+
+    let records = vec![
+        UncryptedRecord::new("entry/0".to_string(), first_entry),
+        UncryptedRecord::new("entry/1".to_string(), second_entry),
+    ];
+
+    // A-ct
+
+    let first_result = UncryptedVault::from_records(&records).unwrap();
+    let second_result = UncryptedVault::from_records(&records).unwrap();
+
+    // A-ssert
+
+    assert_eq!(first_result.get_entries()[0].get_id(), FIRST_LEGACY_ID);
+    assert_eq!(first_result.get_entries()[1].get_id(), SECOND_LEGACY_ID);
+    assert_eq!(first_result.get_entries()[1].title, "second");
+    assert_eq!(first_result, second_result);
+}
+
+#[test]
+fn should_decode_vault_with_derived_entries() {
+    // A-rrange
+
+    let mut uncrypted_vault = UncryptedVault::new();
+    uncrypted_vault.add_derived_entry(DerivationProfile {
+        site: "example.com".to_string(),
+        login: "username".to_string(),
+        counter: 3,
+        restrictions: PasswordRestriction {
+            length: 16,
+            lower_case: true,
+            ..Default::default()
+        },
+    });
+
+    let bytes = uncrypted_vault.as_bytes().unwrap();
+
+    // A-ct
+
+    let result = UncryptedVault::decode(&bytes);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), uncrypted_vault);
+}
+
+#[test]
+fn should_decode_vault_with_sharing_key_pair() {
+    // A-rrange
+
+    let mut uncrypted_vault = UncryptedVault::new();
+    uncrypted_vault.add_entry("title".to_string(), "username".to_string(), "password".to_string());
+    uncrypted_vault.set_sharing_key_pair(SharingKeyPair { private_key: vec![1; 32], public_key: vec![2; 32] });
+
+    let bytes = uncrypted_vault.as_bytes().unwrap();
+
+    // A-ct
+
+    let result = UncryptedVault::decode(&bytes);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), uncrypted_vault);
+}
+
+#[test]
+fn should_split_vault_into_records() {
+    // A-rrange
+
+    let mut uncrypted_vault = UncryptedVault::new();
+    uncrypted_vault.add_entry("title".to_string(), "username".to_string(), "password".to_string());
+    uncrypted_vault.add_entry("other".to_string(), "username".to_string(), "password".to_string());
+    uncrypted_vault.add_derived_entry(DerivationProfile::default());
+    uncrypted_vault.set_sharing_key_pair(SharingKeyPair { private_key: vec![1; 32], public_key: vec![2; 32] });
+    uncrypted_vault.pin_key("alice".to_string(), vec![3; 32]);
+
+    // A-ct
+
+    let records = uncrypted_vault.records().unwrap();
+    let result = UncryptedVault::from_records(&records);
+
+    // A-ssert
+
+    let ids: Vec<&str> = records.iter().map(|record| record.id.as_str()).collect();
+    let entries = uncrypted_vault.get_entries();
+
+    assert_eq!(ids, vec![
+        "settings".to_string(),
+        format!("entry/{}", entries[0].get_id()),
+        format!("entry/{}", entries[1].get_id()),
+        "derived/0".to_string(),
+    ]);
+    assert_eq!(result.unwrap(), uncrypted_vault);
+}
+
+#[test]
+fn should_not_open_unknown_record() {
+    // A-rrange
+
+    let records = vec![UncryptedRecord::new("attachment/0".to_string(), vec![])];
+
+    // A-ct
+
+    let result = UncryptedVault::from_records(&records);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::BinaryDecodingError(error)) => assert_eq!(error, "unknown record: attachment/0."),
+        _ => panic!("Test result should be: 'BinaryDecodingError' error."),
+    }
+}
+
+#[test]
+fn should_replace_pinned_key() {
+    // A-rrange
+
+    let mut uncrypted_vault = UncryptedVault::new();
+    uncrypted_vault.pin_key("alice".to_string(), vec![1; 32]);
+    uncrypted_vault.pin_key("bob".to_string(), vec![2; 32]);
+
+    // A-ct
+
+    uncrypted_vault.pin_key("alice".to_string(), vec![3; 32]);
+
+    // A-ssert
+
+    let decoded_vault = UncryptedVault::decode(&uncrypted_vault.as_bytes().unwrap()).unwrap();
+
+    assert_eq!(decoded_vault, uncrypted_vault);
+    assert_eq!(decoded_vault.get_pinned_key("alice").unwrap().public_key, vec![3; 32]);
+    assert_eq!(decoded_vault.get_pinned_key("bob").unwrap().public_key, vec![2; 32]);
+    assert!(decoded_vault.get_pinned_key("carol").is_none());
+}
+
+#[test]
+fn should_update_derived_entry_counter() {
+    // A-rrange
+
+    let mut uncrypted_vault = UncryptedVault::new();
+    uncrypted_vault.add_derived_entry(DerivationProfile { site: "first".to_string(), ..Default::default() });
+    uncrypted_vault.add_derived_entry(DerivationProfile { site: "second".to_string(), ..Default::default() });
+
+    let mut entry = uncrypted_vault.get_derived_entries()[0].clone();
+    entry.profile.counter += 1;
+
+    // A-ct
+
+    uncrypted_vault.update_derived_entry(entry.clone());
+
+    // A-ssert
+    assert_eq!(uncrypted_vault.get_derived_entries().len(), 2);
+    assert_eq!(uncrypted_vault.get_derived_entries()[0], entry);
+    assert_eq!(uncrypted_vault.get_derived_entries()[1].profile.counter, 0);
+}
+
+#[test]
+fn should_update_entry_in_place() {
+    // A-rrange
+
+    let mut uncrypted_vault = UncryptedVault::new();
+    uncrypted_vault.add_entry("first".to_string(), "username".to_string(), "password".to_string());
+    uncrypted_vault.add_entry("second".to_string(), "username".to_string(), "password".to_string());
+
+    let mut entry = uncrypted_vault.get_entries()[0].clone();
+    entry.save_password("new password");
+
+    // A-ct
+
+    uncrypted_vault.update_entry(entry.clone());
+
+    // A-ssert
+
+    let entries = uncrypted_vault.get_entries();
+
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].get_id(), entry.get_id());
+    assert_eq!(entries[0].password, "new password");
+    assert_eq!(entries[1].title, "second");
+    assert_ne!(entries[0], entry);
+}
+
+#[test]
+fn should_merge_entries_added_on_both_sides() {
+    // A-rrange
+
+    let base = base_vault();
+
+    let mut local = base_vault_copy(&base);
+    local.add_entry("local".to_string(), "username".to_string(), "password".to_string());
+
+    let mut remote = base_vault_copy(&base);
+    remote.add_entry("remote".to_string(), "username".to_string(), "password".to_string());
+
+    // A-ct
+
+    let (merged, conflicts) = UncryptedVault::merge(&base, &local, &remote);
+
+    // A-ssert
+
+    let titles: Vec<&str> = merged.get_entries().iter().map(|entry| entry.title.as_str()).collect();
+
+    assert_eq!(titles, vec!["first", "second", "local", "remote"]);
+    assert!(conflicts.is_empty());
+}
+
+#[test]
+fn should_merge_fields_of_entry_changed_on_both_sides() {
+    // A-rrange
+
+    let base = base_vault();
+
+    let mut local = base_vault_copy(&base);
+    let mut entry = local.get_entries()[0].clone();
+    entry.save_password("local password");
+    local.update_entry(entry);
+
+    let mut remote = base_vault_copy(&base);
+    let mut entry = remote.get_entries()[0].clone();
+    entry.title = "remote title".to_string();
+    remote.update_entry(entry);
+
+    // A-ct
+
+    let (merged, conflicts) = UncryptedVault::merge(&base, &local, &remote);
+
+    // A-ssert
+
+    let entry = &merged.get_entries()[0];
+
+    assert_eq!(entry.title, "remote title");
+    assert_eq!(entry.password, "local password");
+    assert!(conflicts.is_empty());
+    // The merged entry counts as changed against both sides
+    assert_ne!(entry, &local.get_entries()[0]);
+    assert_ne!(entry, &remote.get_entries()[0]);
+}
+
+#[test]
+fn should_report_field_changed_differently_on_both_sides() {
+    // A-rrange
+
+    let base = base_vault();
+
+    let mut local = base_vault_copy(&base);
+    let mut entry = local.get_entries()[1].clone();
+    entry.save_password("local password");
+    local.update_entry(entry);
+
+    let mut remote = base_vault_copy(&base);
+    let mut entry = remote.get_entries()[1].clone();
+    entry.save_password("remote password");
+    remote.update_entry(entry);
+
+    let (mut merged, conflicts) = UncryptedVault::merge(&base, &local, &remote);
+    let unresolved_password = merged.get_entries()[1].password.clone();
+
+    // A-ct
+
+    merged.resolve_conflict(&conflicts[0], MergeSide::Remote);
+
+    // A-ssert
+
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].entry_id, base.get_entries()[1].get_id());
+    assert_eq!(conflicts[0].title, "second");
+    assert_eq!(conflicts[0].field, EntryField::Password);
+    assert_eq!(conflicts[0].local, "local password");
+    assert_eq!(conflicts[0].remote, "remote password");
+    assert_eq!(unresolved_password, "local password");
+    assert_eq!(merged.get_entries()[1].password, "remote password");
+}
+
+#[test]
+fn should_take_side_updated_since_creation_without_base() {
+    // A-rrange
+
+    let local = base_vault();
+
+    let mut remote = base_vault_copy(&local);
+    let mut entry = remote.get_entries()[0].clone();
+    entry.save_password("remote password");
+    remote.update_entry(entry);
+
+    // A-ct
+
+    let (merged, conflicts) = UncryptedVault::merge(&UncryptedVault::new(), &local, &remote);
+
+    // A-ssert
+
+    assert_eq!(merged.get_entries()[0].password, "remote password");
+    assert_eq!(merged.get_entries()[1], local.get_entries()[1]);
+    assert!(conflicts.is_empty());
+}
+
+#[test]
+fn should_report_concurrent_updates_without_base() {
+    // A-rrange
+
+    let base = base_vault();
+
+    let mut local = base_vault_copy(&base);
+    let mut entry = local.get_entries()[0].clone();
+    entry.save_password("local password");
+    local.update_entry(entry);
+
+    let mut remote = base_vault_copy(&base);
+    let mut entry = remote.get_entries()[0].clone();
+    entry.save_password("remote password");
+    remote.update_entry(entry);
+
+    // A-ct
+
+    let (merged, conflicts) = UncryptedVault::merge(&UncryptedVault::new(), &local, &remote);
+
+    // A-ssert
+
+    assert_eq!(merged.get_entries()[0].password, "local password");
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].field, EntryField::Password);
+    assert_eq!(conflicts[0].remote, "remote password");
+}
+
+#[test]
+fn should_only_delete_entry_unchanged_on_other_side() {
+    // A-rrange
+
+    let base = base_vault();
+    let records = base.records().unwrap();
+
+    // The first entry is deleted here, the second one on the other device
+    let mut local = UncryptedVault::from_records(&[records[0].clone(), records[2].clone()]).unwrap();
+    let mut entry = local.get_entries()[0].clone();
+    entry.save_password("local password");
+    local.update_entry(entry);
+
+    let remote = UncryptedVault::from_records(&[records[0].clone(), records[1].clone()]).unwrap();
+
+    // A-ct
+
+    let (merged, conflicts) = UncryptedVault::merge(&base, &local, &remote);
+
+    // A-ssert
+
+    let entries = merged.get_entries();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].title, "second");
+    assert_eq!(entries[0].password, "local password");
+    assert!(conflicts.is_empty());
+}
+
+#[test]
+fn should_keep_derived_entries_added_on_both_sides() {
+    // A-rrange
+
+    let base = base_vault();
+
+    let mut local = base_vault_copy(&base);
+    local.add_derived_entry(DerivationProfile { site: "local".to_string(), ..Default::default() });
+
+    let mut remote = base_vault_copy(&base);
+    remote.add_derived_entry(DerivationProfile { site: "remote".to_string(), ..Default::default() });
+
+    // A-ct
+
+    let (merged, _) = UncryptedVault::merge(&base, &local, &remote);
+
+    // A-ssert
+
+    let derived_entries = merged.get_derived_entries();
+
+    assert_eq!(derived_entries.len(), 2);
+    assert_eq!(derived_entries[0].profile.site, "local");
+    assert_eq!(derived_entries[1].profile.site, "remote");
+    assert_eq!(UncryptedVault::from_records(&merged.records().unwrap()).unwrap(), merged);
+}
+
+fn base_vault() -> UncryptedVault {
+    let mut uncrypted_vault = UncryptedVault::new();
+    uncrypted_vault.add_entry("first".to_string(), "username".to_string(), "password".to_string());
+    uncrypted_vault.add_entry("second".to_string(), "username".to_string(), "password".to_string());
+
+    uncrypted_vault
+}
+
+fn base_vault_copy(base: &UncryptedVault) -> UncryptedVault {
+    UncryptedVault::decode(&base.as_bytes().unwrap()).unwrap()
+}
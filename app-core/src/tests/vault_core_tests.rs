@@ -1,137 +1,1744 @@
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
 use cli_clipboard::{ClipboardContext, ClipboardProvider};
 use generic_array::{GenericArray, typenum::U64};
 
 use crate::{
-    core::vault_core::{Core, CoreService, LoggedCore},
-    cryptography::cryptography_error::CryptographyError,
-    password::password_restriction::PasswordRestriction,
+    core::{
+        core_errors::CoreError,
+        vault_core::{Core, CoreService, LoggedCore, LoggedCoreService},
+    },
+    cryptography::{cryptography_error::CryptographyError, recovery_key::RecoveryKey, secret_share::SecretShare},
+    password::{derivation_profile::DerivationProfile, password_restriction::PasswordRestriction},
     ports::{
         cryptography::{Cryptography, NoKeyCipher},
+        key_management::KeyManagement,
         password_generator::PasswordGenerator,
-        vault_manager::VaultManager,
+        username_generator::UsernameGenerator,
+        vault_manager::{RewrapDataKey, VaultManager},
+    },
+    uncrypted_vault::{Entry, EntryField, MergeSide, SharingKeyPair, UncryptedVault},
+    username::{email_alias::EmailAliasKind, username_restriction::UsernameRestriction},
+    vault::{
+        crypted_record::CryptedRecord,
+        crypted_vault::CryptedVault,
+        key::Key,
+        share::{KeyStatus, ReceivedShare, RecipientKey, SealedShare, SharedEntry},
+        vault_error::VaultError,
     },
-    uncrypted_vault::UncryptedVault,
-    vault::{crypted_vault::CryptedVault, key::Key, vault_error::VaultError},
 };
 
-const USERNAME: &'static str = "username";
-const PASSWORD: &'static str = "password";
-const TITLE: &'static str = "title";
+const USERNAME: &'static str = "username";
+const PASSWORD: &'static str = "password";
+const TITLE: &'static str = "title";
+
+#[test]
+fn should_create_account() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.create_account(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap().get_entries().len(), 0);
+}
+
+#[test]
+fn should_logging() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap().get_entries().len(), 0);
+}
+
+#[test]
+fn should_upload_wrapped_key_on_account_creation() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_records = mock_vault_manager.saved_records.clone();
+    let saved_wrapped_keys = mock_vault_manager.saved_wrapped_keys.clone();
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let mut logged_core = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core.save_vault().unwrap();
+
+    // A-ssert
+
+    assert_eq!(*saved_wrapped_keys.borrow(), vec![[WRAPPED_PREFIX, &DATA_KEY].concat()]);
+    assert!(encrypted_with(&saved_records.borrow()[0], DATA_KEY[0]));
+}
+
+#[test]
+fn should_open_vault_with_wrapped_key() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_records(saved_vault_records());
+    let saved_vaults = mock_vault_manager.saved_vaults.clone();
+    let saved_records = mock_vault_manager.saved_records.clone();
+    let saved_wrapped_keys = mock_vault_manager.saved_wrapped_keys.clone();
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    let entries = result.unwrap().get_entries().to_vec();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].title, TITLE);
+
+    assert!(saved_wrapped_keys.borrow().is_empty());
+    assert!(saved_vaults.borrow().is_empty());
+    assert!(saved_records.borrow().is_empty());
+}
+
+#[test]
+fn should_split_vault_blob_into_records() {
+    // A-rrange
+
+    let mock_vault_manager =
+        MockVaultManager::with_vault(false, vec![DATA_KEY[0]], [WRAPPED_PREFIX, &DATA_KEY].concat());
+    let saved_vaults = mock_vault_manager.saved_vaults.clone();
+    let saved_records = mock_vault_manager.saved_records.clone();
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    assert_eq!(record_ids(&saved_records.borrow()[0]), vec!["settings", "manifest"]);
+    // The blob is emptied once the records are uploaded
+    assert_eq!(*saved_vaults.borrow(), vec![Vec::<u8>::new()]);
+}
+
+#[test]
+fn should_migrate_legacy_vault_to_data_key() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_vault(false, vec![ENCRYPTION_KEY[0]], vec![]);
+    let saved_records = mock_vault_manager.saved_records.clone();
+    let saved_wrapped_keys = mock_vault_manager.saved_wrapped_keys.clone();
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    assert_eq!(*saved_wrapped_keys.borrow(), vec![[WRAPPED_PREFIX, &DATA_KEY].concat()]);
+    assert!(encrypted_with(&saved_records.borrow()[0], DATA_KEY[0]));
+}
+
+#[test]
+fn should_open_vault_left_on_encryption_key_by_interrupted_migration() {
+    // A-rrange
+
+    let mock_vault_manager =
+        MockVaultManager::with_vault(false, vec![ENCRYPTION_KEY[0]], [WRAPPED_PREFIX, &DATA_KEY].concat());
+    let saved_records = mock_vault_manager.saved_records.clone();
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let mut logged_core = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+    logged_core.save_vault().unwrap();
+
+    // A-ssert
+
+    // Nothing changed after the records were split from the blob, the second save uploads nothing
+    assert_eq!(saved_records.borrow().len(), 1);
+    assert!(encrypted_with(&saved_records.borrow()[0], DATA_KEY[0]));
+}
+
+#[test]
+fn should_not_open_vault_with_invalid_wrapped_key() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_vault(false, vec![DATA_KEY[0]], b"tampered".to_vec());
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::CryptographyError(_)) => {}
+        _ => panic!("Test result should be: 'CryptographyError' error."),
+    }
+}
+
+#[test]
+fn should_change_password() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_vault(false, vec![], [WRAPPED_PREFIX, &DATA_KEY].concat());
+    let saved_wrapped_keys = mock_vault_manager.saved_wrapped_keys.clone();
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let mut logged_core = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+
+    let profile = DerivationProfile {
+        site: "example.com".to_string(),
+        ..Default::default()
+    };
+
+    // A-ct
+
+    let result = logged_core.change_password(USERNAME, PASSWORD, "new password");
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(*saved_wrapped_keys.borrow(), vec![[WRAPPED_PREFIX, &DATA_KEY].concat()]);
+    // Derived from the data key, the new password doesn't change it
+    assert_eq!(logged_core.derive_password(&profile).unwrap(), [DERIVATION_PREFIX, &DATA_KEY, b"example.com"].concat());
+}
+
+#[test]
+fn should_get_server_key_once_account_created() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let logged_core = core_service.create_account(USERNAME, PASSWORD).unwrap();
+
+    // A-ssert
+
+    assert_eq!(logged_core.get_server_key(), SERVER_KEY);
+}
+
+#[test]
+fn should_log_in_with_pinned_server_key() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let mut core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    core_service.set_server_key(Some(SERVER_KEY.to_vec()));
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().get_server_key(), SERVER_KEY);
+}
+
+#[test]
+fn should_not_log_in_with_wrong_password() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, WRONG_PASSWORD);
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::InvalidCredentials) => {}
+        _ => panic!("Test result should be: 'InvalidCredentials' error."),
+    }
+}
+
+#[test]
+fn should_not_create_account_if_username_taken() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.create_account(EXISTING_USERNAME, PASSWORD);
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::UserExists) => {}
+        _ => panic!("Test result should be: 'UserExists' error."),
+    }
+}
+
+#[test]
+fn should_keep_retry_after_of_rate_limited_vault_manager() {
+    // A-rrange
+
+    let error = VaultError::RateLimited(Some(30));
+
+    // A-ct
+
+    let core_error = CoreError::from(error);
+
+    // A-ssert
+
+    match core_error {
+        CoreError::RateLimited(retry_after) => assert_eq!(retry_after, Some(30)),
+        _ => panic!("Test result should be: 'RateLimited' error."),
+    }
+}
+
+#[test]
+fn should_not_log_in_if_server_key_changed() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let mut core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    core_service.set_server_key(Some(b"previous server key".to_vec()));
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::VaultManagerError(error)) => {
+            assert_eq!(error, VaultError::ServerKeyChanged.to_string())
+        }
+        _ => panic!("Test result should be: 'VaultManagerError' error."),
+    }
+}
+
+#[test]
+fn should_upgrade_outdated_key_stretching_on_login() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager {
+        outdated_key_stretching: true,
+        ..MockVaultManager::with_vault(false, vec![], [WRAPPED_PREFIX, &DATA_KEY].concat())
+    };
+    let saved_wrapped_keys = Rc::clone(&mock_vault_manager.saved_wrapped_keys);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    // Same data key, wrapped with the encryption key of the new registration
+    assert_eq!(*saved_wrapped_keys.borrow(), vec![[WRAPPED_PREFIX, &DATA_KEY].concat()]);
+}
+
+#[test]
+fn should_not_reregister_on_login_if_key_stretching_is_current() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_vault(false, vec![], [WRAPPED_PREFIX, &DATA_KEY].concat());
+    let saved_wrapped_keys = Rc::clone(&mock_vault_manager.saved_wrapped_keys);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(saved_wrapped_keys.borrow().is_empty());
+}
+
+#[test]
+fn should_not_change_password_with_wrong_old_password() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let mut logged_core = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    let result = logged_core.change_password(USERNAME, "wrong password", "new password");
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::InvalidCredentials) => {}
+        _ => panic!("Test result should be: 'InvalidCredentials' error."),
+    }
+}
+
+#[test]
+fn should_not_change_password_to_empty_password() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let mut logged_core = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    let result = logged_core.change_password(USERNAME, PASSWORD, "");
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::InternalError(error)) => assert_eq!(error, "The new password cannot be empty."),
+        _ => panic!("Test result should be: 'InternalError' error."),
+    }
+}
+
+#[test]
+fn should_create_recovery_key() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_recovery_keys = mock_vault_manager.saved_recovery_keys.clone();
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let logged_core = core_service.create_account(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    let result = logged_core.create_recovery_key();
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().as_bytes(), RECOVERY_KEY);
+    assert_eq!(
+        *saved_recovery_keys.borrow(),
+        vec![([TOKEN_PREFIX, &RECOVERY_KEY].concat(), [WRAPPED_PREFIX, &DATA_KEY].concat())]
+    );
+}
+
+#[test]
+fn should_recover_account() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_vault(false, vec![], [WRAPPED_PREFIX, &DATA_KEY].concat());
+    let saved_wrapped_keys = mock_vault_manager.saved_wrapped_keys.clone();
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let recovery_key = RecoveryKey::new(RECOVERY_KEY.to_vec()).to_string();
+
+    let profile = DerivationProfile {
+        site: "example.com".to_string(),
+        ..Default::default()
+    };
+
+    // A-ct
+
+    let result = core_service.recover_account(USERNAME, &recovery_key, "new password");
+
+    // A-ssert
+
+    assert_eq!(*saved_wrapped_keys.borrow(), vec![[WRAPPED_PREFIX, &DATA_KEY].concat()]);
+    assert_eq!(result.unwrap().derive_password(&profile).unwrap(), [DERIVATION_PREFIX, &DATA_KEY, b"example.com"].concat());
+}
+
+#[test]
+fn should_not_recover_account_with_wrong_recovery_key() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let recovery_key = RecoveryKey::new(vec![10; 20]).to_string();
+
+    // A-ct
+
+    let result = core_service.recover_account(USERNAME, &recovery_key, "new password");
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::VaultManagerError(_)) => {}
+        _ => panic!("Test result should be: 'VaultManagerError' error."),
+    }
+}
+
+#[test]
+fn should_not_recover_account_with_invalid_recovery_key() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.recover_account(USERNAME, "not a recovery key!", "new password");
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::CryptographyError(_)) => {}
+        _ => panic!("Test result should be: 'CryptographyError' error."),
+    }
+}
+
+#[test]
+fn should_not_recover_account_with_empty_password() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let recovery_key = RecoveryKey::new(RECOVERY_KEY.to_vec()).to_string();
+
+    // A-ct
+
+    let result = core_service.recover_account(USERNAME, &recovery_key, "");
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::InternalError(error)) => assert_eq!(error, "The new password cannot be empty."),
+        _ => panic!("Test result should be: 'InternalError' error."),
+    }
+}
+
+#[test]
+fn should_split_recovery_key() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let logged_core = core_service.create_account(USERNAME, PASSWORD).unwrap();
+
+    let recovery_key = RecoveryKey::new(b"recovery key".to_vec());
+
+    // A-ct
+
+    let result = logged_core.split_recovery_key(&recovery_key, 2, 3).unwrap();
+
+    // A-ssert
+
+    assert_eq!(result.len(), 3);
+    assert!(result.iter().all(|share| share.hex == "recovery key"));
+}
+
+#[test]
+fn should_combine_recovery_shares() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let shares = ["recovery key".to_string(), "recovery key".to_string()];
+
+    // A-ct
+
+    let result = core_service.combine_recovery_shares(&shares);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().as_bytes(), b"recovery key");
+}
+
+#[test]
+fn should_not_combine_too_few_recovery_shares() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.combine_recovery_shares(&["recovery key".to_string()]);
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::CryptographyError(_)) => {}
+        _ => panic!("Test result should be: 'CryptographyError' error."),
+    }
+}
+
+#[test]
+fn should_wrap_data_key_with_key_file_on_account_creation() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_wrapped_keys = mock_vault_manager.saved_wrapped_keys.clone();
+
+    let mut core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    core_service.set_key_file(Some(KEY_FILE.to_vec()));
+
+    // A-ct
+
+    let result = core_service.create_account(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(*saved_wrapped_keys.borrow(), vec![[MIXED_PREFIX, KEY_FILE, WRAPPED_PREFIX, &DATA_KEY].concat()]);
+}
+
+#[test]
+fn should_open_vault_with_key_file() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_vault(
+        false,
+        vec![DATA_KEY[0]],
+        [MIXED_PREFIX, KEY_FILE, WRAPPED_PREFIX, &DATA_KEY].concat(),
+    );
+
+    let mut core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    core_service.set_key_file(Some(KEY_FILE.to_vec()));
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_not_open_vault_with_wrong_key_file() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_vault(
+        false,
+        vec![DATA_KEY[0]],
+        [MIXED_PREFIX, KEY_FILE, WRAPPED_PREFIX, &DATA_KEY].concat(),
+    );
+
+    let mut core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    core_service.set_key_file(Some(b"another file".to_vec()));
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::KeyFileError(error)) => assert_eq!(error, "the key file doesn't match this vault."),
+        _ => panic!("Test result should be: 'KeyFileError' error."),
+    }
+}
+
+#[test]
+fn should_not_open_vault_without_its_key_file() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_vault(
+        false,
+        vec![DATA_KEY[0]],
+        [MIXED_PREFIX, KEY_FILE, WRAPPED_PREFIX, &DATA_KEY].concat(),
+    );
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::CryptographyError(error)) => assert!(error.ends_with("The vault may need its key file.")),
+        _ => panic!("Test result should be: 'CryptographyError' error."),
+    }
+}
+
+#[test]
+fn should_add_key_file_to_logged_vault() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_vault(false, vec![], [WRAPPED_PREFIX, &DATA_KEY].concat());
+    let saved_wrapped_keys = mock_vault_manager.saved_wrapped_keys.clone();
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let mut logged_core = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+    let key_file = logged_core.generate_key_file();
+
+    // A-ct
+
+    let result = logged_core.set_key_file(Some(key_file));
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(*saved_wrapped_keys.borrow(), vec![[MIXED_PREFIX, KEY_FILE, WRAPPED_PREFIX, &DATA_KEY].concat()]);
+}
+
+#[test]
+fn should_keep_key_file_when_changing_password() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::with_vault(
+        false,
+        vec![],
+        [MIXED_PREFIX, KEY_FILE, WRAPPED_PREFIX, &DATA_KEY].concat(),
+    );
+    let saved_wrapped_keys = mock_vault_manager.saved_wrapped_keys.clone();
+
+    let mut core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    core_service.set_key_file(Some(KEY_FILE.to_vec()));
+
+    let mut logged_core = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    let result = logged_core.change_password(USERNAME, PASSWORD, "new password");
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(*saved_wrapped_keys.borrow(), vec![[MIXED_PREFIX, KEY_FILE, WRAPPED_PREFIX, &DATA_KEY].concat()]);
+}
+
+#[test]
+fn should_get_entries() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    let result = logged_core_service.get_entries();
+
+    // A-ssert
+    assert_eq!(result.len(), 0);
+}
+
+#[test]
+fn should_add_entry() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    logged_core_service.add_entry(
+        TITLE.to_string(),
+        USERNAME.to_string(),
+        PASSWORD.to_string(),
+    );
+
+    let result = logged_core_service.get_entries();
+
+    // A-ssert
+    assert_eq!(result.len(), 1);
+    assert_eq!(result.get(0).unwrap().title, TITLE);
+    assert_eq!(result.get(0).unwrap().username, USERNAME);
+    assert_eq!(result.get(0).unwrap().password, PASSWORD);
+}
+
+#[test]
+fn should_generate_password() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    let restrictions = PasswordRestriction { length: 18, lower_case: true, upper_case: true, numbers: true, special_characters: true, exclude_ambiguous: false };
+
+    // A-ct
+
+    let result = logged_core_service.generate_password(&restrictions);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), vec![42]);
+}
+
+#[test]
+fn should_get_password_entropy() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    let restrictions = PasswordRestriction { length: 18, lower_case: true, upper_case: true, numbers: true, special_characters: true, exclude_ambiguous: false };
+
+    // A-ct
+
+    let result = logged_core_service.password_entropy(&restrictions);
+
+    // A-ssert
+    assert_eq!(result, 42.0);
+}
+
+#[test]
+fn should_set_password_restriction() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    let restrictions = PasswordRestriction { length: 32, lower_case: true, upper_case: false, numbers: true, special_characters: false, exclude_ambiguous: true };
+
+    // A-ct
+
+    logged_core_service.set_password_restriction(restrictions.clone());
+
+    // A-ssert
+    assert_eq!(logged_core_service.get_password_restriction(), Some(&restrictions));
+}
+
+#[test]
+fn should_add_derived_entry() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    let profile = DerivationProfile { site: TITLE.to_string(), login: USERNAME.to_string(), counter: 1, ..Default::default() };
+
+    // A-ct
+
+    logged_core_service.add_derived_entry(profile.clone());
+
+    let result = logged_core_service.get_derived_entries();
+
+    // A-ssert
+    assert_eq!(result.len(), 1);
+    assert_eq!(result[0].profile, profile);
+    assert!(logged_core_service.get_entries().is_empty());
+}
+
+#[test]
+fn should_derive_password_from_data_key() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let logged_core_service = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+    let profile = DerivationProfile { site: TITLE.to_string(), login: USERNAME.to_string(), counter: 1, ..Default::default() };
+
+    // A-ct
+
+    let result = logged_core_service.derive_password(&profile);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), [DERIVATION_PREFIX, &DATA_KEY, TITLE.as_bytes()].concat());
+}
+
+#[test]
+fn should_generate_username() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    let restrictions = UsernameRestriction { word_count: 2, separator: Some('.'), capitalize: false, digits: 2 };
+
+    // A-ct
+
+    let result = logged_core_service.generate_username(&restrictions);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), USERNAME);
+}
+
+#[test]
+fn should_generate_email_alias() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    let result = logged_core_service.generate_email_alias("john@example.com", &EmailAliasKind::PlusAddressing);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), "john+alias@example.com");
+}
+
+#[test]
+fn should_save_vault() {
+
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    
+    // A-ct
+
+    let result = logged_core_service.save_vault();
+
+    // A-ssert
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_save_every_record_on_first_save() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+
+    // A-ct
+
+    logged_core_service.save_vault().unwrap();
+
+    // A-ssert
+
+    let entry_record = entry_record(&logged_core_service.get_entries()[0]);
+
+    assert_eq!(record_ids(&saved_records.borrow()[0]), vec!["settings", &entry_record, "manifest"]);
+}
+
+#[test]
+fn should_only_upload_changed_records() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    logged_core_service.save_vault().unwrap();
+
+    // A-ct
+
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    logged_core_service.save_vault().unwrap();
+    logged_core_service.save_vault().unwrap();
+
+    // A-ssert
+
+    let entry_record = entry_record(&logged_core_service.get_entries()[1]);
+
+    assert_eq!(saved_records.borrow().len(), 2);
+    assert_eq!(record_ids(&saved_records.borrow()[1]), vec![entry_record.as_str(), "manifest"]);
+}
+
+#[test]
+fn should_save_vault_again_after_reauthentication() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let session_expired = Rc::clone(&mock_vault_manager.session_expired);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    session_expired.set(true);
+
+    // A-ct
+
+    let expired_result = logged_core_service.save_vault();
+    let wrong_password_result = logged_core_service.reauthenticate(USERNAME, WRONG_PASSWORD);
+    let reauthentication_result = logged_core_service.reauthenticate(USERNAME, PASSWORD);
+    let result = logged_core_service.save_vault();
+
+    // A-ssert
+
+    assert!(matches!(expired_result, Err(CoreError::SessionExpired)));
+    assert!(matches!(wrong_password_result, Err(CoreError::InvalidCredentials)));
+    assert!(reauthentication_result.is_ok());
+    assert!(result.is_ok());
+    let entry_record = entry_record(&logged_core_service.get_entries()[0]);
+
+    // The changes refused with the expired session are uploaded by the next save
+    assert_eq!(saved_records.borrow().len(), 1);
+    assert_eq!(record_ids(&saved_records.borrow()[0]), vec!["settings", &entry_record, "manifest"]);
+}
+
+#[test]
+fn should_keep_vault_of_another_device_on_conflict() {
+    // A-rrange
+
+    let base_records = saved_vault_records();
+    let mock_vault_manager = MockVaultManager {
+        remote_records: Some((remote_vault_records(&base_records, add_remote_entry), REMOTE_REVISION.to_string())),
+        ..MockVaultManager::with_records(base_records)
+    };
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+
+    let mut entry = logged_core_service.get_entries()[0].clone();
+    entry.save_password(LOCAL_PASSWORD);
+    logged_core_service.update_entry(entry);
+
+    // A-ct
+
+    let result = logged_core_service.save_vault();
+
+    // A-ssert
+
+    assert!(matches!(result, Err(CoreError::Conflict)));
+    assert!(saved_records.borrow().is_empty());
+    assert_eq!(logged_core_service.get_entries().len(), 1);
+}
+
+#[test]
+fn should_merge_vault_of_another_device() {
+    // A-rrange
+
+    let base_records = saved_vault_records();
+    let mock_vault_manager = MockVaultManager {
+        remote_records: Some((remote_vault_records(&base_records, add_remote_entry), REMOTE_REVISION.to_string())),
+        ..MockVaultManager::with_records(base_records)
+    };
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+
+    let mut entry = logged_core_service.get_entries()[0].clone();
+    entry.save_password(LOCAL_PASSWORD);
+    logged_core_service.update_entry(entry);
+
+    let _ = logged_core_service.save_vault();
+
+    // A-ct
+
+    let merge_result = logged_core_service.merge_remote_vault();
+    let result = logged_core_service.save_vault();
+
+    // A-ssert
+
+    assert_eq!(merge_result.unwrap(), vec![]);
+    assert!(result.is_ok());
+
+    let entries = logged_core_service.get_entries();
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].password, LOCAL_PASSWORD);
+    assert_eq!(entries[1].title, REMOTE_TITLE);
+
+    let entry_record = entry_record(&entries[0]);
+
+    // Only the local change is uploaded on top of the remote vault
+    assert_eq!(record_ids(&saved_records.borrow()[0]), vec![entry_record.as_str(), "manifest"]);
+}
+
+#[test]
+fn should_merge_fields_changed_on_different_devices() {
+    // A-rrange
+
+    let base_records = saved_vault_records();
+
+    let change_title = |logged_core_service: &mut TestLoggedCore| {
+        let mut entry = logged_core_service.get_entries()[0].clone();
+        entry.title = REMOTE_TITLE.to_string();
+        logged_core_service.update_entry(entry);
+    };
+
+    let mock_vault_manager = MockVaultManager {
+        remote_records: Some((remote_vault_records(&base_records, change_title), REMOTE_REVISION.to_string())),
+        ..MockVaultManager::with_records(base_records)
+    };
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+
+    let mut entry = logged_core_service.get_entries()[0].clone();
+    entry.save_password(LOCAL_PASSWORD);
+    logged_core_service.update_entry(entry);
+
+    let _ = logged_core_service.save_vault();
+
+    // A-ct
+
+    let result = logged_core_service.merge_remote_vault();
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), vec![]);
+
+    let entries = logged_core_service.get_entries();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].title, REMOTE_TITLE);
+    assert_eq!(entries[0].password, LOCAL_PASSWORD);
+}
+
+#[test]
+fn should_resolve_field_changed_on_both_devices() {
+    // A-rrange
+
+    let base_records = saved_vault_records();
+
+    let change_password = |logged_core_service: &mut TestLoggedCore| {
+        let mut entry = logged_core_service.get_entries()[0].clone();
+        entry.save_password(REMOTE_PASSWORD);
+        logged_core_service.update_entry(entry);
+    };
+
+    let mock_vault_manager = MockVaultManager {
+        remote_records: Some((remote_vault_records(&base_records, change_password), REMOTE_REVISION.to_string())),
+        ..MockVaultManager::with_records(base_records)
+    };
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+
+    let mut entry = logged_core_service.get_entries()[0].clone();
+    entry.save_password(LOCAL_PASSWORD);
+    logged_core_service.update_entry(entry);
+
+    let _ = logged_core_service.save_vault();
+
+    // A-ct
+
+    let conflicts = logged_core_service.merge_remote_vault().unwrap();
+    let unresolved_password = logged_core_service.get_entries()[0].password.clone();
+
+    logged_core_service.resolve_conflict(&conflicts[0], MergeSide::Remote);
+
+    let result = logged_core_service.save_vault();
+
+    // A-ssert
+
+    assert_eq!(conflicts.len(), 1);
+    assert_eq!(conflicts[0].field, EntryField::Password);
+    assert_eq!(conflicts[0].local, LOCAL_PASSWORD);
+    assert_eq!(conflicts[0].remote, REMOTE_PASSWORD);
+    assert_eq!(unresolved_password, LOCAL_PASSWORD);
+    assert!(result.is_ok());
+    assert_eq!(logged_core_service.get_entries()[0].password, REMOTE_PASSWORD);
+}
+
+#[test]
+fn should_not_merge_without_conflict() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    let result = logged_core_service.merge_remote_vault();
+
+    // A-ssert
+
+    assert!(matches!(result, Err(CoreError::InternalError(_))));
+}
+
+#[test]
+fn should_open_and_upload_changes_saved_offline() {
+    // A-rrange
+
+    let base_records = saved_vault_records();
+
+    let change_password = |logged_core_service: &mut TestLoggedCore| {
+        let mut entry = logged_core_service.get_entries()[0].clone();
+        entry.save_password(LOCAL_PASSWORD);
+        logged_core_service.update_entry(entry);
+    };
+
+    let mock_vault_manager = MockVaultManager {
+        pending_records: changed_vault_records(&base_records, change_password),
+        ..MockVaultManager::with_records(base_records)
+    };
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    let logged_core_service = result.unwrap();
+    let entry_record = entry_record(&logged_core_service.get_entries()[0]);
+
+    assert_eq!(logged_core_service.get_entries()[0].password, LOCAL_PASSWORD);
+    // Only the change saved offline is uploaded on top of the synced records
+    assert_eq!(saved_records.borrow().len(), 1);
+    assert_eq!(record_ids(&saved_records.borrow()[0]), vec![entry_record.as_str(), "manifest"]);
+}
+
+#[test]
+fn should_open_and_keep_error_if_changes_saved_offline_not_uploaded() {
+    // A-rrange
+
+    let base_records = saved_vault_records();
+
+    let change_password = |logged_core_service: &mut TestLoggedCore| {
+        let mut entry = logged_core_service.get_entries()[0].clone();
+        entry.save_password(LOCAL_PASSWORD);
+        logged_core_service.update_entry(entry);
+    };
+
+    let mock_vault_manager = MockVaultManager {
+        pending_records: changed_vault_records(&base_records, change_password),
+        ..MockVaultManager::with_records(base_records)
+    };
+    mock_vault_manager.offline.set(true);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    let mut logged_core_service = result.unwrap();
+
+    assert_eq!(logged_core_service.get_entries()[0].password, LOCAL_PASSWORD);
+    assert!(matches!(logged_core_service.take_pending_error(), Some(CoreError::SavedOffline)));
+    assert!(logged_core_service.take_pending_error().is_none());
+}
+
+#[test]
+fn should_upload_changes_saved_offline_with_next_save() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let offline = Rc::clone(&mock_vault_manager.offline);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    offline.set(true);
+
+    // A-ct
+
+    let offline_result = logged_core_service.save_vault();
+    offline.set(false);
+    let result = logged_core_service.save_vault();
+
+    // A-ssert
+
+    let entry_record = entry_record(&logged_core_service.get_entries()[0]);
+
+    assert!(matches!(offline_result, Err(CoreError::SavedOffline)));
+    assert!(result.is_ok());
+    assert_eq!(saved_records.borrow().len(), 1);
+    assert_eq!(record_ids(&saved_records.borrow()[0]), vec!["settings", &entry_record, "manifest"]);
+}
+
+#[test]
+fn should_logout() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let logged_out = Rc::clone(&mock_vault_manager.logged_out);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    let result = logged_core_service.logout();
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(logged_out.get());
+}
+
+#[test]
+fn should_not_open_vault_with_rolled_back_record() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    logged_core_service.save_vault().unwrap();
+
+    let mut entry = logged_core_service.get_entries()[0].clone();
+    entry.save_password("new password");
+    logged_core_service.update_entry(entry);
+    logged_core_service.save_vault().unwrap();
+
+    // The server serves the first version of the entry with the latest manifest
+    let first_save = saved_records.borrow()[0].clone();
+    let records = vec![first_save[0].clone(), first_save[1].clone(), saved_records.borrow()[1][1].clone()];
+
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(MockVaultManager::with_records(records));
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::CryptographyError(_)) => {}
+        _ => panic!("Test result should be: 'CryptographyError' error."),
+    }
+}
+
+#[test]
+fn should_not_log_in_to_vault_older_than_last_seen() {
+    // A-rrange
+
+    let mut core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(MockVaultManager::with_records(saved_vault_records()));
+
+    // This device already saw the second save, the server replays the first one as a whole
+    core_service.set_min_generation(2);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::VaultRolledBack) => {}
+        _ => panic!("Test result should be: 'VaultRolledBack' error."),
+    }
+}
+
+#[test]
+fn should_log_in_to_vault_last_seen() {
+    // A-rrange
+
+    let mut core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(MockVaultManager::with_records(saved_vault_records()));
+
+    core_service.set_min_generation(1);
+
+    // A-ct
+
+    let result = core_service.logging_in(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().get_generation(), 1);
+}
+
+#[test]
+fn should_not_keep_older_vault_on_conflict() {
+    // A-rrange
+
+    let base_records = saved_vault_records();
+    let mock_vault_manager = MockVaultManager {
+        remote_records: Some((base_records.clone(), REMOTE_REVISION.to_string())),
+        ..MockVaultManager::with_records(remote_vault_records(&base_records, add_remote_entry))
+    };
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+
+    // A-ct
+
+    let result = logged_core_service.save_vault();
+    let merge_result = logged_core_service.merge_remote_vault();
+
+    // A-ssert
+
+    assert!(matches!(result, Err(CoreError::VaultRolledBack)));
+    assert!(merge_result.is_err());
+    assert_eq!(logged_core_service.get_generation(), 2);
+}
+
+#[test]
+fn should_enable_sharing() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let saved_public_keys = Rc::clone(&mock_vault_manager.saved_public_keys);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    logged_core_service.enable_sharing().unwrap();
+    logged_core_service.enable_sharing().unwrap();
+
+    // A-ssert
+
+    // The key pair is saved in the vault once, its public key is published on every call
+    assert_eq!(saved_records.borrow().len(), 1);
+    assert_eq!(*saved_public_keys.borrow(), vec![PUBLIC_KEY.to_vec(), PUBLIC_KEY.to_vec()]);
+}
+
+#[test]
+fn should_share_entry() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let sent_shares = Rc::clone(&mock_vault_manager.sent_shares);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    let entry = logged_core_service.get_entries()[0].clone();
+
+    // A-ct
+
+    let result = logged_core_service.share_entry(&entry, RECIPIENT);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let shared_entry = SharedEntry::from(&entry).as_bytes().unwrap();
+
+    assert_eq!(
+        *sent_shares.borrow(),
+        vec![(RECIPIENT.to_string(), [RECIPIENT_PUBLIC_KEY, SEALED_PREFIX, &shared_entry].concat())]
+    );
+}
+
+#[test]
+fn should_not_share_entry_with_unknown_recipient() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let sent_shares = Rc::clone(&mock_vault_manager.sent_shares);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    let entry = logged_core_service.get_entries()[0].clone();
+
+    // A-ct
+
+    let result = logged_core_service.share_entry(&entry, "unknown");
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::VaultManagerError(_)) => assert!(sent_shares.borrow().is_empty()),
+        _ => panic!("Test result should be: 'VaultManagerError' error."),
+    }
+}
+
+#[test]
+fn should_pin_public_key_of_new_recipient() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    let entry = logged_core_service.get_entries()[0].clone();
+
+    let new_key = logged_core_service.lookup_public_key(RECIPIENT).unwrap();
+
+    // A-ct
+
+    logged_core_service.share_entry(&entry, RECIPIENT).unwrap();
+
+    // A-ssert
+
+    assert_eq!(new_key.status, KeyStatus::New);
+    assert_eq!(new_key.fingerprint, "fingerprint of recipient public key");
+    assert_eq!(logged_core_service.lookup_public_key(RECIPIENT).unwrap().status, KeyStatus::Pinned);
+    assert_eq!(saved_records.borrow().len(), 1);
+}
 
 #[test]
-fn should_create_account() {
+fn should_not_share_entry_if_public_key_changed() {
     // A-rrange
 
     let mock_vault_manager = MockVaultManager::new(false);
-    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockNoKeyCipher> =
+    let sent_shares = Rc::clone(&mock_vault_manager.sent_shares);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
         CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    let entry = logged_core_service.get_entries()[0].clone();
+    logged_core_service.pin_public_key(&recipient_key(b"old key")).unwrap();
 
     // A-ct
 
-    let result = core_service.create_account(USERNAME, PASSWORD);
+    let result = logged_core_service.share_entry(&entry, RECIPIENT);
 
     // A-ssert
 
-    assert!(result.is_ok());
-    assert_eq!(result.unwrap().get_entries().len(), 0);
+    match result {
+        Err(CoreError::PublicKeyChanged(username)) => assert_eq!(username, RECIPIENT),
+        _ => panic!("Test result should be: 'PublicKeyChanged' error."),
+    }
+
+    assert!(sent_shares.borrow().is_empty());
+    assert_eq!(
+        logged_core_service.lookup_public_key(RECIPIENT).unwrap().status,
+        KeyStatus::Changed { pinned_fingerprint: "fingerprint of old key".to_string() }
+    );
 }
 
 #[test]
-fn should_logging() {
+fn should_share_entry_once_changed_public_key_is_trusted() {
     // A-rrange
 
     let mock_vault_manager = MockVaultManager::new(false);
-    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockNoKeyCipher> =
+    let sent_shares = Rc::clone(&mock_vault_manager.sent_shares);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
         CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    let entry = logged_core_service.get_entries()[0].clone();
+    logged_core_service.pin_public_key(&recipient_key(b"old key")).unwrap();
 
     // A-ct
 
-    let result = core_service.logging_in(USERNAME, PASSWORD);
+    let changed_key = logged_core_service.lookup_public_key(RECIPIENT).unwrap();
+    logged_core_service.pin_public_key(&changed_key).unwrap();
+
+    let result = logged_core_service.share_entry(&entry, RECIPIENT);
 
     // A-ssert
 
     assert!(result.is_ok());
-    assert_eq!(result.unwrap().get_entries().len(), 0);
+    assert_eq!(sent_shares.borrow().len(), 1);
 }
 
 #[test]
-fn should_get_entries() {
+fn should_get_sharing_fingerprint_once_sharing_enabled() {
     // A-rrange
 
     let mock_vault_manager = MockVaultManager::new(false);
-    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockNoKeyCipher> =
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
         CoreService::new(mock_vault_manager);
-    let logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
 
     // A-ct
 
-    let result = logged_core_service.get_entries();
+    let before = logged_core_service.get_sharing_fingerprint();
+    logged_core_service.enable_sharing().unwrap();
+    let after = logged_core_service.get_sharing_fingerprint();
 
     // A-ssert
-    assert_eq!(result.len(), 0);
+
+    assert!(before.is_none());
+    assert_eq!(after.unwrap(), "fingerprint of public key");
 }
 
 #[test]
-fn should_add_entry() {
+fn should_get_received_shares() {
     // A-rrange
 
     let mock_vault_manager = MockVaultManager::new(false);
-    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockNoKeyCipher> =
+    let shared_entry = shared_entry();
+    mock_vault_manager.inbox.borrow_mut().extend([
+        sealed_share(1, [PUBLIC_KEY, SEALED_PREFIX, &shared_entry.as_bytes().unwrap()].concat()),
+        sealed_share(2, [RECIPIENT_PUBLIC_KEY, SEALED_PREFIX, &shared_entry.as_bytes().unwrap()].concat()),
+    ]);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
         CoreService::new(mock_vault_manager);
     let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.enable_sharing().unwrap();
 
     // A-ct
 
-    logged_core_service.add_entry(
-        TITLE.to_string(),
-        USERNAME.to_string(),
-        PASSWORD.to_string(),
-    );
-
-    let result = logged_core_service.get_entries();
+    let result = logged_core_service.get_received_shares().unwrap();
 
     // A-ssert
-    assert_eq!(result.len(), 1);
-    assert_eq!(result.get(0).unwrap().title, TITLE);
-    assert_eq!(result.get(0).unwrap().username, USERNAME);
-    assert_eq!(result.get(0).unwrap().password, PASSWORD);
+
+    // The second share was sealed for another key
+    assert_eq!(result, vec![ReceivedShare { id: 1, sender: RECIPIENT.to_string(), entry: shared_entry }]);
 }
 
 #[test]
-fn should_generate_password() {
+fn should_not_get_received_shares_if_sharing_not_enabled() {
     // A-rrange
 
     let mock_vault_manager = MockVaultManager::new(false);
-    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockNoKeyCipher> =
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
         CoreService::new(mock_vault_manager);
     let logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
-    let restrictions = PasswordRestriction { length: 18, lower_case: true, upper_case: true, numbers: true, special_characters: true };
 
     // A-ct
 
-    let result = logged_core_service.generate_password(&restrictions);
+    let result = logged_core_service.get_received_shares();
+
+    // A-ssert
+
+    match result {
+        Err(CoreError::InternalError(error)) => assert_eq!(error, "Sharing isn't enabled for this vault."),
+        _ => panic!("Test result should be: 'InternalError' error."),
+    }
+}
+
+#[test]
+fn should_accept_share() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let inbox = Rc::clone(&mock_vault_manager.inbox);
+    inbox.borrow_mut().push(sealed_share(1, vec![]));
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    let share = ReceivedShare { id: 1, sender: RECIPIENT.to_string(), entry: shared_entry() };
+
+    // A-ct
+
+    let result = logged_core_service.accept_share(&share);
 
     // A-ssert
+
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), vec![42]);
+
+    let entries = logged_core_service.get_entries();
+
+    assert_eq!(entries.len(), 1);
+    assert_eq!(entries[0].title, TITLE);
+    assert_eq!(saved_records.borrow().len(), 1);
+    assert!(inbox.borrow().is_empty());
 }
 
 #[test]
-fn should_save_vault() {
+fn should_not_add_share_twice_if_accepted_again_after_saving_offline() {
+    // A-rrange
+
+    let mock_vault_manager = MockVaultManager::new(false);
+    let offline = Rc::clone(&mock_vault_manager.offline);
+    let inbox = Rc::clone(&mock_vault_manager.inbox);
+    inbox.borrow_mut().push(sealed_share(1, vec![]));
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    let share = ReceivedShare { id: 1, sender: RECIPIENT.to_string(), entry: shared_entry() };
+    offline.set(true);
+
+    // A-ct
+
+    let offline_result = logged_core_service.accept_share(&share);
+
+    // A-ssert
 
+    assert!(matches!(offline_result, Err(CoreError::SavedOffline)));
+    assert!(inbox.borrow().is_empty());
+    assert_eq!(logged_core_service.get_entries().len(), 1);
+}
+
+#[test]
+fn should_decline_share() {
     // A-rrange
 
     let mock_vault_manager = MockVaultManager::new(false);
-    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockNoKeyCipher> =
+    let inbox = Rc::clone(&mock_vault_manager.inbox);
+    inbox.borrow_mut().extend([sealed_share(1, vec![]), sealed_share(2, vec![])]);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
         CoreService::new(mock_vault_manager);
     let logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
-    
+
     // A-ct
 
-    let result = logged_core_service.save_vault();
+    let result = logged_core_service.decline_share(1);
 
     // A-ssert
+
     assert!(result.is_ok());
+    assert_eq!(*inbox.borrow(), vec![sealed_share(2, vec![])]);
+    assert!(logged_core_service.get_entries().is_empty());
 }
 
 #[ignore]
@@ -141,7 +1748,7 @@ fn should_copy_to_clipboard() {
     // A-rrange
 
     let mock_vault_manager = MockVaultManager::new(false);
-    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockNoKeyCipher> =
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
         CoreService::new(mock_vault_manager);
     let logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
     let test = "test";
@@ -158,13 +1765,196 @@ fn should_copy_to_clipboard() {
     assert_eq!(ctx.get_contents().unwrap(), test);
 }
 
+fn shared_entry() -> SharedEntry {
+    SharedEntry { title: TITLE.to_string(), username: USERNAME.to_string(), password: PASSWORD.to_string() }
+}
+
+fn recipient_key(public_key: &[u8]) -> RecipientKey {
+    RecipientKey {
+        username: RECIPIENT.to_string(),
+        public_key: public_key.to_vec(),
+        fingerprint: String::new(),
+        status: KeyStatus::New,
+    }
+}
+
+fn sealed_share(id: u64, sealed_entry: Vec<u8>) -> SealedShare {
+    SealedShare { id, sender: RECIPIENT.to_string(), sealed_entry }
+}
+
+/// Records uploaded by the first save of a vault with one entry
+fn saved_vault_records() -> Vec<CryptedRecord> {
+    let mock_vault_manager = MockVaultManager::new(false);
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let mut logged_core_service = core_service.create_account(USERNAME, PASSWORD).unwrap();
+    logged_core_service.add_entry(TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+    logged_core_service.save_vault().unwrap();
+
+    saved_records.borrow()[0].clone()
+}
+
+/// Records uploaded by the save of another session that changed the vault
+fn changed_vault_records(base_records: &[CryptedRecord], change_vault: impl FnOnce(&mut TestLoggedCore)) -> Vec<CryptedRecord> {
+    let mock_vault_manager = MockVaultManager::with_records(base_records.to_vec());
+    let saved_records = Rc::clone(&mock_vault_manager.saved_records);
+    let core_service: CoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher> =
+        CoreService::new(mock_vault_manager);
+
+    let mut logged_core_service = core_service.logging_in(USERNAME, PASSWORD).unwrap();
+    change_vault(&mut logged_core_service);
+    logged_core_service.save_vault().unwrap();
+
+    saved_records.borrow()[0].clone()
+}
+
+/// Records of the vault once another device changed it
+fn remote_vault_records(base_records: &[CryptedRecord], change_vault: impl FnOnce(&mut TestLoggedCore)) -> Vec<CryptedRecord> {
+    let changed_records = changed_vault_records(base_records, change_vault);
+
+    let mut records: Vec<CryptedRecord> = base_records
+        .iter()
+        .filter(|record| !changed_records.iter().any(|changed_record| changed_record.id == record.id))
+        .cloned()
+        .collect();
+    records.extend(changed_records);
+
+    records
+}
+
+fn add_remote_entry(logged_core_service: &mut TestLoggedCore) {
+    logged_core_service.add_entry(REMOTE_TITLE.to_string(), USERNAME.to_string(), PASSWORD.to_string());
+}
+
+fn entry_record(entry: &Entry) -> String {
+    format!("entry/{}", entry.get_id())
+}
+
+fn record_ids(records: &[CryptedRecord]) -> Vec<&str> {
+    records.iter().map(|record| record.id.as_str()).collect()
+}
+
+fn encrypted_with(records: &[CryptedRecord], key_id: u8) -> bool {
+    records.iter().all(|record| record.content.first() == Some(&key_id))
+}
+
+const DATA_KEY: [u8; 32] = [7; 32];
+const ENCRYPTION_KEY: [u8; 64] = [42; 64];
+const WRAPPED_PREFIX: &[u8] = b"wrapped:";
+const RECOVERY_KEY: [u8; 20] = [9; 20];
+const TOKEN_PREFIX: &[u8] = b"token:";
+const DERIVATION_PREFIX: &[u8] = b"derivation:";
+const KEY_FILE: &[u8] = b"key file";
+const MIXED_PREFIX: &[u8] = b"mixed:";
+const PRIVATE_KEY: &[u8] = b"private key";
+const PUBLIC_KEY: &[u8] = b"public key";
+const RECIPIENT: &str = "recipient";
+const RECIPIENT_PUBLIC_KEY: &[u8] = b"recipient public key";
+const SEALED_PREFIX: &[u8] = b"sealed:";
+const SERVER_KEY: &[u8] = b"server key";
+const EXISTING_USERNAME: &str = "existing username";
+const WRONG_PASSWORD: &str = "wrong password";
+const LOGIN_REVISION: &str = "1";
+const REMOTE_REVISION: &str = "2";
+const SAVED_REVISION: &str = "3";
+const REMOTE_TITLE: &str = "remote title";
+const LOCAL_PASSWORD: &str = "local password";
+const REMOTE_PASSWORD: &str = "remote password";
+
+type TestLoggedCore = LoggedCoreService<MockVaultManager, MockPasswordGenerator, MockUsernameGenerator, MockNoKeyCipher>;
+
+// Recovery token and wrapped key of every saved recovery key
+type SavedRecoveryKeys = Rc<RefCell<Vec<(Vec<u8>, Vec<u8>)>>>;
+// Recipient and sealed entry of every sent share
+type SentShares = Rc<RefCell<Vec<(String, Vec<u8>)>>>;
+// Records of every save, one batch per upload
+type SavedRecordBatches = Rc<RefCell<Vec<Vec<CryptedRecord>>>>;
+
 struct MockVaultManager {
     mock_in_error: bool,
+    content: Vec<u8>,
+    records: Vec<CryptedRecord>,
+    wrapped_key: Vec<u8>,
+    pinned_server_key: Option<Vec<u8>>,
+    outdated_key_stretching: bool,
+    // Shared with the test, the manager itself is moved into the core
+    saved_vaults: Rc<RefCell<Vec<Vec<u8>>>>,
+    saved_records: SavedRecordBatches,
+    saved_wrapped_keys: Rc<RefCell<Vec<Vec<u8>>>>,
+    saved_recovery_keys: SavedRecoveryKeys,
+    saved_public_keys: Rc<RefCell<Vec<Vec<u8>>>>,
+    sent_shares: SentShares,
+    inbox: Rc<RefCell<Vec<SealedShare>>>,
+    // Set by the test once logged in, cleared by a new login
+    session_expired: Rc<Cell<bool>>,
+    logged_out: Rc<Cell<bool>>,
+    // Saved by another device, the saves based on another revision conflict
+    remote_records: Option<(Vec<CryptedRecord>, String)>,
+    // Saved offline on top of `records`
+    pending_records: Vec<CryptedRecord>,
+    // Set by the test, the saves are then kept offline
+    offline: Rc<Cell<bool>>,
 }
 
 impl MockVaultManager {
     fn new(mock_in_error: bool) -> Self {
-        Self { mock_in_error }
+        Self::with_vault(mock_in_error, vec![], vec![])
+    }
+
+    fn with_vault(mock_in_error: bool, content: Vec<u8>, wrapped_key: Vec<u8>) -> Self {
+        Self {
+            mock_in_error,
+            content,
+            records: vec![],
+            wrapped_key,
+            pinned_server_key: None,
+            outdated_key_stretching: false,
+            saved_vaults: Rc::new(RefCell::new(vec![])),
+            saved_records: Rc::new(RefCell::new(vec![])),
+            saved_wrapped_keys: Rc::new(RefCell::new(vec![])),
+            saved_recovery_keys: Rc::new(RefCell::new(vec![])),
+            saved_public_keys: Rc::new(RefCell::new(vec![])),
+            sent_shares: Rc::new(RefCell::new(vec![])),
+            inbox: Rc::new(RefCell::new(vec![])),
+            session_expired: Rc::new(Cell::new(false)),
+            logged_out: Rc::new(Cell::new(false)),
+            remote_records: None,
+            pending_records: vec![],
+            offline: Rc::new(Cell::new(false)),
+        }
+    }
+
+    /// Vault already saved as records, with the data key wrapped by the encryption key
+    fn with_records(records: Vec<CryptedRecord>) -> Self {
+        Self {
+            records,
+            ..Self::with_vault(false, vec![], [WRAPPED_PREFIX, &DATA_KEY].concat())
+        }
+    }
+
+    fn crypted_vault(&self) -> crate::vault::vault_error::Result<CryptedVault<U64>> {
+        if self.mock_in_error {
+            return Err(VaultError::Internal("mock error".to_string()));
+        }
+
+        // The mock server always proves the same key
+        if self.pinned_server_key.as_ref().is_some_and(|server_key| server_key != SERVER_KEY) {
+            return Err(VaultError::ServerKeyChanged);
+        }
+
+        Ok(CryptedVault {
+            content: self.content.clone(),
+            records: self.records.clone(),
+            wrapped_key: self.wrapped_key.clone(),
+            server_key: SERVER_KEY.to_vec(),
+            encryption_key: Key::new(
+                GenericArray::<u8, U64>::from_slice(&ENCRYPTION_KEY).clone(),
+            ),
+            revision: Some(LOGIN_REVISION.to_string()),
+            pending_records: self.pending_records.clone(),
+        })
     }
 }
 
@@ -173,51 +1963,209 @@ impl VaultManager for MockVaultManager {
 
     fn create(
         &mut self,
+        username: &str,
         _: &str,
+    ) -> crate::vault::vault_error::Result<CryptedVault<Self::KeySize>> {
+        if username == EXISTING_USERNAME {
+            return Err(VaultError::UserExists);
+        }
+
+        self.crypted_vault()
+    }
+
+    fn retrieve(
+        &mut self,
         _: &str,
+        password: &str,
     ) -> crate::vault::vault_error::Result<CryptedVault<Self::KeySize>> {
+        if password == WRONG_PASSWORD {
+            return Err(VaultError::InvalidCredentials);
+        }
+
+        self.crypted_vault()
+    }
+
+    fn reauthenticate(&mut self, _: &str, password: &str) -> crate::vault::vault_error::Result<()> {
+        if password == WRONG_PASSWORD {
+            return Err(VaultError::InvalidCredentials);
+        }
+
+        self.session_expired.set(false);
+
+        Ok(())
+    }
+
+    fn logout(&mut self) -> crate::vault::vault_error::Result<()> {
+        self.logged_out.set(true);
+
+        Ok(())
+    }
+
+    fn pin_server_key(&mut self, server_key: Option<Vec<u8>>) {
+        self.pinned_server_key = server_key;
+    }
+
+    fn require_bound_identifiers(&mut self, _required: bool) {}
+
+    fn identifiers_bound(&self) -> bool {
+        !self.logged_out.get()
+    }
+
+    fn needs_reregistration(&self) -> bool {
+        self.outdated_key_stretching
+    }
+
+    fn save(&self, vault: Vec<u8>, revision: Option<&str>) -> crate::vault::vault_error::Result<Option<String>> {
         match self.mock_in_error {
             true => Err(VaultError::Internal("mock error".to_string())),
-            false => Ok(CryptedVault {
-                content: vec![],
-                encryption_key: Key::new(
-                    GenericArray::<u8, Self::KeySize>::from_slice(&[42; 64]).clone(),
-                ),
-            }),
+            false => {
+                self.saved_vaults.borrow_mut().push(vault);
+                Ok(revision.map(str::to_string))
+            }
         }
     }
 
-    fn retrieve(
+    fn save_records(
+        &self,
+        records: Vec<CryptedRecord>,
+        revision: Option<&str>,
+    ) -> crate::vault::vault_error::Result<Option<String>> {
+        if self.logged_out.get() {
+            return Err(VaultError::NotLoggedIn("mock logged out".to_string()));
+        }
+
+        if self.session_expired.get() {
+            return Err(VaultError::SessionExpired);
+        }
+
+        if self.offline.get() {
+            return Err(VaultError::SavedOffline);
+        }
+
+        if let Some((_, remote_revision)) = &self.remote_records
+            && revision != Some(remote_revision.as_str())
+        {
+            return Err(VaultError::Conflict);
+        }
+
+        match self.mock_in_error {
+            true => Err(VaultError::Internal("mock error".to_string())),
+            false => {
+                self.saved_records.borrow_mut().push(records);
+                Ok(Some(SAVED_REVISION.to_string()))
+            }
+        }
+    }
+
+    fn fetch_records(&self) -> crate::vault::vault_error::Result<(Vec<CryptedRecord>, Option<String>)> {
+        match &self.remote_records {
+            Some((records, revision)) => Ok((records.clone(), Some(revision.clone()))),
+            None => Ok((self.records.clone(), Some(LOGIN_REVISION.to_string()))),
+        }
+    }
+
+    fn save_wrapped_key(&self, wrapped_key: Vec<u8>) -> crate::vault::vault_error::Result<()> {
+        match self.mock_in_error {
+            true => Err(VaultError::Internal("mock error".to_string())),
+            false => {
+                self.saved_wrapped_keys.borrow_mut().push(wrapped_key);
+                Ok(())
+            }
+        }
+    }
+
+    fn change_password(
         &mut self,
         _: &str,
+        old_password: &str,
         _: &str,
-    ) -> crate::vault::vault_error::Result<CryptedVault<Self::KeySize>> {
+        wrap_data_key: &dyn Fn(&[u8]) -> crate::vault::vault_error::Result<Vec<u8>>,
+    ) -> crate::vault::vault_error::Result<()> {
+        if self.mock_in_error {
+            return Err(VaultError::ExchangeFailed("mock error".to_string()));
+        }
+
+        if old_password != PASSWORD {
+            return Err(VaultError::InvalidCredentials);
+        }
+
+        let wrapped_key = wrap_data_key(&[43; 64])?;
+        self.saved_wrapped_keys.borrow_mut().push(wrapped_key);
+
+        Ok(())
+    }
+
+    fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> crate::vault::vault_error::Result<()> {
         match self.mock_in_error {
             true => Err(VaultError::Internal("mock error".to_string())),
-            false => Ok(CryptedVault {
-                content: vec![],
-                encryption_key: Key::new(
-                    GenericArray::<u8, Self::KeySize>::from_slice(&[42; 64]).clone(),
-                ),
-            }),
+            false => {
+                self.saved_recovery_keys.borrow_mut().push((recovery_token, wrapped_key));
+                Ok(())
+            }
+        }
+    }
+
+    fn recover(
+        &mut self,
+        _: &str,
+        recovery_token: &[u8],
+        _: &str,
+        rewrap_data_key: &RewrapDataKey<'_>,
+    ) -> crate::vault::vault_error::Result<()> {
+        if self.mock_in_error || recovery_token != [TOKEN_PREFIX, &RECOVERY_KEY].concat() {
+            return Err(VaultError::ExchangeFailed("mock error".to_string()));
         }
+
+        let wrapped_key = rewrap_data_key(&[WRAPPED_PREFIX, &DATA_KEY].concat(), &[43; 64])?;
+        self.saved_wrapped_keys.borrow_mut().push(wrapped_key);
+
+        Ok(())
     }
 
-    fn save(&self, _: Vec<u8>) -> crate::vault::vault_error::Result<()> {
+    fn save_public_key(&self, public_key: Vec<u8>) -> crate::vault::vault_error::Result<()> {
         match self.mock_in_error {
             true => Err(VaultError::Internal("mock error".to_string())),
-            false => Ok(()),
+            false => {
+                self.saved_public_keys.borrow_mut().push(public_key);
+                Ok(())
+            }
+        }
+    }
+
+    fn get_public_key(&self, username: &str) -> crate::vault::vault_error::Result<Vec<u8>> {
+        match username {
+            RECIPIENT => Ok(RECIPIENT_PUBLIC_KEY.to_vec()),
+            _ => Err(VaultError::NotFound),
         }
     }
+
+    fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> crate::vault::vault_error::Result<()> {
+        self.sent_shares.borrow_mut().push((recipient.to_string(), sealed_entry));
+
+        Ok(())
+    }
+
+    fn get_shares(&self) -> crate::vault::vault_error::Result<Vec<SealedShare>> {
+        Ok(self.inbox.borrow().clone())
+    }
+
+    fn delete_share(&self, id: u64) -> crate::vault::vault_error::Result<()> {
+        self.inbox.borrow_mut().retain(|share| share.id != id);
+
+        Ok(())
+    }
 }
 
+/// "Encrypts" a vault into the first byte of its key, decryption fails with any other key.
+/// A record is kept in clear after the key byte and its associated data.
 struct MockCryptography {
     mock_in_error: bool,
+    key_id: u8,
 }
 
 impl MockCryptography {
-    fn new(mock_in_error: bool) -> Self {
-        Self { mock_in_error }
+    fn new(mock_in_error: bool, key_id: u8) -> Self {
+        Self { mock_in_error, key_id }
     }
 }
 
@@ -228,27 +2176,137 @@ impl Cryptography for MockCryptography {
     ) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
         match self.mock_in_error {
             true => Err(CryptographyError::EncryptionError("mock error".to_string())),
-            false => Ok(vec![42]),
+            false => Ok(vec![self.key_id]),
         }
     }
 
-    fn decrypt(&self, _: &[u8]) -> crate::cryptography::cryptography_error::Result<UncryptedVault> {
-        match self.mock_in_error {
+    fn decrypt(&self, vault: &[u8]) -> crate::cryptography::cryptography_error::Result<UncryptedVault> {
+        match self.mock_in_error || vault != [self.key_id] {
             true => Err(CryptographyError::DecryptionError("mock error".to_string())),
             false => Ok(UncryptedVault::new()),
         }
     }
+
+    fn encrypt_record(&self, record: &[u8], aad: &[u8]) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
+        match self.mock_in_error {
+            true => Err(CryptographyError::EncryptionError("mock error".to_string())),
+            false => Ok([&[self.key_id], aad, record].concat()),
+        }
+    }
+
+    fn decrypt_record(&self, crypted_record: &[u8], aad: &[u8]) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
+        match crypted_record.strip_prefix([&[self.key_id], aad].concat().as_slice()) {
+            Some(record) if !self.mock_in_error => Ok(record.to_vec()),
+            _ => Err(CryptographyError::DecryptionError("mock error".to_string())),
+        }
+    }
 }
 
 struct MockNoKeyCipher;
 
 impl NoKeyCipher for MockNoKeyCipher {
     type Crypto = MockCryptography;
+    type Keys = MockKeys;
 
     fn create_cipher_from_key(
-        _: &[u8],
+        key: &[u8],
     ) -> crate::cryptography::cryptography_error::Result<Self::Crypto> {
-        Ok(MockCryptography::new(false))
+        Ok(MockCryptography::new(false, key[0]))
+    }
+
+    fn generate_data_key() -> Vec<u8> {
+        DATA_KEY.to_vec()
+    }
+
+    /// Only a key mixed with a key file shows in the wrapped key, unwrapping needs the same one
+    fn wrap_data_key(key: &[u8], data_key: &[u8]) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
+        match key.starts_with(MIXED_PREFIX) {
+            true => Ok([key, WRAPPED_PREFIX, data_key].concat()),
+            false => Ok([WRAPPED_PREFIX, data_key].concat()),
+        }
+    }
+
+    fn unwrap_data_key(key: &[u8], wrapped_data_key: &[u8]) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
+        let wrapped_data_key = match key.starts_with(MIXED_PREFIX) {
+            true => wrapped_data_key.strip_prefix(key),
+            false => Some(wrapped_data_key),
+        };
+
+        match wrapped_data_key.and_then(|wrapped_data_key| wrapped_data_key.strip_prefix(WRAPPED_PREFIX)) {
+            Some(data_key) => Ok(data_key.to_vec()),
+            None => Err(CryptographyError::DecryptionError("mock error".to_string())),
+        }
+    }
+}
+
+struct MockKeys;
+
+impl KeyManagement for MockKeys {
+    fn generate_recovery_key() -> Vec<u8> {
+        RECOVERY_KEY.to_vec()
+    }
+
+    fn recovery_token(recovery_key: &[u8]) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
+        Ok([TOKEN_PREFIX, recovery_key].concat())
+    }
+
+    fn derivation_key(data_key: &[u8]) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
+        Ok([DERIVATION_PREFIX, data_key].concat())
+    }
+
+    /// Every share holds the whole secret, enough to check what goes through the core
+    fn split_secret(
+        secret: &[u8],
+        _: u8,
+        share_count: u8,
+    ) -> crate::cryptography::cryptography_error::Result<Vec<SecretShare>> {
+        Ok((1..=share_count)
+            .map(|index| SecretShare {
+                index,
+                mnemonic: format!("share {index}"),
+                hex: String::from_utf8_lossy(secret).to_string(),
+            })
+            .collect())
+    }
+
+    fn combine_shares(shares: &[String]) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
+        match shares {
+            [first, _, ..] => Ok(first.as_bytes().to_vec()),
+            _ => Err(CryptographyError::SecretSharingError("mock error".to_string())),
+        }
+    }
+
+    fn generate_key_file() -> Vec<u8> {
+        KEY_FILE.to_vec()
+    }
+
+    fn mix_key_file(_: &[u8], key_file: &[u8]) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
+        Ok([MIXED_PREFIX, key_file].concat())
+    }
+
+    fn generate_sharing_key_pair() -> SharingKeyPair {
+        SharingKeyPair { private_key: PRIVATE_KEY.to_vec(), public_key: PUBLIC_KEY.to_vec() }
+    }
+
+    /// The public key shows in the sealed data, only `PRIVATE_KEY` opens what is sealed for `PUBLIC_KEY`
+    fn seal(public_key: &[u8], data: &[u8]) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
+        Ok([public_key, SEALED_PREFIX, data].concat())
+    }
+
+    fn open_sealed(private_key: &[u8], sealed_data: &[u8]) -> crate::cryptography::cryptography_error::Result<Vec<u8>> {
+        let data = match private_key == PRIVATE_KEY {
+            true => sealed_data.strip_prefix(PUBLIC_KEY).and_then(|data| data.strip_prefix(SEALED_PREFIX)),
+            false => None,
+        };
+
+        match data {
+            Some(data) => Ok(data.to_vec()),
+            None => Err(CryptographyError::SharingError("mock error".to_string())),
+        }
+    }
+
+    fn fingerprint(public_key: &[u8]) -> String {
+        format!("fingerprint of {}", String::from_utf8_lossy(public_key))
     }
 }
 
@@ -258,4 +2316,24 @@ impl PasswordGenerator for MockPasswordGenerator {
     fn generate_password(_: &PasswordRestriction) -> Result<Vec<u8>, String> {
         Ok(vec![42])
     }
+
+    fn entropy(_: &PasswordRestriction) -> f64 {
+        42.0
+    }
+
+    fn derive_password(master_secret: &[u8], profile: &DerivationProfile) -> Result<Vec<u8>, String> {
+        Ok([master_secret, profile.site.as_bytes()].concat())
+    }
 }
+
+struct MockUsernameGenerator;
+
+impl UsernameGenerator for MockUsernameGenerator {
+    fn generate_username(_: &UsernameRestriction) -> Result<String, String> {
+        Ok(USERNAME.to_string())
+    }
+
+    fn generate_email_alias(_: &str, _: &EmailAliasKind) -> Result<String, String> {
+        Ok("john+alias@example.com".to_string())
+    }
+}
\ No newline at end of file
@@ -0,0 +1,2 @@
+pub mod email_alias;
+pub mod username_restriction;
\ No newline at end of file
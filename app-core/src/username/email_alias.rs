@@ -0,0 +1,9 @@
+/// How an alias is built from the base address
+/// `PlusAddressing` keeps the mailbox and adds a tag (`john+tag@example.com`)
+/// `CatchAll` replaces the mailbox entirely (`tag@example.com`), the domain must accept any mailbox
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum EmailAliasKind {
+    #[default]
+    PlusAddressing,
+    CatchAll
+}
\ No newline at end of file
@@ -0,0 +1,7 @@
+#[derive(Clone, Debug, Default)]
+pub struct UsernameRestriction {
+    pub word_count: usize,
+    pub separator: Option<char>,
+    pub capitalize: bool,
+    pub digits: usize
+}
\ No newline at end of file
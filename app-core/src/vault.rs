@@ -1,3 +1,6 @@
+pub mod crypted_record;
 pub mod crypted_vault;
+pub mod emergency_kit;
+pub mod share;
 pub mod vault_error;
-pub mod key;
\ No newline at end of file
+pub mod key;
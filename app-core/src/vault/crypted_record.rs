@@ -0,0 +1,21 @@
+/// Encrypted part of the vault, stored by the server under its id
+#[derive(PartialEq, Debug, Clone)]
+pub struct CryptedRecord {
+    pub id: String,
+    /// Empty once the record was deleted, an encrypted record is never empty
+    pub content: Vec<u8>,
+}
+
+impl CryptedRecord {
+    pub fn new(id: String, content: Vec<u8>) -> Self {
+        Self { id, content }
+    }
+
+    pub fn deleted(id: String) -> Self {
+        Self { id, content: vec![] }
+    }
+
+    pub fn is_deleted(&self) -> bool {
+        self.content.is_empty()
+    }
+}
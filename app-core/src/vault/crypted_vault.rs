@@ -1,17 +1,41 @@
 use generic_array::ArrayLength;
 
-use crate::vault::key::Key;
+use crate::vault::{crypted_record::CryptedRecord, key::Key};
 
 pub struct CryptedVault<N: ArrayLength<u8>> {
+    /// Whole vault as one blob, only left by versions before the records
     pub content: Vec<u8>,
-    pub encryption_key: Key<N>
+    /// Every record and the manifest listing them, empty until the vault is first saved as records
+    pub records: Vec<CryptedRecord>,
+    /// Data key wrapped with the encryption key, empty for vaults created before envelope encryption
+    pub wrapped_key: Vec<u8>,
+    /// Static public key the server proved during the login, to pin for the next ones
+    pub server_key: Vec<u8>,
+    pub encryption_key: Key<N>,
+    /// Revision (ETag) of the vault on the server, `None` if the server doesn't give one
+    pub revision: Option<String>,
+    /// Records saved offline on top of `records` and not uploaded yet, a deleted record has an empty content
+    pub pending_records: Vec<CryptedRecord>
 }
 
 impl<N: ArrayLength<u8>> CryptedVault<N> {
-    pub fn new(content: Vec<u8>, encryption_key: Key<N>) -> Self {
+    pub fn new(
+        content: Vec<u8>,
+        records: Vec<CryptedRecord>,
+        wrapped_key: Vec<u8>,
+        server_key: Vec<u8>,
+        encryption_key: Key<N>,
+        revision: Option<String>,
+        pending_records: Vec<CryptedRecord>,
+    ) -> Self {
         Self {
             content,
-            encryption_key
+            records,
+            wrapped_key,
+            server_key,
+            encryption_key,
+            revision,
+            pending_records
         }
     }
 }
\ No newline at end of file
@@ -0,0 +1,71 @@
+use crate::cryptography::{recovery_key::RecoveryKey, secret_share::SecretShare};
+
+/// Printable sheet to keep offline, everything needed to get back into a vault without the master password
+#[derive(Debug)]
+pub struct EmergencyKit {
+    username: String,
+    server_url: String,
+    recovery_key: RecoveryKey,
+}
+
+impl EmergencyKit {
+    pub fn new(username: &str, server_url: &str, recovery_key: RecoveryKey) -> Self {
+        Self {
+            username: username.to_string(),
+            server_url: server_url.to_string(),
+            recovery_key,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "FERRIS VAULT - EMERGENCY KIT\n\
+             \n\
+             Keep this sheet offline, anyone holding it can take over the vault.\n\
+             \n\
+             Username:     {}\n\
+             Server:       {}\n\
+             Recovery key: {}\n\
+             \n\
+             Forgot the master password? On the home screen select the vault, press <R>,\n\
+             type the recovery key and choose a new master password.\n\
+             Creating a new recovery key makes this one useless.\n",
+            self.username, self.server_url, self.recovery_key
+        )
+    }
+}
+
+/// Sheet for one holder of a recovery key share, useless on its own
+#[derive(Debug)]
+pub struct RecoveryShareSheet {
+    username: String,
+    share: SecretShare,
+    threshold: u8,
+    share_count: u8,
+}
+
+impl RecoveryShareSheet {
+    pub fn new(username: &str, share: SecretShare, threshold: u8, share_count: u8) -> Self {
+        Self {
+            username: username.to_string(),
+            share,
+            threshold,
+            share_count,
+        }
+    }
+
+    pub fn to_text(&self) -> String {
+        format!(
+            "FERRIS VAULT - RECOVERY KEY SHARE {} OF {}\n\
+             \n\
+             Username: {}\n\
+             \n\
+             Words: {}\n\
+             Hex:   {}\n\
+             \n\
+             Any {} shares give the recovery key back, fewer reveal nothing about it.\n\
+             On the recovery screen press <CTRL+S> and type one share per line, words or hex.\n",
+            self.share.index, self.share_count, self.username, self.share.mnemonic, self.share.hex, self.threshold
+        )
+    }
+}
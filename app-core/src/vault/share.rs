@@ -0,0 +1,73 @@
+use bincode::{Decode, Encode, config};
+
+use crate::{
+    cryptography::cryptography_error::{CryptographyError, Result},
+    uncrypted_vault::Entry,
+};
+
+/// What is sealed to the recipient, the id of the entry stays in the vault of the sender
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub struct SharedEntry {
+    pub title: String,
+    pub username: String,
+    pub password: String,
+}
+
+impl SharedEntry {
+    pub fn as_bytes(&self) -> Result<Vec<u8>> {
+        bincode::encode_to_vec(self, config::standard())
+            .map_err(|error| CryptographyError::BinaryEncodingError(error.to_string()))
+    }
+
+    pub fn decode(data: &[u8]) -> Result<Self> {
+        let (shared_entry, _) = bincode::decode_from_slice(data, config::standard())
+            .map_err(|error| CryptographyError::BinaryDecodingError(error.to_string()))?;
+
+        Ok(shared_entry)
+    }
+}
+
+impl From<&Entry> for SharedEntry {
+    fn from(entry: &Entry) -> Self {
+        Self {
+            title: entry.title.clone(),
+            username: entry.username.clone(),
+            password: entry.password.clone(),
+        }
+    }
+}
+
+/// Share as stored in the inbox of the server, only the recipient can open it
+#[derive(PartialEq, Debug, Clone)]
+pub struct SealedShare {
+    pub id: u64,
+    pub sender: String,
+    pub sealed_entry: Vec<u8>,
+}
+
+/// Opened share waiting to be accepted into the vault or declined
+#[derive(PartialEq, Debug, Clone)]
+pub struct ReceivedShare {
+    pub id: u64,
+    /// Named by the server, the sealed entry itself doesn't prove who sent it
+    pub sender: String,
+    pub entry: SharedEntry,
+}
+
+/// Public key the server has for a recipient, with what the vault knows about it
+#[derive(PartialEq, Debug, Clone)]
+pub struct RecipientKey {
+    pub username: String,
+    pub public_key: Vec<u8>,
+    pub fingerprint: String,
+    pub status: KeyStatus,
+}
+
+#[derive(PartialEq, Debug, Clone)]
+pub enum KeyStatus {
+    /// Never seen, it is pinned when the first entry is shared with it
+    New,
+    Pinned,
+    /// The server answered another key than the pinned one, nothing is shared until it is trusted again
+    Changed { pinned_fingerprint: String },
+}
@@ -4,6 +4,17 @@ pub enum VaultError {
     AlreadyLoggedIn(String),
     NotLoggedIn(String),
     NotFound,
+    ServerKeyChanged,
+    InvalidCredentials,
+    UserExists,
+    SessionExpired,
+    // The vault changed on the server since the revision the save was based on
+    Conflict,
+    // The server couldn't be reached, the records were kept in the offline cache instead
+    SavedOffline,
+    // Seconds to wait when the server tells them
+    RateLimited(Option<u64>),
+    ServerUnavailable(String),
     Internal(String)
 }
 
@@ -15,6 +26,15 @@ impl std::fmt::Display for VaultError {
             VaultError::AlreadyLoggedIn(message) => write!(formatter, "Already logged in : {}", message),
             VaultError::NotLoggedIn(message) => write!(formatter, "Not logged in : {}", message),
             VaultError::NotFound => write!(formatter, "Vault not found"),
+            VaultError::ServerKeyChanged => write!(formatter, "The key of the server changed since it was pinned"),
+            VaultError::InvalidCredentials => write!(formatter, "Wrong username or password"),
+            VaultError::UserExists => write!(formatter, "This username is already taken"),
+            VaultError::SessionExpired => write!(formatter, "The session expired, log in again"),
+            VaultError::Conflict => write!(formatter, "The vault was changed by another device"),
+            VaultError::SavedOffline => write!(formatter, "The server can't be reached, the vault was saved offline"),
+            VaultError::RateLimited(Some(seconds)) => write!(formatter, "Too many attempts, retry in {} seconds", seconds),
+            VaultError::RateLimited(None) => write!(formatter, "Too many attempts, retry later"),
+            VaultError::ServerUnavailable(message) => write!(formatter, "The server is unavailable : {}", message),
             VaultError::Internal(message) => write!(formatter, "Internal error : {}", message)
         }
     }
@@ -0,0 +1,105 @@
+use aes_gcm_siv::{
+    AeadCore, Key, KeyInit, Nonce,
+    aead::{Aead, OsRng, Payload},
+};
+use app_core::{
+    cryptography::{
+        cryptography_error::{CryptographyError, Result},
+        uncrypted_vault::UncryptedVault,
+    },
+    ports::cryptography::{Cryptography, NoKeyCipher},
+};
+
+use crate::{
+    envelope,
+    key_hierarchy::{KeyHierarchy, KeyPurpose},
+    key_manager::KeyManager,
+};
+
+pub(crate) const NONCE_LENGTH: usize = 12;
+
+const TOO_SHORT_ERROR: &str = "the data is too short to contain a nonce.";
+
+#[derive(Debug, Default)]
+pub struct NoKeyAes256GcmSiv;
+
+impl NoKeyCipher for NoKeyAes256GcmSiv {
+
+    type Crypto = Aes256GcmSiv;
+    type Keys = KeyManager;
+
+    /// Always derived, even from 32 bytes, so this backend never shares its key with XChaCha20Poly1305
+    fn create_cipher_from_key(key: &[u8]) -> Result<Self::Crypto> {
+
+        let derived = KeyHierarchy::new(key).derive_key(KeyPurpose::VaultAes256GcmSiv)?;
+
+        Ok(Aes256GcmSiv { key: derived })
+    }
+
+    fn generate_data_key() -> Vec<u8> {
+        envelope::generate_data_key()
+    }
+
+    fn wrap_data_key(key: &[u8], data_key: &[u8]) -> Result<Vec<u8>> {
+        envelope::wrap_data_key(key, data_key)
+    }
+
+    fn unwrap_data_key(key: &[u8], wrapped_data_key: &[u8]) -> Result<Vec<u8>> {
+        envelope::unwrap_data_key(key, wrapped_data_key)
+    }
+}
+
+/// Nonce misuse resistant, a repeated nonce only reveals that the same vault was encrypted twice
+#[derive(Debug, Default)]
+pub struct Aes256GcmSiv {
+    key: Key<aes_gcm_siv::Aes256GcmSiv>,
+}
+
+impl Aes256GcmSiv {
+    /// `nonce | ciphertext`, the associated data is authenticated but not included
+    pub(crate) fn encrypt_data(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let cipher = aes_gcm_siv::Aes256GcmSiv::new(&self.key);
+        let nonce = aes_gcm_siv::Aes256GcmSiv::generate_nonce(&mut OsRng);
+
+        let data = cipher
+            .encrypt(&nonce, Payload { msg: data, aad })
+            .map_err(|error| CryptographyError::EncryptionError(error.to_string()))?;
+
+        let mut crypted_data = Vec::with_capacity(NONCE_LENGTH + data.len());
+        crypted_data.extend_from_slice(&nonce);
+        crypted_data.extend_from_slice(&data);
+
+        Ok(crypted_data)
+    }
+
+    pub(crate) fn decrypt_data(&self, crypted_data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if crypted_data.len() < NONCE_LENGTH {
+            return Err(CryptographyError::DecryptionError(TOO_SHORT_ERROR.to_string()));
+        }
+
+        let cipher = aes_gcm_siv::Aes256GcmSiv::new(&self.key);
+        let (nonce, data) = crypted_data.split_at(NONCE_LENGTH);
+
+        cipher
+            .decrypt(Nonce::from_slice(nonce), Payload { msg: data, aad })
+            .map_err(|error| CryptographyError::DecryptionError(error.to_string()))
+    }
+}
+
+impl Cryptography for Aes256GcmSiv {
+    fn encrypt(&self, uncrypted_vault: &UncryptedVault) -> Result<Vec<u8>> {
+        self.encrypt_data(&uncrypted_vault.as_bytes()?, &[])
+    }
+
+    fn decrypt(&self, crypted_vault: &[u8]) -> Result<UncryptedVault> {
+        UncryptedVault::decode(&self.decrypt_data(crypted_vault, &[])?)
+    }
+
+    fn encrypt_record(&self, record: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt_data(record, aad)
+    }
+
+    fn decrypt_record(&self, crypted_record: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.decrypt_data(crypted_record, aad)
+    }
+}
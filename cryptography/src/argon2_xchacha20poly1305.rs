@@ -0,0 +1,285 @@
+use app_core::{
+    cryptography::{
+        cryptography_error::{CryptographyError, Result},
+        uncrypted_vault::UncryptedVault,
+    },
+    generic_array::GenericArray,
+    ports::cryptography::{Cryptography, NoKeyCipher},
+};
+use argon2::{Algorithm, Argon2, Params, Version};
+use chacha20poly1305::{
+    AeadCore, Key, KeyInit,
+    aead::{Aead, OsRng, Payload, rand_core::RngCore},
+};
+
+use crate::{
+    envelope::{self, DATA_KEY_LENGTH},
+    key_manager::KeyManager,
+    xchacha20poly1305::NONCE_LENGTH,
+};
+
+const MAGIC: &[u8; 4] = b"FVA2";
+const HEADER_VERSION: u8 = 1;
+pub(crate) const SALT_LENGTH: usize = 16;
+pub(crate) const HEADER_LENGTH: usize = MAGIC.len() + 1 + 3 * 4 + SALT_LENGTH;
+
+// Refuse headers asking for more than 4 GiB, 64 passes or 16 lanes, a crafted vault shouldn't be able to exhaust the client
+const MAX_MEMORY_COST: u32 = 4 * 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+const INVALID_MAGIC: &str = "not a password protected vault.";
+const UNSUPPORTED_VERSION: &str = "unsupported header version.";
+const TRUNCATED_HEADER: &str = "the vault is too short to contain a header.";
+const MEMORY_COST_TOO_HIGH: &str = "the memory cost is higher than 4 GiB.";
+const ITERATIONS_TOO_HIGH: &str = "the iterations are higher than 64.";
+const PARALLELISM_TOO_HIGH: &str = "the parallelism is higher than 16.";
+const WRONG_PASSWORD: &str = "wrong password or corrupted vault.";
+
+/// Argon2id cost parameters, stored in clear in the vault header so the vault can always be reopened
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Argon2Parameters {
+    /// In KiB
+    pub memory_cost: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+impl Default for Argon2Parameters {
+    /// RFC 9106 second recommended option (64 MiB, 3 passes, 4 lanes)
+    fn default() -> Self {
+        Self {
+            memory_cost: 64 * 1024,
+            iterations: 3,
+            parallelism: 4,
+        }
+    }
+}
+
+impl Argon2Parameters {
+    /// True if any cost is lower than the other parameters, meaning the vault should be re-encrypted
+    pub fn is_weaker_than(&self, other: &Argon2Parameters) -> bool {
+        self.memory_cost < other.memory_cost
+            || self.iterations < other.iterations
+            || self.parallelism < other.parallelism
+    }
+}
+
+/// Clear text prefix of a password protected vault, also authenticated as associated data
+///
+/// `magic (4) | version (1) | memory cost (4) | iterations (4) | parallelism (4) | salt (16)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct VaultHeader {
+    pub parameters: Argon2Parameters,
+    pub salt: [u8; SALT_LENGTH],
+}
+
+impl VaultHeader {
+    fn new(parameters: Argon2Parameters) -> Self {
+        let mut salt = [0u8; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+
+        Self { parameters, salt }
+    }
+
+    pub fn as_bytes(&self) -> Vec<u8> {
+        let mut header = Vec::with_capacity(HEADER_LENGTH);
+
+        header.extend_from_slice(MAGIC);
+        header.push(HEADER_VERSION);
+        header.extend_from_slice(&self.parameters.memory_cost.to_be_bytes());
+        header.extend_from_slice(&self.parameters.iterations.to_be_bytes());
+        header.extend_from_slice(&self.parameters.parallelism.to_be_bytes());
+        header.extend_from_slice(&self.salt);
+
+        header
+    }
+
+    /// Reads the header at the start of a crypted vault
+    pub fn decode(crypted_vault: &[u8]) -> Result<Self> {
+        if crypted_vault.len() < HEADER_LENGTH {
+            return Err(CryptographyError::HeaderError(TRUNCATED_HEADER.to_string()));
+        }
+
+        if &crypted_vault[..MAGIC.len()] != MAGIC {
+            return Err(CryptographyError::HeaderError(INVALID_MAGIC.to_string()));
+        }
+
+        if crypted_vault[MAGIC.len()] != HEADER_VERSION {
+            return Err(CryptographyError::HeaderError(UNSUPPORTED_VERSION.to_string()));
+        }
+
+        let read_u32 = |offset: usize| {
+            u32::from_be_bytes(crypted_vault[offset..offset + 4].try_into().unwrap())
+        };
+
+        let parameters_offset = MAGIC.len() + 1;
+
+        let parameters = Argon2Parameters {
+            memory_cost: read_u32(parameters_offset),
+            iterations: read_u32(parameters_offset + 4),
+            parallelism: read_u32(parameters_offset + 8),
+        };
+
+        if parameters.memory_cost > MAX_MEMORY_COST {
+            return Err(CryptographyError::HeaderError(MEMORY_COST_TOO_HIGH.to_string()));
+        }
+
+        if parameters.iterations > MAX_ITERATIONS {
+            return Err(CryptographyError::HeaderError(ITERATIONS_TOO_HIGH.to_string()));
+        }
+
+        if parameters.parallelism > MAX_PARALLELISM {
+            return Err(CryptographyError::HeaderError(PARALLELISM_TOO_HIGH.to_string()));
+        }
+
+        let mut salt = [0u8; SALT_LENGTH];
+        salt.copy_from_slice(&crypted_vault[parameters_offset + 12..HEADER_LENGTH]);
+
+        Ok(Self { parameters, salt })
+    }
+}
+
+/// Cipher for vaults that never leave the device, the key comes from the master password instead of OPAQUE
+#[derive(Debug, Default)]
+pub struct NoKeyArgon2XChaCha20Poly1305;
+
+impl NoKeyCipher for NoKeyArgon2XChaCha20Poly1305 {
+
+    type Crypto = Argon2XChaCha20Poly1305;
+    type Keys = KeyManager;
+
+    /// The key is the master password itself, stretched with Argon2id on every encryption and decryption
+    fn create_cipher_from_key(key: &[u8]) -> Result<Self::Crypto> {
+        Ok(Argon2XChaCha20Poly1305::new(key, Argon2Parameters::default()))
+    }
+
+    fn generate_data_key() -> Vec<u8> {
+        envelope::generate_data_key()
+    }
+
+    /// Wrapped with the password stretched by Argon2id, the HKDF wrapping of the export key would be too cheap to brute force
+    fn wrap_data_key(key: &[u8], data_key: &[u8]) -> Result<Vec<u8>> {
+        Argon2XChaCha20Poly1305::new(key, Argon2Parameters::default()).encrypt_data(data_key, &[])
+    }
+
+    fn unwrap_data_key(key: &[u8], wrapped_data_key: &[u8]) -> Result<Vec<u8>> {
+        let data_key = Argon2XChaCha20Poly1305::new(key, Argon2Parameters::default()).decrypt_data(wrapped_data_key, &[])?;
+
+        if data_key.len() != DATA_KEY_LENGTH {
+            return Err(CryptographyError::DecryptionError(WRONG_PASSWORD.to_string()));
+        }
+
+        Ok(data_key)
+    }
+}
+
+#[derive(Debug)]
+pub struct Argon2XChaCha20Poly1305 {
+    password: Vec<u8>,
+    parameters: Argon2Parameters,
+}
+
+impl Argon2XChaCha20Poly1305 {
+    /// The parameters are only used to encrypt, decryption always uses the ones from the vault header.
+    /// Saving a vault opened with weaker parameters therefore upgrades it.
+    pub fn new(password: &[u8], parameters: Argon2Parameters) -> Self {
+        Self {
+            password: password.to_vec(),
+            parameters,
+        }
+    }
+
+    fn derive_key(&self, header: &VaultHeader) -> Result<Key> {
+        let params = Params::new(
+            header.parameters.memory_cost,
+            header.parameters.iterations,
+            header.parameters.parallelism,
+            Some(32),
+        )
+        .map_err(|error| CryptographyError::DerivationError(error.to_string()))?;
+
+        let mut key = Key::default();
+
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(&self.password, &header.salt, &mut key)
+            .map_err(|error| CryptographyError::DerivationError(error.to_string()))?;
+
+        Ok(key)
+    }
+}
+
+impl Argon2XChaCha20Poly1305 {
+    /// `header | nonce | ciphertext`, the associated data is the header followed by `aad`
+    pub(crate) fn encrypt_data(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        // A new salt on every save, the key is never reused across vault versions
+        let header = VaultHeader::new(self.parameters);
+        let header_bytes = header.as_bytes();
+        let header_aad = [header_bytes.as_slice(), aad].concat();
+
+        let cipher = chacha20poly1305::XChaCha20Poly1305::new(&self.derive_key(&header)?);
+        let nonce = chacha20poly1305::XChaCha20Poly1305::generate_nonce(&mut OsRng);
+
+        let payload = Payload {
+            msg: data,
+            aad: &header_aad,
+        };
+
+        let data = cipher
+            .encrypt(&nonce, payload)
+            .map_err(|error| CryptographyError::EncryptionError(error.to_string()))?;
+
+        let mut crypted_data = Vec::with_capacity(HEADER_LENGTH + NONCE_LENGTH + data.len());
+        crypted_data.extend_from_slice(&header_bytes);
+        crypted_data.extend_from_slice(&nonce);
+        crypted_data.extend_from_slice(&data);
+
+        Ok(crypted_data)
+    }
+
+    pub(crate) fn decrypt_data(&self, crypted_data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let header = VaultHeader::decode(crypted_data)?;
+
+        let (header_bytes, body) = crypted_data.split_at(HEADER_LENGTH);
+
+        if body.len() < NONCE_LENGTH {
+            return Err(CryptographyError::DecryptionError(WRONG_PASSWORD.to_string()));
+        }
+
+        let (nonce, data) = body.split_at(NONCE_LENGTH);
+        let nonce = GenericArray::from_slice(nonce);
+
+        let cipher = chacha20poly1305::XChaCha20Poly1305::new(&self.derive_key(&header)?);
+
+        let header_aad = [header_bytes, aad].concat();
+
+        let payload = Payload {
+            msg: data,
+            aad: &header_aad,
+        };
+
+        // The AEAD can't tell a wrong password from a modified vault
+        cipher
+            .decrypt(nonce, payload)
+            .map_err(|_| CryptographyError::DecryptionError(WRONG_PASSWORD.to_string()))
+    }
+}
+
+impl Cryptography for Argon2XChaCha20Poly1305 {
+    fn encrypt(&self, uncrypted_vault: &UncryptedVault) -> Result<Vec<u8>> {
+        self.encrypt_data(&uncrypted_vault.as_bytes()?, &[])
+    }
+
+    fn decrypt(&self, crypted_vault: &[u8]) -> Result<UncryptedVault> {
+        UncryptedVault::decode(&self.decrypt_data(crypted_vault, &[])?)
+    }
+
+    /// Every record gets its own salt and pays for its own Argon2id run
+    fn encrypt_record(&self, record: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt_data(record, aad)
+    }
+
+    fn decrypt_record(&self, crypted_record: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.decrypt_data(crypted_record, aad)
+    }
+}
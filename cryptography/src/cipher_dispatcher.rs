@@ -0,0 +1,168 @@
+use app_core::{
+    cryptography::{
+        cryptography_error::{CryptographyError, Result},
+        uncrypted_vault::UncryptedVault,
+    },
+    ports::cryptography::{Cryptography, NoKeyCipher},
+};
+
+#[cfg(feature = "aes-gcm-siv")]
+use crate::aes256gcmsiv::{Aes256GcmSiv, NoKeyAes256GcmSiv};
+use crate::{
+    envelope,
+    key_manager::KeyManager,
+    xchacha20poly1305::{NoKeyXChaCha20Poly1305, XChaCha20Poly1305},
+};
+
+const MAGIC: &[u8; 3] = b"FVC";
+pub(crate) const HEADER_LENGTH: usize = MAGIC.len() + 1;
+
+const UNKNOWN_CIPHER_ID: &str = "unknown cipher id";
+const MISSING_RECORD_HEADER: &str = "the record doesn't start with a header.";
+
+/// Identifies the backend in the vault header, the values are written to disk and must never change
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+#[repr(u8)]
+pub enum CipherId {
+    #[default]
+    XChaCha20Poly1305 = 1,
+    #[cfg(feature = "aes-gcm-siv")]
+    Aes256GcmSiv = 2,
+}
+
+impl TryFrom<u8> for CipherId {
+    type Error = CryptographyError;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            1 => Ok(CipherId::XChaCha20Poly1305),
+            #[cfg(feature = "aes-gcm-siv")]
+            2 => Ok(CipherId::Aes256GcmSiv),
+            _ => Err(CryptographyError::HeaderError(format!("{UNKNOWN_CIPHER_ID}: {value}."))),
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct NoKeyCipherDispatcher;
+
+impl NoKeyCipher for NoKeyCipherDispatcher {
+
+    type Crypto = CipherDispatcher;
+    type Keys = KeyManager;
+
+    /// Every backend is created from the same key, each one derives it with its own label
+    fn create_cipher_from_key(key: &[u8]) -> Result<Self::Crypto> {
+        Ok(CipherDispatcher {
+            xchacha20poly1305: NoKeyXChaCha20Poly1305::create_cipher_from_key(key)?,
+            #[cfg(feature = "aes-gcm-siv")]
+            aes256gcmsiv: NoKeyAes256GcmSiv::create_cipher_from_key(key)?,
+            default_cipher: CipherId::default(),
+        })
+    }
+
+    fn generate_data_key() -> Vec<u8> {
+        envelope::generate_data_key()
+    }
+
+    fn wrap_data_key(key: &[u8], data_key: &[u8]) -> Result<Vec<u8>> {
+        envelope::wrap_data_key(key, data_key)
+    }
+
+    fn unwrap_data_key(key: &[u8], wrapped_data_key: &[u8]) -> Result<Vec<u8>> {
+        envelope::unwrap_data_key(key, wrapped_data_key)
+    }
+}
+
+/// Encrypts with the default backend and decrypts with the one named in the vault header
+///
+/// `magic (3) | cipher id (1) | backend output`, the header is authenticated as associated data.
+/// Vaults written before the header existed are plain XChaCha20Poly1305 and still open, records always have it.
+#[derive(Debug)]
+pub struct CipherDispatcher {
+    xchacha20poly1305: XChaCha20Poly1305,
+    #[cfg(feature = "aes-gcm-siv")]
+    aes256gcmsiv: Aes256GcmSiv,
+    default_cipher: CipherId,
+}
+
+impl CipherDispatcher {
+    /// Only changes how the vault is saved, any known backend can still be read
+    pub fn with_default_cipher(mut self, cipher_id: CipherId) -> Self {
+        self.default_cipher = cipher_id;
+        self
+    }
+
+    pub fn default_cipher(&self) -> CipherId {
+        self.default_cipher
+    }
+
+    /// The associated data of the backend is the header followed by `aad`
+    fn encrypt_with_header(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let mut header = Vec::with_capacity(HEADER_LENGTH);
+        header.extend_from_slice(MAGIC);
+        header.push(self.default_cipher as u8);
+
+        let header_aad = [header.as_slice(), aad].concat();
+
+        let body = match self.default_cipher {
+            CipherId::XChaCha20Poly1305 => self.xchacha20poly1305.encrypt_data(data, &header_aad)?,
+            #[cfg(feature = "aes-gcm-siv")]
+            CipherId::Aes256GcmSiv => self.aes256gcmsiv.encrypt_data(data, &header_aad)?,
+        };
+
+        let mut crypted_data = header;
+        crypted_data.extend_from_slice(&body);
+
+        Ok(crypted_data)
+    }
+
+    fn decrypt_with_header(&self, crypted_data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        let (header, body) = crypted_data.split_at(HEADER_LENGTH);
+
+        let header_aad = [header, aad].concat();
+
+        match CipherId::try_from(header[MAGIC.len()])? {
+            CipherId::XChaCha20Poly1305 => self.xchacha20poly1305.decrypt_data(body, &header_aad),
+            #[cfg(feature = "aes-gcm-siv")]
+            CipherId::Aes256GcmSiv => self.aes256gcmsiv.decrypt_data(body, &header_aad),
+        }
+    }
+}
+
+impl Cryptography for CipherDispatcher {
+    fn encrypt(&self, uncrypted_vault: &UncryptedVault) -> Result<Vec<u8>> {
+        self.encrypt_with_header(&uncrypted_vault.as_bytes()?, &[])
+    }
+
+    fn decrypt(&self, crypted_vault: &[u8]) -> Result<UncryptedVault> {
+        if crypted_vault.len() < HEADER_LENGTH || !crypted_vault.starts_with(MAGIC) {
+            return self.xchacha20poly1305.decrypt(crypted_vault);
+        }
+
+        let result = self
+            .decrypt_with_header(crypted_vault, &[])
+            .and_then(|data| UncryptedVault::decode(&data));
+
+        // The random nonce of a legacy vault starts with the magic once in 2^24 vaults
+        if result.is_err()
+            && let Ok(uncrypted_vault) = self.xchacha20poly1305.decrypt(crypted_vault)
+        {
+            return Ok(uncrypted_vault);
+        }
+
+        result
+    }
+
+    fn encrypt_record(&self, record: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt_with_header(record, aad)
+    }
+
+    fn decrypt_record(&self, crypted_record: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if crypted_record.len() < HEADER_LENGTH || !crypted_record.starts_with(MAGIC) {
+            return Err(CryptographyError::HeaderError(MISSING_RECORD_HEADER.to_string()));
+        }
+
+        self.decrypt_with_header(crypted_record, aad)
+    }
+}
@@ -0,0 +1,42 @@
+use app_core::cryptography::cryptography_error::{CryptographyError, Result};
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+
+use crate::{
+    key_hierarchy::{KeyHierarchy, KeyPurpose},
+    xchacha20poly1305::XChaCha20Poly1305,
+};
+
+pub const DATA_KEY_LENGTH: usize = 32;
+
+const WRAPPED_KEY_AAD: &[u8] = b"ferris-vault-wrapped-data-key";
+const INVALID_WRAPPED_KEY: &str = "wrong key or corrupted wrapped data key.";
+
+pub fn generate_data_key() -> Vec<u8> {
+    let mut data_key = vec![0u8; DATA_KEY_LENGTH];
+    OsRng.fill_bytes(&mut data_key);
+
+    data_key
+}
+
+/// Only the wrapped data key depends on the password, changing it never re-encrypts the vault
+pub fn wrap_data_key(key: &[u8], data_key: &[u8]) -> Result<Vec<u8>> {
+    key_wrapping_cipher(key)?.encrypt_data(data_key, WRAPPED_KEY_AAD)
+}
+
+pub fn unwrap_data_key(key: &[u8], wrapped_data_key: &[u8]) -> Result<Vec<u8>> {
+    let data_key = key_wrapping_cipher(key)?
+        .decrypt_data(wrapped_data_key, WRAPPED_KEY_AAD)
+        .map_err(|_| CryptographyError::DecryptionError(INVALID_WRAPPED_KEY.to_string()))?;
+
+    if data_key.len() != DATA_KEY_LENGTH {
+        return Err(CryptographyError::DecryptionError(INVALID_WRAPPED_KEY.to_string()));
+    }
+
+    Ok(data_key)
+}
+
+fn key_wrapping_cipher(key: &[u8]) -> Result<XChaCha20Poly1305> {
+    let key_wrapping_key = KeyHierarchy::new(key).derive_key(KeyPurpose::KeyWrapping)?;
+
+    Ok(XChaCha20Poly1305::new(key_wrapping_key))
+}
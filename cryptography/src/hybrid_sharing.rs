@@ -0,0 +1,173 @@
+use app_core::cryptography::{
+    cryptography_error::{CryptographyError, Result},
+    uncrypted_vault::SharingKeyPair,
+};
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+use libcrux_ml_kem::{
+    ENCAPS_SEED_SIZE,
+    KEY_GENERATION_SEED_SIZE,
+    mlkem768::{self, MlKem768Ciphertext, MlKem768PublicKey},
+};
+use x25519_dalek::{PublicKey, StaticSecret};
+
+use crate::{
+    key_hierarchy::{DerivedKey, KeyHierarchy, KeyPurpose},
+    sharing,
+    xchacha20poly1305::XChaCha20Poly1305,
+};
+
+pub const ML_KEM_PUBLIC_KEY_LENGTH: usize = 1184;
+pub const ML_KEM_CIPHERTEXT_LENGTH: usize = 1088;
+/// `X25519 public key (32) | ML-KEM-768 encapsulation key (1184)`
+pub const PUBLIC_KEY_LENGTH: usize = sharing::PUBLIC_KEY_LENGTH + ML_KEM_PUBLIC_KEY_LENGTH;
+/// `X25519 private key (32) | ML-KEM-768 key generation seed (64)`, the ML-KEM key pair is generated again from the seed
+pub const PRIVATE_KEY_LENGTH: usize = sharing::PUBLIC_KEY_LENGTH + KEY_GENERATION_SEED_SIZE;
+
+const INVALID_KEY: &str = "a hybrid sharing key has an invalid length.";
+const INVALID_ML_KEM_KEY: &str = "the ML-KEM public key of the share is not valid.";
+const TOO_SHORT_ERROR: &str = "the sealed data is too short to contain a public key and an ML-KEM ciphertext.";
+const NOT_CONTRIBUTORY: &str = "the public key of the share is a low order point.";
+
+/// X25519 + ML-KEM-768 key pair, the private key stays in the encrypted vault
+pub fn generate_key_pair() -> SharingKeyPair {
+    let mut seed = [0u8; KEY_GENERATION_SEED_SIZE];
+    OsRng.fill_bytes(&mut seed);
+
+    let private_key = StaticSecret::random_from_rng(OsRng);
+
+    key_pair_from(private_key, seed)
+}
+
+/// `ephemeral public key (32) | ML-KEM ciphertext (1088) | nonce | ciphertext`
+///
+/// A public key of 32 bytes is a classic one, the data is sealed with X25519 alone so the recipient can open it.
+pub fn seal(public_key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    if public_key.len() == sharing::PUBLIC_KEY_LENGTH {
+        return sharing::seal(public_key, data);
+    }
+
+    let mut randomness = [0u8; ENCAPS_SEED_SIZE];
+    OsRng.fill_bytes(&mut randomness);
+
+    seal_with(public_key, data, StaticSecret::random_from_rng(OsRng), randomness)
+}
+
+/// A private key of 32 bytes is a classic one and opens the sealed boxes of the X25519 path
+pub fn open(private_key: &[u8], sealed_data: &[u8]) -> Result<Vec<u8>> {
+    if private_key.len() == sharing::PUBLIC_KEY_LENGTH {
+        return sharing::open(private_key, sealed_data);
+    }
+
+    let (x25519_private_key, seed) = split_private_key(private_key)?;
+    let ml_kem_key_pair = mlkem768::generate_key_pair(seed);
+    let public_key = [PublicKey::from(&x25519_private_key).as_bytes().as_slice(), ml_kem_key_pair.pk()].concat();
+
+    let Some((ephemeral_public_key, rest)) = sealed_data.split_first_chunk::<{ sharing::PUBLIC_KEY_LENGTH }>() else {
+        return Err(CryptographyError::SharingError(TOO_SHORT_ERROR.to_string()));
+    };
+    let Some((ciphertext, crypted_data)) = rest.split_first_chunk::<ML_KEM_CIPHERTEXT_LENGTH>() else {
+        return Err(CryptographyError::SharingError(TOO_SHORT_ERROR.to_string()));
+    };
+
+    let ephemeral_public_key = PublicKey::from(*ephemeral_public_key);
+    let shared_secret = x25519_private_key.diffie_hellman(&ephemeral_public_key);
+
+    if !shared_secret.was_contributory() {
+        return Err(CryptographyError::SharingError(NOT_CONTRIBUTORY.to_string()));
+    }
+
+    let ml_kem_shared_secret = mlkem768::decapsulate(ml_kem_key_pair.private_key(), &MlKem768Ciphertext::from(*ciphertext));
+
+    let key = hybrid_key(
+        &ml_kem_shared_secret,
+        shared_secret.as_bytes(),
+        ephemeral_public_key.as_bytes(),
+        &public_key[..sharing::PUBLIC_KEY_LENGTH],
+    )?;
+
+    let aad = [ephemeral_public_key.as_bytes().as_slice(), ciphertext, &public_key].concat();
+
+    XChaCha20Poly1305::new(key).decrypt_data(crypted_data, &aad)
+}
+
+/// Both shared secrets go through HKDF with their own label, breaking X25519 or ML-KEM alone is not enough
+///
+/// The X25519 public keys are part of the input, as in X-Wing, the ML-KEM ciphertext is already bound by ML-KEM.
+pub(crate) fn hybrid_key(
+    ml_kem_shared_secret: &[u8],
+    x25519_shared_secret: &[u8],
+    ephemeral_public_key: &[u8],
+    recipient_public_key: &[u8],
+) -> Result<DerivedKey> {
+    let input_key_material = [ml_kem_shared_secret, x25519_shared_secret, ephemeral_public_key, recipient_public_key].concat();
+
+    KeyHierarchy::new(&input_key_material).derive_key(KeyPurpose::HybridSharing)
+}
+
+pub(crate) fn key_pair_from(private_key: StaticSecret, seed: [u8; KEY_GENERATION_SEED_SIZE]) -> SharingKeyPair {
+    let ml_kem_key_pair = mlkem768::generate_key_pair(seed);
+    let public_key = PublicKey::from(&private_key);
+
+    SharingKeyPair {
+        private_key: [private_key.to_bytes().as_slice(), &seed].concat(),
+        public_key: [public_key.as_bytes().as_slice(), ml_kem_key_pair.pk()].concat(),
+    }
+}
+
+pub(crate) fn seal_with(
+    public_key: &[u8],
+    data: &[u8],
+    ephemeral_secret: StaticSecret,
+    randomness: [u8; ENCAPS_SEED_SIZE],
+) -> Result<Vec<u8>> {
+    let Some((recipient_public_key, ml_kem_public_key)) = public_key.split_first_chunk::<{ sharing::PUBLIC_KEY_LENGTH }>()
+    else {
+        return Err(CryptographyError::SharingError(INVALID_KEY.to_string()));
+    };
+
+    let ml_kem_public_key = MlKem768PublicKey::try_from(ml_kem_public_key)
+        .map_err(|_| CryptographyError::SharingError(INVALID_KEY.to_string()))?;
+
+    if !mlkem768::validate_public_key(&ml_kem_public_key) {
+        return Err(CryptographyError::SharingError(INVALID_ML_KEM_KEY.to_string()));
+    }
+
+    let recipient_public_key = PublicKey::from(*recipient_public_key);
+    let ephemeral_public_key = PublicKey::from(&ephemeral_secret);
+    let shared_secret = ephemeral_secret.diffie_hellman(&recipient_public_key);
+
+    if !shared_secret.was_contributory() {
+        return Err(CryptographyError::SharingError(NOT_CONTRIBUTORY.to_string()));
+    }
+
+    let (ciphertext, ml_kem_shared_secret) = mlkem768::encapsulate(&ml_kem_public_key, randomness);
+
+    let key = hybrid_key(
+        &ml_kem_shared_secret,
+        shared_secret.as_bytes(),
+        ephemeral_public_key.as_bytes(),
+        recipient_public_key.as_bytes(),
+    )?;
+
+    let aad = [ephemeral_public_key.as_bytes().as_slice(), ciphertext.as_ref(), public_key].concat();
+    let crypted_data = XChaCha20Poly1305::new(key).encrypt_data(data, &aad)?;
+
+    Ok([ephemeral_public_key.as_bytes().as_slice(), ciphertext.as_ref(), &crypted_data].concat())
+}
+
+fn split_private_key(private_key: &[u8]) -> Result<(StaticSecret, [u8; KEY_GENERATION_SEED_SIZE])> {
+    let private_key: &[u8; PRIVATE_KEY_LENGTH] = private_key
+        .try_into()
+        .map_err(|_| CryptographyError::SharingError(INVALID_KEY.to_string()))?;
+
+    let (x25519_private_key, seed) = private_key.split_at(sharing::PUBLIC_KEY_LENGTH);
+
+    let x25519_private_key: [u8; sharing::PUBLIC_KEY_LENGTH] = x25519_private_key
+        .try_into()
+        .map_err(|_| CryptographyError::SharingError(INVALID_KEY.to_string()))?;
+    let seed = seed
+        .try_into()
+        .map_err(|_| CryptographyError::SharingError(INVALID_KEY.to_string()))?;
+
+    Ok((StaticSecret::from(x25519_private_key), seed))
+}
@@ -0,0 +1,40 @@
+use app_core::cryptography::cryptography_error::{CryptographyError, Result};
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+use hkdf::Hkdf;
+use sha2::{Digest, Sha256};
+
+pub const KEY_FILE_SECRET_LENGTH: usize = 32;
+/// Same length as the OPAQUE export key it replaces
+pub const MIXED_KEY_LENGTH: usize = 64;
+
+const KEY_FILE_HEADER: &str = "FERRIS-VAULT-KEY-FILE-V1";
+// Not a purpose of the key hierarchy, the mixed key is what the hierarchy is built from
+const KEY_FILE_LABEL: &[u8] = b"ferris-vault-v1-key-file";
+const EMPTY_KEY_FILE: &str = "the key file is empty.";
+
+/// Text file with a header and 256 random bits in hex, any other file can be used as a key file too
+pub fn generate_key_file() -> Vec<u8> {
+    let mut secret = [0u8; KEY_FILE_SECRET_LENGTH];
+    OsRng.fill_bytes(&mut secret);
+
+    let secret: String = secret.iter().map(|byte| format!("{byte:02x}")).collect();
+
+    format!("{KEY_FILE_HEADER}\n{secret}\n").into_bytes()
+}
+
+/// The whole file is hashed, a single changed byte gives another key
+pub fn mix_key_file(export_key: &[u8], key_file: &[u8]) -> Result<Vec<u8>> {
+    if key_file.is_empty() {
+        return Err(CryptographyError::KeyFileError(EMPTY_KEY_FILE.to_string()));
+    }
+
+    let key_file_hash = Sha256::digest(key_file);
+    let hkdf = Hkdf::<Sha256>::new(Some(&key_file_hash), export_key);
+
+    let mut mixed_key = vec![0u8; MIXED_KEY_LENGTH];
+
+    hkdf.expand(KEY_FILE_LABEL, &mut mixed_key)
+        .map_err(|error| CryptographyError::DerivationError(error.to_string()))?;
+
+    Ok(mixed_key)
+}
@@ -0,0 +1,85 @@
+use app_core::{
+    cryptography::cryptography_error::{CryptographyError, Result},
+    generic_array::GenericArray,
+};
+use chacha20poly1305::consts::U32;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+pub type DerivedKey = GenericArray<u8, U32>;
+
+/// What a derived key is used for, every purpose has its own HKDF label
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum KeyPurpose {
+    Vault,
+    VaultAes256GcmSiv,
+    Attachments,
+    Sharing,
+    LocalCache,
+    SearchIndex,
+    KeyWrapping,
+    RecoveryToken,
+    PasswordDerivation,
+    HybridSharing,
+}
+
+impl KeyPurpose {
+    pub const ALL: [KeyPurpose; 10] = [
+        KeyPurpose::Vault,
+        KeyPurpose::VaultAes256GcmSiv,
+        KeyPurpose::Attachments,
+        KeyPurpose::Sharing,
+        KeyPurpose::LocalCache,
+        KeyPurpose::SearchIndex,
+        KeyPurpose::KeyWrapping,
+        KeyPurpose::RecoveryToken,
+        KeyPurpose::PasswordDerivation,
+        KeyPurpose::HybridSharing,
+    ];
+
+    /// Changing a label changes the key, data encrypted with the old one can't be read anymore
+    pub fn label(&self) -> &'static [u8] {
+        match self {
+            // Kept from before the hierarchy existed so current vaults still open
+            KeyPurpose::Vault => b"xchacha20-poly1305-key",
+            KeyPurpose::VaultAes256GcmSiv => b"aes-256-gcm-siv-key",
+            KeyPurpose::Attachments => b"ferris-vault-v1-attachments-key",
+            KeyPurpose::Sharing => b"ferris-vault-v1-sharing-key",
+            KeyPurpose::LocalCache => b"ferris-vault-v1-local-cache-key",
+            KeyPurpose::SearchIndex => b"ferris-vault-v1-search-index-key",
+            KeyPurpose::KeyWrapping => b"ferris-vault-v1-key-wrapping-key",
+            KeyPurpose::RecoveryToken => b"ferris-vault-v1-recovery-token",
+            KeyPurpose::PasswordDerivation => b"ferris-vault-v1-password-derivation-key",
+            KeyPurpose::HybridSharing => b"ferris-vault-v1-hybrid-sharing-key",
+        }
+    }
+}
+
+/// Derives independent keys from the OPAQUE export key, a feature must never reuse the key of another one
+pub struct KeyHierarchy {
+    hkdf: Hkdf<Sha256>,
+}
+
+impl KeyHierarchy {
+    pub fn new(export_key: &[u8]) -> Self {
+        Self {
+            hkdf: Hkdf::<Sha256>::new(None, export_key),
+        }
+    }
+
+    pub fn derive_key(&self, purpose: KeyPurpose) -> Result<DerivedKey> {
+        let mut key = DerivedKey::default();
+
+        self.hkdf
+            .expand(purpose.label(), &mut key)
+            .map_err(|error| CryptographyError::DerivationError(error.to_string()))?;
+
+        Ok(key)
+    }
+}
+
+impl std::fmt::Debug for KeyHierarchy {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        formatter.write_str("KeyHierarchy { .. }")
+    }
+}
@@ -0,0 +1,79 @@
+use app_core::{
+    cryptography::{cryptography_error::Result, secret_share::SecretShare, uncrypted_vault::SharingKeyPair},
+    ports::key_management::KeyManagement,
+};
+
+#[cfg(feature = "ml-kem")]
+use crate::hybrid_sharing;
+use crate::{
+    key_file,
+    key_hierarchy::{KeyHierarchy, KeyPurpose},
+    recovery,
+    shamir,
+    sharing,
+};
+
+/// Shared by every cipher of this crate, none of these keys depend on the vault encryption
+#[derive(Debug, Default)]
+pub struct KeyManager;
+
+impl KeyManagement for KeyManager {
+    fn generate_recovery_key() -> Vec<u8> {
+        recovery::generate_recovery_key()
+    }
+
+    fn recovery_token(recovery_key: &[u8]) -> Result<Vec<u8>> {
+        recovery::recovery_token(recovery_key)
+    }
+
+    fn derivation_key(data_key: &[u8]) -> Result<Vec<u8>> {
+        let key = KeyHierarchy::new(data_key).derive_key(KeyPurpose::PasswordDerivation)?;
+
+        Ok(key.to_vec())
+    }
+
+    fn split_secret(secret: &[u8], threshold: u8, share_count: u8) -> Result<Vec<SecretShare>> {
+        shamir::split_secret(secret, threshold, share_count)
+    }
+
+    fn combine_shares(shares: &[String]) -> Result<Vec<u8>> {
+        shamir::combine_shares(shares)
+    }
+
+    fn generate_key_file() -> Vec<u8> {
+        key_file::generate_key_file()
+    }
+
+    fn mix_key_file(key: &[u8], key_file: &[u8]) -> Result<Vec<u8>> {
+        key_file::mix_key_file(key, key_file)
+    }
+
+    /// Hybrid X25519 + ML-KEM-768 with the `ml-kem` feature, an account keeps the key pair it already has
+    fn generate_sharing_key_pair() -> SharingKeyPair {
+        #[cfg(feature = "ml-kem")]
+        return hybrid_sharing::generate_key_pair();
+
+        #[cfg(not(feature = "ml-kem"))]
+        sharing::generate_key_pair()
+    }
+
+    fn seal(public_key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+        #[cfg(feature = "ml-kem")]
+        return hybrid_sharing::seal(public_key, data);
+
+        #[cfg(not(feature = "ml-kem"))]
+        sharing::seal(public_key, data)
+    }
+
+    fn open_sealed(private_key: &[u8], sealed_data: &[u8]) -> Result<Vec<u8>> {
+        #[cfg(feature = "ml-kem")]
+        return hybrid_sharing::open(private_key, sealed_data);
+
+        #[cfg(not(feature = "ml-kem"))]
+        sharing::open(private_key, sealed_data)
+    }
+
+    fn fingerprint(public_key: &[u8]) -> String {
+        sharing::fingerprint(public_key)
+    }
+}
@@ -1,4 +1,17 @@
+#[cfg(feature = "aes-gcm-siv")]
+pub mod aes256gcmsiv;
+pub mod argon2_xchacha20poly1305;
+pub mod cipher_dispatcher;
+pub mod envelope;
+#[cfg(feature = "ml-kem")]
+pub mod hybrid_sharing;
+pub mod key_file;
+pub mod key_hierarchy;
+pub mod key_manager;
+pub mod recovery;
+pub mod shamir;
+pub mod sharing;
 pub mod xchacha20poly1305;
 
 #[cfg(test)]
-mod tests;
\ No newline at end of file
+mod tests;
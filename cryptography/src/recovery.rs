@@ -0,0 +1,21 @@
+use app_core::cryptography::cryptography_error::Result;
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+
+use crate::key_hierarchy::{KeyHierarchy, KeyPurpose};
+
+/// 160 bits, printed as 32 base32 characters in the emergency kit
+pub const RECOVERY_KEY_LENGTH: usize = 20;
+
+pub fn generate_recovery_key() -> Vec<u8> {
+    let mut recovery_key = vec![0u8; RECOVERY_KEY_LENGTH];
+    OsRng.fill_bytes(&mut recovery_key);
+
+    recovery_key
+}
+
+/// What the server stores to authorize a recovery, the data key can't be unwrapped with it
+pub fn recovery_token(recovery_key: &[u8]) -> Result<Vec<u8>> {
+    let token = KeyHierarchy::new(recovery_key).derive_key(KeyPurpose::RecoveryToken)?;
+
+    Ok(token.to_vec())
+}
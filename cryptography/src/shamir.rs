@@ -0,0 +1,317 @@
+use app_core::cryptography::{
+    cryptography_error::{CryptographyError, Result},
+    secret_share::SecretShare,
+};
+use chacha20poly1305::aead::{OsRng, rand_core::RngCore};
+use sha2::{Digest, Sha256};
+
+const SHARE_VERSION: u8 = 1;
+const HEADER_LENGTH: usize = 3;
+const CHECKSUM_LENGTH: usize = 4;
+
+const INVALID_THRESHOLD: &str = "the threshold must be between 2 and the number of shares.";
+const EMPTY_SECRET: &str = "the secret is empty.";
+const MALFORMED_SHARE: &str = "malformed share.";
+const UNKNOWN_WORD: &str = "unknown word in the share.";
+const INVALID_CHECKSUM: &str = "wrong checksum, the share was mistyped.";
+const UNSUPPORTED_VERSION: &str = "unsupported share version.";
+const MISMATCHED_SHARES: &str = "the shares don't come from the same secret.";
+const DUPLICATED_SHARE: &str = "the same share was given twice.";
+
+/// One point of the polynomials, `index` is its x coordinate and is never 0
+#[derive(Clone, PartialEq)]
+pub struct Share {
+    pub threshold: u8,
+    pub index: u8,
+    pub value: Vec<u8>,
+}
+
+impl Share {
+    /// `version (1) | threshold (1) | index (1) | value | checksum (4)`, the checksum is the start of its SHA-256
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = vec![SHARE_VERSION, self.threshold, self.index];
+        bytes.extend_from_slice(&self.value);
+        bytes.extend_from_slice(&checksum(&bytes));
+
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() <= HEADER_LENGTH + CHECKSUM_LENGTH {
+            return Err(CryptographyError::SecretSharingError(MALFORMED_SHARE.to_string()));
+        }
+
+        let (content, expected_checksum) = bytes.split_at(bytes.len() - CHECKSUM_LENGTH);
+
+        if checksum(content) != expected_checksum {
+            return Err(CryptographyError::SecretSharingError(INVALID_CHECKSUM.to_string()));
+        }
+
+        if content[0] != SHARE_VERSION {
+            return Err(CryptographyError::SecretSharingError(UNSUPPORTED_VERSION.to_string()));
+        }
+
+        if content[1] < 2 || content[2] == 0 {
+            return Err(CryptographyError::SecretSharingError(MALFORMED_SHARE.to_string()));
+        }
+
+        Ok(Self {
+            threshold: content[1],
+            index: content[2],
+            value: content[HEADER_LENGTH..].to_vec(),
+        })
+    }
+
+    pub fn to_hex(&self) -> String {
+        self.to_bytes().iter().map(|byte| format!("{byte:02x}")).collect()
+    }
+
+    /// One word per byte, from a list where the first four letters are enough to tell words apart
+    pub fn to_mnemonic(&self) -> String {
+        let words: Vec<&str> = self.to_bytes().iter().map(|byte| WORDLIST[*byte as usize]).collect();
+
+        words.join(" ")
+    }
+
+    /// Accepts both encodings, a single word without spaces is read as hex
+    pub fn parse(text: &str) -> Result<Self> {
+        let words: Vec<&str> = text.split_whitespace().collect();
+
+        let bytes = match words.as_slice() {
+            [hex] => decode_hex(hex)?,
+            _ => words.iter().map(|word| decode_word(word)).collect::<Result<Vec<u8>>>()?,
+        };
+
+        Self::from_bytes(&bytes)
+    }
+}
+
+impl std::fmt::Debug for Share {
+    fn fmt(&self, formatter: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(formatter, "Share {{ threshold: {}, index: {}, .. }}", self.threshold, self.index)
+    }
+}
+
+pub fn split(secret: &[u8], threshold: u8, share_count: u8) -> Result<Vec<Share>> {
+    split_with_rng(secret, threshold, share_count, &mut OsRng)
+}
+
+/// Every byte of the secret is the constant term of its own random polynomial of degree `threshold - 1`
+pub(crate) fn split_with_rng(
+    secret: &[u8],
+    threshold: u8,
+    share_count: u8,
+    rng: &mut impl RngCore,
+) -> Result<Vec<Share>> {
+    if threshold < 2 || threshold > share_count {
+        return Err(CryptographyError::SecretSharingError(INVALID_THRESHOLD.to_string()));
+    }
+
+    if secret.is_empty() {
+        return Err(CryptographyError::SecretSharingError(EMPTY_SECRET.to_string()));
+    }
+
+    let mut coefficients = vec![0u8; threshold as usize - 1];
+
+    let mut shares: Vec<Share> = (1..=share_count)
+        .map(|index| Share {
+            threshold,
+            index,
+            value: Vec::with_capacity(secret.len()),
+        })
+        .collect();
+
+    for byte in secret {
+        rng.fill_bytes(&mut coefficients);
+
+        for share in &mut shares {
+            // Horner, from the highest degree down to the secret byte
+            let value = coefficients
+                .iter()
+                .rev()
+                .fold(0, |accumulator, coefficient| gf_mul(accumulator, share.index) ^ coefficient);
+
+            share.value.push(gf_mul(value, share.index) ^ byte);
+        }
+    }
+
+    coefficients.fill(0);
+
+    Ok(shares)
+}
+
+/// Lagrange interpolation at 0 with the first `threshold` shares
+pub fn combine(shares: &[Share]) -> Result<Vec<u8>> {
+    let Some(first_share) = shares.first() else {
+        return Err(CryptographyError::SecretSharingError(not_enough_shares(2, 0)));
+    };
+
+    let threshold = first_share.threshold as usize;
+
+    if shares
+        .iter()
+        .any(|share| share.threshold != first_share.threshold || share.value.len() != first_share.value.len())
+    {
+        return Err(CryptographyError::SecretSharingError(MISMATCHED_SHARES.to_string()));
+    }
+
+    if shares.len() < threshold {
+        return Err(CryptographyError::SecretSharingError(not_enough_shares(threshold, shares.len())));
+    }
+
+    let shares = &shares[..threshold];
+
+    for (position, share) in shares.iter().enumerate() {
+        if shares[position + 1..].iter().any(|other| other.index == share.index) {
+            return Err(CryptographyError::SecretSharingError(DUPLICATED_SHARE.to_string()));
+        }
+    }
+
+    let basis: Vec<u8> = shares
+        .iter()
+        .map(|share| {
+            shares
+                .iter()
+                .filter(|other| other.index != share.index)
+                .fold(1, |product, other| {
+                    gf_mul(product, gf_mul(other.index, gf_inverse(other.index ^ share.index)))
+                })
+        })
+        .collect();
+
+    let secret = (0..first_share.value.len())
+        .map(|position| {
+            shares
+                .iter()
+                .zip(&basis)
+                .fold(0, |secret_byte, (share, basis)| secret_byte ^ gf_mul(share.value[position], *basis))
+        })
+        .collect();
+
+    Ok(secret)
+}
+
+pub fn split_secret(secret: &[u8], threshold: u8, share_count: u8) -> Result<Vec<SecretShare>> {
+    let shares = split(secret, threshold, share_count)?;
+
+    Ok(shares
+        .iter()
+        .map(|share| SecretShare {
+            index: share.index,
+            mnemonic: share.to_mnemonic(),
+            hex: share.to_hex(),
+        })
+        .collect())
+}
+
+pub fn combine_shares(shares: &[String]) -> Result<Vec<u8>> {
+    let shares = shares
+        .iter()
+        .map(|share| Share::parse(share))
+        .collect::<Result<Vec<Share>>>()?;
+
+    combine(&shares)
+}
+
+/// Multiplication in GF(2^8) modulo x^8 + x^4 + x^3 + x + 1, without tables or branches on secret bytes
+pub(crate) fn gf_mul(mut left: u8, mut right: u8) -> u8 {
+    let mut product = 0;
+
+    for _ in 0..8 {
+        product ^= left & 0u8.wrapping_sub(right & 1);
+
+        let carry = 0u8.wrapping_sub(left >> 7);
+        left = (left << 1) ^ (0x1b & carry);
+        right >>= 1;
+    }
+
+    product
+}
+
+/// `value^254`, the inverse of every non-zero element
+pub(crate) fn gf_inverse(value: u8) -> u8 {
+    let mut result = 1;
+    let mut base = value;
+    let mut exponent: u8 = 254;
+
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            result = gf_mul(result, base);
+        }
+
+        base = gf_mul(base, base);
+        exponent >>= 1;
+    }
+
+    result
+}
+
+fn checksum(bytes: &[u8]) -> [u8; CHECKSUM_LENGTH] {
+    let digest = Sha256::digest(bytes);
+
+    [digest[0], digest[1], digest[2], digest[3]]
+}
+
+fn not_enough_shares(threshold: usize, given: usize) -> String {
+    format!("{threshold} shares are needed, only {given} given.")
+}
+
+fn decode_hex(hex: &str) -> Result<Vec<u8>> {
+    if !hex.len().is_multiple_of(2) || !hex.is_ascii() {
+        return Err(CryptographyError::SecretSharingError(MALFORMED_SHARE.to_string()));
+    }
+
+    (0..hex.len())
+        .step_by(2)
+        .map(|position| {
+            u8::from_str_radix(&hex[position..position + 2], 16)
+                .map_err(|_| CryptographyError::SecretSharingError(MALFORMED_SHARE.to_string()))
+        })
+        .collect()
+}
+
+/// The first four letters are enough, a longer prefix must still match the word
+fn decode_word(word: &str) -> Result<u8> {
+    let word = word.to_ascii_lowercase();
+
+    WORDLIST
+        .iter()
+        .position(|candidate| *candidate == word || (word.len() >= 4 && candidate.starts_with(word.as_str())))
+        .map(|position| position as u8)
+        .ok_or_else(|| CryptographyError::SecretSharingError(UNKNOWN_WORD.to_string()))
+}
+
+pub(crate) const WORDLIST: [&str; 256] = [
+    "acorn", "actor", "agent", "alarm", "album", "alien", "alley", "amber",
+    "angle", "ankle", "apple", "apron", "arena", "armor", "arrow", "atlas",
+    "attic", "audio", "autumn", "bacon", "badge", "bagel", "baker", "bamboo",
+    "banana", "banjo", "barrel", "basil", "basket", "beach", "beaver", "bench",
+    "berry", "border", "bottle", "brain", "bridge", "brush", "bubble", "bucket",
+    "butter", "cabin", "cactus", "camel", "canal", "candle", "canyon", "carbon",
+    "carpet", "castle", "cedar", "cello", "chalk", "cherry", "cider", "circle",
+    "citrus", "clock", "cloud", "cobalt", "cocoa", "comet", "copper", "coral",
+    "cotton", "coyote", "crane", "daisy", "desert", "domino", "donkey", "dragon",
+    "drum", "duck", "eagle", "earth", "elbow", "ember", "engine", "eraser",
+    "fabric", "falcon", "fence", "ferry", "fiddle", "field", "finger", "flame",
+    "flute", "forest", "fossil", "fox", "frost", "fruit", "galaxy", "garden",
+    "garlic", "gecko", "ginger", "globe", "goat", "gold", "grape", "gravel",
+    "guitar", "hammer", "harbor", "hazel", "helmet", "hockey", "honey", "hotel",
+    "husky", "igloo", "island", "ivory", "jacket", "jaguar", "jelly", "jewel",
+    "jigsaw", "jockey", "juice", "jungle", "kayak", "kernel", "kettle", "kiwi",
+    "koala", "ladder", "lagoon", "lamp", "laptop", "lemon", "lizard", "locket",
+    "lunar", "magnet", "mango", "maple", "marble", "meadow", "melon", "meteor",
+    "mirror", "mitten", "monkey", "mosaic", "muffin", "museum", "napkin", "nectar",
+    "needle", "nickel", "noodle", "nutmeg", "oasis", "ocean", "olive", "onion",
+    "orange", "orbit", "orchid", "otter", "oyster", "paddle", "palace", "panda",
+    "paper", "parrot", "peanut", "pebble", "pencil", "pepper", "piano", "pigeon",
+    "pillow", "pilot", "planet", "plum", "pocket", "pony", "potato", "puzzle",
+    "quail", "quartz", "queen", "quiver", "rabbit", "radar", "radio", "raven",
+    "recipe", "rhino", "ribbon", "river", "robot", "rocket", "rubber", "ruby",
+    "saddle", "salmon", "sandal", "satin", "scarf", "shadow", "shell", "silver",
+    "sketch", "sleeve", "socket", "spider", "spoon", "squid", "statue", "sugar",
+    "summit", "sunset", "table", "tango", "teapot", "temple", "tennis", "ticket",
+    "tiger", "timber", "toast", "tomato", "tonic", "torch", "tower", "tulip",
+    "tunnel", "turtle", "urchin", "valley", "velvet", "violin", "voyage", "wagon",
+    "walnut", "walrus", "water", "whale", "wheat", "willow", "window", "winter",
+    "wizard", "wolf", "yacht", "yogurt", "zebra", "zenith", "zigzag", "zipper",
+];
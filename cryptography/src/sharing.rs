@@ -0,0 +1,100 @@
+use app_core::cryptography::{
+    cryptography_error::{CryptographyError, Result},
+    uncrypted_vault::SharingKeyPair,
+};
+use chacha20poly1305::aead::OsRng;
+use sha2::{Digest, Sha256};
+use x25519_dalek::{EphemeralSecret, PublicKey, StaticSecret};
+
+use crate::{
+    key_hierarchy::{KeyHierarchy, KeyPurpose},
+    shamir::WORDLIST,
+    xchacha20poly1305::XChaCha20Poly1305,
+};
+
+pub const PUBLIC_KEY_LENGTH: usize = 32;
+/// 96 bits, a server can't search for another key with the same fingerprint
+pub const FINGERPRINT_WORD_COUNT: usize = 12;
+
+const FINGERPRINT_LABEL: &[u8] = b"ferris-vault-v1-sharing-fingerprint";
+
+const INVALID_KEY: &str = "a sharing key must be 32 bytes.";
+const TOO_SHORT_ERROR: &str = "the sealed data is too short to contain a public key.";
+const NOT_CONTRIBUTORY: &str = "the public key of the share is a low order point.";
+
+/// X25519 key pair, the private key stays in the encrypted vault
+pub fn generate_key_pair() -> SharingKeyPair {
+    let private_key = StaticSecret::random_from_rng(OsRng);
+    let public_key = PublicKey::from(&private_key);
+
+    SharingKeyPair {
+        private_key: private_key.to_bytes().to_vec(),
+        public_key: public_key.to_bytes().to_vec(),
+    }
+}
+
+/// `ephemeral public key (32) | nonce | ciphertext`, a new ephemeral key is used for every share
+///
+/// The key is derived from the X25519 shared secret with the sharing label,
+/// both public keys are authenticated as associated data.
+pub fn seal(public_key: &[u8], data: &[u8]) -> Result<Vec<u8>> {
+    let recipient_public_key = PublicKey::from(to_key_bytes(public_key)?);
+
+    let ephemeral_secret = EphemeralSecret::random_from_rng(OsRng);
+    let ephemeral_public_key = PublicKey::from(&ephemeral_secret);
+
+    let shared_secret = ephemeral_secret.diffie_hellman(&recipient_public_key);
+
+    if !shared_secret.was_contributory() {
+        return Err(CryptographyError::SharingError(NOT_CONTRIBUTORY.to_string()));
+    }
+
+    let aad = [ephemeral_public_key.as_bytes().as_slice(), recipient_public_key.as_bytes()].concat();
+    let crypted_data = sharing_cipher(shared_secret.as_bytes())?.encrypt_data(data, &aad)?;
+
+    Ok([ephemeral_public_key.as_bytes().as_slice(), &crypted_data].concat())
+}
+
+pub fn open(private_key: &[u8], sealed_data: &[u8]) -> Result<Vec<u8>> {
+    let private_key = StaticSecret::from(to_key_bytes(private_key)?);
+    let public_key = PublicKey::from(&private_key);
+
+    let Some((ephemeral_public_key, crypted_data)) = sealed_data.split_first_chunk::<PUBLIC_KEY_LENGTH>() else {
+        return Err(CryptographyError::SharingError(TOO_SHORT_ERROR.to_string()));
+    };
+
+    let ephemeral_public_key = PublicKey::from(*ephemeral_public_key);
+    let shared_secret = private_key.diffie_hellman(&ephemeral_public_key);
+
+    if !shared_secret.was_contributory() {
+        return Err(CryptographyError::SharingError(NOT_CONTRIBUTORY.to_string()));
+    }
+
+    let aad = [ephemeral_public_key.as_bytes().as_slice(), public_key.as_bytes()].concat();
+
+    sharing_cipher(shared_secret.as_bytes())?.decrypt_data(crypted_data, &aad)
+}
+
+/// One word per byte of the labelled SHA-256 of the public key, short enough to compare aloud
+pub fn fingerprint(public_key: &[u8]) -> String {
+    let hash = Sha256::new().chain_update(FINGERPRINT_LABEL).chain_update(public_key).finalize();
+
+    let words: Vec<&str> = hash
+        .iter()
+        .take(FINGERPRINT_WORD_COUNT)
+        .map(|byte| WORDLIST[*byte as usize])
+        .collect();
+
+    words.join(" ")
+}
+
+fn sharing_cipher(shared_secret: &[u8]) -> Result<XChaCha20Poly1305> {
+    let sharing_key = KeyHierarchy::new(shared_secret).derive_key(KeyPurpose::Sharing)?;
+
+    Ok(XChaCha20Poly1305::new(sharing_key))
+}
+
+fn to_key_bytes(key: &[u8]) -> Result<[u8; PUBLIC_KEY_LENGTH]> {
+    key.try_into()
+        .map_err(|_| CryptographyError::SharingError(INVALID_KEY.to_string()))
+}
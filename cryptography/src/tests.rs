@@ -1 +1,13 @@
-pub mod xchacha20poly1305_tests;
\ No newline at end of file
+#[cfg(feature = "aes-gcm-siv")]
+pub mod aes256gcmsiv_tests;
+pub mod argon2_xchacha20poly1305_tests;
+pub mod cipher_dispatcher_tests;
+pub mod envelope_tests;
+#[cfg(feature = "ml-kem")]
+pub mod hybrid_sharing_tests;
+pub mod key_file_tests;
+pub mod key_hierarchy_tests;
+pub mod recovery_tests;
+pub mod shamir_tests;
+pub mod sharing_tests;
+pub mod xchacha20poly1305_tests;
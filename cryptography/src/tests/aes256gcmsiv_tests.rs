@@ -0,0 +1,47 @@
+use app_core::{
+    cryptography::uncrypted_vault::UncryptedVault,
+    ports::cryptography::{Cryptography, NoKeyCipher},
+};
+
+use crate::{aes256gcmsiv::NoKeyAes256GcmSiv, xchacha20poly1305::NoKeyXChaCha20Poly1305};
+
+#[test]
+fn should_encrypt_and_decrypt_vault() {
+    // A-rrange
+
+    let mut uncrypted_vault = UncryptedVault::new();
+    uncrypted_vault.add_entry(
+        "title".to_string(),
+        "username".to_string(),
+        "password".to_string(),
+    );
+
+    let aes = NoKeyAes256GcmSiv::create_cipher_from_key(&[42; 64]).unwrap();
+
+    // A-ct
+
+    let crypted_vault = aes.encrypt(&uncrypted_vault).unwrap();
+    let result = aes.decrypt(&crypted_vault);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), uncrypted_vault);
+}
+
+#[test]
+fn should_not_share_key_with_xchacha() {
+    // A-rrange
+
+    let key = &[42; 32];
+
+    let aes = NoKeyAes256GcmSiv::create_cipher_from_key(key).unwrap();
+    let xchacha = NoKeyXChaCha20Poly1305::create_cipher_from_key(key).unwrap();
+
+    // A-ct
+
+    let crypted_vault = xchacha.encrypt(&UncryptedVault::new()).unwrap();
+    let result = aes.decrypt(&crypted_vault[12..]);
+
+    // A-ssert
+    assert!(result.is_err());
+}
@@ -0,0 +1,247 @@
+use app_core::{
+    cryptography::{cryptography_error::CryptographyError, uncrypted_vault::UncryptedVault},
+    ports::cryptography::{Cryptography, NoKeyCipher},
+};
+
+use crate::argon2_xchacha20poly1305::{
+    Argon2Parameters, Argon2XChaCha20Poly1305, HEADER_LENGTH, NoKeyArgon2XChaCha20Poly1305, VaultHeader,
+};
+
+const PASSWORD: &[u8] = b"correct horse battery staple";
+
+// Cheap parameters, the tests don't need the real cost
+const WEAK_PARAMETERS: Argon2Parameters = Argon2Parameters {
+    memory_cost: 1024,
+    iterations: 1,
+    parallelism: 1,
+};
+
+const STRONG_PARAMETERS: Argon2Parameters = Argon2Parameters {
+    memory_cost: 2048,
+    iterations: 2,
+    parallelism: 2,
+};
+
+#[test]
+fn should_encrypt_and_decrypt_vault_offline() {
+    // A-rrange
+
+    let uncrypted_vault = create_uncrypted_vault();
+
+    let cipher = Argon2XChaCha20Poly1305::new(PASSWORD, WEAK_PARAMETERS);
+
+    // A-ct
+
+    let crypted_vault = cipher.encrypt(&uncrypted_vault).unwrap();
+    let result = cipher.decrypt(&crypted_vault);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), uncrypted_vault);
+}
+
+#[test]
+fn should_authenticate_record_with_aad() {
+    // A-rrange
+
+    let cipher = Argon2XChaCha20Poly1305::new(PASSWORD, WEAK_PARAMETERS);
+
+    let crypted_record = cipher.encrypt_record(b"record", b"entry/0").unwrap();
+
+    // A-ct
+
+    let result = cipher.decrypt_record(&crypted_record, b"entry/0");
+    let other_aad_result = cipher.decrypt_record(&crypted_record, b"entry/1");
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), b"record");
+    assert!(other_aad_result.is_err());
+}
+
+#[test]
+fn should_store_parameters_and_salt_in_header() {
+    // A-rrange
+
+    let uncrypted_vault = create_uncrypted_vault();
+
+    let cipher = Argon2XChaCha20Poly1305::new(PASSWORD, WEAK_PARAMETERS);
+
+    // A-ct
+
+    let first_crypted_vault = cipher.encrypt(&uncrypted_vault).unwrap();
+    let second_crypted_vault = cipher.encrypt(&uncrypted_vault).unwrap();
+
+    // A-ssert
+
+    let first_header = VaultHeader::decode(&first_crypted_vault).unwrap();
+    let second_header = VaultHeader::decode(&second_crypted_vault).unwrap();
+
+    assert_eq!(first_header.parameters, WEAK_PARAMETERS);
+    assert_ne!(first_header.salt, second_header.salt);
+    assert_eq!(first_header.as_bytes(), first_crypted_vault[..HEADER_LENGTH]);
+}
+
+#[test]
+fn should_upgrade_parameters_when_saving_again() {
+    // A-rrange
+
+    let uncrypted_vault = create_uncrypted_vault();
+
+    let old_cipher = Argon2XChaCha20Poly1305::new(PASSWORD, WEAK_PARAMETERS);
+    let old_crypted_vault = old_cipher.encrypt(&uncrypted_vault).unwrap();
+
+    let new_cipher = Argon2XChaCha20Poly1305::new(PASSWORD, STRONG_PARAMETERS);
+
+    // A-ct
+
+    let decrypted_vault = new_cipher.decrypt(&old_crypted_vault).unwrap();
+    let upgraded_crypted_vault = new_cipher.encrypt(&decrypted_vault).unwrap();
+
+    // A-ssert
+
+    let old_header = VaultHeader::decode(&old_crypted_vault).unwrap();
+    let upgraded_header = VaultHeader::decode(&upgraded_crypted_vault).unwrap();
+
+    assert!(old_header.parameters.is_weaker_than(&STRONG_PARAMETERS));
+    assert!(!upgraded_header.parameters.is_weaker_than(&STRONG_PARAMETERS));
+    assert_eq!(upgraded_header.parameters, STRONG_PARAMETERS);
+
+    assert_eq!(old_cipher.decrypt(&upgraded_crypted_vault).unwrap(), uncrypted_vault);
+}
+
+#[test]
+fn should_be_decryption_error_with_wrong_password() {
+    // A-rrange
+
+    let crypted_vault = Argon2XChaCha20Poly1305::new(PASSWORD, WEAK_PARAMETERS)
+        .encrypt(&create_uncrypted_vault())
+        .unwrap();
+
+    let cipher = Argon2XChaCha20Poly1305::new(b"wrong password", WEAK_PARAMETERS);
+
+    // A-ct
+
+    let result = cipher.decrypt(&crypted_vault);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::DecryptionError(error)) => {
+            assert_eq!(error, "wrong password or corrupted vault.")
+        }
+        _ => panic!("Test result should be: 'DecryptionError' error."),
+    }
+}
+
+#[test]
+fn should_be_decryption_error_with_tampered_parameters() {
+    // A-rrange
+
+    let cipher = Argon2XChaCha20Poly1305::new(PASSWORD, WEAK_PARAMETERS);
+
+    let mut crypted_vault = cipher.encrypt(&create_uncrypted_vault()).unwrap();
+
+    // Iterations are bytes 9 to 12, the header is authenticated with the vault
+    crypted_vault[12] += 1;
+
+    // A-ct
+
+    let result = cipher.decrypt(&crypted_vault);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::DecryptionError(_)) => {}
+        _ => panic!("Test result should be: 'DecryptionError' error."),
+    }
+}
+
+#[test]
+fn should_be_header_error_for_unknown_vault() {
+    // A-rrange
+
+    let cipher = Argon2XChaCha20Poly1305::new(PASSWORD, WEAK_PARAMETERS);
+
+    // A-ct
+
+    let truncated_result = cipher.decrypt(b"FVA2");
+    let magic_result = cipher.decrypt(&[42; 64]);
+
+    // A-ssert
+
+    match truncated_result {
+        Err(CryptographyError::HeaderError(_)) => {}
+        _ => panic!("Test result should be: 'HeaderError' error."),
+    }
+
+    match magic_result {
+        Err(CryptographyError::HeaderError(error)) => {
+            assert_eq!(error, "not a password protected vault.")
+        }
+        _ => panic!("Test result should be: 'HeaderError' error."),
+    }
+}
+
+#[test]
+fn should_be_header_error_with_too_costly_parameters() {
+    // A-rrange
+
+    let header = VaultHeader {
+        parameters: WEAK_PARAMETERS,
+        salt: [0; 16],
+    };
+
+    let with_iterations = VaultHeader {
+        parameters: Argon2Parameters { iterations: u32::MAX, ..WEAK_PARAMETERS },
+        ..header.clone()
+    };
+    let with_parallelism = VaultHeader {
+        parameters: Argon2Parameters { parallelism: u32::MAX, ..WEAK_PARAMETERS },
+        ..header
+    };
+
+    // A-ct
+
+    let iterations_result = VaultHeader::decode(&with_iterations.as_bytes());
+    let parallelism_result = VaultHeader::decode(&with_parallelism.as_bytes());
+
+    // A-ssert
+
+    match iterations_result {
+        Err(CryptographyError::HeaderError(error)) => assert_eq!(error, "the iterations are higher than 64."),
+        _ => panic!("Test result should be: 'HeaderError' error."),
+    }
+
+    match parallelism_result {
+        Err(CryptographyError::HeaderError(error)) => assert_eq!(error, "the parallelism is higher than 16."),
+        _ => panic!("Test result should be: 'HeaderError' error."),
+    }
+}
+
+#[test]
+fn should_create_cipher_from_password_with_default_parameters() {
+    // A-rrange
+
+    let password = PASSWORD;
+
+    // A-ct
+
+    let result = NoKeyArgon2XChaCha20Poly1305::create_cipher_from_key(password);
+
+    // A-ssert
+    assert!(result.is_ok());
+    assert_eq!(Argon2Parameters::default().memory_cost, 64 * 1024);
+}
+
+fn create_uncrypted_vault() -> UncryptedVault {
+    let mut uncrypted_vault = UncryptedVault::new();
+
+    uncrypted_vault.add_entry(
+        "title".to_string(),
+        "username".to_string(),
+        "password".to_string(),
+    );
+
+    uncrypted_vault
+}
@@ -0,0 +1,220 @@
+use app_core::{
+    cryptography::{cryptography_error::CryptographyError, uncrypted_vault::UncryptedVault},
+    ports::cryptography::{Cryptography, NoKeyCipher},
+};
+
+#[cfg(feature = "aes-gcm-siv")]
+use crate::aes256gcmsiv::NoKeyAes256GcmSiv;
+use crate::{
+    cipher_dispatcher::{CipherId, HEADER_LENGTH, NoKeyCipherDispatcher},
+    xchacha20poly1305::NoKeyXChaCha20Poly1305,
+};
+
+const KEY: &[u8] = &[42; 64];
+const RECORD: &[u8] = b"record";
+const RECORD_AAD: &[u8] = b"entry/0 version 1";
+
+#[test]
+fn should_encrypt_and_decrypt_with_default_cipher() {
+    // A-rrange
+
+    let uncrypted_vault = create_uncrypted_vault();
+
+    let dispatcher = NoKeyCipherDispatcher::create_cipher_from_key(KEY).unwrap();
+
+    // A-ct
+
+    let crypted_vault = dispatcher.encrypt(&uncrypted_vault).unwrap();
+    let result = dispatcher.decrypt(&crypted_vault);
+
+    // A-ssert
+
+    assert_eq!(&crypted_vault[..HEADER_LENGTH], b"FVC\x01");
+    assert_eq!(dispatcher.default_cipher(), CipherId::XChaCha20Poly1305);
+    assert_eq!(result.unwrap(), uncrypted_vault);
+}
+
+#[test]
+fn should_decrypt_vault_without_header() {
+    // A-rrange
+
+    let uncrypted_vault = create_uncrypted_vault();
+
+    let crypted_vault = NoKeyXChaCha20Poly1305::create_cipher_from_key(KEY)
+        .unwrap()
+        .encrypt(&uncrypted_vault)
+        .unwrap();
+
+    let dispatcher = NoKeyCipherDispatcher::create_cipher_from_key(KEY).unwrap();
+
+    // A-ct
+
+    let result = dispatcher.decrypt(&crypted_vault);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), uncrypted_vault);
+}
+
+#[test]
+fn should_be_header_error_with_unknown_cipher_id() {
+    // A-rrange
+
+    let dispatcher = NoKeyCipherDispatcher::create_cipher_from_key(KEY).unwrap();
+
+    let mut crypted_vault = dispatcher.encrypt(&create_uncrypted_vault()).unwrap();
+    crypted_vault[HEADER_LENGTH - 1] = 255;
+
+    // A-ct
+
+    let result = dispatcher.decrypt(&crypted_vault);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::HeaderError(error)) => assert_eq!(error, "unknown cipher id: 255."),
+        _ => panic!("Test result should be: 'HeaderError' error."),
+    }
+}
+
+#[cfg(feature = "aes-gcm-siv")]
+#[test]
+fn should_decrypt_with_backend_from_header() {
+    // A-rrange
+
+    let uncrypted_vault = create_uncrypted_vault();
+
+    let xchacha_dispatcher = NoKeyCipherDispatcher::create_cipher_from_key(KEY).unwrap();
+    let aes_dispatcher = NoKeyCipherDispatcher::create_cipher_from_key(KEY)
+        .unwrap()
+        .with_default_cipher(CipherId::Aes256GcmSiv);
+
+    // A-ct
+
+    let xchacha_crypted_vault = xchacha_dispatcher.encrypt(&uncrypted_vault).unwrap();
+    let aes_crypted_vault = aes_dispatcher.encrypt(&uncrypted_vault).unwrap();
+
+    // A-ssert
+
+    assert_eq!(aes_crypted_vault[HEADER_LENGTH - 1], CipherId::Aes256GcmSiv as u8);
+
+    assert_eq!(aes_dispatcher.decrypt(&xchacha_crypted_vault).unwrap(), uncrypted_vault);
+    assert_eq!(xchacha_dispatcher.decrypt(&aes_crypted_vault).unwrap(), uncrypted_vault);
+}
+
+#[cfg(feature = "aes-gcm-siv")]
+#[test]
+fn should_be_decryption_error_with_swapped_cipher_id() {
+    // A-rrange
+
+    let dispatcher = NoKeyCipherDispatcher::create_cipher_from_key(KEY)
+        .unwrap()
+        .with_default_cipher(CipherId::Aes256GcmSiv);
+
+    let mut crypted_vault = dispatcher.encrypt(&create_uncrypted_vault()).unwrap();
+    crypted_vault[HEADER_LENGTH - 1] = CipherId::XChaCha20Poly1305 as u8;
+
+    // A-ct
+
+    let result = dispatcher.decrypt(&crypted_vault);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::DecryptionError(_)) => {}
+        _ => panic!("Test result should be: 'DecryptionError' error."),
+    }
+}
+
+#[cfg(feature = "aes-gcm-siv")]
+#[test]
+fn should_not_decrypt_headerless_aes_vault() {
+    // A-rrange
+
+    let crypted_vault = NoKeyAes256GcmSiv::create_cipher_from_key(KEY)
+        .unwrap()
+        .encrypt(&create_uncrypted_vault())
+        .unwrap();
+
+    let dispatcher = NoKeyCipherDispatcher::create_cipher_from_key(KEY).unwrap();
+
+    // A-ct
+
+    let result = dispatcher.decrypt(&crypted_vault);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_encrypt_and_decrypt_record() {
+    // A-rrange
+
+    let dispatcher = NoKeyCipherDispatcher::create_cipher_from_key(KEY).unwrap();
+
+    // A-ct
+
+    let crypted_record = dispatcher.encrypt_record(RECORD, RECORD_AAD).unwrap();
+    let result = dispatcher.decrypt_record(&crypted_record, RECORD_AAD);
+
+    // A-ssert
+
+    assert_eq!(&crypted_record[..HEADER_LENGTH], b"FVC\x01");
+    assert_eq!(result.unwrap(), RECORD);
+}
+
+#[test]
+fn should_be_decryption_error_with_other_record_aad() {
+    // A-rrange
+
+    let dispatcher = NoKeyCipherDispatcher::create_cipher_from_key(KEY).unwrap();
+
+    let crypted_record = dispatcher.encrypt_record(RECORD, RECORD_AAD).unwrap();
+
+    // A-ct
+
+    let result = dispatcher.decrypt_record(&crypted_record, b"entry/0 version 0");
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::DecryptionError(_)) => {}
+        _ => panic!("Test result should be: 'DecryptionError' error."),
+    }
+}
+
+#[test]
+fn should_be_header_error_with_headerless_record() {
+    // A-rrange
+
+    let crypted_record = NoKeyXChaCha20Poly1305::create_cipher_from_key(KEY)
+        .unwrap()
+        .encrypt_record(RECORD, RECORD_AAD)
+        .unwrap();
+
+    let dispatcher = NoKeyCipherDispatcher::create_cipher_from_key(KEY).unwrap();
+
+    // A-ct
+
+    let result = dispatcher.decrypt_record(&crypted_record, RECORD_AAD);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::HeaderError(error)) => assert_eq!(error, "the record doesn't start with a header."),
+        _ => panic!("Test result should be: 'HeaderError' error."),
+    }
+}
+
+fn create_uncrypted_vault() -> UncryptedVault {
+    let mut uncrypted_vault = UncryptedVault::new();
+
+    uncrypted_vault.add_entry(
+        "title".to_string(),
+        "username".to_string(),
+        "password".to_string(),
+    );
+
+    uncrypted_vault
+}
@@ -0,0 +1,104 @@
+use app_core::{
+    cryptography::{cryptography_error::CryptographyError, uncrypted_vault::UncryptedVault},
+    ports::cryptography::{Cryptography, NoKeyCipher},
+};
+
+use crate::{
+    envelope::{DATA_KEY_LENGTH, generate_data_key, unwrap_data_key, wrap_data_key},
+    xchacha20poly1305::NoKeyXChaCha20Poly1305,
+};
+
+const OLD_EXPORT_KEY: &[u8] = &[42; 64];
+const NEW_EXPORT_KEY: &[u8] = &[43; 64];
+
+#[test]
+fn should_wrap_and_unwrap_data_key() {
+    // A-rrange
+
+    let data_key = generate_data_key();
+
+    // A-ct
+
+    let wrapped_data_key = wrap_data_key(OLD_EXPORT_KEY, &data_key).unwrap();
+    let result = unwrap_data_key(OLD_EXPORT_KEY, &wrapped_data_key);
+
+    // A-ssert
+
+    assert_eq!(data_key.len(), DATA_KEY_LENGTH);
+    assert_ne!(wrapped_data_key[..], data_key[..]);
+    assert_eq!(result.unwrap(), data_key);
+}
+
+#[test]
+fn should_open_vault_after_rewrapping_data_key() {
+    // A-rrange
+
+    let mut uncrypted_vault = UncryptedVault::new();
+    uncrypted_vault.add_entry(
+        "title".to_string(),
+        "username".to_string(),
+        "password".to_string(),
+    );
+
+    let data_key = NoKeyXChaCha20Poly1305::generate_data_key();
+
+    let crypted_vault = NoKeyXChaCha20Poly1305::create_cipher_from_key(&data_key)
+        .unwrap()
+        .encrypt(&uncrypted_vault)
+        .unwrap();
+
+    let old_wrapped_data_key = NoKeyXChaCha20Poly1305::wrap_data_key(OLD_EXPORT_KEY, &data_key).unwrap();
+
+    // A-ct
+
+    let unwrapped_data_key = NoKeyXChaCha20Poly1305::unwrap_data_key(OLD_EXPORT_KEY, &old_wrapped_data_key).unwrap();
+    let new_wrapped_data_key = NoKeyXChaCha20Poly1305::wrap_data_key(NEW_EXPORT_KEY, &unwrapped_data_key).unwrap();
+
+    let data_key = NoKeyXChaCha20Poly1305::unwrap_data_key(NEW_EXPORT_KEY, &new_wrapped_data_key).unwrap();
+    let result = NoKeyXChaCha20Poly1305::create_cipher_from_key(&data_key)
+        .unwrap()
+        .decrypt(&crypted_vault);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), uncrypted_vault);
+}
+
+#[test]
+fn should_be_decryption_error_with_wrong_key() {
+    // A-rrange
+
+    let wrapped_data_key = wrap_data_key(OLD_EXPORT_KEY, &generate_data_key()).unwrap();
+
+    // A-ct
+
+    let result = unwrap_data_key(NEW_EXPORT_KEY, &wrapped_data_key);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::DecryptionError(error)) => {
+            assert_eq!(error, "wrong key or corrupted wrapped data key.")
+        }
+        _ => panic!("Test result should be: 'DecryptionError' error."),
+    }
+}
+
+#[test]
+fn should_not_wrap_with_vault_key() {
+    // A-rrange
+
+    let data_key = generate_data_key();
+
+    let wrapped_data_key = wrap_data_key(OLD_EXPORT_KEY, &data_key).unwrap();
+
+    let vault_cipher = NoKeyXChaCha20Poly1305::create_cipher_from_key(OLD_EXPORT_KEY).unwrap();
+
+    // A-ct
+
+    let result = vault_cipher.decrypt(&wrapped_data_key);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
@@ -0,0 +1,388 @@
+use app_core::{cryptography::cryptography_error::CryptographyError, ports::key_management::KeyManagement};
+use libcrux_ml_kem::mlkem768::{self, MlKem768Ciphertext, MlKem768PrivateKey, MlKem768PublicKey};
+use x25519_dalek::StaticSecret;
+
+use crate::{
+    hybrid_sharing::{
+        ML_KEM_CIPHERTEXT_LENGTH,
+        ML_KEM_PUBLIC_KEY_LENGTH,
+        PRIVATE_KEY_LENGTH,
+        PUBLIC_KEY_LENGTH,
+        generate_key_pair,
+        hybrid_key,
+        key_pair_from,
+        open,
+        seal,
+        seal_with,
+    },
+    key_manager::KeyManager,
+    sharing,
+};
+
+const DATA: &[u8] = b"shared entry";
+
+// NIST ACVP vectors for ML-KEM-768 (FIPS 203), one key generation and one encapsulation test case
+const ML_KEM_VECTORS: &str = include_str!("vectors/ml_kem_768.txt");
+
+// RFC 7748, section 6.1
+const ALICE_PRIVATE_KEY: &str = "77076d0a7318a57d3c16c17251b26645df4c2f87ebc0992ab177fba51db92c2a";
+const ALICE_PUBLIC_KEY: &str = "8520f0098930a754748b7ddcb43ef75a0dbf3a0d26381af4eba4a98eaa9b4e6a";
+const BOB_PRIVATE_KEY: &str = "5dab087e624a8a4b79e17f8b83800ee66f3bb1292618b6fd1c2f8b27ff88e0eb";
+const BOB_PUBLIC_KEY: &str = "de9edb7d7b7dc1b4d35b61c2ece435373f8343c85b78674dadfc7e146f882b4f";
+const X25519_SHARED_SECRET: &str = "4a5d9d5ba4ce2de1728e3bf480350f25e07e21c947d19e3376f09b3c1e161742";
+
+// Known answer for the ML-KEM shared secret of the encapsulation vector, the RFC 7748 shared secret,
+// Bob as the ephemeral key and Alice as the recipient
+const HYBRID_KEY: &str = "0e1e39a37b75acdc2289218825a7baf90122f315cf80083d1c2181dd32b11637";
+
+#[test]
+fn should_match_ml_kem_key_generation_vector() {
+    // A-rrange
+
+    let vector = ml_kem_vector(0);
+    let seed = [vector("d"), vector("z")].concat().try_into().unwrap();
+
+    // A-ct
+
+    let result = mlkem768::generate_key_pair(seed);
+
+    // A-ssert
+
+    assert_eq!(result.pk().as_slice(), vector("ek"));
+    assert_eq!(result.sk().as_slice(), vector("dk"));
+}
+
+#[test]
+fn should_match_ml_kem_encapsulation_vector() {
+    // A-rrange
+
+    let vector = ml_kem_vector(1);
+    let public_key = MlKem768PublicKey::try_from(vector("ek").as_slice()).unwrap();
+
+    // A-ct
+
+    let (ciphertext, shared_secret) = mlkem768::encapsulate(&public_key, vector("m").try_into().unwrap());
+
+    // A-ssert
+
+    assert!(mlkem768::validate_public_key(&public_key));
+    assert_eq!(ciphertext.as_ref(), vector("c"));
+    assert_eq!(shared_secret.as_slice(), vector("k"));
+}
+
+#[test]
+fn should_match_ml_kem_decapsulation_vector() {
+    // A-rrange
+
+    let vector = ml_kem_vector(1);
+    let private_key = MlKem768PrivateKey::try_from(vector("dk").as_slice()).unwrap();
+    let ciphertext = MlKem768Ciphertext::try_from(vector("c").as_slice()).unwrap();
+
+    // A-ct
+
+    let result = mlkem768::decapsulate(&private_key, &ciphertext);
+
+    // A-ssert
+
+    assert_eq!(result.as_slice(), vector("k"));
+}
+
+#[test]
+fn should_build_key_pair_from_both_vectors() {
+    // A-rrange
+
+    let vector = ml_kem_vector(0);
+    let seed: [u8; 64] = [vector("d"), vector("z")].concat().try_into().unwrap();
+    let private_key = StaticSecret::from(to_array(ALICE_PRIVATE_KEY));
+
+    // A-ct
+
+    let result = key_pair_from(private_key, seed);
+
+    // A-ssert
+
+    assert_eq!(result.private_key, [from_hex(ALICE_PRIVATE_KEY), seed.to_vec()].concat());
+    assert_eq!(result.public_key, [from_hex(ALICE_PUBLIC_KEY), vector("ek")].concat());
+}
+
+#[test]
+fn should_match_known_hybrid_key() {
+    // A-rrange
+
+    let vector = ml_kem_vector(1);
+    let alice_private_key = StaticSecret::from(to_array(ALICE_PRIVATE_KEY));
+    let bob_public_key = x25519_dalek::PublicKey::from(to_array(BOB_PUBLIC_KEY));
+    let shared_secret = alice_private_key.diffie_hellman(&bob_public_key);
+
+    // A-ct
+
+    let result = hybrid_key(
+        &vector("k"),
+        shared_secret.as_bytes(),
+        &from_hex(BOB_PUBLIC_KEY),
+        &from_hex(ALICE_PUBLIC_KEY),
+    )
+    .unwrap();
+
+    // A-ssert
+
+    assert_eq!(shared_secret.as_bytes().as_slice(), from_hex(X25519_SHARED_SECRET));
+    assert_eq!(to_hex(&result), HYBRID_KEY);
+}
+
+#[test]
+fn should_open_sealed_data() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+
+    // A-ct
+
+    let sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+    let result = open(&key_pair.private_key, &sealed_data).unwrap();
+
+    // A-ssert
+
+    assert_eq!(key_pair.private_key.len(), PRIVATE_KEY_LENGTH);
+    assert_eq!(key_pair.public_key.len(), PUBLIC_KEY_LENGTH);
+    assert!(sealed_data.len() > sharing::PUBLIC_KEY_LENGTH + ML_KEM_CIPHERTEXT_LENGTH + DATA.len());
+    assert_eq!(result, DATA);
+}
+
+#[test]
+fn should_open_data_sealed_with_fixed_randomness() {
+    // A-rrange
+
+    let vector = ml_kem_vector(0);
+    let seed = [vector("d"), vector("z")].concat().try_into().unwrap();
+    let key_pair = key_pair_from(StaticSecret::from(to_array(ALICE_PRIVATE_KEY)), seed);
+
+    // A-ct
+
+    let sealed_data = seal_with(&key_pair.public_key, DATA, StaticSecret::from(to_array(BOB_PRIVATE_KEY)), [7; 32]).unwrap();
+    let result = open(&key_pair.private_key, &sealed_data).unwrap();
+
+    // A-ssert
+
+    assert_eq!(sealed_data[..sharing::PUBLIC_KEY_LENGTH], from_hex(BOB_PUBLIC_KEY));
+    assert_eq!(result, DATA);
+}
+
+#[test]
+fn should_open_classic_sealed_data_with_classic_private_key() {
+    // A-rrange
+
+    let key_pair = sharing::generate_key_pair();
+    let sealed_data = sharing::seal(&key_pair.public_key, DATA).unwrap();
+
+    // A-ct
+
+    let result = open(&key_pair.private_key, &sealed_data).unwrap();
+
+    // A-ssert
+
+    assert_eq!(result, DATA);
+}
+
+#[test]
+fn should_seal_for_classic_public_key_with_x25519_alone() {
+    // A-rrange
+
+    let key_pair = sharing::generate_key_pair();
+
+    // A-ct
+
+    let sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+    let result = sharing::open(&key_pair.private_key, &sealed_data).unwrap();
+
+    // A-ssert
+
+    assert!(sealed_data.len() < sharing::PUBLIC_KEY_LENGTH + ML_KEM_CIPHERTEXT_LENGTH);
+    assert_eq!(result, DATA);
+}
+
+#[test]
+fn should_not_seal_for_hybrid_public_key_on_classic_path() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+
+    // A-ct
+
+    let result = sharing::seal(&key_pair.public_key, DATA);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SharingError(error)) => assert_eq!(error, "a sharing key must be 32 bytes."),
+        _ => panic!("Test result should be: 'SharingError' error."),
+    }
+}
+
+#[test]
+fn should_not_open_hybrid_sealed_data_with_x25519_alone() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+    let sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+
+    // A-ct
+
+    let result = sharing::open(&key_pair.private_key[..sharing::PUBLIC_KEY_LENGTH], &sealed_data);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_open_with_another_private_key() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+    let other_key_pair = generate_key_pair();
+    let sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+
+    // A-ct
+
+    let result = open(&other_key_pair.private_key, &sealed_data);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_open_with_another_ml_kem_key() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+    let other_key_pair = generate_key_pair();
+    let sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+
+    let private_key = [
+        &key_pair.private_key[..sharing::PUBLIC_KEY_LENGTH],
+        &other_key_pair.private_key[sharing::PUBLIC_KEY_LENGTH..],
+    ]
+    .concat();
+
+    // A-ct
+
+    let result = open(&private_key, &sealed_data);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_open_tampered_ml_kem_ciphertext() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+    let mut sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+    sealed_data[sharing::PUBLIC_KEY_LENGTH] ^= 1;
+
+    // A-ct
+
+    let result = open(&key_pair.private_key, &sealed_data);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_seal_for_invalid_public_key() {
+    // A-rrange & A-ct
+
+    let result = seal(&[1; 64], DATA);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SharingError(error)) => assert_eq!(error, "a hybrid sharing key has an invalid length."),
+        _ => panic!("Test result should be: 'SharingError' error."),
+    }
+}
+
+#[test]
+fn should_not_seal_for_invalid_ml_kem_public_key() {
+    // A-rrange
+
+    let public_key = [from_hex(ALICE_PUBLIC_KEY), vec![0xff; ML_KEM_PUBLIC_KEY_LENGTH]].concat();
+
+    // A-ct
+
+    let result = seal(&public_key, DATA);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SharingError(error)) => assert_eq!(error, "the ML-KEM public key of the share is not valid."),
+        _ => panic!("Test result should be: 'SharingError' error."),
+    }
+}
+
+#[test]
+fn should_not_open_too_short_data() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+
+    // A-ct
+
+    let result = open(&key_pair.private_key, &[0; 64]);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SharingError(error)) => {
+            assert_eq!(error, "the sealed data is too short to contain a public key and an ML-KEM ciphertext.")
+        }
+        _ => panic!("Test result should be: 'SharingError' error."),
+    }
+}
+
+#[test]
+fn should_generate_hybrid_key_pair_in_key_manager() {
+    // A-rrange & A-ct
+
+    let key_pair = KeyManager::generate_sharing_key_pair();
+    let sealed_data = KeyManager::seal(&key_pair.public_key, DATA).unwrap();
+    let result = KeyManager::open_sealed(&key_pair.private_key, &sealed_data).unwrap();
+
+    // A-ssert
+
+    assert_eq!(key_pair.public_key.len(), PUBLIC_KEY_LENGTH);
+    assert_eq!(result, DATA);
+}
+
+/// The test cases of the vector file are separated by blank lines, each value is on a `name = hex` line
+fn ml_kem_vector(index: usize) -> impl Fn(&str) -> Vec<u8> {
+    let test_case = ML_KEM_VECTORS.split("\n\n").nth(index).unwrap();
+
+    move |name| {
+        test_case
+            .lines()
+            .filter_map(|line| line.split_once(" = "))
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| from_hex(value))
+            .unwrap()
+    }
+}
+
+fn to_array(hex: &str) -> [u8; 32] {
+    from_hex(hex).try_into().unwrap()
+}
+
+fn from_hex(hex: &str) -> Vec<u8> {
+    (0..hex.len())
+        .step_by(2)
+        .map(|position| u8::from_str_radix(&hex[position..position + 2], 16).unwrap())
+        .collect()
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
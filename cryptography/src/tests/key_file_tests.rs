@@ -0,0 +1,97 @@
+use app_core::cryptography::cryptography_error::CryptographyError;
+
+use crate::{
+    envelope::{generate_data_key, unwrap_data_key, wrap_data_key},
+    key_file::{KEY_FILE_SECRET_LENGTH, MIXED_KEY_LENGTH, generate_key_file, mix_key_file},
+};
+
+const EXPORT_KEY: &[u8] = &[42; 64];
+const KEY_FILE: &[u8] = b"FERRIS-VAULT-KEY-FILE-V1\n0909090909090909090909090909090909090909090909090909090909090909\n";
+
+// Known answer for EXPORT_KEY and KEY_FILE
+const MIXED_KEY: &str = "71cd1b30c6a08e4937e2d102c188edfb286f02091f0b9a290de20780379f0fc5\
+                         0d88b09fbb5161579003f14a4162fa55688c1f360edd81086b7a249ddbe5e5ff";
+
+#[test]
+fn should_generate_random_key_file() {
+    // A-rrange & A-ct
+
+    let first_key_file = generate_key_file();
+    let second_key_file = generate_key_file();
+
+    // A-ssert
+
+    let text = String::from_utf8(first_key_file.clone()).unwrap();
+    let lines: Vec<&str> = text.lines().collect();
+
+    assert_eq!(lines[0], "FERRIS-VAULT-KEY-FILE-V1");
+    assert_eq!(lines[1].len(), KEY_FILE_SECRET_LENGTH * 2);
+    assert_ne!(first_key_file, second_key_file);
+}
+
+#[test]
+fn should_match_known_mixed_key() {
+    // A-rrange & A-ct
+
+    let result = mix_key_file(EXPORT_KEY, KEY_FILE).unwrap();
+
+    // A-ssert
+
+    assert_eq!(result.len(), MIXED_KEY_LENGTH);
+    assert_eq!(to_hex(&result), MIXED_KEY);
+}
+
+#[test]
+fn should_not_unwrap_data_key_with_password_alone() {
+    // A-rrange
+
+    let data_key = generate_data_key();
+    let mixed_key = mix_key_file(EXPORT_KEY, KEY_FILE).unwrap();
+    let wrapped_data_key = wrap_data_key(&mixed_key, &data_key).unwrap();
+
+    // A-ct
+
+    let result = unwrap_data_key(EXPORT_KEY, &wrapped_data_key);
+
+    // A-ssert
+
+    assert!(result.is_err());
+    assert_eq!(unwrap_data_key(&mixed_key, &wrapped_data_key).unwrap(), data_key);
+}
+
+#[test]
+fn should_not_unwrap_data_key_with_another_key_file() {
+    // A-rrange
+
+    let data_key = generate_data_key();
+    let mixed_key = mix_key_file(EXPORT_KEY, KEY_FILE).unwrap();
+    let wrapped_data_key = wrap_data_key(&mixed_key, &data_key).unwrap();
+
+    let other_mixed_key = mix_key_file(EXPORT_KEY, &generate_key_file()).unwrap();
+
+    // A-ct
+
+    let result = unwrap_data_key(&other_mixed_key, &wrapped_data_key);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_mix_empty_key_file() {
+    // A-rrange & A-ct
+
+    let result = mix_key_file(EXPORT_KEY, &[]);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::KeyFileError(error)) => assert_eq!(error, "the key file is empty."),
+        _ => panic!("Test result should be: 'KeyFileError' error."),
+    }
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
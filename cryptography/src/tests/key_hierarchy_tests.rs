@@ -0,0 +1,97 @@
+use app_core::generic_array::GenericArray;
+use chacha20poly1305::consts::U32;
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::key_hierarchy::{KeyHierarchy, KeyPurpose};
+
+const EXPORT_KEY: &[u8] = &[42; 64];
+
+// Known answers for EXPORT_KEY, a change here means every key already in use would change
+const KNOWN_ANSWERS: [(KeyPurpose, &str); 10] = [
+    (KeyPurpose::Vault, "a41b14eba081399b8c5817e2541ca3c3d5ad63279d75c84c8e07788256fc75b0"),
+    (KeyPurpose::VaultAes256GcmSiv, "038a37694a291d8ece748e4dff1a8e5d690d8063441208b8bf991b48b50a8257"),
+    (KeyPurpose::Attachments, "03ae44d7f83a744f2b289c33c9b746187b9b7761903a943132fd671abb54afb0"),
+    (KeyPurpose::Sharing, "0f29edf7e229de50fe3b1371d2593e589d19691e1d8fbb45417134f12511e176"),
+    (KeyPurpose::LocalCache, "8e99181492ff545e4877ce1840e78f5812e7a450b7444708ade355fc3688acd6"),
+    (KeyPurpose::SearchIndex, "420812cee8c63a6afa61b7e91202ae85038080f0d14bffbc05b69397f0ce5b01"),
+    (KeyPurpose::KeyWrapping, "bfeaf0cb64225ec3d1b2926b1c5cf1c246b0b2c1cd95edd7714abbb3c8372ec5"),
+    (KeyPurpose::RecoveryToken, "0d3e484687e80cf7fd61fc591a952a687cb258d2cab678b09e29e133d264b128"),
+    (KeyPurpose::PasswordDerivation, "b573946d33d218e12e4072f61e230963e8b4fea2a0ae7f623839b001f7f89223"),
+    (KeyPurpose::HybridSharing, "844384090fcb104e9ee361b0b140c6e542a4baa5292d984050aa8015d042e11e"),
+];
+
+#[test]
+fn should_match_known_answers() {
+    // A-rrange
+
+    let key_hierarchy = KeyHierarchy::new(EXPORT_KEY);
+
+    for (purpose, expected) in KNOWN_ANSWERS {
+        // A-ct
+
+        let result = key_hierarchy.derive_key(purpose).unwrap();
+
+        // A-ssert
+
+        assert_eq!(to_hex(&result), expected, "{purpose:?}");
+    }
+}
+
+#[test]
+fn should_derive_a_different_key_for_each_purpose() {
+    // A-rrange
+
+    let key_hierarchy = KeyHierarchy::new(EXPORT_KEY);
+
+    // A-ct
+
+    let keys: Vec<_> = KeyPurpose::ALL
+        .iter()
+        .map(|purpose| key_hierarchy.derive_key(*purpose).unwrap())
+        .collect();
+
+    // A-ssert
+
+    for (index, key) in keys.iter().enumerate() {
+        assert!(keys[index + 1..].iter().all(|other| other != key));
+    }
+}
+
+#[test]
+fn should_keep_the_vault_key_of_existing_vaults() {
+    // A-rrange
+
+    let hkdf = Hkdf::<Sha256>::new(None, EXPORT_KEY);
+    let mut expected = GenericArray::<u8, U32>::default();
+    hkdf.expand(b"xchacha20-poly1305-key", &mut expected).unwrap();
+
+    // A-ct
+
+    let result = KeyHierarchy::new(EXPORT_KEY).derive_key(KeyPurpose::Vault).unwrap();
+
+    // A-ssert
+
+    assert_eq!(result, expected);
+}
+
+#[test]
+fn should_depend_on_the_export_key() {
+    // A-rrange
+
+    let first_key_hierarchy = KeyHierarchy::new(EXPORT_KEY);
+    let second_key_hierarchy = KeyHierarchy::new(&[43; 64]);
+
+    // A-ct
+
+    let first_result = first_key_hierarchy.derive_key(KeyPurpose::Sharing).unwrap();
+    let second_result = second_key_hierarchy.derive_key(KeyPurpose::Sharing).unwrap();
+
+    // A-ssert
+
+    assert_ne!(first_result, second_result);
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
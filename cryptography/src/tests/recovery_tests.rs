@@ -0,0 +1,87 @@
+use crate::{
+    envelope::{generate_data_key, unwrap_data_key, wrap_data_key},
+    key_hierarchy::{KeyHierarchy, KeyPurpose},
+    recovery::{RECOVERY_KEY_LENGTH, generate_recovery_key, recovery_token},
+};
+
+const RECOVERY_KEY: &[u8] = &[9; RECOVERY_KEY_LENGTH];
+
+// Known answer for RECOVERY_KEY, the server only knows this token
+const RECOVERY_TOKEN: &str = "f39cef5ec251e8759cba1411a0fe3d0e544cba185b05589739905ba1eb3608da";
+
+#[test]
+fn should_generate_random_recovery_key() {
+    // A-rrange & A-ct
+
+    let first_recovery_key = generate_recovery_key();
+    let second_recovery_key = generate_recovery_key();
+
+    // A-ssert
+
+    assert_eq!(first_recovery_key.len(), RECOVERY_KEY_LENGTH);
+    assert_ne!(first_recovery_key, second_recovery_key);
+}
+
+#[test]
+fn should_match_known_recovery_token() {
+    // A-rrange & A-ct
+
+    let result = recovery_token(RECOVERY_KEY).unwrap();
+
+    // A-ssert
+
+    assert_eq!(to_hex(&result), RECOVERY_TOKEN);
+}
+
+#[test]
+fn should_not_reveal_the_key_wrapping_key_in_the_token() {
+    // A-rrange
+
+    let key_wrapping_key = KeyHierarchy::new(RECOVERY_KEY)
+        .derive_key(KeyPurpose::KeyWrapping)
+        .unwrap();
+
+    // A-ct
+
+    let result = recovery_token(RECOVERY_KEY).unwrap();
+
+    // A-ssert
+
+    assert_ne!(result[..], key_wrapping_key[..]);
+}
+
+#[test]
+fn should_unwrap_data_key_with_recovery_key() {
+    // A-rrange
+
+    let data_key = generate_data_key();
+    let wrapped_data_key = wrap_data_key(RECOVERY_KEY, &data_key).unwrap();
+
+    // A-ct
+
+    let result = unwrap_data_key(RECOVERY_KEY, &wrapped_data_key);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), data_key);
+}
+
+#[test]
+fn should_not_unwrap_data_key_with_another_recovery_key() {
+    // A-rrange
+
+    let data_key = generate_data_key();
+    let wrapped_data_key = wrap_data_key(RECOVERY_KEY, &data_key).unwrap();
+
+    // A-ct
+
+    let result = unwrap_data_key(&generate_recovery_key(), &wrapped_data_key);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
@@ -0,0 +1,239 @@
+use app_core::cryptography::cryptography_error::CryptographyError;
+use chacha20poly1305::aead::rand_core::{self, RngCore};
+
+use crate::shamir::{Share, combine, gf_inverse, gf_mul, split, split_with_rng};
+
+const SECRET: &[u8] = b"ferris";
+
+// Shares of SECRET for a 2 of 3 split whose coefficients are 1, 2, 3...
+const KNOWN_SHARES: [&str; 3] = [
+    "010201676771766c75dd828a6c",
+    "0102026461747a637f4f683e64",
+    "0102036563777e6679c782a0f9",
+];
+const KNOWN_MNEMONIC: &str =
+    "actor agent actor gravel gravel igloo jelly helmet jaguar temple lagoon mango helmet";
+
+#[test]
+fn should_multiply_in_aes_field() {
+    // A-rrange & A-ct & A-ssert
+
+    // FIPS-197 examples
+    assert_eq!(gf_mul(0x57, 0x83), 0xc1);
+    assert_eq!(gf_mul(0x53, 0xca), 0x01);
+    assert_eq!(gf_inverse(0x53), 0xca);
+}
+
+#[test]
+fn should_split_into_known_shares() {
+    // A-rrange
+
+    let mut rng = CountingRng(0);
+
+    // A-ct
+
+    let result = split_with_rng(SECRET, 2, 3, &mut rng).unwrap();
+
+    // A-ssert
+
+    let hex_shares: Vec<String> = result.iter().map(|share| share.to_hex()).collect();
+
+    assert_eq!(hex_shares, KNOWN_SHARES);
+    assert_eq!(result[0].to_mnemonic(), KNOWN_MNEMONIC);
+}
+
+#[test]
+fn should_combine_known_shares() {
+    // A-rrange
+
+    // 3 of 5 split of [0x2a, 0x00, 0xff] whose coefficients are 1, 2, 3...
+    let shares = [
+        Share { threshold: 3, index: 2, value: vec![0x20, 0x16, 0xed] },
+        Share { threshold: 3, index: 4, value: vec![0x0e, 0x4c, 0x8b] },
+        Share { threshold: 3, index: 5, value: vec![0x0d, 0x4b, 0x88] },
+    ];
+
+    // A-ct
+
+    let result = combine(&shares);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), [0x2a, 0x00, 0xff]);
+}
+
+#[test]
+fn should_combine_any_two_of_three_shares() {
+    // A-rrange
+
+    let secret = [42u8; 20];
+    let shares = split(&secret, 2, 3).unwrap();
+
+    for (first, second) in [(0, 1), (0, 2), (1, 2), (2, 0)] {
+        // A-ct
+
+        let result = combine(&[shares[first].clone(), shares[second].clone()]);
+
+        // A-ssert
+
+        assert_eq!(result.unwrap(), secret);
+    }
+}
+
+#[test]
+fn should_parse_mnemonic_and_hex_of_the_same_share() {
+    // A-rrange
+
+    let share = Share::parse(KNOWN_SHARES[0]).unwrap();
+
+    // A-ct
+
+    let result = Share::parse(KNOWN_MNEMONIC);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), share);
+    assert_eq!(share.index, 1);
+    assert_eq!(share.threshold, 2);
+}
+
+#[test]
+fn should_parse_mnemonic_from_first_letters() {
+    // A-rrange
+
+    let mnemonic = "ACTO agen acto grav grav iglo jell helm jagu temp lago mang helm";
+
+    // A-ct
+
+    let result = Share::parse(mnemonic);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), Share::parse(KNOWN_MNEMONIC).unwrap());
+}
+
+#[test]
+fn should_not_parse_share_with_wrong_checksum() {
+    // A-rrange
+
+    let mnemonic = KNOWN_MNEMONIC.replacen("gravel", "gecko", 1);
+
+    // A-ct
+
+    let result = Share::parse(&mnemonic);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SecretSharingError(error)) => {
+            assert_eq!(error, "wrong checksum, the share was mistyped.")
+        }
+        _ => panic!("Test result should be: 'SecretSharingError' error."),
+    }
+}
+
+#[test]
+fn should_not_parse_unknown_word() {
+    // A-rrange
+
+    let mnemonic = KNOWN_MNEMONIC.replacen("gravel", "ferris", 1);
+
+    // A-ct
+
+    let result = Share::parse(&mnemonic);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SecretSharingError(error)) => assert_eq!(error, "unknown word in the share."),
+        _ => panic!("Test result should be: 'SecretSharingError' error."),
+    }
+}
+
+#[test]
+fn should_not_combine_with_too_few_shares() {
+    // A-rrange
+
+    let share = Share::parse(KNOWN_SHARES[0]).unwrap();
+
+    // A-ct
+
+    let result = combine(&[share]);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SecretSharingError(error)) => {
+            assert_eq!(error, "2 shares are needed, only 1 given.")
+        }
+        _ => panic!("Test result should be: 'SecretSharingError' error."),
+    }
+}
+
+#[test]
+fn should_not_combine_the_same_share_twice() {
+    // A-rrange
+
+    let share = Share::parse(KNOWN_SHARES[0]).unwrap();
+
+    // A-ct
+
+    let result = combine(&[share.clone(), share]);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SecretSharingError(error)) => assert_eq!(error, "the same share was given twice."),
+        _ => panic!("Test result should be: 'SecretSharingError' error."),
+    }
+}
+
+#[test]
+fn should_not_split_with_invalid_threshold() {
+    // A-rrange & A-ct
+
+    let results = [split(SECRET, 1, 3), split(SECRET, 4, 3)];
+
+    // A-ssert
+
+    for result in results {
+        match result {
+            Err(CryptographyError::SecretSharingError(error)) => {
+                assert_eq!(error, "the threshold must be between 2 and the number of shares.")
+            }
+            _ => panic!("Test result should be: 'SecretSharingError' error."),
+        }
+    }
+}
+
+/// Returns 1, 2, 3... so the coefficients of the polynomials are known
+struct CountingRng(u8);
+
+impl RngCore for CountingRng {
+    fn next_u32(&mut self) -> u32 {
+        let mut bytes = [0u8; 4];
+        self.fill_bytes(&mut bytes);
+
+        u32::from_le_bytes(bytes)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        let mut bytes = [0u8; 8];
+        self.fill_bytes(&mut bytes);
+
+        u64::from_le_bytes(bytes)
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for byte in dest {
+            self.0 += 1;
+            *byte = self.0;
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand_core::Error> {
+        self.fill_bytes(dest);
+
+        Ok(())
+    }
+}
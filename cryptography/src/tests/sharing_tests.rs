@@ -0,0 +1,152 @@
+use app_core::cryptography::cryptography_error::CryptographyError;
+
+use crate::sharing::{FINGERPRINT_WORD_COUNT, PUBLIC_KEY_LENGTH, fingerprint, generate_key_pair, open, seal};
+
+const DATA: &[u8] = b"shared entry";
+
+// Known answer for a public key of 32 bytes set to 1
+const FINGERPRINT: &str = "chalk honey copper cedar elbow candle comet tunnel river camel garlic kernel";
+
+#[test]
+fn should_open_sealed_data() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+
+    // A-ct
+
+    let sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+    let result = open(&key_pair.private_key, &sealed_data).unwrap();
+
+    // A-ssert
+
+    assert_eq!(key_pair.private_key.len(), 32);
+    assert_eq!(key_pair.public_key.len(), PUBLIC_KEY_LENGTH);
+    assert_eq!(result, DATA);
+}
+
+#[test]
+fn should_seal_with_new_ephemeral_key() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+
+    // A-ct
+
+    let first_sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+    let second_sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+
+    // A-ssert
+
+    assert_ne!(first_sealed_data[..PUBLIC_KEY_LENGTH], second_sealed_data[..PUBLIC_KEY_LENGTH]);
+}
+
+#[test]
+fn should_not_open_with_another_private_key() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+    let other_key_pair = generate_key_pair();
+    let sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+
+    // A-ct
+
+    let result = open(&other_key_pair.private_key, &sealed_data);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_open_tampered_ephemeral_key() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+    let mut sealed_data = seal(&key_pair.public_key, DATA).unwrap();
+    sealed_data[0] ^= 1;
+
+    // A-ct
+
+    let result = open(&key_pair.private_key, &sealed_data);
+
+    // A-ssert
+
+    assert!(result.is_err());
+}
+
+#[test]
+fn should_not_seal_for_invalid_public_key() {
+    // A-rrange & A-ct
+
+    let result = seal(&[1; 16], DATA);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SharingError(error)) => assert_eq!(error, "a sharing key must be 32 bytes."),
+        _ => panic!("Test result should be: 'SharingError' error."),
+    }
+}
+
+#[test]
+fn should_not_seal_for_low_order_public_key() {
+    // A-rrange & A-ct
+
+    let result = seal(&[0; PUBLIC_KEY_LENGTH], DATA);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SharingError(error)) => assert_eq!(error, "the public key of the share is a low order point."),
+        _ => panic!("Test result should be: 'SharingError' error."),
+    }
+}
+
+#[test]
+fn should_not_open_too_short_data() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+
+    // A-ct
+
+    let result = open(&key_pair.private_key, &[0; 8]);
+
+    // A-ssert
+
+    match result {
+        Err(CryptographyError::SharingError(error)) => assert_eq!(error, "the sealed data is too short to contain a public key."),
+        _ => panic!("Test result should be: 'SharingError' error."),
+    }
+}
+
+#[test]
+fn should_match_known_fingerprint() {
+    // A-rrange & A-ct
+
+    let result = fingerprint(&[1; PUBLIC_KEY_LENGTH]);
+
+    // A-ssert
+
+    assert_eq!(result.split(' ').count(), FINGERPRINT_WORD_COUNT);
+    assert_eq!(result, FINGERPRINT);
+}
+
+#[test]
+fn should_give_other_fingerprint_for_other_key() {
+    // A-rrange
+
+    let key_pair = generate_key_pair();
+    let other_key_pair = generate_key_pair();
+
+    // A-ct
+
+    let first_fingerprint = fingerprint(&key_pair.public_key);
+    let second_fingerprint = fingerprint(&other_key_pair.public_key);
+
+    // A-ssert
+
+    assert_eq!(first_fingerprint, fingerprint(&key_pair.public_key));
+    assert_ne!(first_fingerprint, second_fingerprint);
+}
@@ -8,20 +8,27 @@ use app_core::{
 };
 use chacha20poly1305::{
     AeadCore, Key, KeyInit,
-    aead::{Aead, OsRng},
+    aead::{Aead, OsRng, Payload},
     consts::U32,
 };
-use hkdf::Hkdf;
-use sha2::Sha256;
+
+use crate::{
+    envelope,
+    key_hierarchy::{KeyHierarchy, KeyPurpose},
+    key_manager::KeyManager,
+};
 
 pub(crate) const NONCE_LENGTH: usize = 24;
 
+const TOO_SHORT_ERROR: &str = "the data is too short to contain a nonce.";
+
 #[derive(Debug, Default)]
 pub struct NoKeyXChaCha20Poly1305;
 
 impl NoKeyCipher for NoKeyXChaCha20Poly1305 {
 
     type Crypto = XChaCha20Poly1305;
+    type Keys = KeyManager;
 
     /// Used in order to convert the key from 64 to 32 bytes
     fn create_cipher_from_key(key: &[u8]) -> Result<Self::Crypto> {
@@ -32,14 +39,22 @@ impl NoKeyCipher for NoKeyXChaCha20Poly1305 {
             });
         }
 
-        let hkdf = Hkdf::<Sha256>::new(None, key);
-        let mut derived = GenericArray::<u8, U32>::default();
+        let derived = KeyHierarchy::new(key).derive_key(KeyPurpose::Vault)?;
 
-        hkdf.expand(b"xchacha20-poly1305-key", &mut derived)
-            .map_err(|error| CryptographyError::DerivationError(error.to_string()))?;
-        
         Ok(XChaCha20Poly1305 { key: derived })
     }
+
+    fn generate_data_key() -> Vec<u8> {
+        envelope::generate_data_key()
+    }
+
+    fn wrap_data_key(key: &[u8], data_key: &[u8]) -> Result<Vec<u8>> {
+        envelope::wrap_data_key(key, data_key)
+    }
+
+    fn unwrap_data_key(key: &[u8], wrapped_data_key: &[u8]) -> Result<Vec<u8>> {
+        envelope::unwrap_data_key(key, wrapped_data_key)
+    }
 }
 
 #[derive(Debug, Default)]
@@ -47,34 +62,57 @@ pub struct XChaCha20Poly1305 {
     key: Key,
 }
 
-impl Cryptography for XChaCha20Poly1305 {
-    fn encrypt(&self, uncrypted_vault: &UncryptedVault) -> Result<Vec<u8>> {
-        let data = uncrypted_vault.as_bytes()?;
+impl XChaCha20Poly1305 {
+    pub(crate) fn new(key: Key) -> Self {
+        Self { key }
+    }
 
+    /// `nonce | ciphertext`, the associated data is authenticated but not included
+    pub(crate) fn encrypt_data(&self, data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
         let cipher = chacha20poly1305::XChaCha20Poly1305::new(&self.key);
         let nonce = chacha20poly1305::XChaCha20Poly1305::generate_nonce(&mut OsRng);
 
         let data = cipher
-            .encrypt(&nonce, data.as_ref())
+            .encrypt(&nonce, Payload { msg: data, aad })
             .map_err(|error| CryptographyError::EncryptionError(error.to_string()))?;
 
         // nonce.len() should be 24
-        let mut crypted_vault = Vec::with_capacity(NONCE_LENGTH + data.len());
-        crypted_vault.extend_from_slice(&nonce);
-        crypted_vault.extend_from_slice(&data);
+        let mut crypted_data = Vec::with_capacity(NONCE_LENGTH + data.len());
+        crypted_data.extend_from_slice(&nonce);
+        crypted_data.extend_from_slice(&data);
 
-        Ok(crypted_vault)
+        Ok(crypted_data)
     }
 
-    fn decrypt(&self, crypted_vault: &[u8]) -> Result<UncryptedVault> {
+    pub(crate) fn decrypt_data(&self, crypted_data: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        if crypted_data.len() < NONCE_LENGTH {
+            return Err(CryptographyError::DecryptionError(TOO_SHORT_ERROR.to_string()));
+        }
+
         let cipher = chacha20poly1305::XChaCha20Poly1305::new(&self.key);
-        let (nonce, data) = crypted_vault.split_at(NONCE_LENGTH);
+        let (nonce, data) = crypted_data.split_at(NONCE_LENGTH);
         let nonce = GenericArray::from_slice(nonce);
 
-        let data = cipher
-            .decrypt(&nonce, data.as_ref())
-            .map_err(|error| CryptographyError::DecryptionError(error.to_string()))?;
+        cipher
+            .decrypt(nonce, Payload { msg: data, aad })
+            .map_err(|error| CryptographyError::DecryptionError(error.to_string()))
+    }
+}
+
+impl Cryptography for XChaCha20Poly1305 {
+    fn encrypt(&self, uncrypted_vault: &UncryptedVault) -> Result<Vec<u8>> {
+        self.encrypt_data(&uncrypted_vault.as_bytes()?, &[])
+    }
+
+    fn decrypt(&self, crypted_vault: &[u8]) -> Result<UncryptedVault> {
+        UncryptedVault::decode(&self.decrypt_data(crypted_vault, &[])?)
+    }
+
+    fn encrypt_record(&self, record: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.encrypt_data(record, aad)
+    }
 
-        UncryptedVault::decode(&data)
+    fn decrypt_record(&self, crypted_record: &[u8], aad: &[u8]) -> Result<Vec<u8>> {
+        self.decrypt_data(crypted_record, aad)
     }
 }
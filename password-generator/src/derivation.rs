@@ -0,0 +1,137 @@
+use app_core::password::derivation_profile::DerivationProfile;
+use argon2::{Algorithm, Argon2, Params, Version};
+use hkdf::Hkdf;
+use sha2::Sha256;
+
+use crate::{get_character_classes, validate_restrictions};
+
+const EMPTY_MASTER_SECRET_ERROR: &str = "The master secret cannot be empty.";
+const TOO_LONG_ERROR: &str = "A derived password cannot be longer than 512 characters.";
+const EXHAUSTED_STREAM_ERROR: &str = "Not enough derived bytes to build the password.";
+
+// Changing any of these values changes every derived password, bump the domain version instead
+const DERIVATION_DOMAIN: &[u8] = b"ferris-vault-derivation-v1";
+const CHARACTERS_INFO: &[u8] = b"ferris-vault-derivation-v1-characters";
+const ARGON2_MEMORY_COST: u32 = 19 * 1024;
+const ARGON2_ITERATIONS: u32 = 2;
+const ARGON2_PARALLELISM: u32 = 1;
+const DERIVED_KEY_LENGTH: usize = 32;
+
+pub(crate) const MAX_DERIVED_LENGTH: usize = 512;
+
+/// Derives a password from the master secret and the profile (site, login, counter, restrictions)
+///
+/// The master secret is stretched with Argon2id, salted with the profile,
+/// then expanded with HKDF-SHA256 into a stream used to pick and shuffle the characters.
+/// One character of each enabled class is always present, like the random generator.
+pub fn derive_password(master_secret: &[u8], profile: &DerivationProfile) -> Result<Vec<u8>, String> {
+
+    let restrictions = &profile.restrictions;
+
+    if master_secret.is_empty() {
+        return Err(EMPTY_MASTER_SECRET_ERROR.to_string());
+    }
+
+    if restrictions.length > MAX_DERIVED_LENGTH {
+        return Err(TOO_LONG_ERROR.to_string());
+    }
+
+    validate_restrictions(restrictions)?;
+
+    let key = stretch_master_secret(master_secret, profile)?;
+
+    let mut stream = DerivationStream::new(&key, 2 * (4 * restrictions.length + 64))?;
+
+    let character_classes = get_character_classes(restrictions);
+    let password_charset = character_classes.concat();
+
+    let mut password = Vec::with_capacity(restrictions.length);
+
+    for character_class in &character_classes {
+        password.push(character_class[stream.next_index(character_class.len())?]);
+    }
+
+    while password.len() < restrictions.length {
+        password.push(password_charset[stream.next_index(password_charset.len())?]);
+    }
+
+    // Fisher-Yates, so the mandatory characters don't always come first
+    for index in (1..password.len()).rev() {
+        let other = stream.next_index(index + 1)?;
+        password.swap(index, other);
+    }
+
+    Ok(password)
+}
+
+fn stretch_master_secret(master_secret: &[u8], profile: &DerivationProfile) -> Result<[u8; DERIVED_KEY_LENGTH], String> {
+
+    let params = Params::new(
+        ARGON2_MEMORY_COST,
+        ARGON2_ITERATIONS,
+        ARGON2_PARALLELISM,
+        Some(DERIVED_KEY_LENGTH),
+    )
+    .map_err(|error| error.to_string())?;
+
+    let argon2 = Argon2::new(Algorithm::Argon2id, Version::V0x13, params);
+
+    let mut key = [0u8; DERIVED_KEY_LENGTH];
+
+    argon2
+        .hash_password_into(master_secret, &create_salt(profile), &mut key)
+        .map_err(|error| error.to_string())?;
+
+    Ok(key)
+}
+
+/// Length prefixed so that ("ab", "c") and ("a", "bc") never share a salt
+fn create_salt(profile: &DerivationProfile) -> Vec<u8> {
+    let mut salt = Vec::with_capacity(DERIVATION_DOMAIN.len() + profile.site.len() + profile.login.len() + 12);
+
+    salt.extend_from_slice(DERIVATION_DOMAIN);
+    salt.extend_from_slice(&(profile.site.len() as u32).to_be_bytes());
+    salt.extend_from_slice(profile.site.as_bytes());
+    salt.extend_from_slice(&(profile.login.len() as u32).to_be_bytes());
+    salt.extend_from_slice(profile.login.as_bytes());
+    salt.extend_from_slice(&profile.counter.to_be_bytes());
+
+    salt
+}
+
+struct DerivationStream {
+    bytes: Vec<u8>,
+    position: usize,
+}
+
+impl DerivationStream {
+    fn new(key: &[u8], length: usize) -> Result<Self, String> {
+        let hkdf = Hkdf::<Sha256>::new(None, key);
+
+        let mut bytes = vec![0u8; length];
+
+        hkdf.expand(CHARACTERS_INFO, &mut bytes)
+            .map_err(|error| error.to_string())?;
+
+        Ok(Self { bytes, position: 0 })
+    }
+
+    /// Uniform index in 0..bound, rejection sampling on 16 bits values to avoid a modulo bias
+    fn next_index(&mut self, bound: usize) -> Result<usize, String> {
+        let limit = u16::MAX as usize + 1 - (u16::MAX as usize + 1) % bound;
+
+        loop {
+            let Some(sample) = self.bytes.get(self.position..self.position + 2) else {
+                return Err(EXHAUSTED_STREAM_ERROR.to_string());
+            };
+
+            self.position += 2;
+
+            let value = u16::from_be_bytes([sample[0], sample[1]]) as usize;
+
+            if value < limit {
+                return Ok(value % bound);
+            }
+        }
+    }
+}
@@ -1,6 +1,12 @@
-use app_core::{password::password_restriction::PasswordRestriction, ports::password_generator::PasswordGenerator};
+use app_core::{
+    password::{derivation_profile::DerivationProfile, password_restriction::PasswordRestriction},
+    ports::password_generator::PasswordGenerator,
+};
 use rand::seq::{IndexedRandom, SliceRandom};
 
+pub mod derivation;
+pub mod username_generator;
+
 const MORE_RESTRINCTIONS_THAN_LENGTH_ERROR: &'static str = "There cannot be more restrictions than the desired length.";
 const NO_RESTRICTION_FOUND_ERROR:           &'static str = "You must choose at least one password restriction.";
 const ZERO_LENGTH_ERROR:                    &'static str = "A password cannot be of length 0.";
@@ -9,6 +15,7 @@ const LOWER_CASE_LETTERS: &'static [u8; 26] = b"abcdefghijklmnopqrstuvwxyz";
 const UPPER_CASE_LETTERS: &'static [u8; 26] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZ";
 const NUMBERS:            &'static [u8; 10] = b"0123456789";
 const SPECIAL_CHARACTERS: &'static [u8; 32] = b"!\"#$%&'()*+,-./:;<=>?@[\\]^_`{|}~";
+const AMBIGUOUS_CHARACTERS: &[u8] = b"Il1|O0o`'\"";
 
 #[derive(Debug, Default)]
 pub struct SecurePasswordGenerator;
@@ -17,59 +24,88 @@ impl PasswordGenerator for SecurePasswordGenerator {
 
     fn generate_password(restrictions: &PasswordRestriction) -> Result<Vec<u8>, String> {
 
-        if restrictions.length == 0 {
-            return Err(ZERO_LENGTH_ERROR.to_string());
-        }
+        validate_restrictions(restrictions)?;
 
         let mut rng = rand::rng();
 
         let mut password_charset = Vec::new();
         let mut password = Vec::with_capacity(restrictions.length);
 
-        let mut count = 0;
-
-        if restrictions.lower_case {
-            password_charset.extend_from_slice(LOWER_CASE_LETTERS);
-            password.push(LOWER_CASE_LETTERS.choose(&mut rng).unwrap().to_owned());
-            count += 1;
+        for character_class in get_character_classes(restrictions) {
+            password_charset.extend_from_slice(&character_class);
+            password.push(character_class.choose(&mut rng).unwrap().to_owned());
         }
 
-        if restrictions.upper_case {
-            password_charset.extend_from_slice(UPPER_CASE_LETTERS);
-            password.push(UPPER_CASE_LETTERS.choose(&mut rng).unwrap().to_owned());
-            count += 1;
-        }
+        let remaining_character_count = restrictions.length.saturating_sub(password.len());
 
-        if restrictions.numbers {
-            password_charset.extend_from_slice(NUMBERS);
-            password.push(NUMBERS.choose(&mut rng).unwrap().to_owned());
-            count += 1;
+        for _ in 0..remaining_character_count {
+            password.push(password_charset.choose(&mut rng).unwrap().to_owned());
         }
 
-        if restrictions.special_characters {
-            password_charset.extend_from_slice(SPECIAL_CHARACTERS);
-            password.push(SPECIAL_CHARACTERS.choose(&mut rng).unwrap().to_owned());
-            count += 1;
-        }
+        password.shuffle(&mut rng);
 
-        if restrictions.length < count {
-            return Err(MORE_RESTRINCTIONS_THAN_LENGTH_ERROR.to_string());
-        }
+        Ok(password)
+    }
 
-        if password_charset.is_empty() {
-            return Err(NO_RESTRICTION_FOUND_ERROR.to_string());
-        }
+    fn entropy(restrictions: &PasswordRestriction) -> f64 {
 
-        let remaining_character_count = restrictions.length.saturating_sub(password.len());
+        let charset_length: usize = get_character_classes(restrictions)
+            .iter()
+            .map(|character_class| character_class.len())
+            .sum();
 
-        for _ in 0..remaining_character_count {
-            password.push(password_charset.choose(&mut rng).unwrap().to_owned());
+        if charset_length == 0 {
+            return 0.0;
         }
 
-        password.shuffle(&mut rng);
+        restrictions.length as f64 * (charset_length as f64).log2()
+    }
 
-        Ok(password)
+    fn derive_password(master_secret: &[u8], profile: &DerivationProfile) -> Result<Vec<u8>, String> {
+        derivation::derive_password(master_secret, profile)
+    }
+}
+
+fn validate_restrictions(restrictions: &PasswordRestriction) -> Result<(), String> {
+
+    if restrictions.length == 0 {
+        return Err(ZERO_LENGTH_ERROR.to_string());
     }
+
+    let character_class_count = get_character_classes(restrictions).len();
+
+    if restrictions.length < character_class_count {
+        return Err(MORE_RESTRINCTIONS_THAN_LENGTH_ERROR.to_string());
+    }
+
+    if character_class_count == 0 {
+        return Err(NO_RESTRICTION_FOUND_ERROR.to_string());
+    }
+
+    Ok(())
+}
+
+/// Returns every character class enabled by the restrictions, without the ambiguous characters if asked to
+fn get_character_classes(restrictions: &PasswordRestriction) -> Vec<Vec<u8>> {
+
+    let character_classes: [(bool, &[u8]); 4] = [
+        (restrictions.lower_case, LOWER_CASE_LETTERS),
+        (restrictions.upper_case, UPPER_CASE_LETTERS),
+        (restrictions.numbers, NUMBERS),
+        (restrictions.special_characters, SPECIAL_CHARACTERS),
+    ];
+
+    character_classes
+        .into_iter()
+        .filter(|(enabled, _)| *enabled)
+        .map(|(_, characters)| {
+            characters
+                .iter()
+                .filter(|character| !restrictions.exclude_ambiguous || !AMBIGUOUS_CHARACTERS.contains(character))
+                .copied()
+                .collect()
+        })
+        .collect()
 }
 
 #[cfg(test)]
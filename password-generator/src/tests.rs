@@ -1 +1,3 @@
-mod password_generator_tests;
\ No newline at end of file
+mod derivation_tests;
+mod password_generator_tests;
+mod username_generator_tests;
\ No newline at end of file
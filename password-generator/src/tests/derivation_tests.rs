@@ -0,0 +1,188 @@
+use app_core::{
+    password::{derivation_profile::DerivationProfile, password_restriction::PasswordRestriction},
+    ports::password_generator::PasswordGenerator,
+};
+
+use crate::{
+    LOWER_CASE_LETTERS, NO_RESTRICTION_FOUND_ERROR, NUMBERS, SPECIAL_CHARACTERS, SecurePasswordGenerator,
+    UPPER_CASE_LETTERS, derivation::MAX_DERIVED_LENGTH,
+};
+
+const MASTER_SECRET: &[u8] = b"correct horse battery staple";
+
+// Known answers, a change here means every derived password already in use would change
+const KNOWN_ANSWERS: [(&[u8], &str, &str, u32, &str); 4] = [
+    (MASTER_SECRET, "example.com", "john@example.com", 1, "`M_}-%w4_228y^--"),
+    (MASTER_SECRET, "example.com", "john@example.com", 2, "V@1blgP(qu(?K?&~"),
+    (MASTER_SECRET, "example.org", "john@example.com", 1, "ctrEFyecFBPNnYz3iBUS"),
+    (b"hunter2", "bank.example", "12345678", 1, "659808"),
+];
+
+#[test]
+fn should_match_known_answers() {
+
+    // A-rrange
+
+    let restrictions = [
+        all_classes_restriction(16),
+        all_classes_restriction(16),
+        PasswordRestriction {
+            length: 20,
+            lower_case: true,
+            upper_case: true,
+            numbers: true,
+            special_characters: false,
+            exclude_ambiguous: true
+        },
+        PasswordRestriction {
+            length: 6,
+            numbers: true,
+            ..Default::default()
+        },
+    ];
+
+    for ((master_secret, site, login, counter, expected), restrictions) in KNOWN_ANSWERS.into_iter().zip(restrictions) {
+
+        let profile = DerivationProfile {
+            site: site.to_string(),
+            login: login.to_string(),
+            counter,
+            restrictions
+        };
+
+        // A-ct
+
+        let result = match SecurePasswordGenerator::derive_password(master_secret, &profile) {
+            Ok(result) => result,
+            Err(error) => panic!("{error}")
+        };
+
+        // A-ssert
+
+        assert_eq!(str::from_utf8(&result).unwrap(), expected);
+    }
+}
+
+#[test]
+fn should_contain_one_of_each_restriction() {
+
+    // A-rrange
+
+    let profile = DerivationProfile {
+        site: "example.net".to_string(),
+        login: "jane".to_string(),
+        counter: 1,
+        restrictions: all_classes_restriction(4)
+    };
+
+    // A-ct
+
+    let result = SecurePasswordGenerator::derive_password(MASTER_SECRET, &profile).unwrap();
+
+    // A-ssert
+
+    assert_eq!(result.len(), 4);
+    assert!(result.iter().any(|character| LOWER_CASE_LETTERS.contains(character)));
+    assert!(result.iter().any(|character| UPPER_CASE_LETTERS.contains(character)));
+    assert!(result.iter().any(|character| NUMBERS.contains(character)));
+    assert!(result.iter().any(|character| SPECIAL_CHARACTERS.contains(character)));
+}
+
+#[test]
+fn should_not_mix_site_and_login() {
+
+    // A-rrange
+
+    let first_profile = DerivationProfile {
+        site: "ab".to_string(),
+        login: "c".to_string(),
+        counter: 1,
+        restrictions: all_classes_restriction(16)
+    };
+
+    let second_profile = DerivationProfile {
+        site: "a".to_string(),
+        login: "bc".to_string(),
+        ..first_profile.clone()
+    };
+
+    // A-ct
+
+    let first_result = SecurePasswordGenerator::derive_password(MASTER_SECRET, &first_profile).unwrap();
+    let second_result = SecurePasswordGenerator::derive_password(MASTER_SECRET, &second_profile).unwrap();
+
+    // A-ssert
+
+    assert_ne!(first_result, second_result);
+}
+
+#[test]
+fn should_be_error_empty_master_secret() {
+
+    // A-rrange
+
+    let profile = DerivationProfile {
+        site: "example.com".to_string(),
+        restrictions: all_classes_restriction(16),
+        ..Default::default()
+    };
+
+    // A-ct and A-ssert
+
+    match SecurePasswordGenerator::derive_password(b"", &profile) {
+        Ok(_) => panic!("Should have been an Err, got an Ok. A password shouldn't be derived without a master secret."),
+        Err(error) => assert_eq!(error, "The master secret cannot be empty.")
+    };
+}
+
+#[test]
+fn should_be_error_no_restriction() {
+
+    // A-rrange
+
+    let profile = DerivationProfile {
+        site: "example.com".to_string(),
+        restrictions: PasswordRestriction {
+            length: 16,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    // A-ct and A-ssert
+
+    match SecurePasswordGenerator::derive_password(MASTER_SECRET, &profile) {
+        Ok(_) => panic!("Should have been an Err, got an Ok. A password shouldn't be derived if there is no restrictions."),
+        Err(error) => assert_eq!(error, NO_RESTRICTION_FOUND_ERROR)
+    };
+}
+
+#[test]
+fn should_be_error_too_long() {
+
+    // A-rrange
+
+    let profile = DerivationProfile {
+        site: "example.com".to_string(),
+        restrictions: all_classes_restriction(MAX_DERIVED_LENGTH + 1),
+        ..Default::default()
+    };
+
+    // A-ct and A-ssert
+
+    match SecurePasswordGenerator::derive_password(MASTER_SECRET, &profile) {
+        Ok(_) => panic!("Should have been an Err, got an Ok. A derived password shouldn't exceed the maximum length."),
+        Err(error) => assert_eq!(error, "A derived password cannot be longer than 512 characters.")
+    };
+}
+
+fn all_classes_restriction(length: usize) -> PasswordRestriction {
+    PasswordRestriction {
+        length,
+        lower_case: true,
+        upper_case: true,
+        numbers: true,
+        special_characters: true,
+        exclude_ambiguous: false
+    }
+}
@@ -1,5 +1,5 @@
 use crate::{
-    PasswordRestriction, SecurePasswordGenerator, LOWER_CASE_LETTERS, MORE_RESTRINCTIONS_THAN_LENGTH_ERROR, NO_RESTRICTION_FOUND_ERROR, NUMBERS, SPECIAL_CHARACTERS, UPPER_CASE_LETTERS, ZERO_LENGTH_ERROR
+    PasswordRestriction, SecurePasswordGenerator, AMBIGUOUS_CHARACTERS, LOWER_CASE_LETTERS, MORE_RESTRINCTIONS_THAN_LENGTH_ERROR, NO_RESTRICTION_FOUND_ERROR, NUMBERS, SPECIAL_CHARACTERS, UPPER_CASE_LETTERS, ZERO_LENGTH_ERROR
 };
 use app_core::ports::password_generator::PasswordGenerator;
 
@@ -15,7 +15,8 @@ fn should_contain_one_of_each_restriction_and_have_18_chars() {
         lower_case: true,
         upper_case: true,
         numbers: true,
-        special_characters: true
+        special_characters: true,
+        exclude_ambiguous: false
     };
 
     // A-ction
@@ -65,7 +66,8 @@ fn should_contain_only_lower_case_and_have_18_chars() {
         lower_case: true,
         upper_case: false,
         numbers: false,
-        special_characters: false
+        special_characters: false,
+        exclude_ambiguous: false
     };
 
     // A-ction
@@ -115,7 +117,8 @@ fn should_contain_only_upper_case_and_have_18_chars() {
         lower_case: false,
         upper_case: true,
         numbers: false,
-        special_characters: false
+        special_characters: false,
+        exclude_ambiguous: false
     };
 
     // A-ction
@@ -165,7 +168,8 @@ fn should_contain_numbers_case_and_have_18_chars() {
         lower_case: false,
         upper_case: false,
         numbers: true,
-        special_characters: false
+        special_characters: false,
+        exclude_ambiguous: false
     };
 
     // A-ction
@@ -215,7 +219,8 @@ fn should_contain_only_special_characters_and_have_18_chars() {
         lower_case: false,
         upper_case: false,
         numbers: false,
-        special_characters: true
+        special_characters: true,
+        exclude_ambiguous: false
     };
 
     // A-ction
@@ -265,7 +270,8 @@ fn should_be_error_no_restriction() {
         lower_case: false,
         upper_case: false,
         numbers: false,
-        special_characters: false
+        special_characters: false,
+        exclude_ambiguous: false
     };
 
     // A-ction and A-ssert
@@ -288,7 +294,8 @@ fn should_be_error_zero_length() {
         lower_case: true,
         upper_case: false,
         numbers: false,
-        special_characters: false
+        special_characters: false,
+        exclude_ambiguous: false
     };
 
     // A-ction and A-ssert
@@ -311,7 +318,8 @@ fn should_be_error_more_restriction_than_length() {
         lower_case: true,
         upper_case: true,
         numbers: true,
-        special_characters: true
+        special_characters: true,
+        exclude_ambiguous: false
     };
 
     // A-ction and A-ssert
@@ -320,4 +328,107 @@ fn should_be_error_more_restriction_than_length() {
         Ok(_) => panic!("Should have been an Err, got an Ok. A password shouldn't be generated if there is more restriction than the desired length."),
         Err(error) => assert_eq!(error, MORE_RESTRINCTIONS_THAN_LENGTH_ERROR)
     };
-}
\ No newline at end of file
+}
+
+#[test]
+fn should_not_contain_ambiguous_characters() {
+
+    // A-ssuming
+
+    let length = 64;
+
+    let restriction = PasswordRestriction {
+        length,
+        lower_case: true,
+        upper_case: true,
+        numbers: true,
+        special_characters: true,
+        exclude_ambiguous: true
+    };
+
+    // A-ction
+
+    let result = match SecurePasswordGenerator::generate_password(&restriction) {
+        Ok(result) => result,
+        Err(error) => panic!("{error}")
+    };
+
+    // A-ssert
+
+    assert_eq!(length, result.len());
+    assert!(
+        !result
+        .iter()
+        .any(|character| AMBIGUOUS_CHARACTERS.contains(character))
+    );
+}
+
+#[test]
+fn should_compute_entropy_from_charset_and_length() {
+
+    // A-ssuming
+
+    let restriction = PasswordRestriction {
+        length: 18,
+        lower_case: true,
+        upper_case: true,
+        numbers: true,
+        special_characters: true,
+        exclude_ambiguous: false
+    };
+
+    // A-ction
+
+    let result = SecurePasswordGenerator::entropy(&restriction);
+
+    // A-ssert
+
+    // 26 + 26 + 10 + 32 = 94 characters
+    assert!((result - 18.0 * 94f64.log2()).abs() < f64::EPSILON);
+}
+
+#[test]
+fn should_lower_entropy_when_excluding_ambiguous_characters() {
+
+    // A-ssuming
+
+    let mut restriction = PasswordRestriction {
+        length: 18,
+        lower_case: true,
+        upper_case: true,
+        numbers: true,
+        special_characters: true,
+        exclude_ambiguous: false
+    };
+
+    let full_entropy = SecurePasswordGenerator::entropy(&restriction);
+
+    restriction.exclude_ambiguous = true;
+
+    // A-ction
+
+    let result = SecurePasswordGenerator::entropy(&restriction);
+
+    // A-ssert
+
+    assert!(result < full_entropy);
+}
+
+#[test]
+fn should_have_zero_entropy_without_restriction() {
+
+    // A-ssuming
+
+    let restriction = PasswordRestriction {
+        length: 18,
+        ..Default::default()
+    };
+
+    // A-ction
+
+    let result = SecurePasswordGenerator::entropy(&restriction);
+
+    // A-ssert
+
+    assert_eq!(result, 0.0);
+}
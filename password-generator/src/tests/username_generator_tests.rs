@@ -0,0 +1,174 @@
+use app_core::{
+    ports::username_generator::UsernameGenerator,
+    username::{email_alias::EmailAliasKind, username_restriction::UsernameRestriction},
+};
+
+use crate::{
+    NUMBERS,
+    username_generator::{ADJECTIVES, NOUNS, SecureUsernameGenerator},
+};
+
+const BASE_ADDRESS: &str = "john.doe@example.com";
+
+#[test]
+fn should_contain_two_words_separated_and_3_digits() {
+
+    // A-rrange
+
+    let restriction = UsernameRestriction {
+        word_count: 2,
+        separator: Some('_'),
+        capitalize: false,
+        digits: 3
+    };
+
+    // A-ct
+
+    let result = match SecureUsernameGenerator::generate_username(&restriction) {
+        Ok(result) => result,
+        Err(error) => panic!("{error}")
+    };
+
+    // A-ssert
+
+    let (words, digits) = result.split_at(result.len() - 3);
+
+    assert!(digits.bytes().all(|character| NUMBERS.contains(&character)));
+
+    let words: Vec<&str> = words.split('_').collect();
+
+    assert_eq!(words.len(), 2);
+    assert!(ADJECTIVES.contains(&words[0]));
+    assert!(NOUNS.contains(&words[1]));
+
+    println!("Generated username: {result}");
+}
+
+#[test]
+fn should_capitalize_words_without_separator() {
+
+    // A-rrange
+
+    let restriction = UsernameRestriction {
+        word_count: 3,
+        separator: None,
+        capitalize: true,
+        digits: 0
+    };
+
+    // A-ct
+
+    let result = match SecureUsernameGenerator::generate_username(&restriction) {
+        Ok(result) => result,
+        Err(error) => panic!("{error}")
+    };
+
+    // A-ssert
+
+    assert_eq!(result.chars().filter(|character| character.is_uppercase()).count(), 3);
+    assert!(result.chars().all(|character| character.is_alphabetic()));
+    assert!(result.chars().next().unwrap().is_uppercase());
+}
+
+#[test]
+fn should_be_error_zero_word_count() {
+
+    // A-rrange
+
+    let restriction = UsernameRestriction {
+        word_count: 0,
+        separator: None,
+        capitalize: false,
+        digits: 4
+    };
+
+    // A-ct and A-ssert
+
+    match SecureUsernameGenerator::generate_username(&restriction) {
+        Ok(_) => panic!("Should have been an Err, got an Ok. A username shouldn't be generated without words."),
+        Err(error) => assert_eq!(error, "A username must contain at least one word.")
+    };
+}
+
+#[test]
+fn should_generate_plus_addressed_alias() {
+
+    // A-rrange
+
+    let alias_kind = EmailAliasKind::PlusAddressing;
+
+    // A-ct
+
+    let result = match SecureUsernameGenerator::generate_email_alias(BASE_ADDRESS, &alias_kind) {
+        Ok(result) => result,
+        Err(error) => panic!("{error}")
+    };
+
+    // A-ssert
+
+    assert!(result.starts_with("john.doe+"));
+    assert!(result.ends_with("@example.com"));
+    assert_ne!(result, BASE_ADDRESS);
+}
+
+#[test]
+fn should_replace_existing_tag_in_plus_addressed_alias() {
+
+    // A-rrange
+
+    let alias_kind = EmailAliasKind::PlusAddressing;
+
+    // A-ct
+
+    let result = SecureUsernameGenerator::generate_email_alias("john+old@example.com", &alias_kind).unwrap();
+
+    // A-ssert
+
+    let (local_part, domain) = result.split_once('@').unwrap();
+    let (mailbox, tag) = local_part.split_once('+').unwrap();
+
+    assert_eq!(mailbox, "john");
+    assert_eq!(domain, "example.com");
+    assert_ne!(tag, "old");
+    assert!(!tag.contains('+'));
+}
+
+#[test]
+fn should_generate_catch_all_alias() {
+
+    // A-rrange
+
+    let alias_kind = EmailAliasKind::CatchAll;
+
+    // A-ct
+
+    let result = match SecureUsernameGenerator::generate_email_alias(BASE_ADDRESS, &alias_kind) {
+        Ok(result) => result,
+        Err(error) => panic!("{error}")
+    };
+
+    // A-ssert
+
+    let (local_part, domain) = result.split_once('@').unwrap();
+
+    assert_eq!(domain, "example.com");
+    assert!(!local_part.contains("john.doe"));
+    assert!(!local_part.contains('+'));
+}
+
+#[test]
+fn should_be_error_invalid_base_address() {
+
+    // A-rrange
+
+    let alias_kind = EmailAliasKind::CatchAll;
+
+    // A-ct and A-ssert
+
+    for base_address in ["john.doe", "@example.com", "john@", "john doe@example.com"] {
+        match SecureUsernameGenerator::generate_email_alias(base_address, &alias_kind) {
+            Ok(_) => panic!("Should have been an Err, got an Ok for '{base_address}'."),
+            Err(error) => assert_eq!(error, "The base address must be a valid email address (local-part@domain).")
+        };
+    }
+}
@@ -0,0 +1,127 @@
+use app_core::{
+    ports::username_generator::UsernameGenerator,
+    username::{email_alias::EmailAliasKind, username_restriction::UsernameRestriction},
+};
+use rand::seq::IndexedRandom;
+
+use crate::NUMBERS;
+
+const ZERO_WORD_COUNT_ERROR: &str = "A username must contain at least one word.";
+const INVALID_BASE_ADDRESS_ERROR: &str = "The base address must be a valid email address (local-part@domain).";
+
+// Alias tags are always two lower case words and 3 digits, e.g. 'brave.otter042'
+const ALIAS_RESTRICTION: UsernameRestriction = UsernameRestriction {
+    word_count: 2,
+    separator: Some('.'),
+    capitalize: false,
+    digits: 3,
+};
+
+pub(crate) const ADJECTIVES: &[&str] = &[
+    "agile", "amber", "ancient", "azure", "bold", "brave", "bright", "brisk",
+    "calm", "clever", "cosmic", "crimson", "curious", "daring", "dusty", "eager",
+    "electric", "fancy", "fierce", "frosty", "gentle", "giant", "golden", "happy",
+    "hidden", "humble", "icy", "jolly", "keen", "lively", "lucky", "lunar",
+    "mellow", "misty", "noble", "odd", "plucky", "polar", "proud", "quick",
+    "quiet", "rapid", "rusty", "salty", "shiny", "silent", "silver", "sleepy",
+    "sly", "snowy", "solar", "steady", "stormy", "sunny", "swift", "tidy",
+    "tiny", "velvet", "vivid", "wandering", "wild", "windy", "wise", "witty",
+];
+
+pub(crate) const NOUNS: &[&str] = &[
+    "anchor", "badger", "beacon", "bison", "canyon", "cedar", "comet", "coral",
+    "crab", "crane", "dolphin", "dragon", "falcon", "ferret", "fjord", "forest",
+    "fox", "gecko", "glacier", "harbor", "hawk", "heron", "island", "jaguar",
+    "kestrel", "koala", "lagoon", "lantern", "lynx", "maple", "meadow", "meteor",
+    "moose", "nebula", "orbit", "otter", "owl", "panda", "pebble", "pine",
+    "puffin", "quartz", "raven", "reef", "river", "rocket", "sparrow", "spruce",
+    "squid", "summit", "thunder", "tiger", "toucan", "trail", "tundra", "valley",
+    "viper", "walrus", "willow", "wolf", "wombat", "yak", "zebra", "zephyr",
+];
+
+#[derive(Debug, Default)]
+pub struct SecureUsernameGenerator;
+
+impl UsernameGenerator for SecureUsernameGenerator {
+
+    fn generate_username(restrictions: &UsernameRestriction) -> Result<String, String> {
+
+        if restrictions.word_count == 0 {
+            return Err(ZERO_WORD_COUNT_ERROR.to_string());
+        }
+
+        let mut rng = rand::rng();
+
+        let mut words = Vec::with_capacity(restrictions.word_count);
+
+        // Every word is an adjective except the last one, which reads like "swift.brave.otter"
+        for index in 0..restrictions.word_count {
+            let word_list = match index == restrictions.word_count - 1 {
+                true => NOUNS,
+                false => ADJECTIVES,
+            };
+
+            let word = word_list.choose(&mut rng).unwrap();
+
+            match restrictions.capitalize {
+                true => words.push(capitalize(word)),
+                false => words.push(word.to_string()),
+            }
+        }
+
+        let separator = restrictions
+            .separator
+            .map(String::from)
+            .unwrap_or_default();
+
+        let mut username = words.join(&separator);
+
+        for _ in 0..restrictions.digits {
+            username.push(char::from(NUMBERS.choose(&mut rng).unwrap().to_owned()));
+        }
+
+        Ok(username)
+    }
+
+    fn generate_email_alias(base_address: &str, alias_kind: &EmailAliasKind) -> Result<String, String> {
+
+        let (local_part, domain) = split_address(base_address)?;
+
+        let tag = Self::generate_username(&ALIAS_RESTRICTION)?;
+
+        match alias_kind {
+            EmailAliasKind::PlusAddressing => Ok(format!("{}+{}@{}", local_part, tag, domain)),
+            EmailAliasKind::CatchAll => Ok(format!("{}@{}", tag, domain)),
+        }
+    }
+}
+
+/// Splits the base address into its local part (without any existing '+tag') and its domain
+fn split_address(base_address: &str) -> Result<(&str, &str), String> {
+
+    let base_address = base_address.trim();
+
+    let Some((local_part, domain)) = base_address.rsplit_once('@') else {
+        return Err(INVALID_BASE_ADDRESS_ERROR.to_string());
+    };
+
+    let local_part = local_part.split('+').next().unwrap_or_default();
+
+    if local_part.is_empty()
+        || domain.is_empty()
+        || base_address.chars().any(char::is_whitespace)
+    {
+        return Err(INVALID_BASE_ADDRESS_ERROR.to_string());
+    }
+
+    Ok((local_part, domain))
+}
+
+fn capitalize(word: &str) -> String {
+    let mut characters = word.chars();
+
+    match characters.next() {
+        Some(first) => first.to_uppercase().chain(characters).collect(),
+        None => String::new(),
+    }
+}
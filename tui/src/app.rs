@@ -1,62 +1,109 @@
 use std::{
-    fs::{OpenOptions, read_to_string},
+    fs::{File, OpenOptions, read, read_to_string, write},
     io::{self, Write},
+    path::{Path, PathBuf},
+    time::Duration,
 };
 
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
 use app_core::{
-    core::vault_core::{Core, CoreService, LoggedCore, LoggedCoreService},
-    password::password_restriction::PasswordRestriction,
+    core::{
+        core_errors::CoreError,
+        vault_core::{Core, CoreService, LoggedCore, LoggedCoreService},
+    },
+    password::{derivation_profile::DerivationProfile, password_restriction::PasswordRestriction},
+    uncrypted_vault::{EntryConflict, EntryField, MergeSide},
+    username::{email_alias::EmailAliasKind, username_restriction::UsernameRestriction},
+    vault::{
+        emergency_kit::{EmergencyKit, RecoveryShareSheet},
+        share::{KeyStatus, ReceivedShare, RecipientKey},
+        vault_error::VaultError,
+    },
 };
-use cryptography::xchacha20poly1305::{NoKeyXChaCha20Poly1305, XChaCha20Poly1305};
-use password_generator::SecurePasswordGenerator;
+use cryptography::cipher_dispatcher::NoKeyCipherDispatcher;
+use password_generator::{SecurePasswordGenerator, username_generator::SecureUsernameGenerator};
 use ratatui::{
     DefaultTerminal, Frame,
     crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
     layout::{Constraint, Direction, Layout},
     style::{Color, Modifier, Style, Stylize},
-    symbols::border,
+    symbols::{self, border},
     text::Line,
-    widgets::{Block, Borders, List, ListItem},
+    widgets::{Block, BorderType, Borders, LineGauge, List, ListItem, ListState, Paragraph, Wrap},
 };
 use tui_textarea::{Input, Key, TextArea};
-use vault_manager::{opaque_api::OpaqueApi, opaque_vault_manager::OpaqueVaultManager};
+use vault_manager::{
+    async_opaque_api::AsyncOpaqueApi, cached_vault_manager::CachedVaultManager, key_stretching::KeyStretching,
+    opaque_vault_manager::OpaqueVaultManager, request_policy::RequestPolicy,
+};
 
 use crate::{
     config::AppConfig,
     current_screen::CurrentScreen,
     input_mode::InputMode,
     state::State,
+    text_areas::TextAreas,
     vault_entry::{VaultEntry, entries_to_vault_entries},
 };
 
+const MIN_PASSWORD_LENGTH: usize = 4;
+const MAX_PASSWORD_LENGTH: usize = 64;
+
+const CHANGE_PASSWORD_TITLES: [&str; 3] = [" Current password ", " New password ", " Confirm new password "];
+const RECOVERY_TITLES: [&str; 3] = [" Recovery key ", " New password ", " Confirm new password "];
+const PASSWORDS_DONT_MATCH: &str = "The new passwords don't match.";
+const RECOVERY_SHARE_THRESHOLD: u8 = 2;
+const RECOVERY_SHARE_COUNT: u8 = 3;
+const SESSION_EXPIRED_PROMPT: &str = "The session expired, enter your password to save the vault.";
+const VAULT_NOT_SAVED: &str = "The vault wasn't saved, the changes stay until the next save.";
+// Same width whatever the password, its length isn't shown either
+const MASKED_PASSWORD: &str = "********";
+const VAULT_CONFLICT: &str = "The vault was changed on another device, press M to merge its changes with yours and save.";
+const DERIVED_ENTRY_NOT_SHARED: &str = "Derived entries can't be shared, their password comes from a key of your vault.";
+// One vault per line: its name, then the path of its key file, the pinned server key (hex) and the last generation seen
+const VAULT_STORE_SEPARATOR: char = '\t';
+const KEY_FILE_FIELD: usize = 1;
+const SERVER_KEY_FIELD: usize = 2;
+const GENERATION_FIELD: usize = 3;
+const IDENTIFIERS_BOUND_FIELD: usize = 4;
+
 #[derive(Debug, Default)]
 pub struct App {
     vault_core: Option<
-        CoreService<OpaqueVaultManager<OpaqueApi>, SecurePasswordGenerator, NoKeyXChaCha20Poly1305>,
+        CoreService<
+            CachedVaultManager<OpaqueVaultManager<AsyncOpaqueApi>, NoKeyCipherDispatcher>,
+            SecurePasswordGenerator,
+            SecureUsernameGenerator,
+            NoKeyCipherDispatcher,
+        >,
     >,
     vault_logged_code: Option<
         LoggedCoreService<
-            OpaqueVaultManager<OpaqueApi>,
+            CachedVaultManager<OpaqueVaultManager<AsyncOpaqueApi>, NoKeyCipherDispatcher>,
             SecurePasswordGenerator,
-            XChaCha20Poly1305,
+            SecureUsernameGenerator,
+            NoKeyCipherDispatcher,
         >,
     >,
     // vault_core: Option<
-    //     MockCore<OpaqueVaultManager<OpaqueApi>, SecurePasswordGenerator, NoKeyXChaCha20Poly1305>,
+    //     MockCore<OpaqueVaultManager<AsyncOpaqueApi>, SecurePasswordGenerator, SecureUsernameGenerator, NoKeyCipherDispatcher>,
     // >,
     // vault_logged_code: Option<
-    //     MockLoggedCore<OpaqueVaultManager<OpaqueApi>, SecurePasswordGenerator, XChaCha20Poly1305>,
+    //     MockLoggedCore<OpaqueVaultManager<AsyncOpaqueApi>, SecurePasswordGenerator, SecureUsernameGenerator, CipherDispatcher>,
     // >,
     current_screen: CurrentScreen,
     state: State,
     input_mode: InputMode,
     password_restriction: PasswordRestriction,
+    username_restriction: UsernameRestriction,
     app_config: AppConfig,
     exit: bool,
 }
 
 impl App {
-    pub fn new(app_config: AppConfig) -> App {
+    pub fn new(app_config: AppConfig) -> Result<App, VaultError> {
         let mut list_state = ratatui::widgets::ListState::default();
 
         let vaults = get_vaults(&app_config.vault_store.path);
@@ -69,10 +116,10 @@ impl App {
         state.set_vault_items(vaults);
         state.vault_items_state = list_state;
 
-        let core_service = create_core_service(app_config.server.get_server_url());
+        let core_service = create_core_service(&app_config)?;
         // let core_service = create_mocked_core_service();
 
-        App {
+        Ok(App {
             state,
             vault_core: Some(core_service),
             password_restriction: PasswordRestriction {
@@ -81,10 +128,17 @@ impl App {
                 upper_case: true,
                 numbers: true,
                 special_characters: true,
+                exclude_ambiguous: false,
+            },
+            username_restriction: UsernameRestriction {
+                word_count: 2,
+                separator: Some('.'),
+                capitalize: false,
+                digits: 2,
             },
             app_config,
             ..Default::default()
-        }
+        })
     }
 
     /// runs the application's main loop until the user quits
@@ -107,37 +161,71 @@ impl App {
         );
 
         let mut title_area = TextArea::default();
-        title_area.set_placeholder_text("Enter title");
+        title_area.set_placeholder_text("Enter title or site (CTRL+D to save as a derived entry)");
 
         let mut username_area = TextArea::default();
-        username_area.set_placeholder_text("Enter username");
+        username_area.set_placeholder_text("Enter username (CTRL+G generate, CTRL+L email alias)");
 
         let mut entry_areas = [title_area, username_area];
         self.state.selected_entry_textarea = 0;
         activate(&mut entry_areas[0]);
         inactivate(&mut entry_areas[1]);
 
+        let mut password_form_areas: [TextArea<'_>; 3] = Default::default();
+
+        for password_form_area in &mut password_form_areas {
+            password_form_area.set_cursor_line_style(Style::default());
+        }
+
+        reset_password_form(&mut password_form_areas, &CHANGE_PASSWORD_TITLES);
+
+        let mut shares_area = TextArea::default();
+        shares_area.set_cursor_line_style(Style::default());
+        shares_area.set_placeholder_text("One share per line, words or hex");
+        shares_area.set_style(Style::default().fg(Color::LightGreen));
+        shares_area.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Recovery key shares "),
+        );
+
+        let mut recipient_area = TextArea::default();
+        recipient_area.set_cursor_line_style(Style::default());
+        recipient_area.set_placeholder_text("Username of the recipient");
+        recipient_area.set_style(Style::default().fg(Color::LightGreen));
+        recipient_area.set_block(Block::default().borders(Borders::ALL).title(" Share with "));
+
+        let mut text_areas = TextAreas {
+            password_area,
+            vault_name_area,
+            entry_areas,
+            password_form_areas,
+            shares_area,
+            recipient_area,
+        };
+
         while !self.exit {
-            terminal
-                .draw(|frame| self.draw(frame, &password_area, &vault_name_area, &entry_areas))?;
-            self.handle_events(&mut password_area, &mut vault_name_area, &mut entry_areas)?;
+            terminal.draw(|frame| self.draw(frame, &text_areas))?;
+            self.handle_events(&mut text_areas)?;
         }
 
         Ok(())
     }
 
-    fn draw(
-        &mut self,
-        frame: &mut Frame,
-        password_area: &TextArea<'_>,
-        vault_name_area: &TextArea<'_>,
-        entry_areas: &[TextArea<'_>; 2],
-    ) {
+    fn draw(&mut self, frame: &mut Frame, text_areas: &TextAreas<'_>) {
+        let TextAreas {
+            password_area,
+            vault_name_area,
+            entry_areas,
+            password_form_areas,
+            shares_area,
+            recipient_area,
+        } = text_areas;
+
         match self.current_screen {
             CurrentScreen::Home => {
                 if let None = self.vault_core {
-                    self.vault_core =
-                        Some(create_core_service(self.app_config.server.get_server_url()));
+                    self.vault_core = create_core_service(&self.app_config).ok();
                     // self.vault_core = Some(create_mocked_core_service());
                 }
 
@@ -155,33 +243,52 @@ impl App {
             CurrentScreen::Entry => {
                 self.render_entry(frame, entry_areas);
             }
+            CurrentScreen::Generator => {
+                self.render_generator(frame);
+            }
+            CurrentScreen::NewPassword => {
+                self.render_new_password(frame, password_form_areas);
+            }
+            CurrentScreen::RecoveryKey => {
+                self.render_recovery_key(frame);
+            }
+            CurrentScreen::CombineShares => {
+                self.render_combine_shares(frame, shares_area);
+            }
+            CurrentScreen::ShareEntry => {
+                self.render_share_entry(frame, recipient_area);
+            }
+            CurrentScreen::ShareInbox => {
+                self.render_share_inbox(frame);
+            }
+            CurrentScreen::MergeConflicts => {
+                self.render_merge_conflicts(frame);
+            }
         }
     }
 
-    fn handle_events(
-        &mut self,
-        password_area: &mut TextArea<'_>,
-        vault_name_area: &mut TextArea<'_>,
-        entry_areas: &mut [TextArea<'_>; 2],
-    ) -> io::Result<()> {
+    fn handle_events(&mut self, text_areas: &mut TextAreas<'_>) -> io::Result<()> {
         match event::read()? {
             // it's important to check that the event is a key press event as
             // crossterm also emits key release and repeat events on Windows.
             Event::Key(key_event) if key_event.kind == KeyEventKind::Press => {
-                self.handle_key_event(key_event, password_area, vault_name_area, entry_areas)
+                self.handle_key_event(key_event, text_areas)
             }
             _ => {}
         };
         Ok(())
     }
 
-    fn handle_key_event(
-        &mut self,
-        key_event: KeyEvent,
-        password_area: &mut TextArea<'_>,
-        vault_name_area: &mut TextArea<'_>,
-        entry_areas: &mut [TextArea<'_>; 2],
-    ) {
+    fn handle_key_event(&mut self, key_event: KeyEvent, text_areas: &mut TextAreas<'_>) {
+        let TextAreas {
+            password_area,
+            vault_name_area,
+            entry_areas,
+            password_form_areas,
+            shares_area,
+            recipient_area,
+        } = text_areas;
+
         match self.current_screen {
             CurrentScreen::Home => match key_event.code {
                 KeyCode::Char('q') => self.exit(),
@@ -189,8 +296,18 @@ impl App {
                     self.update_state_screen(CurrentScreen::VaultName, InputMode::Register)
                 }
                 KeyCode::Enter => {
+                    self.state.login_error = None;
                     self.update_state_screen(CurrentScreen::Password, InputMode::Loggin)
                 }
+                KeyCode::Char('r') => {
+                    reset_password_form(password_form_areas, &RECOVERY_TITLES);
+                    // Typed back from the emergency kit, hiding it only makes typos likelier
+                    password_form_areas[0].clear_mask_char();
+                    self.state.selected_password_field = 0;
+                    self.state.password_form_error = None;
+
+                    self.update_state_screen(CurrentScreen::NewPassword, InputMode::Recovering)
+                }
                 KeyCode::Up => self.previous_vault(),
                 KeyCode::Down => self.next_vault(),
                 _ => {}
@@ -212,9 +329,15 @@ impl App {
                 }
             },
             CurrentScreen::Password => match key_event.into() {
-                Input { key: Key::Esc, .. } => {
-                    self.update_state_screen(CurrentScreen::Home, InputMode::Neutral)
-                }
+                Input { key: Key::Esc, .. } => match self.input_mode {
+                    InputMode::Reauthenticating => {
+                        set_textarea_value(password_area, "");
+                        self.state.vault_message = Some(VAULT_NOT_SAVED.to_string());
+
+                        self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral)
+                    }
+                    _ => self.update_state_screen(CurrentScreen::Home, InputMode::Neutral),
+                },
                 Input {
                     key: Key::Enter, ..
                 } => match self.input_mode {
@@ -222,6 +345,7 @@ impl App {
                     InputMode::Register => {
                         self.register(vault_name_area.lines(), password_area.lines())
                     }
+                    InputMode::Reauthenticating => self.reauthenticate(password_area),
                     _ => {}
                 },
                 input => {
@@ -229,40 +353,59 @@ impl App {
                 }
             },
             CurrentScreen::Vault => match key_event.code {
-                KeyCode::Char('q') => self.exit(),
-                KeyCode::Char('c') => match &self.vault_logged_code {
-                    Some(vault_logged_core) => vault_logged_core
-                        .copy_to_clipboard(
-                            self.get_vault_entries()
-                                .get(self.state.index_entry_item)
-                                .unwrap()
-                                .0
-                                .password
-                                .clone(),
-                        )
-                        .unwrap(),
-                    None => {
-                        panic!("Internal error, shouldn't call vault logged core at this time.")
-                    }
-                },
-                KeyCode::Enter => match &self.vault_logged_code {
-                    Some(vault_logged_core) => {
-                        vault_logged_core.save_vault().unwrap();
-                        self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral);
+                // The first key only dismisses the message
+                _ if self.state.vault_message.take().is_some() => {}
+                KeyCode::Char('q') => {
+                    self.logout();
+                    self.exit()
+                }
+                KeyCode::Char('l') => self.logout(),
+                KeyCode::Char('c') => match (&self.vault_logged_code, self.get_entry_password(self.state.index_entry_item)) {
+                    (Some(vault_logged_core), Ok(password)) => {
+                        if let Err(error) = vault_logged_core.copy_to_clipboard(password) {
+                            self.state.vault_message = Some(error.to_string());
+                        }
                     }
-                    None => {
+                    (Some(_), Err(error)) => self.state.vault_message = Some(error.to_string()),
+                    (None, _) => {
                         panic!("Internal error, shouldn't call vault logged core at this time.")
                     }
                 },
+                KeyCode::Char('n') => self.next_derived_counter(),
+                KeyCode::Enter => self.save_vault(password_area),
                 KeyCode::Char('a') => {
                     self.update_state_screen(CurrentScreen::Entry, InputMode::CreatingEntry)
                 }
-                KeyCode::Char('s') => match &self.vault_logged_code {
-                    Some(vault_logged_core) => vault_logged_core.save_vault().unwrap(),
-                    None => {
-                        panic!("Internal error, shouldn't call vault logged core at this time.")
+                KeyCode::Char('p') => {
+                    reset_password_form(password_form_areas, &CHANGE_PASSWORD_TITLES);
+                    self.state.selected_password_field = 0;
+                    self.state.password_form_error = None;
+
+                    self.update_state_screen(CurrentScreen::NewPassword, InputMode::ChangingPassword)
+                }
+                KeyCode::Char('k') => self.create_recovery_key(),
+                KeyCode::Char('f') => self.create_key_file(),
+                KeyCode::Char('h') => match self.get_vault_entries().get(self.state.index_entry_item) {
+                    Some(VaultEntry::Stored(_)) => {
+                        set_textarea_value(recipient_area, "");
+                        self.state.share_error = None;
+                        self.state.recipient_key = None;
+
+                        self.update_state_screen(CurrentScreen::ShareEntry, InputMode::Neutral)
                     }
+                    Some(VaultEntry::Derived(_)) => {
+                        self.state.vault_message = Some(DERIVED_ENTRY_NOT_SHARED.to_string())
+                    }
+                    None => {}
                 },
+                KeyCode::Char('i') => self.open_share_inbox(),
+                KeyCode::Char('g') => {
+                    self.state.generator_restriction = self.password_restriction.clone();
+                    self.regenerate_preview();
+                    self.update_state_screen(CurrentScreen::Generator, InputMode::Neutral)
+                }
+                KeyCode::Char('s') => self.save_vault(password_area),
+                KeyCode::Char('m') => self.merge_remote_vault(password_area),
                 KeyCode::Up => self.previous_entry(),
                 KeyCode::Down => self.next_entry(),
                 _ => {}
@@ -277,27 +420,52 @@ impl App {
                         (self.state.selected_entry_textarea + 1) % 2;
                     activate(&mut entry_areas[self.state.selected_entry_textarea]);
                 }
+                Input {
+                    key: Key::Char('d'),
+                    ctrl: true,
+                    ..
+                } => {
+                    match &mut self.vault_logged_code {
+                        Some(vault_logged_core) => vault_logged_core.add_derived_entry(DerivationProfile {
+                            site: get_value_from_lines(entry_areas[0].lines()),
+                            login: get_value_from_lines(entry_areas[1].lines()),
+                            counter: 1,
+                            restrictions: self.password_restriction.clone(),
+                        }),
+                        None => {
+                            panic!("Internal error, shouldn't call vault logged core at this time.")
+                        }
+                    };
+                    self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral);
+                }
+                Input {
+                    key: Key::Char('g'),
+                    ctrl: true,
+                    ..
+                } => self.fill_generated_username(&mut entry_areas[1]),
+                Input {
+                    key: Key::Char('l'),
+                    ctrl: true,
+                    ..
+                } => self.fill_generated_email_alias(&mut entry_areas[1]),
                 Input {
                     key: Key::Enter, ..
                 } => match self.input_mode {
                     InputMode::CreatingEntry => {
-                        match &mut self.vault_logged_code {
-                            Some(vault_logged_core) => vault_logged_core.add_entry(
+                        let Some(vault_logged_core) = &mut self.vault_logged_code else {
+                            panic!("Internal error, shouldn't call vault logged core at this time.")
+                        };
+
+                        // A restriction no password can meet is reported, the entry isn't added
+                        match vault_logged_core.generate_password(&self.password_restriction) {
+                            Ok(password) => vault_logged_core.add_entry(
                                 get_value_from_lines(entry_areas[0].lines()),
                                 get_value_from_lines(entry_areas[1].lines()),
-                                String::from_utf8(
-                                    vault_logged_core
-                                        .generate_password(&self.password_restriction)
-                                        .unwrap(),
-                                )
-                                .unwrap(),
+                                String::from_utf8_lossy(&password).into_owned(),
                             ),
-                            None => {
-                                panic!(
-                                    "Internal error, shouldn't call vault logged core at this time."
-                                )
-                            }
-                        };
+                            Err(error) => self.state.vault_message = Some(error.to_string()),
+                        }
+
                         self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral);
                     }
                     // InputMode::EditingEntry => {}
@@ -307,6 +475,136 @@ impl App {
                     entry_areas[self.state.selected_entry_textarea].input(input);
                 }
             },
+            CurrentScreen::Generator => {
+                let restriction = &mut self.state.generator_restriction;
+
+                match key_event.code {
+                    KeyCode::Esc => {
+                        self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral);
+                        return;
+                    }
+                    // Settings no password can meet stay on the screen with their error
+                    KeyCode::Enter if self.state.generator_error.is_some() => return,
+                    KeyCode::Enter => {
+                        self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral);
+                        self.save_generator_settings(password_area);
+                        return;
+                    }
+                    KeyCode::Left => {
+                        restriction.length = restriction.length.saturating_sub(1).max(MIN_PASSWORD_LENGTH)
+                    }
+                    KeyCode::Right => {
+                        restriction.length = (restriction.length + 1).min(MAX_PASSWORD_LENGTH)
+                    }
+                    KeyCode::Char('1') => restriction.lower_case = !restriction.lower_case,
+                    KeyCode::Char('2') => restriction.upper_case = !restriction.upper_case,
+                    KeyCode::Char('3') => restriction.numbers = !restriction.numbers,
+                    KeyCode::Char('4') => {
+                        restriction.special_characters = !restriction.special_characters
+                    }
+                    KeyCode::Char('5') => {
+                        restriction.exclude_ambiguous = !restriction.exclude_ambiguous
+                    }
+                    KeyCode::Char('r') => {}
+                    _ => return,
+                }
+
+                self.regenerate_preview();
+            }
+            CurrentScreen::NewPassword => match key_event.into() {
+                Input { key: Key::Esc, .. } => match self.input_mode {
+                    InputMode::Recovering => {
+                        self.update_state_screen(CurrentScreen::Home, InputMode::Neutral)
+                    }
+                    _ => self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral),
+                },
+                Input { key: Key::Tab, .. } => {
+                    let titles = match self.input_mode {
+                        InputMode::Recovering => &RECOVERY_TITLES,
+                        _ => &CHANGE_PASSWORD_TITLES,
+                    };
+
+                    self.state.selected_password_field = (self.state.selected_password_field + 1) % 3;
+                    focus_password_field(password_form_areas, titles, self.state.selected_password_field);
+                }
+                Input {
+                    key: Key::Enter, ..
+                } => match self.input_mode {
+                    InputMode::Recovering => self.recover(password_form_areas),
+                    _ => self.change_password(password_form_areas),
+                },
+                Input {
+                    key: Key::Char('s'),
+                    ctrl: true,
+                    ..
+                } if matches!(self.input_mode, InputMode::Recovering) => {
+                    set_textarea_value(shares_area, "");
+                    self.state.shares_error = None;
+
+                    self.update_state_screen(CurrentScreen::CombineShares, InputMode::Recovering)
+                }
+                input => {
+                    password_form_areas[self.state.selected_password_field].input(input);
+                }
+            },
+            // Shown once, any other key goes back to the vault
+            CurrentScreen::RecoveryKey => match key_event.code {
+                KeyCode::Char('s') if self.state.recovery_key.is_some() => self.split_recovery_key(),
+                _ => {
+                    self.state.recovery_key = None;
+                    self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral)
+                }
+            },
+            CurrentScreen::CombineShares => match key_event.into() {
+                Input { key: Key::Esc, .. } => {
+                    self.update_state_screen(CurrentScreen::NewPassword, InputMode::Recovering)
+                }
+                Input {
+                    key: Key::Char('s'),
+                    ctrl: true,
+                    ..
+                } => self.combine_recovery_shares(shares_area, password_form_areas),
+                input => {
+                    shares_area.input(input);
+                }
+            },
+            CurrentScreen::ShareEntry => match key_event.into() {
+                Input { key: Key::Esc, .. } => {
+                    self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral)
+                }
+                Input {
+                    key: Key::Enter, ..
+                } => self.share_entry(recipient_area.lines()),
+                Input {
+                    key: Key::Char('t'),
+                    ctrl: true,
+                    ..
+                } => self.trust_recipient_key(),
+                input => {
+                    if recipient_area.input(input) {
+                        self.state.recipient_key = None;
+                    }
+                }
+            },
+            CurrentScreen::ShareInbox => match key_event.code {
+                KeyCode::Esc => self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral),
+                KeyCode::Enter => self.answer_share(true),
+                KeyCode::Char('d') => self.answer_share(false),
+                KeyCode::Up => self.previous_share(),
+                KeyCode::Down => self.next_share(),
+                _ => {}
+            },
+            CurrentScreen::MergeConflicts => match key_event.code {
+                // The fields left keep the local value, the merged vault is saved with the next save
+                KeyCode::Esc => self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral),
+                KeyCode::Char('l') => self.resolve_conflict(MergeSide::Local, password_area),
+                KeyCode::Char('r') => self.resolve_conflict(MergeSide::Remote, password_area),
+                KeyCode::Char('c') => self.copy_conflict_value(MergeSide::Local),
+                KeyCode::Char('t') => self.copy_conflict_value(MergeSide::Remote),
+                KeyCode::Up => self.previous_conflict(),
+                KeyCode::Down => self.next_conflict(),
+                _ => {}
+            },
         }
     }
 
@@ -322,19 +620,69 @@ impl App {
             .get_vault_items()
             .get(self.state.index_vault_item)
         {
-            Some(value) => value,
+            Some(value) => value.clone(),
             None => {
                 return;
             }
         };
 
-        let core = self.vault_core.take();
+        let key_file = match get_key_file_path(&self.app_config.vault_store.path, &username) {
+            Some(key_file_path) => match read(&key_file_path) {
+                Ok(key_file) => Some(key_file),
+                Err(error) => {
+                    self.state.login_error = Some(format!("Couldn't read the key file {key_file_path}: {error}"));
+                    return;
+                }
+            },
+            None => None,
+        };
 
-        let logged_core = match core {
-            Some(core) => core.logging_in(username, &password).unwrap(),
+        let server_key = get_pinned_server_key(&self.app_config.vault_store.path, &username);
+        let identifiers_bound = get_identifiers_bound(&self.app_config.vault_store.path, &username);
+        let generation = get_last_generation(&self.app_config.vault_store.path, &username);
+
+        let result = match self.vault_core.take() {
+            Some(mut core) => {
+                core.set_key_file(key_file);
+                core.set_server_key(server_key.clone());
+                core.set_identifiers_bound(identifiers_bound);
+                core.set_min_generation(generation);
+                core.logging_in(&username, &password)
+            }
             _ => panic!("Internal error occured, couldn't find Core"),
         };
 
+        let mut logged_core = match result {
+            Ok(logged_core) => logged_core,
+            Err(error) => {
+                // The core is consumed even when the login fails
+                self.vault_core = create_core_service(&self.app_config).ok();
+                self.state.login_error = Some(error.to_string());
+                return;
+            }
+        };
+
+        self.state.login_error = None;
+
+        // Vaults registered before the pinning trust the key of their first login
+        if server_key.is_none() {
+            set_pinned_server_key(&self.app_config.vault_store.path, &username, logged_core.get_server_key());
+        }
+
+        // Once logged in with its identifiers, the vault never falls back to the default ones
+        if !identifiers_bound && logged_core.get_identifiers_bound() {
+            set_identifiers_bound(&self.app_config.vault_store.path, &username);
+        }
+
+        set_last_generation(&self.app_config.vault_store.path, &username, logged_core.get_generation());
+
+        if let Some(password_restriction) = logged_core.get_password_restriction() {
+            self.password_restriction = password_restriction.clone();
+        }
+
+        // The vault is open, an upload or an upgrade that failed after the login is only reported
+        self.state.vault_message = logged_core.take_pending_error().map(|error| error.to_string());
+        self.state.vault_name = username.clone();
         self.vault_logged_code = Some(logged_core);
 
         self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral);
@@ -345,164 +693,799 @@ impl App {
 
         let username = get_value_from_lines(username_lines);
 
-        let core = self.vault_core.take();
-
-        let logged_core = match core {
-            Some(core) => core.create_account(&username, &password).unwrap(),
+        let result = match self.vault_core.take() {
+            Some(core) => core.create_account(&username, &password),
             _ => panic!("Internal error occured, couldn't find Core"),
         };
 
+        let logged_core = match result {
+            Ok(logged_core) => logged_core,
+            Err(error) => {
+                // The core is consumed even when the registration fails
+                self.vault_core = create_core_service(&self.app_config).ok();
+                self.state.login_error = Some(error.to_string());
+                return;
+            }
+        };
+
+        self.state.login_error = None;
         self.vault_logged_code = Some(logged_core);
+        self.state.vault_name = username.clone();
 
         self.state.add_vault_item(&username);
-        add_vault_name_to_vault_store(&self.app_config.vault_store.path, username);
+        add_vault_name_to_vault_store(&self.app_config.vault_store.path, username.clone());
+
+        if let Some(logged_core) = &self.vault_logged_code {
+            set_pinned_server_key(&self.app_config.vault_store.path, &username, logged_core.get_server_key());
+            set_identifiers_bound(&self.app_config.vault_store.path, &username);
+        }
 
         self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral);
     }
 
-    fn previous_vault(&mut self) {
-        let entries = self.state.get_vault_items();
+    fn change_password(&mut self, password_form_areas: &[TextArea<'_>; 3]) {
+        let [old_password, new_password, confirmation] =
+            password_form_areas.each_ref().map(|area| get_value_from_lines(area.lines()));
 
-        if entries.is_empty() {
+        if new_password != confirmation {
+            self.state.password_form_error = Some(PASSWORDS_DONT_MATCH.to_string());
             return;
         }
 
-        if self.state.index_vault_item > 0 {
-            self.state.index_vault_item -= 1;
-        } else {
-            self.state.index_vault_item = entries.len() - 1;
-        }
+        let result = match &mut self.vault_logged_code {
+            Some(vault_logged_core) => {
+                vault_logged_core.change_password(&self.state.vault_name, &old_password, &new_password)
+            }
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
 
-        self.state
-            .vault_items_state
-            .select(Some(self.state.index_vault_item));
+        match result {
+            Ok(()) => self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral),
+            Err(error) => self.state.password_form_error = Some(error.to_string()),
+        }
     }
 
-    fn next_vault(&mut self) {
-        let entries = self.state.get_vault_items();
+    fn recover(&mut self, password_form_areas: &[TextArea<'_>; 3]) {
+        let [recovery_key, new_password, confirmation] =
+            password_form_areas.each_ref().map(|area| get_value_from_lines(area.lines()));
 
-        if entries.is_empty() {
+        if new_password != confirmation {
+            self.state.password_form_error = Some(PASSWORDS_DONT_MATCH.to_string());
             return;
         }
 
-        if self.state.index_vault_item < entries.len() - 1 {
-            self.state.index_vault_item += 1;
-        } else {
-            self.state.index_vault_item = 0;
-        }
+        let Some(username) = self.state.get_vault_items().get(self.state.index_vault_item).cloned() else {
+            return;
+        };
 
-        self.state
-            .vault_items_state
-            .select(Some(self.state.index_vault_item));
-    }
+        let server_key = get_pinned_server_key(&self.app_config.vault_store.path, &username);
+        let identifiers_bound = get_identifiers_bound(&self.app_config.vault_store.path, &username);
+        let generation = get_last_generation(&self.app_config.vault_store.path, &username);
+
+        // The recovery key stands for the password and the key file, the recovered vault has no key file
+        let result = match self.vault_core.take() {
+            Some(mut core) => {
+                core.set_server_key(server_key);
+                core.set_identifiers_bound(identifiers_bound);
+                core.set_min_generation(generation);
+                core.recover_account(&username, &recovery_key, &new_password)
+            }
+            None => panic!("Internal error occured, couldn't find Core"),
+        };
 
-    fn previous_entry(&mut self) {
-        let entries = self.get_vault_entries();
+        match result {
+            Ok(mut logged_core) => {
+                set_key_file_path(&self.app_config.vault_store.path, &username, None);
 
-        if entries.is_empty() {
-            return;
-        }
+                if logged_core.get_identifiers_bound() {
+                    set_identifiers_bound(&self.app_config.vault_store.path, &username);
+                }
 
-        if self.state.index_entry_item > 0 {
-            self.state.index_entry_item -= 1;
-        } else {
-            self.state.index_entry_item = entries.len() - 1;
-        }
+                set_last_generation(&self.app_config.vault_store.path, &username, logged_core.get_generation());
 
-        self.state
-            .vault_entries_state
-            .select(Some(self.state.index_entry_item));
-    }
+                if let Some(password_restriction) = logged_core.get_password_restriction() {
+                    self.password_restriction = password_restriction.clone();
+                }
 
-    fn next_entry(&mut self) {
-        let entries = self.get_vault_entries();
+                self.state.vault_message = logged_core.take_pending_error().map(|error| error.to_string());
+                self.state.vault_name = username;
+                self.vault_logged_code = Some(logged_core);
 
-        if entries.is_empty() {
-            return;
+                self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral);
+            }
+            Err(error) => {
+                // The core is consumed even when the recovery fails
+                self.vault_core = create_core_service(&self.app_config).ok();
+                self.state.password_form_error = Some(error.to_string());
+            }
         }
+    }
 
-        if self.state.index_entry_item < entries.len() - 1 {
-            self.state.index_entry_item += 1;
-        } else {
-            self.state.index_entry_item = 0;
-        }
+    /// Replaces the previous recovery key and saves the emergency kit next to the vault store
+    fn create_recovery_key(&mut self) {
+        let result = match &self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.create_recovery_key(),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
 
-        self.state
-            .vault_entries_state
-            .select(Some(self.state.index_entry_item));
-    }
+        self.state.recovery_key_message = match result {
+            Ok(recovery_key) => {
+                self.state.recovery_key = Some(recovery_key.clone());
 
-    fn render_home(&mut self, frame: &mut Frame) {
-        let instructions = Line::from(vec![
-            " Log in ".into(),
-            "<ENTER>".blue().bold(),
-            " Create vault ".into(),
-            "<C>".blue().bold(),
-            " Quit ".into(),
-            "<Q> ".blue().bold(),
-        ]);
+                let emergency_kit = EmergencyKit::new(
+                    &self.state.vault_name,
+                    &self.app_config.server.get_server_url(),
+                    recovery_key,
+                );
 
-        let block = create_main_block(instructions);
+                let path = Path::new(&self.app_config.vault_store.path)
+                    .with_file_name(format!("{}-emergency-kit.txt", self.state.vault_name));
 
-        let items: Vec<ListItem> = self
-            .state
-            .get_vault_items()
-            .iter()
-            .map(|vault_name| {
-                ListItem::new(vault_name.clone())
-                    .style(Style::default())
-                    .fg(Color::White)
-            })
-            .collect();
+                // Holds the recovery key in clear, never replace a kit that may not be printed yet
+                let written = create_private_file(&path)
+                    .and_then(|mut file| file.write_all(emergency_kit.to_text().as_bytes()));
 
-        let list = List::new(items)
-            .block(block)
-            .highlight_style(
-                Style::default()
-                    .bg(Color::Blue)
-                    .fg(Color::White)
-                    .add_modifier(Modifier::BOLD),
-            )
-            .highlight_symbol(">> ");
+                match written {
+                    Ok(()) => format!("Emergency kit saved to {}, print it and delete the file.", path.display()),
+                    Err(error) => format!("Couldn't save the emergency kit, write the key down: {error}"),
+                }
+            }
+            Err(error) => error.to_string(),
+        };
 
-        frame.render_stateful_widget(list, frame.area(), &mut self.state.vault_items_state);
+        self.update_state_screen(CurrentScreen::RecoveryKey, InputMode::Neutral);
     }
 
-    fn render_password(&mut self, frame: &mut Frame, password_area: &TextArea<'_>) {
-        frame.render_widget(password_area, frame.area());
-    }
+    /// The key file is written before the data key is wrapped with it, a failed upload only leaves an unused file
+    fn create_key_file(&mut self) {
+        let path = Path::new(&self.app_config.vault_store.path).with_file_name(format!("{}.key", self.state.vault_name));
+
+        let result = match &mut self.vault_logged_code {
+            Some(vault_logged_core) => {
+                let key_file = vault_logged_core.generate_key_file();
+
+                // Never overwrite a key file, it may still be the one the vault needs
+                create_private_file(&path)
+                    .and_then(|mut file| file.write_all(&key_file))
+                    .map_err(|error| format!("Couldn't write the key file {}: {error}", path.display()))
+                    .and_then(|()| {
+                        vault_logged_core
+                            .set_key_file(Some(key_file))
+                            .map_err(|error| error.to_string())
+                    })
+            }
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
 
-    fn render_vault_name(&mut self, frame: &mut Frame, vault_name_area: &TextArea<'_>) {
-        frame.render_widget(vault_name_area, frame.area());
+        self.state.vault_message = Some(match result {
+            Ok(()) => {
+                let path = path.display().to_string();
+                set_key_file_path(&self.app_config.vault_store.path, &self.state.vault_name, Some(&path));
+
+                format!("Key file saved to {path}, the vault can't be opened without it. Keep a copy somewhere safe.")
+            }
+            Err(error) => error,
+        });
     }
 
-    fn render_vault(&mut self, frame: &mut Frame) {
-        let instructions = Line::from(vec![
-            " Copy Password ".into(),
-            "<C>".blue().bold(),
-            " Add entry ".into(),
-            "<A>".blue().bold(),
-            " Save vault ".into(),
-            "<S>".blue().bold(),
-            " Quit ".into(),
-            "<Q> ".blue().bold(),
-        ]);
+    /// Saves one sheet per share next to the emergency kit, to hand out to the share holders
+    fn split_recovery_key(&mut self) {
+        let result = match (&self.vault_logged_code, &self.state.recovery_key) {
+            (Some(vault_logged_core), Some(recovery_key)) => {
+                vault_logged_core.split_recovery_key(recovery_key, RECOVERY_SHARE_THRESHOLD, RECOVERY_SHARE_COUNT)
+            }
+            _ => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
 
-        let block = create_main_block(instructions);
+        self.state.recovery_key_message = match result {
+            Ok(shares) => {
+                let vault_store = Path::new(&self.app_config.vault_store.path);
+
+                let written: io::Result<Vec<_>> = shares
+                    .into_iter()
+                    .map(|share| {
+                        let path = vault_store
+                            .with_file_name(format!("{}-recovery-share-{}.txt", self.state.vault_name, share.index));
+                        let sheet = RecoveryShareSheet::new(
+                            &self.state.vault_name,
+                            share,
+                            RECOVERY_SHARE_THRESHOLD,
+                            RECOVERY_SHARE_COUNT,
+                        );
+
+                        create_private_file(&path)
+                            .and_then(|mut file| file.write_all(sheet.to_text().as_bytes()))
+                            .map(|()| path)
+                    })
+                    .collect();
+
+                match written {
+                    Ok(paths) => format!(
+                        "{} shares saved next to the vault store ({}), any {} of them give the key back. Hand them out and delete the files.",
+                        paths.len(),
+                        vault_store.with_file_name("").display(),
+                        RECOVERY_SHARE_THRESHOLD
+                    ),
+                    Err(error) => format!("Couldn't save the recovery key shares: {error}"),
+                }
+            }
+            Err(error) => error.to_string(),
+        };
+    }
 
-        let items: Vec<ListItem> = self
-            .get_vault_entries()
+    /// Fills the recovery key field back from the shares, the recovery itself stays the same
+    fn combine_recovery_shares(&mut self, shares_area: &TextArea<'_>, password_form_areas: &mut [TextArea<'_>; 3]) {
+        let shares: Vec<String> = shares_area
+            .lines()
             .iter()
-            .map(|entry| {
-                ListItem::from(entry)
-                    .style(Style::default())
-                    .fg(Color::White)
-            })
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
             .collect();
 
-        let list = List::new(items)
-            .block(block)
-            .highlight_style(
+        let result = match &self.vault_core {
+            Some(core) => core.combine_recovery_shares(&shares),
+            None => panic!("Internal error occured, couldn't find Core"),
+        };
+
+        match result {
+            Ok(recovery_key) => {
+                set_textarea_value(&mut password_form_areas[0], &recovery_key.to_string());
+
+                self.state.selected_password_field = 1;
+                focus_password_field(password_form_areas, &RECOVERY_TITLES, 1);
+
+                self.update_state_screen(CurrentScreen::NewPassword, InputMode::Recovering)
+            }
+            Err(error) => self.state.shares_error = Some(error.to_string()),
+        }
+    }
+
+    /// The first Enter looks the key up to show its fingerprint, the second one seals the entry with it
+    fn share_entry(&mut self, recipient_lines: &[String]) {
+        let recipient = get_value_from_lines(recipient_lines);
+
+        let Some(VaultEntry::Stored(entry)) = self.get_vault_entries().into_iter().nth(self.state.index_entry_item)
+        else {
+            return;
+        };
+
+        let Some(vault_logged_core) = &mut self.vault_logged_code else {
+            panic!("Internal error, shouldn't call vault logged core at this time.")
+        };
+
+        let looked_up = matches!(&self.state.recipient_key, Some(key) if key.username == recipient);
+
+        if !looked_up {
+            match vault_logged_core.lookup_public_key(&recipient) {
+                Ok(recipient_key) => {
+                    self.state.recipient_key = Some(recipient_key);
+                    self.state.share_error = None;
+                }
+                Err(error) => self.state.share_error = Some(error.to_string()),
+            }
+
+            return;
+        }
+
+        let result = vault_logged_core.share_entry(&entry, &recipient);
+
+        match result {
+            Ok(()) => {
+                self.state.vault_message = Some(format!("{} shared with {recipient}.", entry.title));
+                self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral)
+            }
+            Err(error) => self.state.share_error = Some(error.to_string()),
+        }
+    }
+
+    /// Only reached after the fingerprints were compared with the recipient, the new key replaces the pinned one
+    fn trust_recipient_key(&mut self) {
+        let Some(recipient_key) = self.state.recipient_key.as_mut() else {
+            return;
+        };
+
+        if !matches!(recipient_key.status, KeyStatus::Changed { .. }) {
+            return;
+        }
+
+        let result = match &mut self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.pin_public_key(recipient_key),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        match result {
+            Ok(()) => {
+                recipient_key.status = KeyStatus::Pinned;
+                self.state.share_error = None;
+            }
+            Err(error) => self.state.share_error = Some(error.to_string()),
+        }
+    }
+
+    /// Sharing is enabled on the first visit, the public key is published so others can share with this vault
+    fn open_share_inbox(&mut self) {
+        let result = match &mut self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core
+                .enable_sharing()
+                .and_then(|()| vault_logged_core.get_received_shares()),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        match result {
+            Ok(received_shares) => {
+                self.state.sharing_fingerprint =
+                    self.vault_logged_code.as_ref().and_then(|core| core.get_sharing_fingerprint());
+                self.set_received_shares(received_shares);
+                self.update_state_screen(CurrentScreen::ShareInbox, InputMode::Neutral)
+            }
+            Err(error) => self.state.vault_message = Some(error.to_string()),
+        }
+    }
+
+    fn answer_share(&mut self, accept: bool) {
+        let Some(share) = self.state.received_shares.get(self.state.index_share_item).cloned() else {
+            return;
+        };
+
+        let result = match &mut self.vault_logged_code {
+            Some(vault_logged_core) if accept => vault_logged_core.accept_share(&share),
+            Some(vault_logged_core) => vault_logged_core.decline_share(share.id),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        // A share saved offline is already out of the inbox of the server
+        if matches!(result, Ok(()) | Err(CoreError::SavedOffline)) {
+            let mut received_shares = std::mem::take(&mut self.state.received_shares);
+            received_shares.retain(|received_share| received_share.id != share.id);
+
+            self.set_received_shares(received_shares);
+        }
+
+        self.state.share_error = result.err().map(|error| error.to_string());
+    }
+
+    fn set_received_shares(&mut self, received_shares: Vec<ReceivedShare>) {
+        self.state.index_share_item = 0;
+        self.state.received_shares_state = ListState::default();
+
+        if !received_shares.is_empty() {
+            self.state.received_shares_state.select_first();
+        }
+
+        self.state.received_shares = received_shares;
+    }
+
+    fn previous_share(&mut self) {
+        let share_count = self.state.received_shares.len();
+
+        if share_count == 0 {
+            return;
+        }
+
+        self.state.index_share_item = (self.state.index_share_item + share_count - 1) % share_count;
+        self.state
+            .received_shares_state
+            .select(Some(self.state.index_share_item));
+    }
+
+    fn next_share(&mut self) {
+        let share_count = self.state.received_shares.len();
+
+        if share_count == 0 {
+            return;
+        }
+
+        self.state.index_share_item = (self.state.index_share_item + 1) % share_count;
+        self.state
+            .received_shares_state
+            .select(Some(self.state.index_share_item));
+    }
+
+    fn regenerate_preview(&mut self) {
+        let password = match &self.vault_logged_code {
+            Some(vault_logged_core) => {
+                vault_logged_core.generate_password(&self.state.generator_restriction)
+            }
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        match password {
+            Ok(password) => {
+                self.state.generator_preview = String::from_utf8(password).unwrap();
+                self.state.generator_error = None;
+            }
+            Err(error) => {
+                self.state.generator_preview = String::new();
+                self.state.generator_error = Some(error.to_string());
+            }
+        }
+    }
+
+    /// Uses the generator settings for new entries and stores them as the vault's defaults
+    fn save_generator_settings(&mut self, password_area: &mut TextArea<'_>) {
+        let restriction = self.state.generator_restriction.clone();
+
+        match &mut self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.set_password_restriction(restriction.clone()),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        }
+
+        self.password_restriction = restriction;
+
+        self.save_vault(password_area);
+    }
+
+    /// An expired session asks for the password, the save is retried once logged in again
+    fn save_vault(&mut self, password_area: &mut TextArea<'_>) {
+        let result = match &mut self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.save_vault(),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        match result {
+            Ok(()) => self.store_last_generation(),
+            Err(CoreError::SessionExpired) => {
+                set_textarea_value(password_area, "");
+                self.state.login_error = Some(SESSION_EXPIRED_PROMPT.to_string());
+
+                self.update_state_screen(CurrentScreen::Password, InputMode::Reauthenticating)
+            }
+            Err(CoreError::Conflict) => self.state.vault_message = Some(VAULT_CONFLICT.to_string()),
+            Err(error) => self.state.vault_message = Some(error.to_string()),
+        }
+    }
+
+    /// Sharing and accepted shares save the vault too, the generation is stored again when the vault is closed
+    fn store_last_generation(&self) {
+        if let Some(vault_logged_core) = &self.vault_logged_code {
+            set_last_generation(&self.app_config.vault_store.path, &self.state.vault_name, vault_logged_core.get_generation());
+        }
+    }
+
+    /// Only after a save was refused by a conflict, the merged vault is saved once every conflict is resolved
+    fn merge_remote_vault(&mut self, password_area: &mut TextArea<'_>) {
+        let result = match &mut self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.merge_remote_vault(),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        match result {
+            Ok(conflicts) if conflicts.is_empty() => self.save_vault(password_area),
+            Ok(conflicts) => {
+                self.set_merge_conflicts(conflicts);
+                self.update_state_screen(CurrentScreen::MergeConflicts, InputMode::Neutral)
+            }
+            Err(error) => self.state.vault_message = Some(error.to_string()),
+        }
+    }
+
+    fn resolve_conflict(&mut self, side: MergeSide, password_area: &mut TextArea<'_>) {
+        let mut conflicts = std::mem::take(&mut self.state.merge_conflicts);
+
+        if self.state.index_conflict_item >= conflicts.len() {
+            return;
+        }
+
+        let conflict = conflicts.remove(self.state.index_conflict_item);
+
+        match &mut self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.resolve_conflict(&conflict, side),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        }
+
+        let resolved = conflicts.is_empty();
+        self.set_merge_conflicts(conflicts);
+
+        if resolved {
+            self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral);
+            self.save_vault(password_area);
+        }
+    }
+
+    /// Passwords in conflict are masked on screen, they are compared by pasting them
+    fn copy_conflict_value(&mut self, side: MergeSide) {
+        let Some(conflict) = self.state.merge_conflicts.get(self.state.index_conflict_item) else {
+            return;
+        };
+
+        let value = match side {
+            MergeSide::Local => conflict.local.clone(),
+            MergeSide::Remote => conflict.remote.clone(),
+        };
+
+        let result = match &self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.copy_to_clipboard(value),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        self.state.conflict_error = result.err().map(|error| error.to_string());
+    }
+
+    fn set_merge_conflicts(&mut self, conflicts: Vec<EntryConflict>) {
+        self.state.index_conflict_item = 0;
+        self.state.merge_conflicts_state = ListState::default();
+
+        if !conflicts.is_empty() {
+            self.state.merge_conflicts_state.select_first();
+        }
+
+        self.state.merge_conflicts = conflicts;
+        self.state.conflict_error = None;
+    }
+
+    fn previous_conflict(&mut self) {
+        let conflict_count = self.state.merge_conflicts.len();
+
+        if conflict_count == 0 {
+            return;
+        }
+
+        self.state.index_conflict_item = (self.state.index_conflict_item + conflict_count - 1) % conflict_count;
+        self.state
+            .merge_conflicts_state
+            .select(Some(self.state.index_conflict_item));
+    }
+
+    fn next_conflict(&mut self) {
+        let conflict_count = self.state.merge_conflicts.len();
+
+        if conflict_count == 0 {
+            return;
+        }
+
+        self.state.index_conflict_item = (self.state.index_conflict_item + 1) % conflict_count;
+        self.state
+            .merge_conflicts_state
+            .select(Some(self.state.index_conflict_item));
+    }
+
+    fn reauthenticate(&mut self, password_area: &mut TextArea<'_>) {
+        let password = get_value_from_lines(password_area.lines());
+
+        let result = match &mut self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.reauthenticate(&self.state.vault_name, &password),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        match result {
+            Ok(()) => {
+                set_textarea_value(password_area, "");
+                self.state.login_error = None;
+                self.update_state_screen(CurrentScreen::Vault, InputMode::Neutral);
+
+                self.save_vault(password_area);
+            }
+            Err(error) => self.state.login_error = Some(error.to_string()),
+        }
+    }
+
+    /// The vault is closed even if the server can't be reached, the session is dropped on this side either way
+    fn logout(&mut self) {
+        self.store_last_generation();
+
+        if let Some(mut vault_logged_core) = self.vault_logged_code.take() {
+            let _ = vault_logged_core.logout();
+        }
+
+        self.state.vault_name = String::new();
+        self.vault_core = None;
+
+        self.update_state_screen(CurrentScreen::Home, InputMode::Neutral);
+    }
+
+    fn fill_generated_username(&self, username_area: &mut TextArea<'_>) {
+        let username = match &self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.generate_username(&self.username_restriction),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        if let Ok(username) = username {
+            set_textarea_value(username_area, &username);
+        }
+    }
+
+    fn fill_generated_email_alias(&self, username_area: &mut TextArea<'_>) {
+        // Aliases are only available when a base address is set in the config
+        let Some(email_alias) = &self.app_config.email_alias else {
+            return;
+        };
+
+        let alias_kind = match email_alias.catch_all {
+            true => EmailAliasKind::CatchAll,
+            false => EmailAliasKind::PlusAddressing,
+        };
+
+        let alias = match &self.vault_logged_code {
+            Some(vault_logged_core) => {
+                vault_logged_core.generate_email_alias(&email_alias.base_address, &alias_kind)
+            }
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        if let Ok(alias) = alias {
+            set_textarea_value(username_area, &alias);
+        }
+    }
+
+    fn previous_vault(&mut self) {
+        let entries = self.state.get_vault_items();
+
+        if entries.is_empty() {
+            return;
+        }
+
+        if self.state.index_vault_item > 0 {
+            self.state.index_vault_item -= 1;
+        } else {
+            self.state.index_vault_item = entries.len() - 1;
+        }
+
+        self.state
+            .vault_items_state
+            .select(Some(self.state.index_vault_item));
+    }
+
+    fn next_vault(&mut self) {
+        let entries = self.state.get_vault_items();
+
+        if entries.is_empty() {
+            return;
+        }
+
+        if self.state.index_vault_item < entries.len() - 1 {
+            self.state.index_vault_item += 1;
+        } else {
+            self.state.index_vault_item = 0;
+        }
+
+        self.state
+            .vault_items_state
+            .select(Some(self.state.index_vault_item));
+    }
+
+    fn previous_entry(&mut self) {
+        let entries = self.get_vault_entries();
+
+        if entries.is_empty() {
+            return;
+        }
+
+        if self.state.index_entry_item > 0 {
+            self.state.index_entry_item -= 1;
+        } else {
+            self.state.index_entry_item = entries.len() - 1;
+        }
+
+        self.state
+            .vault_entries_state
+            .select(Some(self.state.index_entry_item));
+    }
+
+    fn next_entry(&mut self) {
+        let entries = self.get_vault_entries();
+
+        if entries.is_empty() {
+            return;
+        }
+
+        if self.state.index_entry_item < entries.len() - 1 {
+            self.state.index_entry_item += 1;
+        } else {
+            self.state.index_entry_item = 0;
+        }
+
+        self.state
+            .vault_entries_state
+            .select(Some(self.state.index_entry_item));
+    }
+
+    fn render_home(&mut self, frame: &mut Frame) {
+        let instructions = Line::from(vec![
+            " Log in ".into(),
+            "<ENTER>".blue().bold(),
+            " Create vault ".into(),
+            "<C>".blue().bold(),
+            " Recover ".into(),
+            "<R>".blue().bold(),
+            " Quit ".into(),
+            "<Q> ".blue().bold(),
+        ]);
+
+        let block = create_main_block(instructions);
+
+        let items: Vec<ListItem> = self
+            .state
+            .get_vault_items()
+            .iter()
+            .map(|vault_name| {
+                ListItem::new(vault_name.clone())
+                    .style(Style::default())
+                    .fg(Color::White)
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::default()
+                    .bg(Color::Blue)
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+
+        frame.render_stateful_widget(list, frame.area(), &mut self.state.vault_items_state);
+    }
+
+    fn render_password(&mut self, frame: &mut Frame, password_area: &TextArea<'_>) {
+        let Some(error) = &self.state.login_error else {
+            frame.render_widget(password_area, frame.area());
+            return;
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(3)])
+            .split(frame.area());
+
+        frame.render_widget(password_area, chunks[0]);
+
+        let error = Paragraph::new(error.as_str())
+            .fg(Color::Red)
+            .block(Block::default().borders(Borders::ALL));
+
+        frame.render_widget(error, chunks[1]);
+    }
+
+    fn render_vault_name(&mut self, frame: &mut Frame, vault_name_area: &TextArea<'_>) {
+        frame.render_widget(vault_name_area, frame.area());
+    }
+
+    fn render_vault(&mut self, frame: &mut Frame) {
+        let instructions = Line::from(vec![
+            " Copy Password ".into(),
+            "<C>".blue().bold(),
+            " Add entry ".into(),
+            "<A>".blue().bold(),
+            " Next counter ".into(),
+            "<N>".blue().bold(),
+            " Save vault ".into(),
+            "<S>".blue().bold(),
+            " Merge ".into(),
+            "<M>".blue().bold(),
+            " Generator ".into(),
+            "<G>".blue().bold(),
+            " Change password ".into(),
+            "<P>".blue().bold(),
+            " Recovery key ".into(),
+            "<K>".blue().bold(),
+            " Key file ".into(),
+            "<F>".blue().bold(),
+            " Share ".into(),
+            "<H>".blue().bold(),
+            " Inbox ".into(),
+            "<I>".blue().bold(),
+            " Logout ".into(),
+            "<L>".blue().bold(),
+            " Quit ".into(),
+            "<Q> ".blue().bold(),
+        ]);
+
+        let block = create_main_block(instructions);
+
+        let items: Vec<ListItem> = self
+            .get_vault_entries()
+            .iter()
+            .map(|entry| {
+                ListItem::from(entry)
+                    .style(Style::default())
+                    .fg(Color::White)
+            })
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
                 Style::default()
                     .bg(Color::Blue)
                     .fg(Color::White)
@@ -510,7 +1493,23 @@ impl App {
             )
             .highlight_symbol(">> ");
 
-        frame.render_stateful_widget(list, frame.area(), &mut self.state.vault_entries_state);
+        let Some(message) = &self.state.vault_message else {
+            frame.render_stateful_widget(list, frame.area(), &mut self.state.vault_entries_state);
+            return;
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(3)])
+            .split(frame.area());
+
+        frame.render_stateful_widget(list, chunks[0], &mut self.state.vault_entries_state);
+
+        let message = Paragraph::new(message.as_str())
+            .fg(Color::Yellow)
+            .block(Block::default().borders(Borders::ALL));
+
+        frame.render_widget(message, chunks[1]);
     }
 
     fn render_entry(&mut self, frame: &mut Frame, entries_area: &[TextArea<'_>; 2]) {
@@ -525,6 +1524,415 @@ impl App {
         }
     }
 
+    fn render_generator(&mut self, frame: &mut Frame) {
+        let instructions = Line::from(vec![
+            " Length ".into(),
+            "<LEFT/RIGHT>".blue().bold(),
+            " Toggle ".into(),
+            "<1-5>".blue().bold(),
+            " Regenerate ".into(),
+            "<R>".blue().bold(),
+            " Save as default ".into(),
+            "<ENTER>".blue().bold(),
+            " Back ".into(),
+            "<ESC> ".blue().bold(),
+        ]);
+
+        let block = create_main_block(instructions);
+        let inner_area = block.inner(frame.area());
+
+        frame.render_widget(block, frame.area());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(7),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(inner_area);
+
+        let restriction = &self.state.generator_restriction;
+
+        let length_gauge = LineGauge::default()
+            .block(Block::default().borders(Borders::ALL).title(" Length "))
+            .filled_style(Style::default().fg(Color::LightGreen))
+            .line_set(symbols::line::THICK)
+            .label(format!("{:>2} ", restriction.length))
+            .ratio(restriction.length as f64 / MAX_PASSWORD_LENGTH as f64);
+
+        frame.render_widget(length_gauge, chunks[0]);
+
+        let options = [
+            ("1", "Lower case (a-z)", restriction.lower_case),
+            ("2", "Upper case (A-Z)", restriction.upper_case),
+            ("3", "Numbers (0-9)", restriction.numbers),
+            ("4", "Special characters (!@#...)", restriction.special_characters),
+            ("5", "Exclude ambiguous characters (Il1O0...)", restriction.exclude_ambiguous),
+        ];
+
+        let items: Vec<ListItem> = options
+            .iter()
+            .map(|(key, label, enabled)| {
+                let checkbox = match enabled {
+                    true => "[x]",
+                    false => "[ ]",
+                };
+
+                ListItem::new(format!(" {} {} {}", key, checkbox, label)).fg(Color::White)
+            })
+            .collect();
+
+        let list = List::new(items).block(Block::default().borders(Borders::ALL).title(" Options "));
+
+        frame.render_widget(list, chunks[1]);
+
+        let preview = match &self.state.generator_error {
+            Some(error) => Paragraph::new(error.as_str()).fg(Color::Red),
+            None => Paragraph::new(self.state.generator_preview.as_str()).fg(Color::LightGreen),
+        };
+
+        frame.render_widget(
+            preview.block(Block::default().borders(Borders::ALL).title(" Preview ")),
+            chunks[2],
+        );
+
+        let entropy = match &self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.password_entropy(restriction),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        };
+
+        let (strength, color) = match entropy {
+            entropy if entropy < 50.0 => ("weak", Color::Red),
+            entropy if entropy < 80.0 => ("fair", Color::Yellow),
+            entropy if entropy < 100.0 => ("strong", Color::LightGreen),
+            _ => ("very strong", Color::Green),
+        };
+
+        let entropy_readout = Paragraph::new(format!("{:.1} bits ({})", entropy, strength))
+            .fg(color)
+            .block(Block::default().borders(Borders::ALL).title(" Entropy "));
+
+        frame.render_widget(entropy_readout, chunks[3]);
+    }
+
+    fn render_new_password(&mut self, frame: &mut Frame, password_form_areas: &[TextArea<'_>; 3]) {
+        let action = match self.input_mode {
+            InputMode::Recovering => " Recover vault ",
+            _ => " Change password ",
+        };
+
+        let mut instructions = vec![
+            " Next field ".into(),
+            "<TAB>".blue().bold(),
+            action.into(),
+            "<ENTER>".blue().bold(),
+        ];
+
+        if let InputMode::Recovering = self.input_mode {
+            instructions.extend([" Combine shares ".into(), "<CTRL+S>".blue().bold()]);
+        }
+
+        instructions.extend([" Back ".into(), "<ESC> ".blue().bold()]);
+
+        let instructions = Line::from(instructions);
+
+        let block = create_main_block(instructions);
+        let inner_area = block.inner(frame.area());
+
+        frame.render_widget(block, frame.area());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Min(0),
+            ])
+            .split(inner_area);
+
+        for (password_form_area, chunk) in password_form_areas.iter().zip(chunks.iter()) {
+            frame.render_widget(password_form_area, *chunk);
+        }
+
+        let message = match &self.state.password_form_error {
+            Some(error) => Paragraph::new(error.as_str()).fg(Color::Red),
+            None => Paragraph::new(match self.input_mode {
+                InputMode::Recovering => "The recovery key is in the emergency kit. Derived passwords stay the same.",
+                _ => "Derived passwords are computed from a key of the vault, they stay the same.",
+            })
+            .fg(Color::Yellow),
+        };
+
+        frame.render_widget(message.block(Block::default().borders(Borders::ALL)), chunks[3]);
+    }
+
+    fn render_recovery_key(&mut self, frame: &mut Frame) {
+        let instructions = Line::from(vec![
+            format!(" Split in {RECOVERY_SHARE_COUNT} shares ").into(),
+            "<S>".blue().bold(),
+            " Back ".into(),
+            "<ANY KEY> ".blue().bold(),
+        ]);
+
+        let block = create_main_block(instructions);
+        let inner_area = block.inner(frame.area());
+
+        frame.render_widget(block, frame.area());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Length(3), Constraint::Min(0)])
+            .split(inner_area);
+
+        if let Some(recovery_key) = &self.state.recovery_key {
+            let recovery_key = Paragraph::new(recovery_key.to_string())
+                .fg(Color::LightGreen)
+                .bold()
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(" Recovery key, shown only once "),
+                );
+
+            frame.render_widget(recovery_key, chunks[0]);
+        }
+
+        let message = Paragraph::new(self.state.recovery_key_message.as_str())
+            .fg(Color::Yellow)
+            .block(Block::default().borders(Borders::ALL));
+
+        frame.render_widget(message, chunks[1]);
+    }
+
+    fn render_combine_shares(&mut self, frame: &mut Frame, shares_area: &TextArea<'_>) {
+        let instructions = Line::from(vec![
+            " Combine ".into(),
+            "<CTRL+S>".blue().bold(),
+            " Back ".into(),
+            "<ESC> ".blue().bold(),
+        ]);
+
+        let block = create_main_block(instructions);
+        let inner_area = block.inner(frame.area());
+
+        frame.render_widget(block, frame.area());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(5), Constraint::Length(3)])
+            .split(inner_area);
+
+        frame.render_widget(shares_area, chunks[0]);
+
+        let message = match &self.state.shares_error {
+            Some(error) => Paragraph::new(error.as_str()).fg(Color::Red),
+            None => Paragraph::new(format!(
+                "Type one share per line, any {RECOVERY_SHARE_THRESHOLD} of them give the recovery key back."
+            ))
+            .fg(Color::Yellow),
+        };
+
+        frame.render_widget(message.block(Block::default().borders(Borders::ALL)), chunks[1]);
+    }
+
+    fn render_share_entry(&mut self, frame: &mut Frame, recipient_area: &TextArea<'_>) {
+        let key_changed = matches!(
+            &self.state.recipient_key,
+            Some(RecipientKey { status: KeyStatus::Changed { .. }, .. })
+        );
+
+        let mut instructions = vec![
+            if self.state.recipient_key.is_some() { " Share " } else { " Look up key " }.into(),
+            "<ENTER>".blue().bold(),
+        ];
+
+        if key_changed {
+            instructions.extend([" Trust new key ".into(), "<CTRL+T>".blue().bold()]);
+        }
+
+        instructions.extend([" Back ".into(), "<ESC> ".blue().bold()]);
+
+        let block = create_main_block(Line::from(instructions));
+        let inner_area = block.inner(frame.area());
+
+        frame.render_widget(block, frame.area());
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([
+                Constraint::Length(3),
+                Constraint::Length(3),
+                Constraint::Length(7),
+                Constraint::Min(0),
+            ])
+            .split(inner_area);
+
+        frame.render_widget(recipient_area, chunks[0]);
+
+        let message = match &self.state.share_error {
+            Some(error) => Paragraph::new(error.as_str()).fg(Color::Red),
+            None => Paragraph::new(
+                "The recipient must have opened their share inbox once, it publishes the key the entry is sealed with.",
+            )
+            .fg(Color::Yellow),
+        };
+
+        frame.render_widget(message.block(Block::default().borders(Borders::ALL)), chunks[1]);
+
+        let Some(recipient_key) = &self.state.recipient_key else {
+            return;
+        };
+
+        let fingerprint = match &recipient_key.status {
+            KeyStatus::New => Paragraph::new(vec![
+                Line::from(recipient_key.fingerprint.as_str()).white().bold(),
+                Line::from("First share with this user, the key gets pinned. Compare the words with them to be sure."),
+            ])
+            .fg(Color::Yellow)
+            .block(Block::default().borders(Borders::ALL).title(" Fingerprint ")),
+            KeyStatus::Pinned => Paragraph::new(vec![
+                Line::from(recipient_key.fingerprint.as_str()).white().bold(),
+                Line::from("Same key as the one pinned in this vault."),
+            ])
+            .fg(Color::Green)
+            .block(Block::default().borders(Borders::ALL).title(" Fingerprint ")),
+            // Someone may be swapping keys on the server, sharing stays blocked until the user trusts the new key
+            KeyStatus::Changed { pinned_fingerprint } => Paragraph::new(vec![
+                Line::from(format!("The public key of {} changed!", recipient_key.username)).bold(),
+                Line::from(format!("Pinned: {pinned_fingerprint}")),
+                Line::from(format!("New:    {}", recipient_key.fingerprint)),
+                Line::from("Compare the new words with the recipient in person or by phone before trusting the key."),
+            ])
+            .fg(Color::Red)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_type(BorderType::Thick)
+                    .border_style(Style::default().fg(Color::Red))
+                    .title(" WARNING ".bold()),
+            ),
+        };
+
+        frame.render_widget(fingerprint.wrap(Wrap { trim: true }), chunks[2]);
+    }
+
+    fn render_share_inbox(&mut self, frame: &mut Frame) {
+        let instructions = Line::from(vec![
+            " Accept ".into(),
+            "<ENTER>".blue().bold(),
+            " Decline ".into(),
+            "<D>".blue().bold(),
+            " Back ".into(),
+            "<ESC> ".blue().bold(),
+        ]);
+
+        let block = create_main_block(instructions);
+
+        let items: Vec<ListItem> = self
+            .state
+            .received_shares
+            .iter()
+            .map(|share| ListItem::new(format!("{}: {}", share.sender, share.entry.title)).fg(Color::White))
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::default()
+                    .bg(Color::Blue)
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+
+        let fingerprint = match &self.state.sharing_fingerprint {
+            Some(fingerprint) => format!(" Your fingerprint: {fingerprint} "),
+            None => String::new(),
+        };
+
+        let message = match &self.state.share_error {
+            Some(error) => Paragraph::new(error.as_str()).fg(Color::Red),
+            None if self.state.received_shares.is_empty() => Paragraph::new("No shares waiting.").fg(Color::Yellow),
+            // The server names the sender, the sealed entry itself doesn't prove who sent it
+            None => Paragraph::new("Senders are named by the server, only accept entries you expect.").fg(Color::Yellow),
+        };
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(3)])
+            .split(frame.area());
+
+        frame.render_stateful_widget(list, chunks[0], &mut self.state.received_shares_state);
+        frame.render_widget(message.block(Block::default().borders(Borders::ALL).title(fingerprint)), chunks[1]);
+    }
+
+    fn render_merge_conflicts(&mut self, frame: &mut Frame) {
+        let instructions = Line::from(vec![
+            " Keep mine ".into(),
+            "<L>".blue().bold(),
+            " Take theirs ".into(),
+            "<R>".blue().bold(),
+            " Copy mine ".into(),
+            "<C>".blue().bold(),
+            " Copy theirs ".into(),
+            "<T>".blue().bold(),
+            " Back ".into(),
+            "<ESC> ".blue().bold(),
+        ]);
+
+        let block = create_main_block(instructions);
+
+        let items: Vec<ListItem> = self
+            .state
+            .merge_conflicts
+            .iter()
+            .map(|conflict| ListItem::new(format!("{}: {}", conflict.title, conflict.field)).fg(Color::White))
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(
+                Style::default()
+                    .bg(Color::Blue)
+                    .fg(Color::White)
+                    .add_modifier(Modifier::BOLD),
+            )
+            .highlight_symbol(">> ");
+
+        let mut values = match self.state.merge_conflicts.get(self.state.index_conflict_item) {
+            Some(conflict) if conflict.field == EntryField::Password => vec![
+                Line::from(format!("Mine: {MASKED_PASSWORD}")),
+                Line::from(format!("Theirs: {MASKED_PASSWORD}")),
+            ],
+            Some(conflict) => vec![
+                Line::from(format!("Mine: {}", conflict.local)),
+                Line::from(format!("Theirs: {}", conflict.remote)),
+            ],
+            None => vec![],
+        };
+
+        if let Some(error) = &self.state.conflict_error {
+            values.push(Line::from(error.as_str()).fg(Color::Red));
+        }
+
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Min(3), Constraint::Length(values.len() as u16 + 2)])
+            .split(frame.area());
+
+        let values_block = Block::default()
+            .borders(Borders::ALL)
+            .title(" Changed on both devices ");
+
+        frame.render_stateful_widget(list, chunks[0], &mut self.state.merge_conflicts_state);
+        frame.render_widget(Paragraph::new(values).fg(Color::Yellow).block(values_block), chunks[1]);
+    }
+
     fn update_state_screen(&mut self, next_screen: CurrentScreen, next_input: InputMode) {
         self.current_screen = next_screen;
         self.input_mode = next_input;
@@ -532,10 +1940,43 @@ impl App {
 
     pub fn get_vault_entries(&self) -> Vec<VaultEntry> {
         match &self.vault_logged_code {
-            Some(vault_logged_core) => entries_to_vault_entries(vault_logged_core.get_entries()),
+            Some(vault_logged_core) => entries_to_vault_entries(
+                vault_logged_core.get_entries(),
+                vault_logged_core.get_derived_entries(),
+            ),
             None => panic!("Error, shouldn't call this method at this time."),
         }
     }
+
+    /// The password of a derived entry is recomputed, a profile no password can meet fails
+    fn get_entry_password(&self, index: usize) -> Result<String, CoreError> {
+        let Some(vault_logged_core) = &self.vault_logged_code else {
+            panic!("Error, shouldn't call this method at this time.");
+        };
+
+        match self.get_vault_entries().get(index).unwrap() {
+            VaultEntry::Stored(entry) => Ok(entry.password.clone()),
+            VaultEntry::Derived(entry) => vault_logged_core
+                .derive_password(&entry.profile)
+                .map(|password| String::from_utf8_lossy(&password).into_owned()),
+        }
+    }
+
+    /// Rotates the password of a derived entry, the previous one can be recomputed by lowering the counter
+    fn next_derived_counter(&mut self) {
+        let Some(VaultEntry::Derived(mut entry)) =
+            self.get_vault_entries().into_iter().nth(self.state.index_entry_item)
+        else {
+            return;
+        };
+
+        entry.profile.counter += 1;
+
+        match &mut self.vault_logged_code {
+            Some(vault_logged_core) => vault_logged_core.update_derived_entry(entry),
+            None => panic!("Internal error, shouldn't call vault logged core at this time."),
+        }
+    }
 }
 
 fn inactivate(textarea: &mut TextArea<'_>) {
@@ -560,6 +2001,40 @@ fn activate(textarea: &mut TextArea<'_>) {
     );
 }
 
+/// Keeps the titles of the password fields, only the border color shows the focus
+fn focus_password_field(password_form_areas: &mut [TextArea<'_>; 3], titles: &[&'static str; 3], selected: usize) {
+    for (index, password_form_area) in password_form_areas.iter_mut().enumerate() {
+        let color = match index == selected {
+            true => Color::LightGreen,
+            false => Color::DarkGray,
+        };
+
+        password_form_area.set_style(Style::default().fg(color));
+        password_form_area.set_block(
+            Block::default()
+                .borders(Borders::ALL)
+                .style(Style::default().fg(color))
+                .title(titles[index]),
+        );
+    }
+}
+
+/// The form is shared by the password change and the recovery, every field starts empty and masked
+fn reset_password_form(password_form_areas: &mut [TextArea<'_>; 3], titles: &[&'static str; 3]) {
+    for password_form_area in password_form_areas.iter_mut() {
+        set_textarea_value(password_form_area, "");
+        password_form_area.set_mask_char('\u{002A}');
+    }
+
+    focus_password_field(password_form_areas, titles, 0);
+}
+
+fn set_textarea_value(textarea: &mut TextArea<'_>, value: &str) {
+    textarea.select_all();
+    textarea.cut();
+    textarea.insert_str(value);
+}
+
 fn create_main_block(instructions: Line<'_>) -> Block<'_> {
     let title = Line::from(" Ferris Vault 🦀 ".bold());
 
@@ -576,12 +2051,50 @@ fn get_value_from_lines(lines: &[String]) -> String {
     }
 }
 
-fn create_core_service(
-    server_url: String,
-) -> CoreService<OpaqueVaultManager<OpaqueApi>, SecurePasswordGenerator, NoKeyXChaCha20Poly1305> {
-    let api = OpaqueApi::new(server_url);
-    let vault_manager = OpaqueVaultManager::new(api);
-    CoreService::new(vault_manager)
+type AppCoreService = CoreService<
+    CachedVaultManager<OpaqueVaultManager<AsyncOpaqueApi>, NoKeyCipherDispatcher>,
+    SecurePasswordGenerator,
+    SecureUsernameGenerator,
+    NoKeyCipherDispatcher,
+>;
+
+/// The blocking vault manager, each request freezes the interface until it is answered or one of its timeouts elapses
+fn create_core_service(app_config: &AppConfig) -> Result<AppCoreService, VaultError> {
+    let request_policy = app_config
+        .requests
+        .as_ref()
+        .map(|config| RequestPolicy {
+            connect_timeout: Duration::from_secs(config.connect_timeout_secs),
+            read_timeout: Duration::from_secs(config.read_timeout_secs),
+            timeout: Duration::from_secs(config.timeout_secs),
+            max_retries: config.max_retries,
+            base_delay: Duration::from_millis(config.base_delay_ms),
+            max_delay: Duration::from_millis(config.max_delay_ms),
+        })
+        .unwrap_or_default();
+    let api = AsyncOpaqueApi::with_request_policy(app_config.server.get_server_url(), request_policy)?;
+    let key_stretching = app_config
+        .key_stretching
+        .as_ref()
+        .map(|config| KeyStretching::new(config.memory_kib, config.iterations, config.parallelism))
+        .unwrap_or_default();
+    let vault_manager = OpaqueVaultManager::with_key_stretching(api, key_stretching)?;
+    let cache_dir = app_config
+        .offline_cache
+        .as_ref()
+        .map(|config| PathBuf::from(&config.path));
+    Ok(CoreService::new(CachedVaultManager::with_key_stretching(vault_manager, cache_dir, key_stretching)))
+}
+
+/// Only readable by its owner, fails if the file already exists
+fn create_private_file(path: &Path) -> io::Result<File> {
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    options.open(path)
 }
 
 fn add_vault_name_to_vault_store(vault_store_path: &str, username: String) {
@@ -599,17 +2112,92 @@ fn get_vaults(path: &str) -> Vec<String> {
     let vaults: Vec<String> = read_to_string(path)
         .unwrap()
         .lines()
-        .map(String::from)
+        .map(|line| line.split(VAULT_STORE_SEPARATOR).next().unwrap_or_default().to_string())
         .collect();
 
     vaults
 }
 
+fn get_key_file_path(vault_store_path: &str, vault_name: &str) -> Option<String> {
+    get_vault_store_field(vault_store_path, vault_name, KEY_FILE_FIELD)
+}
+
+fn set_key_file_path(vault_store_path: &str, vault_name: &str, key_file_path: Option<&str>) {
+    set_vault_store_field(vault_store_path, vault_name, KEY_FILE_FIELD, key_file_path);
+}
+
+fn get_pinned_server_key(vault_store_path: &str, vault_name: &str) -> Option<Vec<u8>> {
+    get_vault_store_field(vault_store_path, vault_name, SERVER_KEY_FIELD)
+        .and_then(|server_key| hex::decode(server_key).ok())
+}
+
+fn set_pinned_server_key(vault_store_path: &str, vault_name: &str, server_key: &[u8]) {
+    set_vault_store_field(vault_store_path, vault_name, SERVER_KEY_FIELD, Some(&hex::encode(server_key)));
+}
+
+fn get_identifiers_bound(vault_store_path: &str, vault_name: &str) -> bool {
+    get_vault_store_field(vault_store_path, vault_name, IDENTIFIERS_BOUND_FIELD).is_some()
+}
+
+fn set_identifiers_bound(vault_store_path: &str, vault_name: &str) {
+    set_vault_store_field(vault_store_path, vault_name, IDENTIFIERS_BOUND_FIELD, Some("1"));
+}
+
+fn get_last_generation(vault_store_path: &str, vault_name: &str) -> u64 {
+    get_vault_store_field(vault_store_path, vault_name, GENERATION_FIELD)
+        .and_then(|generation| generation.parse().ok())
+        .unwrap_or_default()
+}
+
+fn set_last_generation(vault_store_path: &str, vault_name: &str, generation: u64) {
+    set_vault_store_field(vault_store_path, vault_name, GENERATION_FIELD, Some(&generation.to_string()));
+}
+
+fn get_vault_store_field(vault_store_path: &str, vault_name: &str, field: usize) -> Option<String> {
+    read_to_string(vault_store_path)
+        .ok()?
+        .lines()
+        .map(|line| line.split(VAULT_STORE_SEPARATOR).collect::<Vec<_>>())
+        .find(|fields| fields[0] == vault_name)?
+        .get(field)
+        .filter(|value| !value.is_empty())
+        .map(|value| value.to_string())
+}
+
+/// An empty field is kept only when a later one is set
+fn set_vault_store_field(vault_store_path: &str, vault_name: &str, field: usize, value: Option<&str>) {
+    let vault_store = read_to_string(vault_store_path).unwrap();
+
+    let lines: String = vault_store
+        .lines()
+        .map(|line| {
+            let mut fields: Vec<&str> = line.split(VAULT_STORE_SEPARATOR).collect();
+
+            if fields[0] == vault_name {
+                if fields.len() <= field {
+                    fields.resize(field + 1, "");
+                }
+
+                fields[field] = value.unwrap_or_default();
+
+                while fields.last() == Some(&"") {
+                    fields.pop();
+                }
+            }
+
+            format!("{}\n", fields.join(&VAULT_STORE_SEPARATOR.to_string()))
+        })
+        .collect();
+
+    write(vault_store_path, lines).unwrap();
+}
+
 // fn create_mocked_core_service()
-// -> MockCore<OpaqueVaultManager<OpaqueApi>, SecurePasswordGenerator, NoKeyXChaCha20Poly1305> {
+// -> MockCore<OpaqueVaultManager<AsyncOpaqueApi>, SecurePasswordGenerator, SecureUsernameGenerator, NoKeyCipherDispatcher> {
 //     MockCore {
 //         _phantom_nkc: PhantomData,
 //         _phantom_pg: PhantomData,
+//         _phantom_ug: PhantomData,
 //         _phantom_vm: PhantomData,
 //     }
 // }
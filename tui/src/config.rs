@@ -4,7 +4,11 @@ use serde::Deserialize;
 #[derive(Debug, Deserialize, Default)]
 pub struct AppConfig {
     pub server: ServerInfo,
-    pub vault_store: VaultStore
+    pub vault_store: VaultStore,
+    pub email_alias: Option<EmailAliasConfig>,
+    pub key_stretching: Option<KeyStretchingConfig>,
+    pub requests: Option<RequestsConfig>,
+    pub offline_cache: Option<OfflineCacheConfig>
 }
 
 #[derive(Debug, Deserialize, Default)]
@@ -18,6 +22,38 @@ pub struct VaultStore {
     pub path: String
 }
 
+#[derive(Debug, Deserialize, Default)]
+pub struct EmailAliasConfig {
+    pub base_address: String,
+    #[serde(default)]
+    pub catch_all: bool
+}
+
+/// Argon2id parameters used when registering, the defaults of Argon2 apply without them
+#[derive(Debug, Deserialize)]
+pub struct KeyStretchingConfig {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32
+}
+
+/// Timeouts of the requests and retries of the idempotent ones, the defaults of the vault manager apply without them
+#[derive(Debug, Deserialize)]
+pub struct RequestsConfig {
+    pub connect_timeout_secs: u64,
+    pub read_timeout_secs: u64,
+    pub timeout_secs: u64,
+    pub max_retries: u32,
+    pub base_delay_ms: u64,
+    pub max_delay_ms: u64
+}
+
+/// Directory of the encrypted copies of the vaults, they can't be opened without the server unless it is set
+#[derive(Debug, Deserialize)]
+pub struct OfflineCacheConfig {
+    pub path: String
+}
+
 impl AppConfig {
     pub fn build(args: Vec<String>) -> Result<Self, String> {
         if args.len() < 2 {
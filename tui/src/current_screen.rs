@@ -5,5 +5,12 @@ pub enum CurrentScreen {
     VaultName,
     Password,
     Vault,
-    Entry
+    Entry,
+    Generator,
+    NewPassword,
+    RecoveryKey,
+    CombineShares,
+    ShareEntry,
+    ShareInbox,
+    MergeConflicts
 }
\ No newline at end of file
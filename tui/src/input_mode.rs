@@ -5,5 +5,8 @@ pub enum InputMode {
     Loggin,
     Register,
     CreatingEntry,
+    ChangingPassword,
+    Recovering,
+    Reauthenticating,
     // EditingEntry
 }
\ No newline at end of file
@@ -8,6 +8,7 @@ mod state;
 mod input_mode;
 mod config;
 mod vault_entry;
+mod text_areas;
 
 // mod mock_core;
 
@@ -23,9 +24,17 @@ fn main() -> Result<()> {
         }
     };
 
+    let mut app = match App::new(app_config) {
+        Ok(app) => app,
+        Err(error) => {
+            eprintln!("Error creating the vault manager: {error}");
+            exit(1);
+        }
+    };
+
     let mut terminal = ratatui::init();
 
-    let app_result = App::new(app_config).run(&mut terminal);
+    let app_result = app.run(&mut terminal);
 
     ratatui::restore();
 
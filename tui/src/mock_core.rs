@@ -6,30 +6,36 @@ use app_core::{
         core_errors::{CoreError, Result},
         vault_core::{Core, LoggedCore},
     },
-    password::password_restriction::PasswordRestriction,
+    cryptography::{recovery_key::RecoveryKey, secret_share::SecretShare},
+    password::{derivation_profile::DerivationProfile, password_restriction::PasswordRestriction},
     ports::{
         cryptography::{Cryptography, NoKeyCipher},
         password_generator::PasswordGenerator,
+        username_generator::UsernameGenerator,
         vault_manager::VaultManager,
     },
-    uncrypted_vault::{Entry, UncryptedVault},
+    uncrypted_vault::{DerivedEntry, Entry, EntryConflict, MergeSide, UncryptedVault},
+    username::{email_alias::EmailAliasKind, username_restriction::UsernameRestriction},
+    vault::share::{KeyStatus, ReceivedShare, RecipientKey},
 };
-use password_generator::SecurePasswordGenerator;
+use password_generator::{SecurePasswordGenerator, username_generator::SecureUsernameGenerator};
 
 #[derive(Debug)]
-pub struct MockCore<VM: VaultManager, PG: PasswordGenerator, NKC: NoKeyCipher> {
+pub struct MockCore<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, NKC: NoKeyCipher> {
     pub _phantom_vm: PhantomData<VM>,
     pub _phantom_pg: PhantomData<PG>,
+    pub _phantom_ug: PhantomData<UG>,
     pub _phantom_nkc: PhantomData<NKC>,
 }
 
-impl<VM: VaultManager, PG: PasswordGenerator, NKC: NoKeyCipher> Core<VM, PG, NKC> for MockCore<VM, PG, NKC> {
-    type LoggedType = MockLoggedCore<VM, PG, NKC::Crypto>;
+impl<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, NKC: NoKeyCipher> Core<VM, PG, UG, NKC> for MockCore<VM, PG, UG, NKC> {
+    type LoggedType = MockLoggedCore<VM, PG, UG, NKC::Crypto>;
 
     fn create_account(self, _: &str, _: &str) -> Result<Self::LoggedType> {
         Ok(MockLoggedCore {
             _phantom_c: PhantomData,
             _phantom_pg: PhantomData,
+            _phantom_ug: PhantomData,
             _phantom_vm: PhantomData,
             vault: UncryptedVault::new(),
         })
@@ -39,22 +45,40 @@ impl<VM: VaultManager, PG: PasswordGenerator, NKC: NoKeyCipher> Core<VM, PG, NKC
         Ok(MockLoggedCore {
             _phantom_c: PhantomData,
             _phantom_pg: PhantomData,
+            _phantom_ug: PhantomData,
             _phantom_vm: PhantomData,
             vault: UncryptedVault::new(),
         })
     }
+
+    fn recover_account(self, username: &str, _: &str, new_password: &str) -> Result<Self::LoggedType> {
+        self.logging_in(username, new_password)
+    }
+
+    fn combine_recovery_shares(&self, _: &[String]) -> Result<RecoveryKey> {
+        Ok(RecoveryKey::new(vec![0; 20]))
+    }
+
+    fn set_key_file(&mut self, _: Option<Vec<u8>>) {}
+
+    fn set_server_key(&mut self, _: Option<Vec<u8>>) {}
+
+    fn set_identifiers_bound(&mut self, _: bool) {}
+
+    fn set_min_generation(&mut self, _: u64) {}
 }
 
 #[derive(Debug)]
-pub struct MockLoggedCore<VM: VaultManager, PG: PasswordGenerator, C: Cryptography> {
+pub struct MockLoggedCore<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, C: Cryptography> {
     _phantom_vm: PhantomData<VM>,
     _phantom_pg: PhantomData<PG>,
+    _phantom_ug: PhantomData<UG>,
     _phantom_c: PhantomData<C>,
     vault: UncryptedVault,
 }
 
-impl<VM: VaultManager, PG: PasswordGenerator, C: Cryptography> LoggedCore<VM, PG, C>
-    for MockLoggedCore<VM, PG, C>
+impl<VM: VaultManager, PG: PasswordGenerator, UG: UsernameGenerator, C: Cryptography> LoggedCore<VM, PG, UG, C>
+    for MockLoggedCore<VM, PG, UG, C>
 {
     fn get_entries(&self) -> &[Entry] {
         self.vault.get_entries()
@@ -68,12 +92,149 @@ impl<VM: VaultManager, PG: PasswordGenerator, C: Cryptography> LoggedCore<VM, PG
         self.vault.update_entry(entry);
     }
 
+    fn get_derived_entries(&self) -> &[DerivedEntry] {
+        self.vault.get_derived_entries()
+    }
+
+    fn add_derived_entry(&mut self, profile: DerivationProfile) {
+        self.vault.add_derived_entry(profile);
+    }
+
+    fn update_derived_entry(&mut self, entry: DerivedEntry) {
+        self.vault.update_derived_entry(entry);
+    }
+
+    fn derive_password(&self, profile: &DerivationProfile) -> Result<Vec<u8>> {
+        SecurePasswordGenerator::derive_password(b"mock master secret", profile)
+            .map_err(|error| CoreError::PasswordGeneratorError(error.to_string()))
+    }
+
     fn generate_password(&self, restrictions: &PasswordRestriction) -> Result<Vec<u8>> {
         SecurePasswordGenerator::generate_password(restrictions)
             .map_err(|error| CoreError::PasswordGeneratorError(error.to_string()))
     }
 
-    fn save_vault(&self) -> Result<()> {
+    fn password_entropy(&self, restrictions: &PasswordRestriction) -> f64 {
+        SecurePasswordGenerator::entropy(restrictions)
+    }
+
+    fn get_password_restriction(&self) -> Option<&PasswordRestriction> {
+        self.vault.get_password_restriction()
+    }
+
+    fn get_server_key(&self) -> &[u8] {
+        &[]
+    }
+
+    fn get_identifiers_bound(&self) -> bool {
+        true
+    }
+
+    fn take_pending_error(&mut self) -> Option<CoreError> {
+        None
+    }
+
+    fn get_generation(&self) -> u64 {
+        0
+    }
+
+    fn set_password_restriction(&mut self, restrictions: PasswordRestriction) {
+        self.vault.set_password_restriction(restrictions);
+    }
+
+    fn generate_username(&self, restrictions: &UsernameRestriction) -> Result<String> {
+        SecureUsernameGenerator::generate_username(restrictions)
+            .map_err(|error| CoreError::UsernameGeneratorError(error.to_string()))
+    }
+
+    fn generate_email_alias(&self, base_address: &str, alias_kind: &EmailAliasKind) -> Result<String> {
+        SecureUsernameGenerator::generate_email_alias(base_address, alias_kind)
+            .map_err(|error| CoreError::UsernameGeneratorError(error.to_string()))
+    }
+
+    fn save_vault(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn merge_remote_vault(&mut self) -> Result<Vec<EntryConflict>> {
+        Ok(vec![])
+    }
+
+    fn resolve_conflict(&mut self, conflict: &EntryConflict, side: MergeSide) {
+        self.vault.resolve_conflict(conflict, side);
+    }
+
+    fn reauthenticate(&mut self, _: &str, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn logout(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn change_password(&mut self, _: &str, _: &str, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn create_recovery_key(&self) -> Result<RecoveryKey> {
+        Ok(RecoveryKey::new(vec![0; 20]))
+    }
+
+    fn split_recovery_key(&self, _: &RecoveryKey, _: u8, share_count: u8) -> Result<Vec<SecretShare>> {
+        Ok((1..=share_count)
+            .map(|index| SecretShare {
+                index,
+                mnemonic: String::new(),
+                hex: String::new(),
+            })
+            .collect())
+    }
+
+    fn generate_key_file(&self) -> Vec<u8> {
+        vec![0; 32]
+    }
+
+    fn set_key_file(&mut self, _: Option<Vec<u8>>) -> Result<()> {
+        Ok(())
+    }
+
+    fn enable_sharing(&mut self) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_sharing_fingerprint(&self) -> Option<String> {
+        None
+    }
+
+    fn lookup_public_key(&self, username: &str) -> Result<RecipientKey> {
+        Ok(RecipientKey {
+            username: username.to_string(),
+            public_key: vec![0; 32],
+            fingerprint: String::new(),
+            status: KeyStatus::New,
+        })
+    }
+
+    fn pin_public_key(&mut self, _: &RecipientKey) -> Result<()> {
+        Ok(())
+    }
+
+    fn share_entry(&mut self, _: &Entry, _: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn get_received_shares(&self) -> Result<Vec<ReceivedShare>> {
+        Ok(vec![])
+    }
+
+    fn accept_share(&mut self, share: &ReceivedShare) -> Result<()> {
+        let entry = share.entry.clone();
+        self.vault.add_entry(entry.title, entry.username, entry.password);
+
+        Ok(())
+    }
+
+    fn decline_share(&self, _: u64) -> Result<()> {
         Ok(())
     }
 
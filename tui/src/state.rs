@@ -1,3 +1,9 @@
+use app_core::{
+    cryptography::recovery_key::RecoveryKey,
+    password::password_restriction::PasswordRestriction,
+    uncrypted_vault::EntryConflict,
+    vault::share::{ReceivedShare, RecipientKey},
+};
 use ratatui::widgets::ListState;
 
 #[derive(Debug, Default)]
@@ -7,7 +13,28 @@ pub struct State {
     pub vault_items_state: ListState,
     pub index_entry_item: usize,
     pub vault_entries_state: ListState,
-    pub selected_entry_textarea: usize
+    pub selected_entry_textarea: usize,
+    pub generator_restriction: PasswordRestriction,
+    pub generator_preview: String,
+    pub generator_error: Option<String>,
+    pub vault_name: String,
+    pub selected_password_field: usize,
+    pub password_form_error: Option<String>,
+    pub recovery_key: Option<RecoveryKey>,
+    pub recovery_key_message: String,
+    pub shares_error: Option<String>,
+    pub login_error: Option<String>,
+    pub vault_message: Option<String>,
+    pub share_error: Option<String>,
+    pub recipient_key: Option<RecipientKey>,
+    pub sharing_fingerprint: Option<String>,
+    pub received_shares: Vec<ReceivedShare>,
+    pub index_share_item: usize,
+    pub received_shares_state: ListState,
+    pub merge_conflicts: Vec<EntryConflict>,
+    pub index_conflict_item: usize,
+    pub merge_conflicts_state: ListState,
+    pub conflict_error: Option<String>
 }
 
 impl State {
@@ -0,0 +1,12 @@
+use tui_textarea::TextArea;
+
+/// Every text input of the app, created once and kept between screens
+#[derive(Debug, Default)]
+pub struct TextAreas<'a> {
+    pub password_area: TextArea<'a>,
+    pub vault_name_area: TextArea<'a>,
+    pub entry_areas: [TextArea<'a>; 2],
+    pub password_form_areas: [TextArea<'a>; 3],
+    pub shares_area: TextArea<'a>,
+    pub recipient_area: TextArea<'a>,
+}
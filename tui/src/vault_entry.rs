@@ -1,14 +1,22 @@
-use app_core::uncrypted_vault::Entry;
+use app_core::uncrypted_vault::{DerivedEntry, Entry};
 use ratatui::{style::palette::tailwind::SLATE, text::Line, widgets::ListItem};
 
-#[derive(Debug, Default)]
-pub struct VaultEntry(pub Entry);
+#[derive(Debug)]
+pub enum VaultEntry {
+    Stored(Entry),
+    // Password recomputed on demand, never stored in the vault
+    Derived(DerivedEntry),
+}
 
-pub fn entries_to_vault_entries(entries: &[Entry]) -> Vec<VaultEntry> {
+pub fn entries_to_vault_entries(entries: &[Entry], derived_entries: &[DerivedEntry]) -> Vec<VaultEntry> {
     let mut result = Vec::new();
 
     for entry in entries {
-        result.push(VaultEntry(entry.clone()));
+        result.push(VaultEntry::Stored(entry.clone()));
+    }
+
+    for derived_entry in derived_entries {
+        result.push(VaultEntry::Derived(derived_entry.clone()));
     }
 
     result
@@ -16,12 +24,17 @@ pub fn entries_to_vault_entries(entries: &[Entry]) -> Vec<VaultEntry> {
 
 impl From<&VaultEntry> for ListItem<'_> {
     fn from(value: &VaultEntry) -> Self {
-        ListItem::new(Line::styled(
-            format!(
+        let line = match value {
+            VaultEntry::Stored(entry) => format!(
                 " 🔐 Entry: {} | username: {}",
-                value.0.title, value.0.username
+                entry.title, entry.username
             ),
-            SLATE.c200,
-        ))
+            VaultEntry::Derived(entry) => format!(
+                " 🔑 Derived: {} | login: {} | counter: {}",
+                entry.profile.site, entry.profile.login, entry.profile.counter
+            ),
+        };
+
+        ListItem::new(Line::styled(line, SLATE.c200))
     }
 }
\ No newline at end of file
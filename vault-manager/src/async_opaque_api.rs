@@ -0,0 +1,712 @@
+use opaque_ke::{
+    ClientLoginFinishResult, ClientLoginStartResult, ClientRegistrationFinishResult,
+    ClientRegistrationStartResult, CredentialResponse, RegistrationResponse,
+};
+use reqwest::{Client, Response, StatusCode, header::HeaderMap};
+
+use hkdf::Hkdf;
+use std::time::{Duration, Instant};
+use tokio::time::sleep;
+
+use crate::{
+    Result,
+    constants::*,
+    error_utils::{
+        to_exchange_failed_vault_error, to_internal_vault_error, to_request_vault_error, to_status_vault_error,
+    },
+    http_utils::{
+        add_if_match_header, add_key_stretching_header, add_nonce_header, get_key_stretching, get_opaque_headers, get_recovery_headers, get_retry_after,
+        get_revision, get_session_lifetime, get_vault_request_headers, verify_response_signature,
+    },
+    key_stretching::KeyStretching,
+    opaque_vault_manager::{SessionHash, StandardCipherSuite},
+    request_policy::RequestPolicy,
+};
+use app_core::vault::{crypted_record::CryptedRecord, share::SealedShare, vault_error::VaultError};
+
+const METHOD_NOT_ALLOWED: &'static str = "Method not allowed.";
+const NO_SESSION_AFTER_LOGIN: &'static str = "No session after loggin.";
+const EMPTY_REGISTRATION_RESPONSE: &str = "Empty registration response.";
+const MALFORMED_RECOVERY_RESPONSE: &str = "Malformed recovery response.";
+const MALFORMED_INBOX_RESPONSE: &str = "Malformed share inbox response.";
+const MALFORMED_RECORDS_RESPONSE: &str = "Malformed vault records response.";
+
+/// Same exchanges as `Api`, without blocking the caller while the server answers
+///
+/// The futures are `Send`, they can be spawned on a multi-threaded runtime.
+pub trait AsyncApi {
+    fn start_server_registration(
+        &self,
+        username: &str,
+        client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+    ) -> impl Future<Output = Result<RegistrationResponse<StandardCipherSuite>>> + Send;
+
+    fn finish_server_registration(
+        &self,
+        username: &str,
+        client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        key_stretching: &KeyStretching,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn start_server_login(
+        &self,
+        username: &str,
+        client_login_start_result: &ClientLoginStartResult<StandardCipherSuite>,
+    ) -> impl Future<Output = Result<(CredentialResponse<StandardCipherSuite>, KeyStretching)>> + Send;
+
+    fn finish_server_login(
+        &mut self,
+        username: &str,
+        client_login_finish_result: &ClientLoginFinishResult<StandardCipherSuite>,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn logout(&mut self) -> impl Future<Output = Result<()>> + Send;
+
+    fn start_password_change(
+        &self,
+        client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+    ) -> impl Future<Output = Result<RegistrationResponse<StandardCipherSuite>>> + Send;
+
+    fn finish_password_change(
+        &self,
+        client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        wrapped_key: Vec<u8>,
+        key_stretching: &KeyStretching,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+
+    fn start_recovery(
+        &self,
+        username: &str,
+        recovery_token: &[u8],
+        client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+    ) -> impl Future<Output = Result<(RegistrationResponse<StandardCipherSuite>, Vec<u8>)>> + Send;
+
+    fn finish_recovery(
+        &self,
+        username: &str,
+        recovery_token: &[u8],
+        client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        wrapped_key: Vec<u8>,
+        key_stretching: &KeyStretching,
+    ) -> impl Future<Output = Result<()>> + Send;
+
+    fn get_vault(&self) -> impl Future<Output = Result<(Vec<u8>, Option<String>)>> + Send;
+    fn save_vault(&self, vault: Vec<u8>, revision: Option<&str>) -> impl Future<Output = Result<Option<String>>> + Send;
+    fn get_records(&self) -> impl Future<Output = Result<(Vec<CryptedRecord>, Option<String>)>> + Send;
+    fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> impl Future<Output = Result<Option<String>>> + Send;
+    fn get_wrapped_key(&self) -> impl Future<Output = Result<Vec<u8>>> + Send;
+    fn save_wrapped_key(&self, wrapped_key: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+
+    fn save_public_key(&self, public_key: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+    fn get_public_key(&self, username: &str) -> impl Future<Output = Result<Vec<u8>>> + Send;
+    fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> impl Future<Output = Result<()>> + Send;
+    fn get_shares(&self) -> impl Future<Output = Result<Vec<SealedShare>>> + Send;
+    fn delete_share(&self, id: u64) -> impl Future<Output = Result<()>> + Send;
+
+    fn is_logged_in(&self) -> bool;
+    fn get_server_identifier(&self) -> &str;
+}
+
+#[derive(Debug, Default)]
+struct Session {
+    session_key: Vec<u8>,
+    session_token: String,
+    // Reported by the server at the login, the session never expires without it
+    expires_at: Option<Instant>,
+}
+
+impl Session {
+    pub fn new(session_key: Vec<u8>, session_token: String, expires_at: Option<Instant>) -> Self {
+        Self {
+            session_key,
+            session_token,
+            expires_at,
+        }
+    }
+
+    fn is_expired(&self) -> bool {
+        self.expires_at.is_some_and(|expires_at| Instant::now() >= expires_at)
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct AsyncOpaqueApi {
+    client: Client,
+    server_url: String,
+    session: Option<Session>,
+    request_policy: RequestPolicy,
+}
+
+impl AsyncOpaqueApi {
+    pub fn new(server_url: String) -> Result<Self> {
+        Self::with_request_policy(server_url, RequestPolicy::default())
+    }
+
+    /// Fails if the HTTP client can't be built, rather than sending requests without the timeouts
+    pub fn with_request_policy(server_url: String, request_policy: RequestPolicy) -> Result<Self> {
+        let client = Client::builder()
+            .connect_timeout(request_policy.connect_timeout)
+            .read_timeout(request_policy.read_timeout)
+            .timeout(request_policy.timeout)
+            .build()
+            .map_err(to_internal_vault_error)?;
+
+        Ok(Self {
+            client,
+            server_url,
+            session: None,
+            request_policy,
+        })
+    }
+
+    async fn web_server_request(
+        &self,
+        uri: String,
+        verb: &'static str,
+        body: Option<Vec<u8>>,
+        headers: HeaderMap,
+    ) -> Result<Response> {
+        let response = self.send_request(uri, verb, body, headers, None).await?;
+
+        if let Err(status_error) = response.error_for_status_ref() {
+            let retry_after = get_retry_after(response.headers());
+            let body = response.text().await.unwrap_or_default();
+
+            return Err(to_status_vault_error(status_error, retry_after, &body, false));
+        }
+
+        Ok(response)
+    }
+
+    /// The status isn't checked, an authenticated error is only read once its signature is verified
+    async fn send_request(
+        &self,
+        uri: String,
+        verb: &'static str,
+        body: Option<Vec<u8>>,
+        headers: HeaderMap,
+        bearer_token: Option<&str>,
+    ) -> Result<Response> {
+        let mut client = match verb {
+            GET => self.client.get(uri),
+            POST => self.client.post(uri),
+            _ => return Err(VaultError::Internal(METHOD_NOT_ALLOWED.to_string())),
+        };
+
+        if let Some(body) = body {
+            client = client.body(body);
+        };
+
+        if let Some(bearer_token) = bearer_token {
+            client = client.bearer_auth(bearer_token);
+        }
+
+        client
+            .headers(headers)
+            .send()
+            .await
+            .map_err(to_request_vault_error)
+    }
+
+    fn create_session(&mut self, session_key: &[u8], lifetime: Option<u64>) -> Result<()> {
+        let hkdf = Hkdf::<SessionHash>::from_prk(session_key)
+            .map_err(|error| VaultError::Internal(error.to_string()))?;
+
+        let mut token = vec![0u8; 64];
+
+        hkdf.expand(b"opaque-session-token", &mut token)
+            .map_err(|error| VaultError::Internal(error.to_string()))?;
+
+        let expires_at = lifetime.map(|lifetime| Instant::now() + Duration::from_secs(lifetime));
+
+        self.session = Some(Session::new(session_key.to_owned(), hex::encode(token), expires_at));
+
+        Ok(())
+    }
+
+    async fn vault_request(
+        &self,
+        verb: &'static str,
+        path: &'static str,
+        content: Option<Vec<u8>>,
+    ) -> Result<Option<Vec<u8>>> {
+        self.vault_request_with_headers(verb, path, content, HeaderMap::new()).await
+    }
+
+    async fn vault_request_with_headers(
+        &self,
+        verb: &'static str,
+        path: &'static str,
+        content: Option<Vec<u8>>,
+        extra_headers: HeaderMap,
+    ) -> Result<Option<Vec<u8>>> {
+        let (body, _) = self.retried_request(verb, path, content, extra_headers).await?;
+
+        Ok(Some(body))
+    }
+
+    /// Sent with `If-Match` when the revision is known, the server answers the new one as `ETag`
+    async fn revision_request(
+        &self,
+        verb: &'static str,
+        path: &'static str,
+        content: Option<Vec<u8>>,
+        revision: Option<&str>,
+    ) -> Result<(Vec<u8>, Option<String>)> {
+        let mut headers = HeaderMap::new();
+
+        if let Some(revision) = revision {
+            add_if_match_header(&mut headers, revision)?;
+        }
+
+        let (body, response_headers) = self.retried_request(verb, path, content, headers).await?;
+
+        Ok((body, get_revision(&response_headers)))
+    }
+
+    /// Signed again for every attempt, the server never sees the same nonce twice
+    async fn retried_request(
+        &self,
+        verb: &'static str,
+        path: &'static str,
+        content: Option<Vec<u8>>,
+        extra_headers: HeaderMap,
+    ) -> Result<(Vec<u8>, HeaderMap)> {
+        let mut attempt = 0;
+
+        loop {
+            match self.signed_request(verb, path, content.clone(), extra_headers.clone()).await {
+                // Only the idempotent requests are sent again
+                Err(error) if verb == GET => match self.request_policy.retry_delay(&error, attempt) {
+                    Some(delay) => {
+                        sleep(delay).await;
+                        attempt += 1;
+                    }
+                    None => return Err(error),
+                },
+                result => return result,
+            }
+        }
+    }
+
+    async fn signed_request(
+        &self,
+        verb: &'static str,
+        path: &'static str,
+        content: Option<Vec<u8>>,
+        extra_headers: HeaderMap,
+    ) -> Result<(Vec<u8>, HeaderMap)> {
+        let uri = format!("{}{}", &self.server_url, path);
+
+        let Some(session) = self.session.as_ref() else {
+            return Err(VaultError::NotLoggedIn(NO_SESSION_AFTER_LOGIN.to_string()));
+        };
+
+        // The server would refuse it anyway, nothing signed with an expired session is sent
+        if session.is_expired() {
+            return Err(VaultError::SessionExpired);
+        }
+
+        let (mut headers, nonce) = get_vault_request_headers(
+            &session.session_key,
+            verb,
+            &self.server_url,
+            path,
+            content.as_deref().unwrap_or_default(),
+        )?;
+
+        headers.extend(extra_headers);
+
+        let vault_response =
+            self.send_request(uri, verb, content, headers, Some(&session.session_token)).await?;
+
+        let status = vault_response.status();
+        let status_error = vault_response.error_for_status_ref().err();
+        let retry_after = get_retry_after(vault_response.headers());
+        let response_headers = vault_response.headers().clone();
+
+        // A body that stalls past the timeout is as unavailable as a server that doesn't answer
+        let vault_reponse_bytes = vault_response.bytes().await.map_err(to_request_vault_error)?;
+
+        let signature = verify_response_signature(&session.session_key, &nonce, status, &vault_reponse_bytes, &response_headers);
+
+        match (status_error, signature) {
+            (None, signature) => signature.map(|_| (vault_reponse_bytes.to_vec(), response_headers)),
+            (Some(status_error), Ok(())) => Err(to_status_vault_error(
+                status_error,
+                retry_after,
+                &String::from_utf8_lossy(&vault_reponse_bytes),
+                true,
+            )),
+            // A proxy in front of the server can't sign, an unsigned answer is only trusted to say the server is unavailable
+            (Some(status_error), Err(_)) if status.is_server_error() || status == StatusCode::TOO_MANY_REQUESTS => {
+                Err(to_status_vault_error(status_error, retry_after, "", true))
+            }
+            (Some(_), Err(error)) => Err(error),
+        }
+    }
+}
+
+impl AsyncApi for AsyncOpaqueApi {
+    async fn start_server_registration(
+        &self,
+        username: &str,
+        client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+    ) -> Result<RegistrationResponse<StandardCipherSuite>> {
+        let registration_response = self.web_server_request(
+            format!("{}{}", &self.server_url, OPAQUE_REGISTRATION_START),
+            POST,
+            Some(
+                client_registration_start_result
+                    .message
+                    .serialize()
+                    .to_vec(),
+            ),
+            get_opaque_headers(username)?,
+        ).await?;
+
+        let registration_response_bytes = registration_response
+            .bytes()
+            .await
+            .map_err(to_request_vault_error)?;
+
+        RegistrationResponse::deserialize(&registration_response_bytes).map_err(to_exchange_failed_vault_error)
+    }
+
+    async fn finish_server_registration(
+        &self,
+        username: &str,
+        client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        key_stretching: &KeyStretching,
+    ) -> Result<()> {
+        let mut headers = get_opaque_headers(username)?;
+        add_key_stretching_header(&mut headers, key_stretching)?;
+
+        let _ = self.web_server_request(
+            format!("{}{}", &self.server_url, OPAQUE_REGISTRATION_FINISH),
+            POST,
+            Some(
+                client_registration_finish_result
+                    .message
+                    .serialize()
+                    .to_vec(),
+            ),
+            headers,
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn start_server_login(
+        &self,
+        username: &str,
+        client_login_start_result: &ClientLoginStartResult<StandardCipherSuite>,
+    ) -> Result<(CredentialResponse<StandardCipherSuite>, KeyStretching)> {
+        let login_response = self.web_server_request(
+            format!("{}{}", &self.server_url, OPAQUE_LOGIN_START),
+            POST,
+            Some(client_login_start_result.message.serialize().to_vec()),
+            get_opaque_headers(username)?,
+        ).await?;
+
+        let key_stretching = get_key_stretching(login_response.headers())?;
+
+        let login_response_bytes = login_response.bytes().await.map_err(to_request_vault_error)?;
+
+        let credential_response = CredentialResponse::deserialize(&login_response_bytes)
+            .map_err(to_exchange_failed_vault_error)?;
+
+        Ok((credential_response, key_stretching))
+    }
+
+    async fn finish_server_login(
+        &mut self,
+        username: &str,
+        client_login_finish_result: &ClientLoginFinishResult<StandardCipherSuite>,
+    ) -> Result<()> {
+        let mut headers = get_opaque_headers(username)?;
+        let nonce = add_nonce_header(&mut headers)?;
+
+        let login_response = self.web_server_request(
+            format!("{}{}", &self.server_url, OPAQUE_LOGIN_FINISH),
+            POST,
+            Some(client_login_finish_result.message.serialize().to_vec()),
+            headers,
+        ).await?;
+
+        let status = login_response.status();
+        let response_headers = login_response.headers().clone();
+        let lifetime = get_session_lifetime(&response_headers);
+
+        // The session ends at the lifetime the server gave, a lifetime nobody signed could be forged to end it early
+        if lifetime.is_some() {
+            let login_response_bytes = login_response.bytes().await.map_err(to_request_vault_error)?;
+
+            verify_response_signature(
+                &client_login_finish_result.session_key,
+                &nonce,
+                status,
+                &login_response_bytes,
+                &response_headers,
+            )?;
+        }
+
+        self.create_session(&client_login_finish_result.session_key, lifetime)?;
+
+        Ok(())
+    }
+
+    /// The session is dropped even if the server can't be reached, an expired one isn't sent
+    async fn logout(&mut self) -> Result<()> {
+        let result = match &self.session {
+            Some(session) if !session.is_expired() => self.vault_request(POST, OPAQUE_LOGOUT, None).await.map(|_| ()),
+            _ => Ok(()),
+        };
+
+        self.session = None;
+
+        match result {
+            // Already invalidated on the server
+            Err(VaultError::SessionExpired) => Ok(()),
+            result => result,
+        }
+    }
+
+    async fn start_password_change(
+        &self,
+        client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+    ) -> Result<RegistrationResponse<StandardCipherSuite>> {
+        let Some(registration_response_bytes) = self.vault_request(
+            POST,
+            OPAQUE_PASSWORD_CHANGE_START,
+            Some(
+                client_registration_start_result
+                    .message
+                    .serialize()
+                    .to_vec(),
+            ),
+        ).await?
+        else {
+            return Err(VaultError::ExchangeFailed(EMPTY_REGISTRATION_RESPONSE.to_string()));
+        };
+
+        RegistrationResponse::deserialize(&registration_response_bytes)
+            .map_err(to_exchange_failed_vault_error)
+    }
+
+    async fn finish_password_change(
+        &self,
+        client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        wrapped_key: Vec<u8>,
+        key_stretching: &KeyStretching,
+    ) -> Result<()> {
+        let body = frame(&client_registration_finish_result.message.serialize(), &wrapped_key);
+
+        let mut headers = HeaderMap::new();
+        add_key_stretching_header(&mut headers, key_stretching)?;
+
+        let _ = self.vault_request_with_headers(POST, OPAQUE_PASSWORD_CHANGE_FINISH, Some(body), headers).await?;
+
+        Ok(())
+    }
+
+    async fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> Result<()> {
+        let _ = self.vault_request(POST, VAULT_RECOVERY, Some(frame(&recovery_token, &wrapped_key))).await?;
+
+        Ok(())
+    }
+
+    async fn start_recovery(
+        &self,
+        username: &str,
+        recovery_token: &[u8],
+        client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+    ) -> Result<(RegistrationResponse<StandardCipherSuite>, Vec<u8>)> {
+        let recovery_response = self.web_server_request(
+            format!("{}{}", &self.server_url, OPAQUE_RECOVERY_START),
+            POST,
+            Some(
+                client_registration_start_result
+                    .message
+                    .serialize()
+                    .to_vec(),
+            ),
+            get_recovery_headers(username, recovery_token)?,
+        ).await?;
+
+        let recovery_response_bytes = recovery_response.bytes().await.map_err(to_request_vault_error)?;
+
+        let Some((registration_response_bytes, wrapped_key)) = unframe(&recovery_response_bytes) else {
+            return Err(VaultError::ExchangeFailed(MALFORMED_RECOVERY_RESPONSE.to_string()));
+        };
+
+        let registration_response = RegistrationResponse::deserialize(registration_response_bytes)
+            .map_err(to_exchange_failed_vault_error)?;
+
+        Ok((registration_response, wrapped_key.to_vec()))
+    }
+
+    async fn finish_recovery(
+        &self,
+        username: &str,
+        recovery_token: &[u8],
+        client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        wrapped_key: Vec<u8>,
+        key_stretching: &KeyStretching,
+    ) -> Result<()> {
+        let mut headers = get_recovery_headers(username, recovery_token)?;
+        add_key_stretching_header(&mut headers, key_stretching)?;
+
+        let _ = self.web_server_request(
+            format!("{}{}", &self.server_url, OPAQUE_RECOVERY_FINISH),
+            POST,
+            Some(frame(&client_registration_finish_result.message.serialize(), &wrapped_key)),
+            headers,
+        ).await?;
+
+        Ok(())
+    }
+
+    async fn get_vault(&self) -> Result<(Vec<u8>, Option<String>)> {
+        self.revision_request(GET, VAULT, None, None).await
+    }
+
+    async fn save_vault(&self, vault: Vec<u8>, revision: Option<&str>) -> Result<Option<String>> {
+        let (_, revision) = self.revision_request(POST, VAULT, Some(vault), revision).await?;
+
+        Ok(revision)
+    }
+
+    async fn get_records(&self) -> Result<(Vec<CryptedRecord>, Option<String>)> {
+        let (records, revision) = self.revision_request(GET, VAULT_RECORDS, None, None).await?;
+
+        let records = decode_records(&records)
+            .ok_or_else(|| VaultError::ExchangeFailed(MALFORMED_RECORDS_RESPONSE.to_string()))?;
+
+        Ok((records, revision))
+    }
+
+    async fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> Result<Option<String>> {
+        let (_, revision) = self.revision_request(POST, VAULT_RECORDS, Some(encode_records(&records)), revision).await?;
+
+        Ok(revision)
+    }
+
+    async fn get_wrapped_key(&self) -> Result<Vec<u8>> {
+        let Some(wrapped_key) = self.vault_request(GET, VAULT_KEY, None).await? else {
+            return Err(VaultError::NotFound);
+        };
+
+        Ok(wrapped_key)
+    }
+
+    async fn save_wrapped_key(&self, wrapped_key: Vec<u8>) -> Result<()> {
+        let _ = self.vault_request(POST, VAULT_KEY, Some(wrapped_key)).await?;
+
+        Ok(())
+    }
+
+    async fn save_public_key(&self, public_key: Vec<u8>) -> Result<()> {
+        let _ = self.vault_request(POST, SHARING_KEY, Some(public_key)).await?;
+
+        Ok(())
+    }
+
+    async fn get_public_key(&self, username: &str) -> Result<Vec<u8>> {
+        match self.vault_request(POST, SHARING_KEY_LOOKUP, Some(username.as_bytes().to_vec())).await? {
+            Some(public_key) if !public_key.is_empty() => Ok(public_key),
+            _ => Err(VaultError::NotFound),
+        }
+    }
+
+    async fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> Result<()> {
+        let _ = self.vault_request(POST, SHARING_INBOX, Some(frame(recipient.as_bytes(), &sealed_entry))).await?;
+
+        Ok(())
+    }
+
+    async fn get_shares(&self) -> Result<Vec<SealedShare>> {
+        let Some(inbox) = self.vault_request(GET, SHARING_INBOX, None).await? else {
+            return Ok(vec![]);
+        };
+
+        decode_shares(&inbox).ok_or_else(|| VaultError::ExchangeFailed(MALFORMED_INBOX_RESPONSE.to_string()))
+    }
+
+    async fn delete_share(&self, id: u64) -> Result<()> {
+        let _ = self.vault_request(POST, SHARING_INBOX_DELETE, Some(id.to_be_bytes().to_vec())).await?;
+
+        Ok(())
+    }
+
+    fn is_logged_in(&self) -> bool {
+        self.session.is_some()
+    }
+
+    fn get_server_identifier(&self) -> &str {
+        &self.server_url
+    }
+}
+
+/// `head length (4, big endian) | head | tail`
+fn frame(head: &[u8], tail: &[u8]) -> Vec<u8> {
+    let mut body = Vec::with_capacity(4 + head.len() + tail.len());
+    body.extend_from_slice(&(head.len() as u32).to_be_bytes());
+    body.extend_from_slice(head);
+    body.extend_from_slice(tail);
+
+    body
+}
+
+fn unframe(body: &[u8]) -> Option<(&[u8], &[u8])> {
+    let (head_length, rest) = body.split_first_chunk::<4>()?;
+    let head_length = u32::from_be_bytes(*head_length) as usize;
+
+    (head_length <= rest.len()).then(|| rest.split_at(head_length))
+}
+
+/// Repeated `share length (4, big endian) | id (8, big endian) | frame(sender, sealed entry)`
+fn decode_shares(mut body: &[u8]) -> Option<Vec<SealedShare>> {
+    let mut shares = vec![];
+
+    while !body.is_empty() {
+        let (share, rest) = unframe(body)?;
+        let (id, share) = share.split_first_chunk::<8>()?;
+        let (sender, sealed_entry) = unframe(share)?;
+
+        shares.push(SealedShare {
+            id: u64::from_be_bytes(*id),
+            sender: String::from_utf8(sender.to_vec()).ok()?,
+            sealed_entry: sealed_entry.to_vec(),
+        });
+
+        body = rest;
+    }
+
+    Some(shares)
+}
+
+/// Repeated `record length (4, big endian) | frame(id, content)`, in both directions
+fn encode_records(records: &[CryptedRecord]) -> Vec<u8> {
+    records
+        .iter()
+        .flat_map(|record| frame(&frame(record.id.as_bytes(), &record.content), &[]))
+        .collect()
+}
+
+fn decode_records(mut body: &[u8]) -> Option<Vec<CryptedRecord>> {
+    let mut records = vec![];
+
+    while !body.is_empty() {
+        let (record, rest) = unframe(body)?;
+        let (id, content) = unframe(record)?;
+
+        records.push(CryptedRecord::new(String::from_utf8(id.to_vec()).ok()?, content.to_vec()));
+
+        body = rest;
+    }
+
+    Some(records)
+}
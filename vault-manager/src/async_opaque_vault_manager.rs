@@ -0,0 +1,470 @@
+use app_core::{
+    ports::vault_manager::{AsyncVaultManager, SyncRewrapDataKey},
+    vault::{crypted_record::CryptedRecord, crypted_vault::CryptedVault, key::Key, share::SealedShare, vault_error::*},
+};
+
+use crate::{
+    Result,
+    async_opaque_api::AsyncApi,
+    error_utils::to_exchange_failed_vault_error,
+    key_stretching::KeyStretching,
+    opaque_vault_manager::{ExportKeySize, StandardCipherSuite},
+};
+
+use opaque_ke::{
+    ClientLogin, ClientLoginFinishParameters, ClientLoginFinishResult, ClientRegistration,
+    ClientRegistrationFinishParameters, ClientRegistrationFinishResult, Identifiers, errors::ProtocolError,
+    rand::rngs::OsRng,
+};
+
+// Error constants
+const CANNOT_SAVE_VAULT_IF_NOT_LOGGED_IN: &'static str =
+    "Cannot create a new vault if you are already logged in.";
+const CANNOT_CREATE_VAULT_IF_LOGGED_IN: &'static str =
+    "Cannot save a vault if you are not logged in.";
+const CANNOT_CHANGE_PASSWORD_IF_NOT_LOGGED_IN: &str =
+    "Cannot change the password if you are not logged in.";
+const CANNOT_SAVE_RECOVERY_KEY_IF_NOT_LOGGED_IN: &str =
+    "Cannot save a recovery key if you are not logged in.";
+const CANNOT_RECOVER_IF_LOGGED_IN: &str = "Cannot recover a vault if you are already logged in.";
+const CANNOT_FETCH_VAULT_IF_NOT_LOGGED_IN: &str = "Cannot fetch the vault if you are not logged in.";
+const CANNOT_SHARE_IF_NOT_LOGGED_IN: &str = "Cannot use the share inbox if you are not logged in.";
+
+#[derive(Debug, Default)]
+pub struct AsyncOpaqueVaultManager<T: AsyncApi> {
+    api: T,
+    // Static public key of the server, pinned by the registration or the first login
+    server_key: Option<Vec<u8>>,
+    // Used by the registrations, an account registered with weaker parameters is upgraded
+    key_stretching: KeyStretching,
+    // Recorded for the account by the server, known once logged in
+    account_key_stretching: Option<KeyStretching>,
+    // The last login only succeeded with the default identifiers, the account was registered before they were bound
+    legacy_identifiers: bool,
+    // The account already logged in with its identifiers, the default ones are refused
+    bound_identifiers_required: bool,
+}
+
+impl<T: AsyncApi> AsyncOpaqueVaultManager<T> {
+    pub fn new(api: T) -> Self {
+        Self::with_key_stretching(api, KeyStretching::default())
+    }
+
+    pub fn with_key_stretching(api: T, key_stretching: KeyStretching) -> Self {
+        Self {
+            api,
+            server_key: None,
+            key_stretching,
+            account_key_stretching: None,
+            legacy_identifiers: false,
+            bound_identifiers_required: false,
+        }
+    }
+
+    fn identifiers<'a>(&'a self, username: &'a str) -> Identifiers<'a> {
+        Identifiers {
+            client: Some(username.as_bytes()),
+            server: Some(self.api.get_server_identifier().as_bytes()),
+        }
+    }
+
+    /// Pins the first key seen, then refuses any other one
+    fn check_server_key(&mut self, server_key: &[u8]) -> Result<()> {
+        match &self.server_key {
+            Some(pinned_server_key) if pinned_server_key != server_key => Err(VaultError::ServerKeyChanged),
+            Some(_) => Ok(()),
+            None => {
+                self.server_key = Some(server_key.to_vec());
+                Ok(())
+            }
+        }
+    }
+
+    async fn register(
+        &mut self,
+        username: &str,
+        password: &str,
+    ) -> Result<ClientRegistrationFinishResult<StandardCipherSuite>> {
+        let mut client_rng = OsRng;
+
+        let client_registration_start_result =
+            ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
+                .map_err(to_exchange_failed_vault_error)?;
+
+        let server_registration_response = self
+            .api
+            .start_server_registration(username, &client_registration_start_result)
+            .await?;
+
+        let ksf = self.key_stretching.to_ksf()?;
+
+        let client_registration_finish_result = client_registration_start_result
+            .state
+            .finish(
+                &mut client_rng,
+                password.as_bytes(),
+                server_registration_response,
+                ClientRegistrationFinishParameters::new(self.identifiers(username), Some(&ksf)),
+            )
+            .map_err(to_exchange_failed_vault_error)?;
+
+        self.check_server_key(&client_registration_finish_result.server_s_pk.serialize())?;
+
+        self.api
+            .finish_server_registration(username, &client_registration_finish_result, &self.key_stretching)
+            .await?;
+
+        Ok(client_registration_finish_result)
+    }
+
+    async fn login(
+        &mut self,
+        username: &str,
+        password: &str,
+    ) -> Result<ClientLoginFinishResult<StandardCipherSuite>> {
+        let mut client_rng = OsRng;
+
+        let client_login_start_result =
+            ClientLogin::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
+                .map_err(to_exchange_failed_vault_error)?;
+
+        let (server_login_response, key_stretching) = self
+            .api
+            .start_server_login(username, &client_login_start_result)
+            .await?;
+
+        // Refuses parameters that would exhaust the client, wrong ones only fail the login
+        let ksf = key_stretching.to_ksf()?;
+
+        let (client_login_finish_result, legacy_identifiers) = match client_login_start_result.state.clone().finish(
+            &mut client_rng,
+            password.as_bytes(),
+            server_login_response.clone(),
+            ClientLoginFinishParameters::new(None, self.identifiers(username), Some(&ksf)),
+        ) {
+            // Accounts registered before the identifiers were bound still use the default ones, until they logged in with them
+            Err(ProtocolError::InvalidLoginError) if !self.bound_identifiers_required => client_login_start_result
+                .state
+                .finish(
+                    &mut client_rng,
+                    password.as_bytes(),
+                    server_login_response,
+                    ClientLoginFinishParameters::new(None, Identifiers::default(), Some(&ksf)),
+                )
+                .map(|client_login_finish_result| (client_login_finish_result, true)),
+            result => result.map(|client_login_finish_result| (client_login_finish_result, false)),
+        }
+        .map_err(to_exchange_failed_vault_error)?;
+
+        // Checked before the login is finished, an impostor server never gets the key confirmation
+        self.check_server_key(&client_login_finish_result.server_s_pk.serialize())?;
+
+        self.api
+            .finish_server_login(username, &client_login_finish_result)
+            .await?;
+
+        self.account_key_stretching = Some(key_stretching);
+        self.legacy_identifiers = legacy_identifiers;
+
+        Ok(client_login_finish_result)
+    }
+
+    /// Generic over the closure, the blocking wrapper passes one that isn't `Sync`
+    pub(crate) async fn change_password_with<F: Fn(&[u8]) -> Result<Vec<u8>> + ?Sized>(
+        &mut self,
+        username: &str,
+        old_password: &str,
+        new_password: &str,
+        wrap_data_key: &F,
+    ) -> Result<()> {
+        if !self.api.is_logged_in() {
+            return Err(VaultError::NotLoggedIn(
+                CANNOT_CHANGE_PASSWORD_IF_NOT_LOGGED_IN.to_string(),
+            ));
+        }
+
+        // Proves the old password again, an open session alone isn't enough to replace it
+        let _ = self.login(username, old_password).await?;
+
+        let mut client_rng = OsRng;
+
+        let client_registration_start_result =
+            ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, new_password.as_bytes())
+                .map_err(to_exchange_failed_vault_error)?;
+
+        let server_registration_response = self
+            .api
+            .start_password_change(&client_registration_start_result)
+            .await?;
+
+        let ksf = self.key_stretching.to_ksf()?;
+
+        let client_registration_finish_result = client_registration_start_result
+            .state
+            .finish(
+                &mut client_rng,
+                new_password.as_bytes(),
+                server_registration_response,
+                ClientRegistrationFinishParameters::new(self.identifiers(username), Some(&ksf)),
+            )
+            .map_err(to_exchange_failed_vault_error)?;
+
+        self.check_server_key(&client_registration_finish_result.server_s_pk.serialize())?;
+
+        let wrapped_key = wrap_data_key(&client_registration_finish_result.export_key)?;
+
+        self.api
+            .finish_password_change(&client_registration_finish_result, wrapped_key, &self.key_stretching)
+            .await?;
+
+        self.account_key_stretching = Some(self.key_stretching);
+        self.legacy_identifiers = false;
+
+        Ok(())
+    }
+
+    pub(crate) async fn recover_with<F: Fn(&[u8], &[u8]) -> Result<Vec<u8>> + ?Sized>(
+        &mut self,
+        username: &str,
+        recovery_token: &[u8],
+        new_password: &str,
+        rewrap_data_key: &F,
+    ) -> Result<()> {
+        if self.api.is_logged_in() {
+            return Err(VaultError::AlreadyLoggedIn(
+                CANNOT_RECOVER_IF_LOGGED_IN.to_string(),
+            ));
+        }
+
+        let mut client_rng = OsRng;
+
+        let client_registration_start_result =
+            ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, new_password.as_bytes())
+                .map_err(to_exchange_failed_vault_error)?;
+
+        let (server_registration_response, recovery_wrapped_key) = self.api.start_recovery(
+            username,
+            recovery_token,
+            &client_registration_start_result,
+        ).await?;
+
+        let ksf = self.key_stretching.to_ksf()?;
+
+        let client_registration_finish_result = client_registration_start_result
+            .state
+            .finish(
+                &mut client_rng,
+                new_password.as_bytes(),
+                server_registration_response,
+                ClientRegistrationFinishParameters::new(self.identifiers(username), Some(&ksf)),
+            )
+            .map_err(to_exchange_failed_vault_error)?;
+
+        // The recovery replaces the registration, an impostor server must not receive it
+        self.check_server_key(&client_registration_finish_result.server_s_pk.serialize())?;
+
+        let wrapped_key = rewrap_data_key(
+            &recovery_wrapped_key,
+            &client_registration_finish_result.export_key,
+        )?;
+
+        self.api.finish_recovery(
+            username,
+            recovery_token,
+            &client_registration_finish_result,
+            wrapped_key,
+            &self.key_stretching,
+        ).await
+    }
+
+    async fn get_wrapped_key(&self) -> Result<Vec<u8>> {
+        self.api.get_wrapped_key().await
+    }
+
+    fn check_logged_in_for_sharing(&self) -> Result<()> {
+        if !self.api.is_logged_in() {
+            return Err(VaultError::NotLoggedIn(
+                CANNOT_SHARE_IF_NOT_LOGGED_IN.to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+/// `Sync` api, the futures of the port hold a reference to it
+impl<T: AsyncApi + Send + Sync> AsyncVaultManager for AsyncOpaqueVaultManager<T> {
+    type KeySize = ExportKeySize<StandardCipherSuite>;
+
+    async fn create(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>> {
+        // For now there is no feature to handle multiple vault at the same time
+        if self.api.is_logged_in() {
+            return Err(VaultError::AlreadyLoggedIn(
+                CANNOT_CREATE_VAULT_IF_LOGGED_IN.to_string(),
+            ));
+        }
+
+        let _ = self.register(username, password).await?;
+        let client_login_finish_result = self.login(username, password).await?;
+
+        let export_key = Key::new(client_login_finish_result.export_key);
+        let server_key = client_login_finish_result.server_s_pk.serialize().to_vec();
+
+        let (vault_content, revision) = self.api.get_vault().await?;
+
+        // A new account has no data key nor records yet, the core generates them
+        Ok(CryptedVault::new(vault_content, vec![], vec![], server_key, export_key, revision, vec![]))
+    }
+
+    async fn retrieve(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>> {
+        let client_login_finish_result = self.login(username, password).await?;
+
+        let (vault_content, vault_revision) = self.api.get_vault().await?;
+        let (records, records_revision) = self.api.get_records().await?;
+        let wrapped_key = self.get_wrapped_key().await?;
+
+        let export_key = Key::new(client_login_finish_result.export_key);
+        let server_key = client_login_finish_result.server_s_pk.serialize().to_vec();
+
+        // The blob and the records share the revision of the vault, the records are read last
+        let revision = records_revision.or(vault_revision);
+
+        Ok(CryptedVault::new(vault_content, records, wrapped_key, server_key, export_key, revision, vec![]))
+    }
+
+    /// Same OPAQUE login as `retrieve` without fetching the vault, also after an offline unlock,
+    /// the pinned server key is checked again
+    async fn reauthenticate(&mut self, username: &str, password: &str) -> Result<()> {
+        let _ = self.login(username, password).await?;
+
+        Ok(())
+    }
+
+    async fn logout(&mut self) -> Result<()> {
+        if !self.api.is_logged_in() {
+            return Ok(());
+        }
+
+        self.api.logout().await
+    }
+
+    fn pin_server_key(&mut self, server_key: Option<Vec<u8>>) {
+        self.server_key = server_key;
+    }
+
+    fn require_bound_identifiers(&mut self, required: bool) {
+        self.bound_identifiers_required = required;
+    }
+
+    fn identifiers_bound(&self) -> bool {
+        self.api.is_logged_in() && !self.legacy_identifiers
+    }
+
+    /// A legacy account is registered again with the identifiers bound, like an account with weaker parameters
+    fn needs_reregistration(&self) -> bool {
+        self.legacy_identifiers
+            || self
+                .account_key_stretching
+                .is_some_and(|account_key_stretching| account_key_stretching.is_weaker_than(&self.key_stretching))
+    }
+
+    async fn save(&self, vault: Vec<u8>, revision: Option<&str>) -> Result<Option<String>> {
+        if !self.api.is_logged_in() {
+            return Err(VaultError::NotLoggedIn(
+                CANNOT_SAVE_VAULT_IF_NOT_LOGGED_IN.to_string(),
+            ));
+        }
+
+        self.api.save_vault(vault, revision).await
+    }
+
+    async fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> Result<Option<String>> {
+        if !self.api.is_logged_in() {
+            return Err(VaultError::NotLoggedIn(
+                CANNOT_SAVE_VAULT_IF_NOT_LOGGED_IN.to_string(),
+            ));
+        }
+
+        self.api.save_records(records, revision).await
+    }
+
+    async fn fetch_records(&self) -> Result<(Vec<CryptedRecord>, Option<String>)> {
+        if !self.api.is_logged_in() {
+            return Err(VaultError::NotLoggedIn(
+                CANNOT_FETCH_VAULT_IF_NOT_LOGGED_IN.to_string(),
+            ));
+        }
+
+        self.api.get_records().await
+    }
+
+    async fn save_wrapped_key(&self, wrapped_key: Vec<u8>) -> Result<()> {
+        if !self.api.is_logged_in() {
+            return Err(VaultError::NotLoggedIn(
+                CANNOT_SAVE_VAULT_IF_NOT_LOGGED_IN.to_string(),
+            ));
+        }
+
+        self.api.save_wrapped_key(wrapped_key).await
+    }
+
+    async fn change_password(
+        &mut self,
+        username: &str,
+        old_password: &str,
+        new_password: &str,
+        wrap_data_key: &(dyn Fn(&[u8]) -> Result<Vec<u8>> + Sync),
+    ) -> Result<()> {
+        self.change_password_with(username, old_password, new_password, wrap_data_key).await
+    }
+
+    async fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> Result<()> {
+        if !self.api.is_logged_in() {
+            return Err(VaultError::NotLoggedIn(
+                CANNOT_SAVE_RECOVERY_KEY_IF_NOT_LOGGED_IN.to_string(),
+            ));
+        }
+
+        self.api.save_recovery_key(recovery_token, wrapped_key).await
+    }
+
+    async fn recover(
+        &mut self,
+        username: &str,
+        recovery_token: &[u8],
+        new_password: &str,
+        rewrap_data_key: &SyncRewrapDataKey<'_>,
+    ) -> Result<()> {
+        self.recover_with(username, recovery_token, new_password, rewrap_data_key).await
+    }
+
+    async fn save_public_key(&self, public_key: Vec<u8>) -> Result<()> {
+        self.check_logged_in_for_sharing()?;
+
+        self.api.save_public_key(public_key).await
+    }
+
+    async fn get_public_key(&self, username: &str) -> Result<Vec<u8>> {
+        self.check_logged_in_for_sharing()?;
+
+        self.api.get_public_key(username).await
+    }
+
+    async fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> Result<()> {
+        self.check_logged_in_for_sharing()?;
+
+        self.api.send_share(recipient, sealed_entry).await
+    }
+
+    async fn get_shares(&self) -> Result<Vec<SealedShare>> {
+        self.check_logged_in_for_sharing()?;
+
+        self.api.get_shares().await
+    }
+
+    async fn delete_share(&self, id: u64) -> Result<()> {
+        self.check_logged_in_for_sharing()?;
+
+        self.api.delete_share(id).await
+    }
+}
@@ -0,0 +1,367 @@
+use std::{
+    cell::RefCell,
+    marker::PhantomData,
+    path::{Path, PathBuf},
+};
+
+use app_core::{
+    ports::{
+        cryptography::NoKeyCipher,
+        vault_manager::{RewrapDataKey, VaultManager},
+    },
+    vault::{
+        crypted_record::CryptedRecord, crypted_vault::CryptedVault, key::Key, share::SealedShare,
+        vault_error::VaultError,
+    },
+};
+use crypto_common::generic_array::GenericArray;
+use zeroize::Zeroizing;
+
+use crate::{Result, key_stretching::KeyStretching, offline_cache::OfflineCache};
+
+const CACHE_EXTENSION: &str = "cache";
+
+const MALFORMED_ENCRYPTION_KEY: &str = "Malformed encryption key in the offline cache.";
+
+/// Keeps the last vault read from the server in an encrypted file, to unlock it and save without the server
+///
+/// Saves made offline are kept in the file and uploaded by the next save once the server is reachable.
+/// Without a cache directory every call goes straight to the inner manager.
+/// The encryption key in the cache is sealed with the cipher `NKC` of the vault.
+#[derive(Debug, Default)]
+pub struct CachedVaultManager<VM: VaultManager, NKC: NoKeyCipher> {
+    manager: RefCell<VM>,
+    cache_dir: Option<PathBuf>,
+    key_stretching: KeyStretching,
+    offline_cache: RefCell<Option<OfflineCache>>,
+    username: Option<String>,
+    /// Only kept while unlocked offline, to log in to the server at the next save, wiped once logged in
+    offline_password: RefCell<Option<Zeroizing<String>>>,
+    _phantom_nkc: PhantomData<NKC>,
+}
+
+impl<VM: VaultManager, NKC: NoKeyCipher> CachedVaultManager<VM, NKC> {
+    pub fn new(manager: VM, cache_dir: Option<PathBuf>) -> Self {
+        Self::with_key_stretching(manager, cache_dir, KeyStretching::default())
+    }
+
+    /// The key of the cache is stretched from the password like the login
+    pub fn with_key_stretching(manager: VM, cache_dir: Option<PathBuf>, key_stretching: KeyStretching) -> Self {
+        Self {
+            manager: RefCell::new(manager),
+            cache_dir,
+            key_stretching,
+            offline_cache: RefCell::new(None),
+            username: None,
+            offline_password: RefCell::new(None),
+            _phantom_nkc: PhantomData,
+        }
+    }
+
+    /// Unlocked from the cache, the server couldn't be reached at the login
+    pub fn is_offline(&self) -> bool {
+        self.offline_password.borrow().is_some()
+    }
+
+    /// One file per user, named after the hex of the username so any username gives a valid file name
+    fn cache_path(&self, username: &str) -> Option<PathBuf> {
+        self.cache_dir
+            .as_ref()
+            .map(|cache_dir| cache_dir.join(format!("{}.{CACHE_EXTENSION}", hex::encode(username))))
+    }
+
+    fn store_cache(&self) -> Result<()> {
+        let offline_cache = self.offline_cache.borrow();
+        let path = self.username.as_deref().and_then(|username| self.cache_path(username));
+
+        match (offline_cache.as_ref(), path) {
+            (Some(offline_cache), Some(path)) => offline_cache.store(&path),
+            _ => Ok(()),
+        }
+    }
+
+    fn update_cache(&self, update: impl FnOnce(&mut OfflineCache)) -> Result<()> {
+        match self.offline_cache.borrow_mut().as_mut() {
+            Some(offline_cache) => update(offline_cache),
+            None => return Ok(()),
+        }
+
+        self.store_cache()
+    }
+
+    /// Caches the vault just read from the server, the records saved offline stay on top of it
+    fn cache_retrieved(
+        &mut self,
+        username: &str,
+        password: &str,
+        path: &Path,
+        mut crypted_vault: CryptedVault<VM::KeySize>,
+    ) -> Result<CryptedVault<VM::KeySize>> {
+        let mut offline_cache = match OfflineCache::load(path)? {
+            Some(mut offline_cache) if !offline_cache.get_pending_records().is_empty() => {
+                crypted_vault.records = offline_cache.get_records();
+                crypted_vault.revision = offline_cache.revision.clone();
+                crypted_vault.pending_records = offline_cache.get_pending_records();
+
+                offline_cache.seal_encryption_key::<NKC>(username, password, crypted_vault.encryption_key.as_bytes())?;
+                offline_cache
+            }
+            _ => {
+                let mut offline_cache = OfflineCache::new::<NKC>(
+                    username,
+                    password,
+                    crypted_vault.encryption_key.as_bytes(),
+                    &self.key_stretching,
+                    vec![],
+                    vec![],
+                )?;
+                offline_cache.set_synced_records(&crypted_vault.records, crypted_vault.revision.clone());
+                offline_cache
+            }
+        };
+
+        offline_cache.wrapped_key = crypted_vault.wrapped_key.clone();
+        offline_cache.server_key = crypted_vault.server_key.clone();
+        offline_cache.store(path)?;
+
+        self.username = Some(username.to_string());
+        *self.offline_cache.borrow_mut() = Some(offline_cache);
+        *self.offline_password.borrow_mut() = None;
+
+        Ok(crypted_vault)
+    }
+
+    /// Opens the cached vault with the password, the server key pinned at the last login is handed out again
+    fn unlock_offline(
+        &mut self,
+        username: &str,
+        password: &str,
+        path: &Path,
+    ) -> Result<Option<CryptedVault<VM::KeySize>>> {
+        let Some(offline_cache) = OfflineCache::load(path)? else {
+            return Ok(None);
+        };
+
+        let encryption_key = offline_cache.open_encryption_key::<NKC>(username, password)?;
+        let encryption_key = GenericArray::from_exact_iter(encryption_key.iter().copied())
+            .ok_or_else(|| VaultError::Internal(MALFORMED_ENCRYPTION_KEY.to_string()))?;
+
+        let crypted_vault = CryptedVault::new(
+            vec![],
+            offline_cache.get_records(),
+            offline_cache.wrapped_key.clone(),
+            offline_cache.server_key.clone(),
+            Key::new(encryption_key),
+            offline_cache.revision.clone(),
+            offline_cache.get_pending_records(),
+        );
+
+        self.username = Some(username.to_string());
+        *self.offline_cache.borrow_mut() = Some(offline_cache);
+        *self.offline_password.borrow_mut() = Some(Zeroizing::new(password.to_string()));
+
+        Ok(Some(crypted_vault))
+    }
+
+    /// Logs in to the server after an offline unlock, the cached vault stays the base of the saves
+    ///
+    /// The password and its copy are wiped as soon as the server accepted it.
+    fn reconnect(&self) -> Result<()> {
+        let (Some(username), Some(password)) = (&self.username, self.offline_password.borrow().clone()) else {
+            return Ok(());
+        };
+
+        self.manager.borrow_mut().reauthenticate(username, &password)?;
+        *self.offline_password.borrow_mut() = None;
+
+        Ok(())
+    }
+}
+
+impl<VM: VaultManager, NKC: NoKeyCipher> VaultManager for CachedVaultManager<VM, NKC> {
+    type KeySize = VM::KeySize;
+
+    fn create(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>> {
+        let crypted_vault = self.manager.get_mut().create(username, password)?;
+
+        let Some(path) = self.cache_path(username) else {
+            return Ok(crypted_vault);
+        };
+
+        let mut offline_cache = OfflineCache::new::<NKC>(
+            username,
+            password,
+            crypted_vault.encryption_key.as_bytes(),
+            &self.key_stretching,
+            crypted_vault.wrapped_key.clone(),
+            crypted_vault.server_key.clone(),
+        )?;
+        offline_cache.set_synced_records(&crypted_vault.records, crypted_vault.revision.clone());
+        offline_cache.store(&path)?;
+
+        self.username = Some(username.to_string());
+        *self.offline_cache.get_mut() = Some(offline_cache);
+        *self.offline_password.get_mut() = None;
+
+        Ok(crypted_vault)
+    }
+
+    fn retrieve(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>> {
+        let retrieved = self.manager.get_mut().retrieve(username, password);
+
+        let Some(path) = self.cache_path(username) else {
+            return retrieved;
+        };
+
+        match retrieved {
+            Ok(crypted_vault) => self.cache_retrieved(username, password, &path, crypted_vault),
+            Err(VaultError::ServerUnavailable(message)) => self
+                .unlock_offline(username, password, &path)?
+                .ok_or(VaultError::ServerUnavailable(message)),
+            Err(error) => Err(error),
+        }
+    }
+
+    fn reauthenticate(&mut self, username: &str, password: &str) -> Result<()> {
+        if self.is_offline() {
+            return self.reconnect();
+        }
+
+        self.manager.get_mut().reauthenticate(username, password)
+    }
+
+    fn logout(&mut self) -> Result<()> {
+        self.username = None;
+        *self.offline_cache.get_mut() = None;
+        *self.offline_password.get_mut() = None;
+
+        self.manager.get_mut().logout()
+    }
+
+    fn pin_server_key(&mut self, server_key: Option<Vec<u8>>) {
+        self.manager.get_mut().pin_server_key(server_key)
+    }
+
+    fn require_bound_identifiers(&mut self, required: bool) {
+        self.manager.get_mut().require_bound_identifiers(required)
+    }
+
+    /// Unlocked offline, the server didn't check the identifiers
+    fn identifiers_bound(&self) -> bool {
+        !self.is_offline() && self.manager.borrow().identifiers_bound()
+    }
+
+    fn needs_reregistration(&self) -> bool {
+        self.manager.borrow().needs_reregistration()
+    }
+
+    fn save(&self, vault: Vec<u8>, revision: Option<&str>) -> Result<Option<String>> {
+        self.reconnect()?;
+        self.manager.borrow().save(vault, revision)
+    }
+
+    /// Kept as pending in the cache while the server can't be reached, they replace the previous pending records
+    fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> Result<Option<String>> {
+        let saved = self.reconnect().and_then(|_| self.manager.borrow().save_records(records.clone(), revision));
+
+        match saved {
+            Ok(new_revision) => {
+                self.update_cache(|offline_cache| offline_cache.add_synced_records(&records, new_revision.clone()))?;
+                Ok(new_revision)
+            }
+            Err(VaultError::ServerUnavailable(_)) if self.offline_cache.borrow().is_some() => {
+                self.update_cache(|offline_cache| offline_cache.set_pending_records(&records))?;
+                Err(VaultError::SavedOffline)
+            }
+            Err(error) => Err(error),
+        }
+    }
+
+    fn fetch_records(&self) -> Result<(Vec<CryptedRecord>, Option<String>)> {
+        self.reconnect()?;
+        self.manager.borrow().fetch_records()
+    }
+
+    fn save_wrapped_key(&self, wrapped_key: Vec<u8>) -> Result<()> {
+        self.reconnect()?;
+        self.manager.borrow().save_wrapped_key(wrapped_key.clone())?;
+
+        self.update_cache(|offline_cache| offline_cache.wrapped_key = wrapped_key)
+    }
+
+    /// The cache is sealed again with the new password once the server registered it
+    fn change_password(
+        &mut self,
+        username: &str,
+        old_password: &str,
+        new_password: &str,
+        wrap_data_key: &dyn Fn(&[u8]) -> Result<Vec<u8>>,
+    ) -> Result<()> {
+        let new_keys = RefCell::new(None);
+        let capture_keys = |encryption_key: &[u8]| {
+            let wrapped_key = wrap_data_key(encryption_key)?;
+            *new_keys.borrow_mut() = Some((Zeroizing::new(encryption_key.to_vec()), wrapped_key.clone()));
+            Ok(wrapped_key)
+        };
+
+        self.reconnect()?;
+        self.manager
+            .get_mut()
+            .change_password(username, old_password, new_password, &capture_keys)?;
+
+        let Some((encryption_key, wrapped_key)) = new_keys.into_inner() else {
+            return Ok(());
+        };
+
+        let mut sealed = Ok(());
+        self.update_cache(|offline_cache| {
+            offline_cache.wrapped_key = wrapped_key;
+            sealed = offline_cache.seal_encryption_key::<NKC>(username, new_password, &encryption_key);
+        })?;
+
+        sealed
+    }
+
+    fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> Result<()> {
+        self.reconnect()?;
+        self.manager.borrow().save_recovery_key(recovery_token, wrapped_key)
+    }
+
+    /// The cache is sealed again at the next login with the new password
+    fn recover(
+        &mut self,
+        username: &str,
+        recovery_token: &[u8],
+        new_password: &str,
+        rewrap_data_key: &RewrapDataKey<'_>,
+    ) -> Result<()> {
+        self.manager
+            .get_mut()
+            .recover(username, recovery_token, new_password, rewrap_data_key)
+    }
+
+    fn save_public_key(&self, public_key: Vec<u8>) -> Result<()> {
+        self.reconnect()?;
+        self.manager.borrow().save_public_key(public_key)
+    }
+
+    fn get_public_key(&self, username: &str) -> Result<Vec<u8>> {
+        self.reconnect()?;
+        self.manager.borrow().get_public_key(username)
+    }
+
+    fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> Result<()> {
+        self.reconnect()?;
+        self.manager.borrow().send_share(recipient, sealed_entry)
+    }
+
+    fn get_shares(&self) -> Result<Vec<SealedShare>> {
+        self.reconnect()?;
+        self.manager.borrow().get_shares()
+    }
+
+    fn delete_share(&self, id: u64) -> Result<()> {
+        self.reconnect()?;
+        self.manager.borrow().delete_share(id)
+    }
+}
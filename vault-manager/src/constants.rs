@@ -7,4 +7,16 @@ pub const OPAQUE_REGISTRATION_START: &'static str = "/opaque/registration/start"
 pub const OPAQUE_REGISTRATION_FINISH: &'static str = "/opaque/registration/finish";
 pub const OPAQUE_LOGIN_START: &'static str = "/opaque/login/start";
 pub const OPAQUE_LOGIN_FINISH: &'static str = "/opaque/login/finish";
-pub const VAULT: &'static str = "/vault";
\ No newline at end of file
+pub const OPAQUE_LOGOUT: &str = "/opaque/logout";
+pub const OPAQUE_PASSWORD_CHANGE_START: &str = "/opaque/password/start";
+pub const OPAQUE_PASSWORD_CHANGE_FINISH: &str = "/opaque/password/finish";
+pub const OPAQUE_RECOVERY_START: &str = "/opaque/recovery/start";
+pub const OPAQUE_RECOVERY_FINISH: &str = "/opaque/recovery/finish";
+pub const VAULT: &'static str = "/vault";
+pub const VAULT_RECORDS: &str = "/vault/records";
+pub const VAULT_KEY: &str = "/vault/key";
+pub const VAULT_RECOVERY: &str = "/vault/recovery";
+pub const SHARING_KEY: &str = "/sharing/key";
+pub const SHARING_KEY_LOOKUP: &str = "/sharing/key/lookup";
+pub const SHARING_INBOX: &str = "/sharing/inbox";
+pub const SHARING_INBOX_DELETE: &str = "/sharing/inbox/delete";
@@ -1,11 +1,61 @@
 use app_core::vault::vault_error::VaultError;
 
 use opaque_ke::errors::ProtocolError;
+use reqwest::StatusCode;
+
+// Codes the server can answer in the body of an error, they take precedence over the status
+const INVALID_CREDENTIALS_CODE: &str = "invalid_credentials";
+const USER_EXISTS_CODE: &str = "user_exists";
+const SESSION_EXPIRED_CODE: &str = "session_expired";
+const CONFLICT_CODE: &str = "conflict";
 
 pub fn to_exchange_failed_vault_error(protocol_error: ProtocolError) -> VaultError {
-    VaultError::ExchangeFailed(protocol_error.to_string())
+    match protocol_error {
+        // The envelope of the server couldn't be opened with this password
+        ProtocolError::InvalidLoginError => VaultError::InvalidCredentials,
+        protocol_error => VaultError::ExchangeFailed(protocol_error.to_string()),
+    }
 }
 
 pub fn to_internal_vault_error(reqwest_error: reqwest::Error) -> VaultError {
     VaultError::Internal(reqwest_error.to_string())
-}
\ No newline at end of file
+}
+
+pub fn to_request_vault_error(reqwest_error: reqwest::Error) -> VaultError {
+    if reqwest_error.is_connect() || reqwest_error.is_timeout() {
+        return VaultError::ServerUnavailable(reqwest_error.to_string());
+    }
+
+    VaultError::ExchangeFailed(reqwest_error.to_string())
+}
+
+/// A rejected session means it expired, a rejected anonymous request means wrong credentials,
+/// a conflict is a stale revision with a session and a taken username without one
+pub fn to_status_vault_error(
+    status_error: reqwest::Error,
+    retry_after: Option<u64>,
+    body: &str,
+    authenticated: bool,
+) -> VaultError {
+    match body.trim() {
+        INVALID_CREDENTIALS_CODE => return VaultError::InvalidCredentials,
+        USER_EXISTS_CODE => return VaultError::UserExists,
+        SESSION_EXPIRED_CODE => return VaultError::SessionExpired,
+        CONFLICT_CODE => return VaultError::Conflict,
+        _ => {}
+    }
+
+    match status_error.status() {
+        Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) if authenticated => VaultError::SessionExpired,
+        Some(StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN) => VaultError::InvalidCredentials,
+        // The `If-Match` of a save didn't match the current revision
+        Some(StatusCode::CONFLICT | StatusCode::PRECONDITION_FAILED) if authenticated => VaultError::Conflict,
+        Some(StatusCode::CONFLICT) => VaultError::UserExists,
+        Some(StatusCode::PRECONDITION_FAILED) => VaultError::Conflict,
+        Some(StatusCode::TOO_MANY_REQUESTS) => VaultError::RateLimited(retry_after),
+        Some(StatusCode::BAD_GATEWAY | StatusCode::SERVICE_UNAVAILABLE | StatusCode::GATEWAY_TIMEOUT) => {
+            VaultError::ServerUnavailable(status_error.to_string())
+        }
+        _ => VaultError::ExchangeFailed(status_error.to_string()),
+    }
+}
@@ -1,8 +1,12 @@
-use crate::Result;
+use crate::{Result, key_stretching::KeyStretching};
 use app_core::vault::vault_error::VaultError;
 use hmac::{Hmac, Mac};
-use reqwest::header::{HeaderMap, HeaderValue};
-use sha2::Sha512;
+use opaque_ke::rand::{RngCore, rngs::OsRng};
+use reqwest::{
+    StatusCode,
+    header::{ETAG, HeaderMap, HeaderValue, IF_MATCH, RETRY_AFTER},
+};
+use sha2::{Digest, Sha512};
 
 #[cfg(test)]
 use mock_instant::global::{SystemTime, UNIX_EPOCH};
@@ -16,6 +20,19 @@ const X_TIMESTAMP: &'static str = "X-Timestamp";
 const HOST: &'static str = "Host";
 const X_SIGNATURE: &'static str = "X-Signature";
 const X_USERNAME: &'static str = "X-Username";
+const X_RECOVERY_TOKEN: &str = "X-Recovery-Token";
+const X_NONCE: &str = "X-Nonce";
+const X_KEY_STRETCHING: &str = "X-Key-Stretching";
+const X_SESSION_LIFETIME: &str = "X-Session-Lifetime";
+
+const REQUEST_SIGNATURE_VERSION: &str = "ferris-vault-v1-request";
+// v2 signs the status and the headers the client acts on along with the body
+const RESPONSE_SIGNATURE_VERSION: &str = "ferris-vault-v2-response";
+const NONCE_SIZE: usize = 16;
+
+const MISSING_RESPONSE_SIGNATURE: &str = "The response isn't signed.";
+const INVALID_RESPONSE_SIGNATURE: &str = "The response signature doesn't match.";
+const MALFORMED_KEY_STRETCHING: &str = "Malformed key stretching parameters.";
 
 pub type HmacSha512 = Hmac<Sha512>;
 
@@ -31,14 +48,89 @@ pub fn get_opaque_headers(username: &str) -> Result<HeaderMap> {
     Ok(headers)
 }
 
+/// There is no session during a recovery, the token derived from the recovery key authorizes it
+pub fn get_recovery_headers(username: &str, recovery_token: &[u8]) -> Result<HeaderMap> {
+    let mut headers = get_opaque_headers(username)?;
+
+    let mut recovery_token_header_value = HeaderValue::from_str(&hex::encode(recovery_token))
+        .map_err(|error| VaultError::Internal(error.to_string()))?;
+    recovery_token_header_value.set_sensitive(true);
+
+    headers.insert(X_RECOVERY_TOKEN, recovery_token_header_value);
+
+    Ok(headers)
+}
+
+/// Sent with every registration, the server records the parameters for the next logins
+pub fn add_key_stretching_header(headers: &mut HeaderMap, key_stretching: &KeyStretching) -> Result<()> {
+    let key_stretching_header_value = HeaderValue::from_str(&key_stretching.encode())
+        .map_err(|error| VaultError::Internal(error.to_string()))?;
+
+    headers.insert(X_KEY_STRETCHING, key_stretching_header_value);
+
+    Ok(())
+}
+
+/// Parameters recorded for the account, the default ones when the server has none
+pub fn get_key_stretching(headers: &HeaderMap) -> Result<KeyStretching> {
+    let Some(key_stretching) = headers.get(X_KEY_STRETCHING) else {
+        return Ok(KeyStretching::default());
+    };
+
+    key_stretching
+        .to_str()
+        .ok()
+        .and_then(KeyStretching::decode)
+        .ok_or_else(|| VaultError::ExchangeFailed(MALFORMED_KEY_STRETCHING.to_string()))
+}
+
+/// A delay in seconds or an HTTP date, a date already past means no delay
+pub fn get_retry_after(headers: &HeaderMap) -> Option<u64> {
+    let retry_after = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+
+    if let Ok(seconds) = retry_after.parse() {
+        return Some(seconds);
+    }
+
+    let retry_date = httpdate::parse_http_date(retry_after)
+        .ok()?
+        .duration_since(std::time::UNIX_EPOCH)
+        .ok()?
+        .as_secs();
+
+    let now = SystemTime::now().duration_since(UNIX_EPOCH).ok()?.as_secs();
+
+    Some(retry_date.saturating_sub(now))
+}
+
+/// The revision is sent back as it was received, the server compares it with the current one
+pub fn add_if_match_header(headers: &mut HeaderMap, revision: &str) -> Result<()> {
+    let revision_header_value =
+        HeaderValue::from_str(revision).map_err(|error| VaultError::Internal(error.to_string()))?;
+
+    headers.insert(IF_MATCH, revision_header_value);
+
+    Ok(())
+}
+
+/// `None` when the server doesn't version the vault, the saves then overwrite it
+pub fn get_revision(headers: &HeaderMap) -> Option<String> {
+    headers.get(ETAG)?.to_str().ok().map(str::to_string)
+}
+
+/// Seconds the session lasts from the login, `None` when the server doesn't limit it
+pub fn get_session_lifetime(headers: &HeaderMap) -> Option<u64> {
+    headers.get(X_SESSION_LIFETIME)?.to_str().ok()?.trim().parse().ok()
+}
+
+/// Signs the request with the session key, the nonce is returned to verify the response
 pub fn get_vault_request_headers(
     session_key: &[u8],
     verb: &str,
     host: &str,
     path: &str,
-) -> Result<HeaderMap> {
-    let uri = format!("{}{}", host, path);
-
+    body: &[u8],
+) -> Result<(HeaderMap, String)> {
     let mut headers = get_default_headers();
 
     let host_header_value =
@@ -54,7 +146,11 @@ pub fn get_vault_request_headers(
 
     headers.insert(X_TIMESTAMP, timestamp_header_value);
 
-    let signature = create_signature(verb, &uri, &timestamp, session_key)?;
+    let nonce = add_nonce_header(&mut headers)?;
+
+    let canonical_request = get_canonical_request(verb, path, body, &timestamp, &nonce);
+
+    let signature = create_signature(&canonical_request, session_key)?;
 
     let mut signature_header_value = HeaderValue::from_str(&hex::encode(signature))
         .map_err(|error| VaultError::Internal(error.to_string()))?;
@@ -62,7 +158,77 @@ pub fn get_vault_request_headers(
 
     headers.insert(X_SIGNATURE, signature_header_value);
 
-    Ok(headers)
+    Ok((headers, nonce))
+}
+
+/// Sent with the login finish too, the server signs its answer once it has the session key
+pub fn add_nonce_header(headers: &mut HeaderMap) -> Result<String> {
+    let nonce = create_nonce();
+
+    let nonce_header_value =
+        HeaderValue::from_str(&nonce).map_err(|error| VaultError::Internal(error.to_string()))?;
+
+    headers.insert(X_NONCE, nonce_header_value);
+
+    Ok(nonce)
+}
+
+/// The server signs its response, errors included, with the session key and the nonce of the request,
+/// a response can't be altered or replayed for another request
+pub fn verify_response_signature(
+    session_key: &[u8],
+    nonce: &str,
+    status: StatusCode,
+    body: &[u8],
+    headers: &HeaderMap,
+) -> Result<()> {
+    let Some(signature) = headers.get(X_SIGNATURE) else {
+        return Err(VaultError::ExchangeFailed(MISSING_RESPONSE_SIGNATURE.to_string()));
+    };
+
+    let signature = signature
+        .to_str()
+        .ok()
+        .and_then(|signature| hex::decode(signature).ok())
+        .ok_or_else(|| VaultError::ExchangeFailed(INVALID_RESPONSE_SIGNATURE.to_string()))?;
+
+    let mut mac = HmacSha512::new_from_slice(session_key)
+        .map_err(|error| VaultError::Internal(error.to_string()))?;
+
+    mac.update(get_canonical_response(nonce, status, headers, body).as_bytes());
+
+    mac.verify_slice(&signature)
+        .map_err(|_| VaultError::ExchangeFailed(INVALID_RESPONSE_SIGNATURE.to_string()))
+}
+
+/// One field per line: version, verb, path, SHA-512 of the body (hex), content type, timestamp and nonce
+pub fn get_canonical_request(verb: &str, path: &str, body: &[u8], timestamp: &str, nonce: &str) -> String {
+    [
+        REQUEST_SIGNATURE_VERSION,
+        verb,
+        path,
+        &hex::encode(Sha512::digest(body)),
+        APPLICATION_OCTET_STREAM,
+        timestamp,
+        nonce,
+    ]
+    .join("\n")
+}
+
+/// One field per line: version, nonce of the request, status code, `ETag`, session lifetime and SHA-512 of the body (hex),
+/// a header the server didn't send is an empty line
+pub fn get_canonical_response(nonce: &str, status: StatusCode, headers: &HeaderMap, body: &[u8]) -> String {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok()).unwrap_or_default();
+
+    [
+        RESPONSE_SIGNATURE_VERSION,
+        nonce,
+        status.as_str(),
+        header(ETAG.as_str()),
+        header(X_SESSION_LIFETIME),
+        &hex::encode(Sha512::digest(body)),
+    ]
+    .join("\n")
 }
 
 fn get_default_headers() -> HeaderMap {
@@ -83,13 +249,19 @@ fn get_current_time_to_string() -> Result<String> {
     Ok(timestamp.to_string())
 }
 
-fn create_signature(verb: &str, uri: &str, timestamp: &str, session_key: &[u8]) -> Result<Vec<u8>> {
-    let raw_signature = format!("{}|{}|{}", verb, uri, timestamp);
+fn create_nonce() -> String {
+    let mut nonce = [0u8; NONCE_SIZE];
+
+    OsRng.fill_bytes(&mut nonce);
+
+    hex::encode(nonce)
+}
 
+fn create_signature(canonical_request: &str, session_key: &[u8]) -> Result<Vec<u8>> {
     let mut mac = HmacSha512::new_from_slice(session_key)
         .map_err(|error| VaultError::Internal(error.to_string()))?;
 
-    mac.update(raw_signature.as_bytes());
+    mac.update(canonical_request.as_bytes());
 
     Ok(mac.finalize().into_bytes().to_vec())
 }
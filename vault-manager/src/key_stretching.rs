@@ -0,0 +1,74 @@
+use app_core::vault::vault_error::VaultError;
+use opaque_ke::argon2::{Algorithm, Argon2, Params, Version};
+
+use crate::Result;
+
+// Above these a server could exhaust the client with the parameters it hands out
+const MAX_MEMORY_KIB: u32 = 4 * 1024 * 1024;
+const MAX_ITERATIONS: u32 = 64;
+const MAX_PARALLELISM: u32 = 16;
+
+const INVALID_KEY_STRETCHING: &str = "Invalid key stretching parameters.";
+
+/// Argon2id parameters of an account, the server records them at the registration and hands them out at the login
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyStretching {
+    pub memory_kib: u32,
+    pub iterations: u32,
+    pub parallelism: u32,
+}
+
+/// Parameters of `Argon2::default()`, accounts registered before they were recorded use them
+impl Default for KeyStretching {
+    fn default() -> Self {
+        Self {
+            memory_kib: Params::DEFAULT_M_COST,
+            iterations: Params::DEFAULT_T_COST,
+            parallelism: Params::DEFAULT_P_COST,
+        }
+    }
+}
+
+impl KeyStretching {
+    pub fn new(memory_kib: u32, iterations: u32, parallelism: u32) -> Self {
+        Self {
+            memory_kib,
+            iterations,
+            parallelism,
+        }
+    }
+
+    pub fn to_ksf(&self) -> Result<Argon2<'static>> {
+        if self.memory_kib > MAX_MEMORY_KIB || self.iterations > MAX_ITERATIONS || self.parallelism > MAX_PARALLELISM {
+            return Err(VaultError::ExchangeFailed(INVALID_KEY_STRETCHING.to_string()));
+        }
+
+        let params = Params::new(self.memory_kib, self.iterations, self.parallelism, None)
+            .map_err(|error| VaultError::ExchangeFailed(format!("{INVALID_KEY_STRETCHING} {error}")))?;
+
+        Ok(Argon2::new(Algorithm::Argon2id, Version::V0x13, params))
+    }
+
+    /// Less memory or fewer iterations, the parallelism alone doesn't make it weaker
+    pub fn is_weaker_than(&self, other: &KeyStretching) -> bool {
+        self.memory_kib < other.memory_kib || self.iterations < other.iterations
+    }
+
+    /// Same notation as a PHC string, `m=19456,t=2,p=1`
+    pub fn encode(&self) -> String {
+        format!("m={},t={},p={}", self.memory_kib, self.iterations, self.parallelism)
+    }
+
+    pub fn decode(value: &str) -> Option<Self> {
+        let mut fields = value.split(',').map(|field| field.split_once('='));
+
+        match (fields.next()?, fields.next()?, fields.next()?, fields.next()) {
+            (Some(("m", memory_kib)), Some(("t", iterations)), Some(("p", parallelism)), None) => Some(Self::new(
+                memory_kib.parse().ok()?,
+                iterations.parse().ok()?,
+                parallelism.parse().ok()?,
+            )),
+            _ => None,
+        }
+    }
+}
@@ -1,7 +1,13 @@
 use app_core::vault::vault_error::VaultError;
 
 pub mod opaque_vault_manager;
+pub mod async_opaque_vault_manager;
+pub mod cached_vault_manager;
 pub mod opaque_api;
+pub mod async_opaque_api;
+pub mod key_stretching;
+pub mod request_policy;
+mod offline_cache;
 mod http_utils;
 mod error_utils;
 mod constants;
@@ -0,0 +1,210 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, ErrorKind, Write},
+    path::Path,
+};
+
+#[cfg(unix)]
+use std::os::unix::fs::OpenOptionsExt;
+
+use app_core::{
+    ports::cryptography::{Cryptography, NoKeyCipher},
+    vault::{crypted_record::CryptedRecord, vault_error::VaultError},
+};
+use bincode::{Decode, Encode, config};
+use opaque_ke::rand::{RngCore, rngs::OsRng};
+use zeroize::Zeroizing;
+
+use crate::{Result, key_stretching::KeyStretching};
+
+const SEALED_KEY_AAD_LABEL: &[u8] = b"ferris-vault-v1-offline-key";
+const SALT_LENGTH: usize = 16;
+const SEALING_KEY_LENGTH: usize = 32;
+const TEMPORARY_EXTENSION: &str = "tmp";
+
+const MALFORMED_CACHE: &str = "Malformed offline cache.";
+
+/// Last vault synced with the server and the records saved since without it, as stored in the cache file
+///
+/// Everything but the encryption key is already encrypted by the core. The encryption key is sealed by the
+/// cipher of the vault with a key stretched from the password, so the cache can only be opened with the password,
+/// like the server.
+#[derive(Encode, Decode, PartialEq, Debug, Clone)]
+pub struct OfflineCache {
+    memory_kib: u32,
+    iterations: u32,
+    parallelism: u32,
+    salt: Vec<u8>,
+    sealed_key: Vec<u8>,
+    pub wrapped_key: Vec<u8>,
+    pub server_key: Vec<u8>,
+    records: Vec<(String, Vec<u8>)>,
+    pub revision: Option<String>,
+    pending_records: Vec<(String, Vec<u8>)>,
+}
+
+impl OfflineCache {
+    pub fn new<NKC: NoKeyCipher>(
+        username: &str,
+        password: &str,
+        encryption_key: &[u8],
+        key_stretching: &KeyStretching,
+        wrapped_key: Vec<u8>,
+        server_key: Vec<u8>,
+    ) -> Result<Self> {
+        let mut offline_cache = Self {
+            memory_kib: key_stretching.memory_kib,
+            iterations: key_stretching.iterations,
+            parallelism: key_stretching.parallelism,
+            salt: vec![],
+            sealed_key: vec![],
+            wrapped_key,
+            server_key,
+            records: vec![],
+            revision: None,
+            pending_records: vec![],
+        };
+
+        offline_cache.seal_encryption_key::<NKC>(username, password, encryption_key)?;
+
+        Ok(offline_cache)
+    }
+
+    /// `None` if no vault of this user was cached yet
+    pub fn load(path: &Path) -> Result<Option<Self>> {
+        let data = match fs::read(path) {
+            Ok(data) => data,
+            Err(error) if error.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(error) => return Err(VaultError::Internal(error.to_string())),
+        };
+
+        let (offline_cache, _) = bincode::decode_from_slice(&data, config::standard())
+            .map_err(|error| VaultError::Internal(format!("{MALFORMED_CACHE} {error}")))?;
+
+        Ok(Some(offline_cache))
+    }
+
+    /// The cache directory is created at the first login
+    ///
+    /// Written to a temporary file only readable by its owner, then renamed over the previous cache,
+    /// a crash while writing leaves the previous one whole.
+    pub fn store(&self, path: &Path) -> Result<()> {
+        if let Some(cache_dir) = path.parent() {
+            fs::create_dir_all(cache_dir).map_err(|error| VaultError::Internal(error.to_string()))?;
+        }
+
+        let data = bincode::encode_to_vec(self, config::standard())
+            .map_err(|error| VaultError::Internal(error.to_string()))?;
+
+        let temporary_path = path.with_extension(TEMPORARY_EXTENSION);
+
+        write_private_file(&temporary_path, &data)
+            .and_then(|_| fs::rename(&temporary_path, path))
+            .map_err(|error| VaultError::Internal(error.to_string()))
+    }
+
+    /// A new salt every time, after every login or password change, the cipher picks its own nonce
+    pub fn seal_encryption_key<NKC: NoKeyCipher>(&mut self, username: &str, password: &str, encryption_key: &[u8]) -> Result<()> {
+        let mut salt = vec![0; SALT_LENGTH];
+        OsRng.fill_bytes(&mut salt);
+
+        self.sealed_key = self
+            .sealing_cipher::<NKC>(password, &salt)?
+            .encrypt_record(encryption_key, &sealed_key_aad(username))
+            .map_err(|error| VaultError::Internal(error.to_string()))?;
+        self.salt = salt;
+
+        Ok(())
+    }
+
+    /// A wrong password fails like a refused login
+    pub fn open_encryption_key<NKC: NoKeyCipher>(&self, username: &str, password: &str) -> Result<Zeroizing<Vec<u8>>> {
+        self.sealing_cipher::<NKC>(password, &self.salt)?
+            .decrypt_record(&self.sealed_key, &sealed_key_aad(username))
+            .map(Zeroizing::new)
+            .map_err(|_| VaultError::InvalidCredentials)
+    }
+
+    pub fn get_records(&self) -> Vec<CryptedRecord> {
+        to_crypted_records(&self.records)
+    }
+
+    pub fn get_pending_records(&self) -> Vec<CryptedRecord> {
+        to_crypted_records(&self.pending_records)
+    }
+
+    /// The vault as just read from the server, nothing is pending on top of it
+    pub fn set_synced_records(&mut self, records: &[CryptedRecord], revision: Option<String>) {
+        self.records = from_crypted_records(records);
+        self.revision = revision;
+        self.pending_records = vec![];
+    }
+
+    /// Applies the uploaded changes to the synced records, deleted records are removed
+    pub fn add_synced_records(&mut self, changed_records: &[CryptedRecord], revision: Option<String>) {
+        let mut records = self.get_records();
+        records.retain(|record| !changed_records.iter().any(|changed_record| changed_record.id == record.id));
+        records.extend(changed_records.iter().filter(|record| !record.is_deleted()).cloned());
+
+        self.set_synced_records(&records, revision);
+    }
+
+    /// Every save sends all the changes since the synced records, the previous pending ones are replaced
+    pub fn set_pending_records(&mut self, pending_records: &[CryptedRecord]) {
+        self.pending_records = from_crypted_records(pending_records);
+    }
+
+    fn sealing_cipher<NKC: NoKeyCipher>(&self, password: &str, salt: &[u8]) -> Result<NKC::Crypto> {
+        if salt.len() != SALT_LENGTH {
+            return Err(VaultError::Internal(MALFORMED_CACHE.to_string()));
+        }
+
+        let key_stretching = KeyStretching::new(self.memory_kib, self.iterations, self.parallelism);
+
+        let mut sealing_key = Zeroizing::new([0; SEALING_KEY_LENGTH]);
+
+        key_stretching
+            .to_ksf()?
+            .hash_password_into(password.as_bytes(), salt, sealing_key.as_mut())
+            .map_err(|error| VaultError::Internal(error.to_string()))?;
+
+        NKC::create_cipher_from_key(sealing_key.as_ref()).map_err(|error| VaultError::Internal(error.to_string()))
+    }
+}
+
+/// Only readable by its owner, a temporary file left by a crash is replaced
+fn write_private_file(path: &Path, data: &[u8]) -> io::Result<()> {
+    match fs::remove_file(path) {
+        Err(error) if error.kind() != ErrorKind::NotFound => return Err(error),
+        _ => {}
+    }
+
+    let mut options = OpenOptions::new();
+    options.write(true).create_new(true);
+
+    #[cfg(unix)]
+    options.mode(0o600);
+
+    let mut file = options.open(path)?;
+    file.write_all(data)?;
+    file.sync_all()
+}
+
+/// Binds the sealed key to its user, the cache of another vault can't be swapped in
+fn sealed_key_aad(username: &str) -> Vec<u8> {
+    [SEALED_KEY_AAD_LABEL, username.as_bytes()].concat()
+}
+
+fn to_crypted_records(records: &[(String, Vec<u8>)]) -> Vec<CryptedRecord> {
+    records
+        .iter()
+        .map(|(id, content)| CryptedRecord::new(id.clone(), content.clone()))
+        .collect()
+}
+
+fn from_crypted_records(records: &[CryptedRecord]) -> Vec<(String, Vec<u8>)> {
+    records
+        .iter()
+        .map(|record| (record.id.clone(), record.content.clone()))
+        .collect()
+}
@@ -2,25 +2,18 @@ use opaque_ke::{
     ClientLoginFinishResult, ClientLoginStartResult, ClientRegistrationFinishResult,
     ClientRegistrationStartResult, CredentialResponse, RegistrationResponse,
 };
-use reqwest::{
-    blocking::{Client, Response},
-    header::HeaderMap,
-};
-
-use hkdf::Hkdf;
-use sha2::Sha512;
+use tokio::runtime::{Builder, Runtime};
 
 use crate::{
     Result,
-    constants::*,
-    error_utils::{to_exchange_failed_vault_error, to_internal_vault_error},
-    http_utils::{get_opaque_headers, get_vault_request_headers},
+    async_opaque_api::{AsyncApi, AsyncOpaqueApi},
+    key_stretching::KeyStretching,
     opaque_vault_manager::StandardCipherSuite,
+    request_policy::RequestPolicy,
 };
-use app_core::vault::vault_error::VaultError;
+use app_core::vault::{crypted_record::CryptedRecord, share::SealedShare, vault_error::VaultError};
 
-const METHOD_NOT_ALLOWED: &'static str = "Method not allowed.";
-const NO_SESSION_AFTER_LOGIN: &'static str = "No session after loggin.";
+const RUNTIME_ERROR: &str = "The runtime of the blocking api couldn't be built.";
 
 pub trait Api {
     fn start_server_registration(
@@ -29,17 +22,20 @@ pub trait Api {
         client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
     ) -> Result<RegistrationResponse<StandardCipherSuite>>;
 
+    /// The server records the key stretching parameters of the account with its registration
     fn finish_server_registration(
         &self,
         username: &str,
         client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        key_stretching: &KeyStretching,
     ) -> Result<()>;
 
+    /// The server answers with the key stretching parameters recorded for the account
     fn start_server_login(
         &self,
         username: &str,
         client_login_start_result: &ClientLoginStartResult<StandardCipherSuite>,
-    ) -> Result<CredentialResponse<StandardCipherSuite>>;
+    ) -> Result<(CredentialResponse<StandardCipherSuite>, KeyStretching)>;
 
     fn finish_server_login(
         &mut self,
@@ -47,111 +43,95 @@ pub trait Api {
         client_login_finish_result: &ClientLoginFinishResult<StandardCipherSuite>,
     ) -> Result<()>;
 
-    fn get_vault(&self) -> Result<Vec<u8>>;
-    fn save_vault(&self, vault: Vec<u8>) -> Result<()>;
-    fn is_logged_in(&self) -> bool;
-}
-
-#[derive(Debug, Default)]
-struct Session {
-    session_key: Vec<u8>,
-    session_token: String,
-}
+    /// Invalidates the session token on the server
+    fn logout(&mut self) -> Result<()>;
 
-impl Session {
-    pub fn new(session_key: Vec<u8>, session_token: String) -> Self {
-        Self {
-            session_key,
-            session_token,
-        }
-    }
-}
+    /// Same exchange as the registration, but authenticated with the current session
+    fn start_password_change(
+        &self,
+        client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+    ) -> Result<RegistrationResponse<StandardCipherSuite>>;
 
-#[derive(Debug, Default)]
-pub struct OpaqueApi {
-    client: Client,
-    server_url: String,
-    session: Option<Session>,
-}
+    /// The new registration record and the wrapped data key are sent together, the server swaps both or neither
+    fn finish_password_change(
+        &self,
+        client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        wrapped_key: Vec<u8>,
+        key_stretching: &KeyStretching,
+    ) -> Result<()>;
 
-impl OpaqueApi {
-    pub fn new(server_url: String) -> Self {
-        Self {
-            client: Client::new(),
-            server_url,
-            session: None,
-        }
-    }
+    /// Replaces the recovery token and the data key wrapped with the recovery key
+    fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> Result<()>;
 
-    fn web_server_request(
+    /// Registration authorized by the recovery token instead of a session,
+    /// the server answers with the data key wrapped with the recovery key
+    fn start_recovery(
         &self,
-        uri: String,
-        verb: &'static str, // Change to ENUM ? todo!()
-        body: Option<Vec<u8>>,
-        headers: HeaderMap,
-        bearer_token: Option<&str>,
-    ) -> Result<Response> {
-        // Check if you need the mut and reassignement of client below todo!()
-        let mut client = match verb {
-            GET => self.client.get(uri),
-            POST => self.client.post(uri),
-            _ => return Err(VaultError::Internal(METHOD_NOT_ALLOWED.to_string())),
-        };
-
-        if let Some(body) = body {
-            client = client.body(body);
-        };
-
-        if let Some(bearer_token) = bearer_token {
-            client = client.bearer_auth(bearer_token);
-        }
-
-        let response = client
-            .headers(headers)
-            .send()
-            .map_err(|error| VaultError::ExchangeFailed(error.to_string()))?;
-
-        if let Err(error) = response.error_for_status_ref() {
-            return Err(VaultError::ExchangeFailed(error.to_string()));
-        }
-
-        Ok(response)
-    }
+        username: &str,
+        recovery_token: &[u8],
+        client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+    ) -> Result<(RegistrationResponse<StandardCipherSuite>, Vec<u8>)>;
 
-    fn create_session(&mut self, session_key: &[u8]) -> Result<()> {
-        let hkdf = Hkdf::<Sha512>::from_prk(session_key)
-            .map_err(|error| VaultError::Internal(error.to_string()))?;
+    fn finish_recovery(
+        &self,
+        username: &str,
+        recovery_token: &[u8],
+        client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        wrapped_key: Vec<u8>,
+        key_stretching: &KeyStretching,
+    ) -> Result<()>;
 
-        let mut token = vec![0u8; 64];
+    /// With the revision of the vault (its `ETag`), `None` if the server doesn't give one
+    fn get_vault(&self) -> Result<(Vec<u8>, Option<String>)>;
+    /// Sent with `If-Match` when the revision is known, the server answers the new one
+    fn save_vault(&self, vault: Vec<u8>, revision: Option<&str>) -> Result<Option<String>>;
+    /// Empty body if the vault was never saved as records
+    fn get_records(&self) -> Result<(Vec<CryptedRecord>, Option<String>)>;
+    /// Only the records sent are replaced, the server drops the ones sent with an empty content
+    fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> Result<Option<String>>;
+    /// Empty body if the vault has no wrapped key yet
+    fn get_wrapped_key(&self) -> Result<Vec<u8>>;
+    fn save_wrapped_key(&self, wrapped_key: Vec<u8>) -> Result<()>;
+
+    /// Publishes the sharing public key of the session user, replaces the previous one
+    fn save_public_key(&self, public_key: Vec<u8>) -> Result<()>;
+    /// Empty body if the user hasn't published a sharing key
+    fn get_public_key(&self, username: &str) -> Result<Vec<u8>>;
+    /// The server records the session user as the sender
+    fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> Result<()>;
+    /// Shares sent to the session user, they stay in the inbox until deleted
+    fn get_shares(&self) -> Result<Vec<SealedShare>>;
+    fn delete_share(&self, id: u64) -> Result<()>;
 
-        hkdf.expand(b"opaque-session-token", &mut token)
-            .map_err(|error| VaultError::Internal(error.to_string()))?;
+    fn is_logged_in(&self) -> bool;
+    /// Bound with the username into every OPAQUE registration and login
+    fn get_server_identifier(&self) -> &str;
+}
 
-        self.session = Some(Session::new(session_key.to_owned(), hex::encode(token)));
+/// Blocking wrapper of `AsyncOpaqueApi`, each request runs to completion on its own runtime
+///
+/// Its calls block, they must not be made from a task of an async runtime.
+#[derive(Debug)]
+pub struct OpaqueApi {
+    api: AsyncOpaqueApi,
+    runtime: Runtime,
+}
 
-        Ok(())
+impl OpaqueApi {
+    pub fn new(server_url: String) -> Result<Self> {
+        Self::with_request_policy(server_url, RequestPolicy::default())
     }
 
-    fn vault_request(
-        &self,
-        verb: &'static str,
-        content: Option<Vec<u8>>,
-    ) -> Result<Option<Vec<u8>>> {
-        let uri = format!("{}{}", &self.server_url, VAULT);
-
-        let Some(session) = self.session.as_ref() else {
-            return Err(VaultError::NotLoggedIn(NO_SESSION_AFTER_LOGIN.to_string()));
-        };
-
-        let headers =
-            get_vault_request_headers(&session.session_key, verb, &self.server_url, VAULT)?;
-
-        let vault_response =
-            self.web_server_request(uri, verb, content, headers, Some(&session.session_token))?;
+    pub fn with_request_policy(server_url: String, request_policy: RequestPolicy) -> Result<Self> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|error| VaultError::Internal(format!("{RUNTIME_ERROR} {error}")))?;
 
-        let vault_reponse_bytes = vault_response.bytes().map_err(to_internal_vault_error)?;
-
-        Ok(Some(vault_reponse_bytes.to_vec()))
+        Ok(Self {
+            api: AsyncOpaqueApi::with_request_policy(server_url, request_policy)?,
+            runtime,
+        })
     }
 }
 
@@ -161,67 +141,28 @@ impl Api for OpaqueApi {
         username: &str,
         client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
     ) -> Result<RegistrationResponse<StandardCipherSuite>> {
-        let registration_response = self.web_server_request(
-            format!("{}{}", &self.server_url, OPAQUE_REGISTRATION_START),
-            POST,
-            Some(
-                client_registration_start_result
-                    .message
-                    .serialize()
-                    .to_vec(),
-            ),
-            get_opaque_headers(username)?,
-            None,
-        )?;
-
-        let registration_response_bytes = registration_response
-            .bytes()
-            .map_err(to_internal_vault_error)?;
-
-        Ok(
-            RegistrationResponse::deserialize(&registration_response_bytes)
-                .map_err(to_exchange_failed_vault_error)?,
-        )
+        self.runtime.block_on(self.api.start_server_registration(username, client_registration_start_result))
     }
 
     fn finish_server_registration(
         &self,
         username: &str,
         client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        key_stretching: &KeyStretching,
     ) -> Result<()> {
-        let _ = self.web_server_request(
-            format!("{}{}", &self.server_url, OPAQUE_REGISTRATION_FINISH),
-            POST,
-            Some(
-                client_registration_finish_result
-                    .message
-                    .serialize()
-                    .to_vec(),
-            ),
-            get_opaque_headers(username)?,
-            None,
-        )?;
-
-        Ok(())
+        self.runtime.block_on(self.api.finish_server_registration(
+            username,
+            client_registration_finish_result,
+            key_stretching,
+        ))
     }
 
     fn start_server_login(
         &self,
         username: &str,
         client_login_start_result: &ClientLoginStartResult<StandardCipherSuite>,
-    ) -> Result<CredentialResponse<StandardCipherSuite>> {
-        let login_response = self.web_server_request(
-            format!("{}{}", &self.server_url, OPAQUE_LOGIN_START),
-            POST,
-            Some(client_login_start_result.message.serialize().to_vec()),
-            get_opaque_headers(username)?,
-            None,
-        )?;
-
-        let login_response_bytes = login_response.bytes().map_err(to_internal_vault_error)?;
-
-        Ok(CredentialResponse::deserialize(&login_response_bytes)
-            .map_err(to_exchange_failed_vault_error)?)
+    ) -> Result<(CredentialResponse<StandardCipherSuite>, KeyStretching)> {
+        self.runtime.block_on(self.api.start_server_login(username, client_login_start_result))
     }
 
     fn finish_server_login(
@@ -229,37 +170,112 @@ impl Api for OpaqueApi {
         username: &str,
         client_login_finish_result: &ClientLoginFinishResult<StandardCipherSuite>,
     ) -> Result<()> {
-        let _ = self.web_server_request(
-            format!("{}{}", &self.server_url, OPAQUE_LOGIN_FINISH),
-            POST,
-            Some(client_login_finish_result.message.serialize().to_vec()),
-            get_opaque_headers(username)?,
-            None,
-        )?;
+        self.runtime.block_on(self.api.finish_server_login(username, client_login_finish_result))
+    }
+
+    fn logout(&mut self) -> Result<()> {
+        self.runtime.block_on(self.api.logout())
+    }
+
+    fn start_password_change(
+        &self,
+        client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+    ) -> Result<RegistrationResponse<StandardCipherSuite>> {
+        self.runtime.block_on(self.api.start_password_change(client_registration_start_result))
+    }
+
+    fn finish_password_change(
+        &self,
+        client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        wrapped_key: Vec<u8>,
+        key_stretching: &KeyStretching,
+    ) -> Result<()> {
+        self.runtime.block_on(self.api.finish_password_change(
+            client_registration_finish_result,
+            wrapped_key,
+            key_stretching,
+        ))
+    }
+
+    fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> Result<()> {
+        self.runtime.block_on(self.api.save_recovery_key(recovery_token, wrapped_key))
+    }
+
+    fn start_recovery(
+        &self,
+        username: &str,
+        recovery_token: &[u8],
+        client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+    ) -> Result<(RegistrationResponse<StandardCipherSuite>, Vec<u8>)> {
+        self.runtime.block_on(self.api.start_recovery(username, recovery_token, client_registration_start_result))
+    }
+
+    fn finish_recovery(
+        &self,
+        username: &str,
+        recovery_token: &[u8],
+        client_registration_finish_result: &ClientRegistrationFinishResult<StandardCipherSuite>,
+        wrapped_key: Vec<u8>,
+        key_stretching: &KeyStretching,
+    ) -> Result<()> {
+        self.runtime.block_on(self.api.finish_recovery(
+            username,
+            recovery_token,
+            client_registration_finish_result,
+            wrapped_key,
+            key_stretching,
+        ))
+    }
+
+    fn get_vault(&self) -> Result<(Vec<u8>, Option<String>)> {
+        self.runtime.block_on(self.api.get_vault())
+    }
 
-        self.create_session(&client_login_finish_result.session_key)?;
+    fn save_vault(&self, vault: Vec<u8>, revision: Option<&str>) -> Result<Option<String>> {
+        self.runtime.block_on(self.api.save_vault(vault, revision))
+    }
 
-        Ok(())
+    fn get_records(&self) -> Result<(Vec<CryptedRecord>, Option<String>)> {
+        self.runtime.block_on(self.api.get_records())
     }
 
-    fn get_vault(&self) -> Result<Vec<u8>> {
-        let Some(vault) = self.vault_request(GET, None)? else {
-            return Err(VaultError::NotFound);
-        };
+    fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> Result<Option<String>> {
+        self.runtime.block_on(self.api.save_records(records, revision))
+    }
 
-        Ok(vault)
+    fn get_wrapped_key(&self) -> Result<Vec<u8>> {
+        self.runtime.block_on(self.api.get_wrapped_key())
     }
 
-    fn save_vault(&self, vault: Vec<u8>) -> Result<()> {
-        let _ = self.vault_request(POST, Some(vault))?;
+    fn save_wrapped_key(&self, wrapped_key: Vec<u8>) -> Result<()> {
+        self.runtime.block_on(self.api.save_wrapped_key(wrapped_key))
+    }
 
-        Ok(())
+    fn save_public_key(&self, public_key: Vec<u8>) -> Result<()> {
+        self.runtime.block_on(self.api.save_public_key(public_key))
+    }
+
+    fn get_public_key(&self, username: &str) -> Result<Vec<u8>> {
+        self.runtime.block_on(self.api.get_public_key(username))
+    }
+
+    fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> Result<()> {
+        self.runtime.block_on(self.api.send_share(recipient, sealed_entry))
+    }
+
+    fn get_shares(&self) -> Result<Vec<SealedShare>> {
+        self.runtime.block_on(self.api.get_shares())
+    }
+
+    fn delete_share(&self, id: u64) -> Result<()> {
+        self.runtime.block_on(self.api.delete_share(id))
     }
 
     fn is_logged_in(&self) -> bool {
-        match self.session {
-            Some(_) => true,
-            None => false,
-        }
+        self.api.is_logged_in()
+    }
+
+    fn get_server_identifier(&self) -> &str {
+        self.api.get_server_identifier()
     }
 }
@@ -1,159 +1,169 @@
 use app_core::{
-    ports::vault_manager::VaultManager,
-    vault::{crypted_vault::CryptedVault, key::Key, vault_error::*},
+    ports::vault_manager::{AsyncVaultManager, RewrapDataKey, VaultManager},
+    vault::{crypted_record::CryptedRecord, crypted_vault::CryptedVault, share::SealedShare, vault_error::VaultError},
 };
 use crypto_common::OutputSizeUser;
+use tokio::runtime::{Builder, Runtime};
 
-use crate::{Result, error_utils::to_exchange_failed_vault_error, opaque_api::Api};
-
-use opaque_ke::{
-    CipherSuite, ClientLogin, ClientLoginFinishParameters, ClientLoginFinishResult,
-    ClientRegistration, ClientRegistrationFinishParameters, ClientRegistrationFinishResult,
-    argon2::Argon2, rand::rngs::OsRng,
+use crate::{
+    Result, async_opaque_api::AsyncApi, async_opaque_vault_manager::AsyncOpaqueVaultManager,
+    key_stretching::KeyStretching,
 };
 
-// Error constants
-const CANNOT_SAVE_VAULT_IF_NOT_LOGGED_IN: &'static str =
-    "Cannot create a new vault if you are already logged in.";
-const CANNOT_CREATE_VAULT_IF_LOGGED_IN: &'static str =
-    "Cannot save a vault if you are not logged in.";
+use opaque_ke::{CipherSuite, argon2::Argon2};
+
+const RUNTIME_ERROR: &str = "The runtime of the blocking vault manager couldn't be built.";
 
 pub type ExportKeySize<CS> =
     <<<CS as CipherSuite>::OprfCs as voprf::CipherSuite>::Hash as OutputSizeUser>::OutputSize;
 
+/// Hash of the key exchange, its output is the size of the session key
+#[cfg(not(feature = "p256"))]
+pub type SessionHash = sha2::Sha512;
+#[cfg(feature = "p256")]
+pub type SessionHash = sha2::Sha256;
+
 /// Standard Cipher Suite for the vault-manager
 /// Using Ristretto255 as an Oprf and. Triple Diffie Hellman for key exchange algorithm and sha512 for hashing
-/// And Argon2 (default parameters, argon2id) as a key derivation
+/// And Argon2 (argon2id, parameters recorded per account) as a key derivation
 #[derive(Debug, Default)]
 pub struct StandardCipherSuite;
 
+#[cfg(not(feature = "p256"))]
 impl CipherSuite for StandardCipherSuite {
     type OprfCs = opaque_ke::Ristretto255;
-    type KeyExchange = opaque_ke::TripleDh<opaque_ke::Ristretto255, sha2::Sha512>;
+    type KeyExchange = opaque_ke::TripleDh<opaque_ke::Ristretto255, SessionHash>;
     type Ksf = Argon2<'static>;
 }
 
-#[derive(Debug, Default)]
-pub struct OpaqueVaultManager<T: Api> {
-    api: T,
+/// NIST P-256 with sha256 instead, for deployments that require FIPS approved curves
+#[cfg(feature = "p256")]
+impl CipherSuite for StandardCipherSuite {
+    type OprfCs = p256::NistP256;
+    type KeyExchange = opaque_ke::TripleDh<p256::NistP256, SessionHash>;
+    type Ksf = Argon2<'static>;
 }
 
-impl<T: Api> OpaqueVaultManager<T> {
-    pub fn new(api: T) -> Self {
-        Self { api }
-    }
-
-    fn register(
-        &self,
-        username: &str,
-        password: &str,
-    ) -> Result<ClientRegistrationFinishResult<StandardCipherSuite>> {
-        let mut client_rng = OsRng;
-
-        let client_registration_start_result =
-            ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
-                .map_err(to_exchange_failed_vault_error)?;
-
-        let server_registration_response = self
-            .api
-            .start_server_registration(username, &client_registration_start_result)?;
-
-        let client_registration_finish_result = client_registration_start_result
-            .state
-            .finish(
-                &mut client_rng,
-                password.as_bytes(),
-                server_registration_response,
-                ClientRegistrationFinishParameters::default(),
-            )
-            .map_err(to_exchange_failed_vault_error)?;
+/// Blocking wrapper of `AsyncOpaqueVaultManager`, each call runs to completion on the runtime it owns
+///
+/// Its calls block, they must not be made from a task of an async runtime.
+#[derive(Debug)]
+pub struct OpaqueVaultManager<T: AsyncApi> {
+    manager: AsyncOpaqueVaultManager<T>,
+    runtime: Runtime,
+}
 
-        self.api
-            .finish_server_registration(username, &client_registration_finish_result)?;
+impl<T: AsyncApi> OpaqueVaultManager<T> {
+    pub fn new(api: T) -> Result<Self> {
+        Self::with_key_stretching(api, KeyStretching::default())
+    }
 
-        Ok(client_registration_finish_result)
+    /// Fails if the runtime can't be built
+    pub fn with_key_stretching(api: T, key_stretching: KeyStretching) -> Result<Self> {
+        let runtime = Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .map_err(|error| VaultError::Internal(format!("{RUNTIME_ERROR} {error}")))?;
+
+        Ok(Self {
+            manager: AsyncOpaqueVaultManager::with_key_stretching(api, key_stretching),
+            runtime,
+        })
     }
+}
 
-    fn login(
-        &mut self,
-        username: &str,
-        password: &str,
-    ) -> Result<ClientLoginFinishResult<StandardCipherSuite>> {
-        let mut client_rng = OsRng;
+impl<T: AsyncApi + Send + Sync> VaultManager for OpaqueVaultManager<T> {
+    type KeySize = ExportKeySize<StandardCipherSuite>;
 
-        let client_login_start_result =
-            ClientLogin::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
-                .map_err(to_exchange_failed_vault_error)?;
+    fn create(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>> {
+        self.runtime.block_on(self.manager.create(username, password))
+    }
 
-        let server_login_response = self
-            .api
-            .start_server_login(username, &client_login_start_result)?;
+    fn retrieve(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>> {
+        self.runtime.block_on(self.manager.retrieve(username, password))
+    }
 
-        let client_login_finish_result = client_login_start_result
-            .state
-            .finish(
-                &mut client_rng,
-                password.as_bytes(),
-                server_login_response,
-                ClientLoginFinishParameters::default(),
-            )
-            .map_err(to_exchange_failed_vault_error)?;
+    fn reauthenticate(&mut self, username: &str, password: &str) -> Result<()> {
+        self.runtime.block_on(self.manager.reauthenticate(username, password))
+    }
 
-        self.api
-            .finish_server_login(username, &client_login_finish_result)?;
+    fn logout(&mut self) -> Result<()> {
+        self.runtime.block_on(self.manager.logout())
+    }
 
-        Ok(client_login_finish_result)
+    fn pin_server_key(&mut self, server_key: Option<Vec<u8>>) {
+        self.manager.pin_server_key(server_key)
     }
 
-    fn get_vault(&self) -> Result<Vec<u8>> {
-        Ok(self.api.get_vault()?)
+    fn require_bound_identifiers(&mut self, required: bool) {
+        self.manager.require_bound_identifiers(required)
     }
 
-    fn save_vault(&self, content: Vec<u8>) -> Result<()> {
-        Ok(self.api.save_vault(content)?)
+    fn identifiers_bound(&self) -> bool {
+        self.manager.identifiers_bound()
     }
-}
 
-impl<T: Api> VaultManager for OpaqueVaultManager<T> {
-    type KeySize = ExportKeySize<StandardCipherSuite>;
+    fn needs_reregistration(&self) -> bool {
+        self.manager.needs_reregistration()
+    }
 
-    fn create(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>> {
-        // For now there is no feature to handle multiple vault at the same time
-        if self.api.is_logged_in() {
-            return Err(VaultError::AlreadyLoggedIn(
-                CANNOT_CREATE_VAULT_IF_LOGGED_IN.to_string(),
-            ));
-        }
+    fn save(&self, vault: Vec<u8>, revision: Option<&str>) -> Result<Option<String>> {
+        self.runtime.block_on(self.manager.save(vault, revision))
+    }
 
-        let _ = self.register(username, password)?;
-        let client_login_finish_result = self.login(username, password)?;
+    fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> Result<Option<String>> {
+        self.runtime.block_on(self.manager.save_records(records, revision))
+    }
 
-        let export_key = Key::new(client_login_finish_result.export_key);
+    fn fetch_records(&self) -> Result<(Vec<CryptedRecord>, Option<String>)> {
+        self.runtime.block_on(self.manager.fetch_records())
+    }
 
-        let vault_content = self.get_vault()?;
+    fn save_wrapped_key(&self, wrapped_key: Vec<u8>) -> Result<()> {
+        self.runtime.block_on(self.manager.save_wrapped_key(wrapped_key))
+    }
 
-        Ok(CryptedVault::new(vault_content, export_key))
+    fn change_password(
+        &mut self,
+        username: &str,
+        old_password: &str,
+        new_password: &str,
+        wrap_data_key: &dyn Fn(&[u8]) -> Result<Vec<u8>>,
+    ) -> Result<()> {
+        self.runtime.block_on(self.manager.change_password_with(username, old_password, new_password, wrap_data_key))
     }
 
-    fn retrieve(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>> {
-        let client_login_finish_result = self.login(username, password)?;
+    fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> Result<()> {
+        self.runtime.block_on(self.manager.save_recovery_key(recovery_token, wrapped_key))
+    }
 
-        let vault_content = self.get_vault()?;
+    fn recover(
+        &mut self,
+        username: &str,
+        recovery_token: &[u8],
+        new_password: &str,
+        rewrap_data_key: &RewrapDataKey<'_>,
+    ) -> Result<()> {
+        self.runtime.block_on(self.manager.recover_with(username, recovery_token, new_password, rewrap_data_key))
+    }
 
-        let export_key = Key::new(client_login_finish_result.export_key);
+    fn save_public_key(&self, public_key: Vec<u8>) -> Result<()> {
+        self.runtime.block_on(self.manager.save_public_key(public_key))
+    }
 
-        Ok(CryptedVault::new(vault_content, export_key))
+    fn get_public_key(&self, username: &str) -> Result<Vec<u8>> {
+        self.runtime.block_on(self.manager.get_public_key(username))
     }
 
-    fn save(&self, vault: Vec<u8>) -> Result<()> {
-        if !self.api.is_logged_in() {
-            return Err(VaultError::NotLoggedIn(
-                CANNOT_SAVE_VAULT_IF_NOT_LOGGED_IN.to_string(),
-            ));
-        }
+    fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> Result<()> {
+        self.runtime.block_on(self.manager.send_share(recipient, sealed_entry))
+    }
 
-        self.save_vault(vault)?;
+    fn get_shares(&self) -> Result<Vec<SealedShare>> {
+        self.runtime.block_on(self.manager.get_shares())
+    }
 
-        Ok(())
+    fn delete_share(&self, id: u64) -> Result<()> {
+        self.runtime.block_on(self.manager.delete_share(id))
     }
 }
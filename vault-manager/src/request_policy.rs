@@ -0,0 +1,62 @@
+use std::time::Duration;
+
+use app_core::vault::vault_error::VaultError;
+use opaque_ke::rand::{RngCore, rngs::OsRng};
+
+/// Timeouts of every request and retries of the idempotent ones
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RequestPolicy {
+    pub connect_timeout: Duration,
+    // Applies to each read of the response, a stalled server fails once it elapses
+    pub read_timeout: Duration,
+    // Whole request, from the connection to the last byte of the body, a server trickling its answer fails once it elapses
+    pub timeout: Duration,
+    pub max_retries: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RequestPolicy {
+    fn default() -> Self {
+        Self {
+            connect_timeout: Duration::from_secs(5),
+            read_timeout: Duration::from_secs(30),
+            timeout: Duration::from_secs(60),
+            max_retries: 3,
+            base_delay: Duration::from_millis(250),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RequestPolicy {
+    /// Delay before the next attempt, none when the error isn't transient or the retries are exhausted
+    ///
+    /// The delay doubles with each attempt up to `max_delay`, a random half of it is kept so clients don't retry together.
+    /// A `Retry-After` of the server is waited as is, unless it is longer than `max_delay`.
+    pub fn retry_delay(&self, error: &VaultError, attempt: u32) -> Option<Duration> {
+        if attempt >= self.max_retries {
+            return None;
+        }
+
+        match error {
+            VaultError::RateLimited(Some(seconds)) => {
+                let retry_after = Duration::from_secs(*seconds);
+                (retry_after <= self.max_delay).then_some(retry_after)
+            }
+            VaultError::RateLimited(None) | VaultError::ServerUnavailable(_) => Some(self.backoff(attempt)),
+            _ => None,
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let delay = self
+            .base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay);
+
+        let jitter = OsRng.next_u64() % (delay.as_millis() as u64 / 2 + 1);
+
+        delay / 2 + Duration::from_millis(jitter)
+    }
+}
@@ -1,3 +1,8 @@
+mod async_opaque_api_tests;
+mod async_opaque_vault_manager_tests;
+mod cached_vault_manager_tests;
 mod http_utils_tests;
+mod key_stretching_tests;
 mod opaque_api_tests;
-mod opaque_vault_manager_tests;
\ No newline at end of file
+mod opaque_vault_manager_tests;
+mod request_policy_tests;
\ No newline at end of file
@@ -0,0 +1,105 @@
+use app_core::vault::vault_error::VaultError;
+use mockito::Server;
+
+use crate::{
+    async_opaque_api::{AsyncApi, AsyncOpaqueApi},
+    constants::{GET, OPAQUE_LOGIN_FINISH, POST, VAULT},
+    tests::opaque_api_tests::{fast_request_policy, login, signed_with},
+};
+
+const USERNAME: &str = "username";
+const X_SIGNATURE: &str = "X-Signature";
+
+#[tokio::test]
+async fn should_get_vault() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let expected = vec![42];
+
+    let mut server = Server::new_async().await;
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create_async().await;
+
+    let vault_mock = server
+        .mock(GET, VAULT)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &expected))
+        .with_body(&expected)
+        .create_async()
+        .await;
+
+    let mut async_opaque_api = AsyncOpaqueApi::new(server.url()).unwrap();
+
+    async_opaque_api.finish_server_login(USERNAME, &client_login_finish_result).await.unwrap();
+
+    // A-ct
+
+    let result = async_opaque_api.get_vault().await;
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().0, expected);
+    assert!(async_opaque_api.is_logged_in());
+
+    vault_mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn should_retry_get_vault_while_server_unavailable() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let expected = vec![42];
+
+    let mut server = Server::new_async().await;
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create_async().await;
+
+    let unavailable_mock = server.mock(GET, VAULT).with_status(503).expect(2).create_async().await;
+
+    let vault_mock = server
+        .mock(GET, VAULT)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &expected))
+        .with_body(&expected)
+        .create_async()
+        .await;
+
+    let mut async_opaque_api = AsyncOpaqueApi::with_request_policy(server.url(), fast_request_policy()).unwrap();
+
+    async_opaque_api.finish_server_login(USERNAME, &client_login_finish_result).await.unwrap();
+
+    // A-ct
+
+    let result = async_opaque_api.get_vault().await;
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().0, expected);
+
+    unavailable_mock.assert_async().await;
+    vault_mock.assert_async().await;
+}
+
+#[tokio::test]
+async fn should_not_get_vault_if_not_logged_in() {
+    // A-rrange
+
+    let server = Server::new_async().await;
+
+    let async_opaque_api = AsyncOpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = async_opaque_api.get_vault().await;
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::NotLoggedIn(_)) => {}
+        _ => panic!("Test result should be Err(VaultError::NotLoggedIn)."),
+    }
+}
@@ -0,0 +1,86 @@
+use app_core::{ports::vault_manager::AsyncVaultManager, vault::vault_error::VaultError};
+
+use crate::{
+    async_opaque_vault_manager::AsyncOpaqueVaultManager,
+    tests::opaque_vault_manager_tests::{MockOpaqueClient, register_user},
+};
+
+#[tokio::test]
+async fn should_create_vault() {
+    // A-rrange
+
+    let mut async_opaque_vault_manager = AsyncOpaqueVaultManager::new(MockOpaqueClient::new(false));
+
+    // A-ct
+
+    let vault = async_opaque_vault_manager.create("username", "password").await.unwrap();
+
+    // A-ssert
+
+    assert_eq!(vault.content, vec![42]);
+    assert!(vault.wrapped_key.is_empty());
+}
+
+#[tokio::test]
+async fn should_retrieve_vault() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    let mut async_opaque_vault_manager = AsyncOpaqueVaultManager::new(mock_opaque_api);
+
+    // A-ct
+
+    let vault = async_opaque_vault_manager.retrieve("username", "password").await.unwrap();
+
+    // A-ssert
+
+    assert_eq!(vault.content, vec![42]);
+    assert!(!async_opaque_vault_manager.needs_reregistration());
+}
+
+#[tokio::test]
+async fn should_not_retrieve_vault_with_wrong_password() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    let mut async_opaque_vault_manager = AsyncOpaqueVaultManager::new(mock_opaque_api);
+
+    // A-ct
+
+    let result = async_opaque_vault_manager.retrieve("username", "wrong password").await;
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::InvalidCredentials) => {}
+        _ => panic!("Test result should be: 'InvalidCredentials' error."),
+    }
+}
+
+#[tokio::test]
+async fn should_retrieve_vault_on_spawned_task() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    let mut async_opaque_vault_manager = AsyncOpaqueVaultManager::new(mock_opaque_api);
+
+    // A-ct
+
+    let vault = tokio::spawn(async move { async_opaque_vault_manager.retrieve("username", "password").await })
+        .await
+        .unwrap()
+        .unwrap();
+
+    // A-ssert
+
+    assert_eq!(vault.content, vec![42]);
+}
@@ -0,0 +1,476 @@
+use app_core::ports::vault_manager::{RewrapDataKey, VaultManager};
+use app_core::vault::crypted_record::CryptedRecord;
+use app_core::vault::crypted_vault::CryptedVault;
+use app_core::vault::key::Key;
+use app_core::vault::share::SealedShare;
+use app_core::vault::vault_error::VaultError;
+use crypto_common::generic_array::GenericArray;
+use crypto_common::generic_array::typenum::U32;
+use cryptography::cipher_dispatcher::NoKeyCipherDispatcher;
+use std::cell::{Cell, RefCell};
+use std::path::PathBuf;
+use std::rc::Rc;
+use tempfile::TempDir;
+
+use crate::cached_vault_manager::CachedVaultManager;
+use crate::key_stretching::KeyStretching;
+
+const USERNAME: &str = "username";
+const PASSWORD: &str = "password";
+const ENCRYPTION_KEY: [u8; 32] = [7; 32];
+const WRAPPED_KEY: &[u8] = &[1, 2, 3];
+const SERVER_KEY: &[u8] = &[4, 5, 6];
+
+#[test]
+fn should_unlock_cached_vault_offline() {
+    // A-rrange
+
+    let cache_dir = TempDir::new().unwrap();
+    let server = MockServer::new(vec![record("manifest", 1), record("entry-a", 2)]);
+
+    create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()))
+        .retrieve(USERNAME, PASSWORD)
+        .unwrap();
+    server.online.set(false);
+
+    let mut cached_vault_manager = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()));
+
+    // A-ct
+
+    let crypted_vault = cached_vault_manager.retrieve(USERNAME, PASSWORD).unwrap();
+
+    // A-ssert
+
+    assert!(cached_vault_manager.is_offline());
+    assert_eq!(crypted_vault.records, vec![record("manifest", 1), record("entry-a", 2)]);
+    assert_eq!(crypted_vault.wrapped_key, WRAPPED_KEY);
+    assert_eq!(crypted_vault.server_key, SERVER_KEY);
+    assert_eq!(crypted_vault.encryption_key.as_bytes(), ENCRYPTION_KEY);
+    assert_eq!(crypted_vault.revision, Some("1".to_string()));
+    assert!(crypted_vault.pending_records.is_empty());
+}
+
+#[test]
+fn should_not_unlock_cached_vault_offline_with_wrong_password() {
+    // A-rrange
+
+    let cache_dir = TempDir::new().unwrap();
+    let server = MockServer::new(vec![record("manifest", 1)]);
+
+    create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()))
+        .retrieve(USERNAME, PASSWORD)
+        .unwrap();
+    server.online.set(false);
+
+    let mut cached_vault_manager = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()));
+
+    // A-ct
+
+    let crypted_vault = cached_vault_manager.retrieve(USERNAME, "wrong password");
+
+    // A-ssert
+
+    assert!(matches!(crypted_vault, Err(VaultError::InvalidCredentials)));
+    assert!(!cached_vault_manager.is_offline());
+}
+
+#[cfg(unix)]
+#[test]
+fn should_store_cache_only_readable_by_owner() {
+    use std::{fs, os::unix::fs::PermissionsExt};
+
+    // A-rrange
+
+    let cache_dir = TempDir::new().unwrap();
+    let server = MockServer::new(vec![record("manifest", 1)]);
+
+    let mut cached_vault_manager = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()));
+    cached_vault_manager.retrieve(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    cached_vault_manager.save_records(vec![record("entry-a", 2)], Some("1")).unwrap();
+
+    // A-ssert
+
+    let cache_files: Vec<_> = fs::read_dir(cache_dir.path()).unwrap().map(|entry| entry.unwrap().path()).collect();
+
+    assert_eq!(cache_files, vec![cache_dir.path().join(format!("{}.cache", hex::encode(USERNAME)))]);
+    assert_eq!(fs::metadata(&cache_files[0]).unwrap().permissions().mode() & 0o777, 0o600);
+}
+
+#[test]
+fn should_not_unlock_offline_without_cache() {
+    // A-rrange
+
+    let cache_dir = TempDir::new().unwrap();
+    let server = MockServer::new(vec![record("manifest", 1)]);
+    server.online.set(false);
+
+    let mut cached_vault_manager = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()));
+    let mut uncached_vault_manager = create_cached_vault_manager(&server, None);
+
+    // A-ct
+
+    let cached_result = cached_vault_manager.retrieve(USERNAME, PASSWORD);
+    let uncached_result = uncached_vault_manager.retrieve(USERNAME, PASSWORD);
+
+    // A-ssert
+
+    assert!(matches!(cached_result, Err(VaultError::ServerUnavailable(_))));
+    assert!(matches!(uncached_result, Err(VaultError::ServerUnavailable(_))));
+}
+
+#[test]
+fn should_keep_records_saved_offline_as_pending() {
+    // A-rrange
+
+    let cache_dir = TempDir::new().unwrap();
+    let server = MockServer::new(vec![record("manifest", 1), record("entry-a", 2)]);
+
+    let mut cached_vault_manager = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()));
+    cached_vault_manager.retrieve(USERNAME, PASSWORD).unwrap();
+    server.online.set(false);
+
+    // A-ct
+
+    let first_save = cached_vault_manager.save_records(vec![record("entry-b", 3)], Some("1"));
+    let second_save = cached_vault_manager.save_records(
+        vec![record("manifest", 4), record("entry-b", 5), CryptedRecord::deleted("entry-a".to_string())],
+        Some("1"),
+    );
+
+    // A-ssert
+
+    assert!(matches!(first_save, Err(VaultError::SavedOffline)));
+    assert!(matches!(second_save, Err(VaultError::SavedOffline)));
+
+    let crypted_vault = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()))
+        .retrieve(USERNAME, PASSWORD)
+        .unwrap();
+
+    assert_eq!(crypted_vault.records, vec![record("manifest", 1), record("entry-a", 2)]);
+    assert_eq!(crypted_vault.revision, Some("1".to_string()));
+    assert_eq!(
+        crypted_vault.pending_records,
+        vec![record("manifest", 4), record("entry-b", 5), CryptedRecord::deleted("entry-a".to_string())]
+    );
+}
+
+#[test]
+fn should_return_records_saved_offline_on_top_of_cached_ones_once_online() {
+    // A-rrange
+
+    let cache_dir = TempDir::new().unwrap();
+    let server = MockServer::new(vec![record("manifest", 1)]);
+
+    let mut cached_vault_manager = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()));
+    cached_vault_manager.retrieve(USERNAME, PASSWORD).unwrap();
+    server.online.set(false);
+    let _ = cached_vault_manager.save_records(vec![record("manifest", 2)], Some("1"));
+
+    server.online.set(true);
+    server.records.replace(vec![record("manifest", 9)]);
+    server.revision.set(3);
+
+    // A-ct
+
+    let crypted_vault = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()))
+        .retrieve(USERNAME, PASSWORD)
+        .unwrap();
+
+    // A-ssert
+
+    assert_eq!(crypted_vault.records, vec![record("manifest", 1)]);
+    assert_eq!(crypted_vault.revision, Some("1".to_string()));
+    assert_eq!(crypted_vault.pending_records, vec![record("manifest", 2)]);
+}
+
+#[test]
+fn should_upload_records_saved_offline_once_server_is_reachable() {
+    // A-rrange
+
+    let cache_dir = TempDir::new().unwrap();
+    let server = MockServer::new(vec![record("manifest", 1), record("entry-a", 2)]);
+
+    create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()))
+        .retrieve(USERNAME, PASSWORD)
+        .unwrap();
+    server.online.set(false);
+
+    let mut cached_vault_manager = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()));
+    cached_vault_manager.retrieve(USERNAME, PASSWORD).unwrap();
+    let pending_records = vec![record("manifest", 3), CryptedRecord::deleted("entry-a".to_string())];
+    let _ = cached_vault_manager.save_records(pending_records.clone(), Some("1"));
+
+    server.online.set(true);
+    let retrieved = server.retrieved.get();
+
+    // A-ct
+
+    let revision = cached_vault_manager.save_records(pending_records, Some("1")).unwrap();
+
+    // A-ssert
+
+    assert_eq!(revision, Some("2".to_string()));
+    assert!(!cached_vault_manager.is_offline());
+    assert_eq!(server.retrieved.get(), retrieved);
+    assert_eq!(server.records.borrow().clone(), vec![record("manifest", 3)]);
+
+    let crypted_vault = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()))
+        .retrieve(USERNAME, PASSWORD)
+        .unwrap();
+
+    assert_eq!(crypted_vault.records, vec![record("manifest", 3)]);
+    assert_eq!(crypted_vault.revision, Some("2".to_string()));
+    assert!(crypted_vault.pending_records.is_empty());
+}
+
+#[test]
+fn should_unlock_cache_with_new_password_after_password_change() {
+    // A-rrange
+
+    let cache_dir = TempDir::new().unwrap();
+    let server = MockServer::new(vec![record("manifest", 1)]);
+    let new_password = "new password";
+
+    let mut cached_vault_manager = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()));
+    cached_vault_manager.retrieve(USERNAME, PASSWORD).unwrap();
+
+    // A-ct
+
+    cached_vault_manager
+        .change_password(USERNAME, PASSWORD, new_password, &|_| Ok(vec![8, 8]))
+        .unwrap();
+
+    // A-ssert
+
+    server.online.set(false);
+
+    let old_password_result =
+        create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf())).retrieve(USERNAME, PASSWORD);
+    let crypted_vault = create_cached_vault_manager(&server, Some(cache_dir.path().to_path_buf()))
+        .retrieve(USERNAME, new_password)
+        .unwrap();
+
+    assert!(matches!(old_password_result, Err(VaultError::InvalidCredentials)));
+    assert_eq!(crypted_vault.wrapped_key, vec![8, 8]);
+    assert_eq!(crypted_vault.encryption_key.as_bytes(), [new_password.len() as u8; 32]);
+}
+
+fn record(id: &str, content: u8) -> CryptedRecord {
+    CryptedRecord::new(id.to_string(), vec![content])
+}
+
+fn create_cached_vault_manager(
+    server: &MockServer,
+    cache_dir: Option<PathBuf>,
+) -> CachedVaultManager<MockVaultManager, NoKeyCipherDispatcher> {
+    // Smallest Argon2id parameters, the tests don't need the cache to resist guessing
+    CachedVaultManager::with_key_stretching(
+        MockVaultManager::new(server.clone()),
+        cache_dir,
+        KeyStretching::new(8, 1, 1),
+    )
+}
+
+/// State of the server shared by the managers of a test, which stand for successive runs of the app
+#[derive(Clone)]
+struct MockServer {
+    online: Rc<Cell<bool>>,
+    records: Rc<RefCell<Vec<CryptedRecord>>>,
+    revision: Rc<Cell<u64>>,
+    password: Rc<RefCell<String>>,
+    /// Vaults fetched, logging in again after an offline unlock must not fetch it
+    retrieved: Rc<Cell<usize>>,
+}
+
+impl MockServer {
+    fn new(records: Vec<CryptedRecord>) -> Self {
+        Self {
+            online: Rc::new(Cell::new(true)),
+            records: Rc::new(RefCell::new(records)),
+            revision: Rc::new(Cell::new(1)),
+            password: Rc::new(RefCell::new(PASSWORD.to_string())),
+            retrieved: Rc::new(Cell::new(0)),
+        }
+    }
+
+    fn check_online(&self) -> Result<(), VaultError> {
+        match self.online.get() {
+            true => Ok(()),
+            false => Err(VaultError::ServerUnavailable("Connection refused.".to_string())),
+        }
+    }
+}
+
+struct MockVaultManager {
+    server: MockServer,
+    logged_in: bool,
+}
+
+impl MockVaultManager {
+    fn new(server: MockServer) -> Self {
+        Self {
+            server,
+            logged_in: false,
+        }
+    }
+
+    fn check_session(&self) -> Result<(), VaultError> {
+        self.server.check_online()?;
+
+        match self.logged_in {
+            true => Ok(()),
+            false => Err(VaultError::NotLoggedIn("No session.".to_string())),
+        }
+    }
+}
+
+/// The encryption key is derived from the length of the password, enough to tell two passwords apart
+fn encryption_key(password: &str) -> Key<U32> {
+    match password == PASSWORD {
+        true => Key::new(GenericArray::clone_from_slice(&ENCRYPTION_KEY)),
+        false => Key::new(GenericArray::clone_from_slice(&[password.len() as u8; 32])),
+    }
+}
+
+impl VaultManager for MockVaultManager {
+    type KeySize = U32;
+
+    fn create(&mut self, _username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>, VaultError> {
+        self.server.check_online()?;
+        self.logged_in = true;
+
+        Ok(CryptedVault::new(vec![], vec![], vec![], SERVER_KEY.to_vec(), encryption_key(password), None, vec![]))
+    }
+
+    fn retrieve(&mut self, username: &str, password: &str) -> Result<CryptedVault<Self::KeySize>, VaultError> {
+        self.reauthenticate(username, password)?;
+        self.server.retrieved.set(self.server.retrieved.get() + 1);
+
+        Ok(CryptedVault::new(
+            vec![],
+            self.server.records.borrow().clone(),
+            WRAPPED_KEY.to_vec(),
+            SERVER_KEY.to_vec(),
+            encryption_key(password),
+            Some(self.server.revision.get().to_string()),
+            vec![],
+        ))
+    }
+
+    fn reauthenticate(&mut self, _username: &str, password: &str) -> Result<(), VaultError> {
+        self.server.check_online()?;
+
+        if *self.server.password.borrow() != password {
+            return Err(VaultError::InvalidCredentials);
+        }
+
+        self.logged_in = true;
+
+        Ok(())
+    }
+
+    fn logout(&mut self) -> Result<(), VaultError> {
+        self.logged_in = false;
+        Ok(())
+    }
+
+    fn pin_server_key(&mut self, _server_key: Option<Vec<u8>>) {}
+
+    fn require_bound_identifiers(&mut self, _required: bool) {}
+
+    fn identifiers_bound(&self) -> bool {
+        self.logged_in
+    }
+
+    fn needs_reregistration(&self) -> bool {
+        false
+    }
+
+    fn save(&self, _vault: Vec<u8>, _revision: Option<&str>) -> Result<Option<String>, VaultError> {
+        self.check_session()?;
+        Ok(None)
+    }
+
+    fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> Result<Option<String>, VaultError> {
+        self.check_session()?;
+
+        if revision != Some(self.server.revision.get().to_string().as_str()) {
+            return Err(VaultError::Conflict);
+        }
+
+        let mut server_records = self.server.records.borrow_mut();
+        server_records.retain(|record| !records.iter().any(|changed_record| changed_record.id == record.id));
+        server_records.extend(records.into_iter().filter(|record| !record.is_deleted()));
+
+        self.server.revision.set(self.server.revision.get() + 1);
+
+        Ok(Some(self.server.revision.get().to_string()))
+    }
+
+    fn fetch_records(&self) -> Result<(Vec<CryptedRecord>, Option<String>), VaultError> {
+        self.check_session()?;
+        Ok((self.server.records.borrow().clone(), Some(self.server.revision.get().to_string())))
+    }
+
+    fn save_wrapped_key(&self, _wrapped_key: Vec<u8>) -> Result<(), VaultError> {
+        self.check_session()
+    }
+
+    fn change_password(
+        &mut self,
+        _username: &str,
+        old_password: &str,
+        new_password: &str,
+        wrap_data_key: &dyn Fn(&[u8]) -> Result<Vec<u8>, VaultError>,
+    ) -> Result<(), VaultError> {
+        self.check_session()?;
+
+        if *self.server.password.borrow() != old_password {
+            return Err(VaultError::InvalidCredentials);
+        }
+
+        wrap_data_key(encryption_key(new_password).as_bytes())?;
+        self.server.password.replace(new_password.to_string());
+
+        Ok(())
+    }
+
+    fn save_recovery_key(&self, _recovery_token: Vec<u8>, _wrapped_key: Vec<u8>) -> Result<(), VaultError> {
+        self.check_session()
+    }
+
+    fn recover(
+        &mut self,
+        _username: &str,
+        _recovery_token: &[u8],
+        _new_password: &str,
+        _rewrap_data_key: &RewrapDataKey<'_>,
+    ) -> Result<(), VaultError> {
+        self.server.check_online()
+    }
+
+    fn save_public_key(&self, _public_key: Vec<u8>) -> Result<(), VaultError> {
+        self.check_session()
+    }
+
+    fn get_public_key(&self, _username: &str) -> Result<Vec<u8>, VaultError> {
+        self.check_session()?;
+        Ok(vec![])
+    }
+
+    fn send_share(&self, _recipient: &str, _sealed_entry: Vec<u8>) -> Result<(), VaultError> {
+        self.check_session()
+    }
+
+    fn get_shares(&self) -> Result<Vec<SealedShare>, VaultError> {
+        self.check_session()?;
+        Ok(vec![])
+    }
+
+    fn delete_share(&self, _id: u64) -> Result<(), VaultError> {
+        self.check_session()
+    }
+}
@@ -1,13 +1,20 @@
 use crate::http_utils::*;
+use app_core::vault::vault_error::VaultError;
 use hmac::Mac;
+use sha2::{Digest, Sha512};
 use mock_instant::global::{UNIX_EPOCH, MockClock, SystemTime};
-use reqwest::header::HOST;
+use reqwest::{
+    StatusCode,
+    header::{ETAG, HOST, HeaderMap, HeaderValue, IF_MATCH, RETRY_AFTER},
+};
 use std::time::Duration;
 
 const CONTENT_TYPE: &'static str = "Content-Type";
 const X_TIMESTAMP: &'static str = "X-Timestamp";
 const X_SIGNATURE: &'static str = "X-Signature";
 const X_USERNAME: &'static str = "X-Username";
+const X_NONCE: &str = "X-Nonce";
+const X_SESSION_LIFETIME: &str = "X-Session-Lifetime";
 
 #[test]
 fn opaque_headers_should_contain_content_type_and_username() {
@@ -47,8 +54,7 @@ fn vault_headers_should_contain_timestamp_signature_as_sensitive() {
     let verb = "GET";
     let server_url = "http://localhost";
     let path = "/test";
-
-    let uri = format!("{}{}", server_url, path);
+    let body = [42];
 
     let duration = Duration::from_millis(1763127134822);
     MockClock::set_system_time(duration);
@@ -57,18 +63,26 @@ fn vault_headers_should_contain_timestamp_signature_as_sensitive() {
         .as_secs()
         .to_string();
 
-    let raw_signature = format!("{}|{}|{}", verb, uri, timestamp);
+    // A-ct
 
-    let mut mac = HmacSha512::new_from_slice(session_key).unwrap();
-    mac.update(raw_signature.as_bytes());
+    let (headers, nonce) = get_vault_request_headers(session_key, verb, server_url, path, &body).unwrap();
 
-    let signature = hex::encode(mac.finalize().into_bytes().to_vec());
+    // A-ssert
 
-    // A-ct
+    let raw_signature = format!(
+        "ferris-vault-v1-request\n{}\n{}\n{}\n{}\n{}\n{}",
+        verb,
+        path,
+        hex::encode(Sha512::digest(body)),
+        content_type_value,
+        timestamp,
+        nonce
+    );
 
-    let headers = get_vault_request_headers(session_key, verb, server_url, path).unwrap();
+    let mut mac = HmacSha512::new_from_slice(session_key).unwrap();
+    mac.update(raw_signature.as_bytes());
 
-    // A-ssert
+    let signature = hex::encode(mac.finalize().into_bytes());
 
     assert!(headers.contains_key(CONTENT_TYPE));
     assert_eq!(headers.get(CONTENT_TYPE).unwrap(), content_type_value);
@@ -84,5 +98,283 @@ fn vault_headers_should_contain_timestamp_signature_as_sensitive() {
     assert_eq!(headers.get(X_TIMESTAMP).unwrap(), &timestamp);
     assert!(headers.get(X_TIMESTAMP).unwrap().is_sensitive());
 
-    assert_eq!(headers.len(), 4);
+    assert_eq!(headers.get(X_NONCE).unwrap(), &nonce);
+    assert_eq!(nonce.len(), 32);
+
+    assert_eq!(headers.len(), 5);
+}
+
+#[test]
+fn vault_headers_should_use_a_new_nonce_for_each_request() {
+
+    // A-rrange
+
+    let session_key = "session_key".as_bytes();
+
+    MockClock::set_system_time(Duration::from_millis(1763127134822));
+
+    // A-ct
+
+    let (first_headers, first_nonce) = get_vault_request_headers(session_key, "GET", "http://localhost", "/test", &[]).unwrap();
+    let (second_headers, second_nonce) = get_vault_request_headers(session_key, "GET", "http://localhost", "/test", &[]).unwrap();
+
+    // A-ssert
+
+    assert_ne!(first_nonce, second_nonce);
+    assert_ne!(first_headers.get(X_SIGNATURE), second_headers.get(X_SIGNATURE));
+}
+
+#[test]
+fn canonical_request_should_change_with_body() {
+
+    // A-rrange
+
+    let timestamp = "1763127134";
+    let nonce = "00112233445566778899aabbccddeeff";
+
+    // A-ct
+
+    let canonical_request = get_canonical_request("POST", "/vault", &[42], timestamp, nonce);
+    let other_canonical_request = get_canonical_request("POST", "/vault", &[41], timestamp, nonce);
+
+    // A-ssert
+
+    assert_ne!(canonical_request, other_canonical_request);
+}
+
+#[test]
+fn should_verify_response_signature() {
+
+    // A-rrange
+
+    let session_key = "session_key".as_bytes();
+    let nonce = "00112233445566778899aabbccddeeff";
+    let body = [42];
+
+    let headers = signed_response_headers(session_key, nonce, "200\n\n", &body);
+
+    // A-ct
+
+    let result = verify_response_signature(session_key, nonce, StatusCode::OK, &body, &headers);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_not_verify_response_signed_for_another_request() {
+
+    // A-rrange
+
+    let session_key = "session_key".as_bytes();
+    let body = [42];
+
+    let headers = signed_response_headers(session_key, "00112233445566778899aabbccddeeff", "200\n\n", &body);
+
+    // A-ct
+
+    let result = verify_response_signature(session_key, "ffeeddccbbaa99887766554433221100", StatusCode::OK, &body, &headers);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "The response signature doesn't match."),
+        _ => panic!("Test result should be: 'ExchangeFailed' error."),
+    }
+}
+
+#[test]
+fn should_verify_signed_error_response() {
+
+    // A-rrange
+
+    let session_key = "session_key".as_bytes();
+    let nonce = "00112233445566778899aabbccddeeff";
+    let body = b"conflict";
+
+    let mut headers = signed_response_headers(session_key, nonce, "412\n\"7\"\n", body);
+    headers.insert(ETAG, HeaderValue::from_static("\"7\""));
+
+    // A-ct
+
+    let result = verify_response_signature(session_key, nonce, StatusCode::PRECONDITION_FAILED, body, &headers);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_not_verify_response_with_another_status() {
+
+    // A-rrange
+
+    let session_key = "session_key".as_bytes();
+    let nonce = "00112233445566778899aabbccddeeff";
+    let body = [42];
+
+    let headers = signed_response_headers(session_key, nonce, "200\n\n", &body);
+
+    // A-ct
+
+    let result = verify_response_signature(session_key, nonce, StatusCode::UNAUTHORIZED, &body, &headers);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "The response signature doesn't match."),
+        _ => panic!("Test result should be: 'ExchangeFailed' error."),
+    }
+}
+
+#[test]
+fn should_not_verify_response_with_rewritten_headers() {
+
+    // A-rrange
+
+    let session_key = "session_key".as_bytes();
+    let nonce = "00112233445566778899aabbccddeeff";
+    let body = [42];
+
+    let mut etag_headers = signed_response_headers(session_key, nonce, "200\n\"7\"\n", &body);
+    etag_headers.insert(ETAG, HeaderValue::from_static("\"8\""));
+
+    let mut lifetime_headers = signed_response_headers(session_key, nonce, "200\n\n900", &body);
+    lifetime_headers.insert(X_SESSION_LIFETIME, HeaderValue::from_static("0"));
+
+    // A-ct
+
+    let etag_result = verify_response_signature(session_key, nonce, StatusCode::OK, &body, &etag_headers);
+    let lifetime_result = verify_response_signature(session_key, nonce, StatusCode::OK, &body, &lifetime_headers);
+
+    // A-ssert
+
+    assert!(matches!(etag_result, Err(VaultError::ExchangeFailed(_))));
+    assert!(matches!(lifetime_result, Err(VaultError::ExchangeFailed(_))));
+}
+
+#[test]
+fn should_not_verify_response_signed_with_another_key() {
+
+    // A-rrange
+
+    let nonce = "00112233445566778899aabbccddeeff";
+    let body = [42];
+
+    let headers = signed_response_headers("other_key".as_bytes(), nonce, "200\n\n", &body);
+
+    // A-ct
+
+    let result = verify_response_signature("session_key".as_bytes(), nonce, StatusCode::OK, &body, &headers);
+
+    // A-ssert
+
+    assert!(matches!(result, Err(VaultError::ExchangeFailed(_))));
+}
+
+#[test]
+fn should_not_verify_unsigned_response() {
+
+    // A-rrange
+
+    let headers = HeaderMap::new();
+
+    // A-ct
+
+    let result = verify_response_signature("session_key".as_bytes(), "00112233445566778899aabbccddeeff", StatusCode::OK, &[42], &headers);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "The response isn't signed."),
+        _ => panic!("Test result should be: 'ExchangeFailed' error."),
+    }
+}
+
+#[test]
+fn should_get_retry_after_in_seconds() {
+
+    // A-rrange
+
+    MockClock::set_system_time(Duration::from_secs(1445412360));
+
+    let mut past_headers = HeaderMap::new();
+    past_headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:00:00 GMT"));
+
+    let mut headers = HeaderMap::new();
+    headers.insert(RETRY_AFTER, HeaderValue::from_static("120"));
+
+    let mut dated_headers = HeaderMap::new();
+    dated_headers.insert(RETRY_AFTER, HeaderValue::from_static("Wed, 21 Oct 2015 07:28:00 GMT"));
+
+    // A-ct
+
+    let retry_after = get_retry_after(&headers);
+    let dated_retry_after = get_retry_after(&dated_headers);
+    let past_retry_after = get_retry_after(&past_headers);
+
+    // A-ssert
+
+    assert_eq!(retry_after, Some(120));
+    assert_eq!(dated_retry_after, Some(120));
+    assert_eq!(past_retry_after, Some(0));
+    assert_eq!(get_retry_after(&HeaderMap::new()), None);
+}
+
+#[test]
+fn should_get_session_lifetime_in_seconds() {
+
+    // A-rrange
+
+    let mut headers = HeaderMap::new();
+    headers.insert(X_SESSION_LIFETIME, HeaderValue::from_static("900"));
+
+    let mut malformed_headers = HeaderMap::new();
+    malformed_headers.insert(X_SESSION_LIFETIME, HeaderValue::from_static("15 minutes"));
+
+    // A-ct
+
+    let session_lifetime = get_session_lifetime(&headers);
+    let malformed_session_lifetime = get_session_lifetime(&malformed_headers);
+
+    // A-ssert
+
+    assert_eq!(session_lifetime, Some(900));
+    assert_eq!(malformed_session_lifetime, None);
+    assert_eq!(get_session_lifetime(&HeaderMap::new()), None);
+}
+
+#[test]
+fn should_send_revision_as_if_match_and_read_it_from_etag() {
+
+    // A-rrange
+
+    let mut headers = HeaderMap::new();
+
+    let mut response_headers = HeaderMap::new();
+    response_headers.insert(ETAG, HeaderValue::from_static("\"7\""));
+
+    // A-ct
+
+    let result = add_if_match_header(&mut headers, "\"6\"");
+    let revision = get_revision(&response_headers);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(headers.get(IF_MATCH).unwrap(), "\"6\"");
+    assert_eq!(revision.as_deref(), Some("\"7\""));
+    assert_eq!(get_revision(&HeaderMap::new()), None);
+}
+
+/// The signed fields are the status, the `ETag` and the session lifetime, one per line
+fn signed_response_headers(session_key: &[u8], nonce: &str, signed_fields: &str, body: &[u8]) -> HeaderMap {
+    let mut mac = HmacSha512::new_from_slice(session_key).unwrap();
+    mac.update(format!("ferris-vault-v2-response\n{}\n{}\n{}", nonce, signed_fields, hex::encode(Sha512::digest(body))).as_bytes());
+
+    let mut headers = HeaderMap::new();
+    headers.insert(X_SIGNATURE, HeaderValue::from_str(&hex::encode(mac.finalize().into_bytes())).unwrap());
+
+    headers
 }
\ No newline at end of file
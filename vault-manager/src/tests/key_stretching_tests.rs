@@ -0,0 +1,66 @@
+use crate::key_stretching::KeyStretching;
+
+#[test]
+fn should_decode_encoded_key_stretching() {
+    // A-rrange
+
+    let key_stretching = KeyStretching::new(65536, 3, 4);
+
+    // A-ct
+
+    let encoded = key_stretching.encode();
+
+    // A-ssert
+
+    assert_eq!(encoded, "m=65536,t=3,p=4");
+    assert_eq!(KeyStretching::decode(&encoded), Some(key_stretching));
+}
+
+#[test]
+fn should_not_decode_malformed_key_stretching() {
+    // A-rrange
+
+    let malformed = ["m=65536,t=3", "t=3,m=65536,p=4", "m=65536,t=3,p=4,x=1", "m=-1,t=3,p=4", ""];
+
+    // A-ct
+
+    let decoded: Vec<_> = malformed.iter().map(|value| KeyStretching::decode(value)).collect();
+
+    // A-ssert
+
+    assert!(decoded.iter().all(Option::is_none));
+}
+
+#[test]
+fn should_be_weaker_with_less_memory_or_iterations() {
+    // A-rrange
+
+    let key_stretching = KeyStretching::new(65536, 3, 4);
+
+    // A-ct
+
+    let less_memory = KeyStretching::new(32768, 3, 4).is_weaker_than(&key_stretching);
+    let fewer_iterations = KeyStretching::new(65536, 2, 4).is_weaker_than(&key_stretching);
+    let less_parallelism = KeyStretching::new(65536, 3, 1).is_weaker_than(&key_stretching);
+
+    // A-ssert
+
+    assert!(less_memory);
+    assert!(fewer_iterations);
+    assert!(!less_parallelism);
+}
+
+#[test]
+fn should_use_argon2_default_parameters_by_default() {
+    // A-rrange
+
+    let key_stretching = KeyStretching::default();
+
+    // A-ct
+
+    let encoded = key_stretching.encode();
+
+    // A-ssert
+
+    assert_eq!(encoded, "m=19456,t=2,p=1");
+}
@@ -1,5 +1,14 @@
-use app_core::{vault::vault_error::VaultError};
-use mockito::Server;
+use std::{
+    sync::{Arc, Mutex},
+    thread,
+    time::{Duration, Instant},
+};
+
+use app_core::vault::{crypted_record::CryptedRecord, share::SharedEntry, vault_error::VaultError};
+use cryptography::sharing;
+use hmac::Mac;
+use mockito::{Request, Server};
+use reqwest::{StatusCode, header::{HeaderMap, HeaderValue}};
 use opaque_ke::{
     ClientLogin, ClientLoginFinishParameters, ClientLoginFinishResult, ClientLoginStartResult,
     ClientRegistration, ClientRegistrationFinishParameters, ClientRegistrationFinishResult,
@@ -9,13 +18,30 @@ use opaque_ke::{
 };
 
 use crate::{
-    constants::{GET, OPAQUE_LOGIN_FINISH, OPAQUE_LOGIN_START, OPAQUE_REGISTRATION_FINISH, OPAQUE_REGISTRATION_START, POST, VAULT},
+    constants::{
+        GET, OPAQUE_LOGIN_FINISH, OPAQUE_LOGIN_START, OPAQUE_LOGOUT, OPAQUE_PASSWORD_CHANGE_FINISH, OPAQUE_PASSWORD_CHANGE_START,
+        OPAQUE_RECOVERY_FINISH, OPAQUE_RECOVERY_START, OPAQUE_REGISTRATION_FINISH, OPAQUE_REGISTRATION_START, POST,
+        SHARING_INBOX, SHARING_INBOX_DELETE, SHARING_KEY, SHARING_KEY_LOOKUP, VAULT, VAULT_KEY, VAULT_RECORDS,
+        VAULT_RECOVERY,
+    },
+    http_utils::{HmacSha512, get_canonical_request, get_canonical_response},
+    key_stretching::KeyStretching,
     opaque_api::{Api, OpaqueApi},
     opaque_vault_manager::StandardCipherSuite,
+    request_policy::RequestPolicy,
 };
 
 const USERNAME: &'static str = "username";
 const PASSWORD: &'static str = "password";
+const NEW_PASSWORD: &str = "new password";
+const RECOVERY_TOKEN: &[u8] = &[9; 32];
+const X_SIGNATURE: &str = "X-Signature";
+const X_NONCE: &str = "X-Nonce";
+const X_TIMESTAMP: &str = "X-Timestamp";
+const X_KEY_STRETCHING: &str = "X-Key-Stretching";
+const X_SESSION_LIFETIME: &str = "X-Session-Lifetime";
+const IF_MATCH: &str = "If-Match";
+const ETAG: &str = "ETag";
 
 #[test]
 fn should_start_server_registration() {
@@ -45,7 +71,7 @@ fn should_start_server_registration() {
         .with_body(server_registration_start_result_bytes)
         .create();
 
-    let opaque_api = OpaqueApi::new(server.url());
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
 
     // A-ct
 
@@ -88,15 +114,19 @@ fn should_finish_server_registration() {
 
     let mock = server
         .mock(POST, OPAQUE_REGISTRATION_FINISH)
+        .match_header(X_KEY_STRETCHING, "m=19456,t=2,p=1")
         .with_status(200)
         .create();
 
-    let opaque_api = OpaqueApi::new(server.url());
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
 
     // A-ct
 
-    let result =
-        opaque_api.finish_server_registration(USERNAME, &client_registration_finish_result);
+    let result = opaque_api.finish_server_registration(
+        USERNAME,
+        &client_registration_finish_result,
+        &KeyStretching::default(),
+    );
 
     // A-ssert
 
@@ -133,7 +163,7 @@ fn should_start_server_login() {
         .with_body(server_login_start_result_bytes)
         .create();
 
-    let opaque_api = OpaqueApi::new(server.url());
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
 
     // A-ct
 
@@ -142,12 +172,76 @@ fn should_start_server_login() {
     // A-ssert
 
     assert!(result.is_ok());
-    assert_eq!(result.unwrap(), expected_server_result);
+    // Nothing recorded for the account, it was registered with the default parameters
+    assert_eq!(result.unwrap(), (expected_server_result, KeyStretching::default()));
     assert!(!opaque_api.is_logged_in());
 
     mock.assert();
 }
 
+#[test]
+fn should_start_server_login_with_recorded_key_stretching() {
+    // A-rrange
+
+    let client_login_start_result = start_client_login(OsRng, PASSWORD);
+
+    let server_login_start_result_bytes = credential_response_bytes(&client_login_start_result);
+
+    let mut server = Server::new();
+
+    let mock = server
+        .mock(POST, OPAQUE_LOGIN_START)
+        .with_status(200)
+        .with_header(X_KEY_STRETCHING, "m=8192,t=1,p=1")
+        .with_body(server_login_start_result_bytes)
+        .create();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_server_login(USERNAME, &client_login_start_result);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().1, KeyStretching::new(8192, 1, 1));
+
+    mock.assert();
+}
+
+#[test]
+fn should_not_start_server_login_with_malformed_key_stretching() {
+    // A-rrange
+
+    let client_login_start_result = start_client_login(OsRng, PASSWORD);
+
+    let server_login_start_result_bytes = credential_response_bytes(&client_login_start_result);
+
+    let mut server = Server::new();
+
+    let mock = server
+        .mock(POST, OPAQUE_LOGIN_START)
+        .with_status(200)
+        .with_header(X_KEY_STRETCHING, "m=8192,t=1")
+        .with_body(server_login_start_result_bytes)
+        .create();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_server_login(USERNAME, &client_login_start_result);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "Malformed key stretching parameters."),
+        _ => panic!("Test result should be: 'ExchangeFailed' error."),
+    }
+
+    mock.assert();
+}
+
 #[test]
 fn should_finish_server_login() {
     // A-rrange
@@ -161,7 +255,7 @@ fn should_finish_server_login() {
         .with_status(200)
         .create();
 
-    let mut opaque_api = OpaqueApi::new(server.url());
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
 
     // A-ct
 
@@ -175,6 +269,174 @@ fn should_finish_server_login() {
     mock.assert();
 }
 
+#[test]
+fn should_logout() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+
+    let logout_mock = server
+        .mock(POST, OPAQUE_LOGOUT)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.logout();
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(!opaque_api.is_logged_in());
+
+    logout_mock.assert();
+}
+
+#[test]
+fn should_drop_session_on_logout_even_if_server_unavailable() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+    let _ = server.mock(POST, OPAQUE_LOGOUT).with_status(503).create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.logout();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ServerUnavailable(_)) => {}
+        _ => panic!("Test result should be Err(VaultError::ServerUnavailable)."),
+    }
+
+    assert!(!opaque_api.is_logged_in());
+}
+
+#[test]
+fn should_not_send_request_once_session_lifetime_elapsed() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .with_header(X_SESSION_LIFETIME, "0")
+        .with_header_from_request(X_SIGNATURE, signed_response(&client_login_finish_result.session_key, StatusCode::OK, session_lifetime("0"), &[]))
+        .create();
+
+    let vault_mock = server.mock(GET, VAULT).expect(0).create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_vault();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::SessionExpired) => {}
+        _ => panic!("Test result should be Err(VaultError::SessionExpired)."),
+    }
+
+    vault_mock.assert();
+}
+
+#[test]
+fn should_send_request_within_session_lifetime() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let expected = vec![42];
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .with_header(X_SESSION_LIFETIME, "900")
+        .with_header_from_request(X_SIGNATURE, signed_response(&client_login_finish_result.session_key, StatusCode::OK, session_lifetime("900"), &[]))
+        .create();
+
+    let _ = server
+        .mock(GET, VAULT)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &expected))
+        .with_body(&expected)
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_vault();
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().0, expected);
+}
+
+#[test]
+fn should_not_log_in_with_unsigned_session_lifetime() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .with_header(X_SESSION_LIFETIME, "0")
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.finish_server_login(USERNAME, &client_login_finish_result);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "The response isn't signed."),
+        _ => panic!("Test result should be Err(VaultError::ExchangeFailed)."),
+    }
+
+    assert!(!opaque_api.is_logged_in());
+}
+
 #[test]
 fn should_get_vault() {
 
@@ -194,11 +456,12 @@ fn should_get_vault() {
     let vault_mock = server
         .mock(GET, VAULT)
         .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &expected))
         .with_header("Content-Type", "application/octet-stream")
         .with_body(&expected)
         .create();
 
-    let mut opaque_api = OpaqueApi::new(server.url());
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
 
     opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
 
@@ -212,8 +475,8 @@ fn should_get_vault() {
 
     let result = result.unwrap();
 
-    assert!(!result.is_empty());
-    assert_eq!(result, expected);
+    assert!(!result.0.is_empty());
+    assert_eq!(result.0, expected);
 
     vault_mock.assert();
 }
@@ -235,9 +498,10 @@ fn should_save_vault() {
     let vault_mock = server
         .mock(POST, VAULT)
         .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[]))
         .create();
 
-    let mut opaque_api = OpaqueApi::new(server.url());
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
 
     opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
 
@@ -245,7 +509,7 @@ fn should_save_vault() {
 
     // A-ct
 
-    let result = opaque_api.save_vault(vault);
+    let result = opaque_api.save_vault(vault, None);
 
     // A-ssert
 
@@ -255,73 +519,1589 @@ fn should_save_vault() {
 }
 
 #[test]
-fn should_be_exchange_failed_error() {
+fn should_sign_vault_request_with_its_body() {
 
     // A-rrange
 
-    let client_rng = OsRng;
+    let client_login_finish_result = login();
 
-    let client_registration_start_result = start_client_registration(client_rng, PASSWORD);
+    let vault = vec![42];
 
-    let server = Server::new();
+    let signed_request = Arc::new(Mutex::new(vec![]));
+    let captured_request = Arc::clone(&signed_request);
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let vault_mock = server
+        .mock(POST, VAULT)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[]))
+        .with_body_from_request(move |request| {
+            *captured_request.lock().unwrap() = [X_TIMESTAMP, X_NONCE, X_SIGNATURE]
+                .iter()
+                .map(|header| request.header(*header)[0].to_str().unwrap().to_string())
+                .collect();
+            vec![]
+        })
+        .create();
 
-    let opaque_api = OpaqueApi::new(server.url());
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
 
     // A-ct
 
-    let result = opaque_api.start_server_registration(USERNAME, &client_registration_start_result);
+    let result = opaque_api.save_vault(vault.clone(), None);
 
     // A-ssert
 
-    assert!(result.is_err());
-    
-    match result {
-        Err(VaultError::ExchangeFailed(_)) => assert!(true),
-        _ => panic!("Test result should be Err(VaultError::ExchangeFailed).")
-    }
-}
+    assert!(result.is_ok());
 
-fn start_client_registration(
-    mut client_rng: OsRng,
-    password: &str,
-) -> ClientRegistrationStartResult<StandardCipherSuite> {
-    ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes()).unwrap()
-}
+    let signed_request = signed_request.lock().unwrap().clone();
+    let (timestamp, nonce, signature) = (&signed_request[0], &signed_request[1], &signed_request[2]);
 
-fn start_server_registration(
-    username: &str,
-    server_setup: &ServerSetup<StandardCipherSuite>,
-    client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
-) -> ServerRegistrationStartResult<StandardCipherSuite> {
-    ServerRegistration::<StandardCipherSuite>::start(
-        server_setup,
-        client_registration_start_result.message.clone(),
-        username.as_bytes(),
-    )
-    .unwrap()
-}
+    let mut mac = HmacSha512::new_from_slice(&client_login_finish_result.session_key).unwrap();
+    mac.update(get_canonical_request(POST, VAULT, &vault, timestamp, nonce).as_bytes());
 
-fn finish_client_registration(
-    client_registration_start_result: ClientRegistrationStartResult<StandardCipherSuite>,
-    mut client_rng: OsRng,
-    password: &str,
-    server_registration_response: RegistrationResponse<StandardCipherSuite>,
-) -> ClientRegistrationFinishResult<StandardCipherSuite> {
-    client_registration_start_result
-        .state
-        .finish(
-            &mut client_rng,
-            password.as_bytes(),
-            server_registration_response,
-            ClientRegistrationFinishParameters::default(),
-        )
-        .unwrap()
+    assert_eq!(signature, &hex::encode(mac.finalize().into_bytes()));
+
+    vault_mock.assert();
 }
 
-fn finish_server_registration(
-    client_registration_finish_result: ClientRegistrationFinishResult<StandardCipherSuite>,
-) -> ServerRegistration<StandardCipherSuite> {
-    ServerRegistration::<StandardCipherSuite>::finish(
+#[test]
+fn should_not_get_vault_with_unsigned_response() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let vault_mock = server
+        .mock(GET, VAULT)
+        .with_status(200)
+        .with_body([42])
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_vault();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "The response isn't signed."),
+        _ => panic!("Test result should be: 'ExchangeFailed' error."),
+    }
+
+    vault_mock.assert();
+}
+
+#[test]
+fn should_not_get_vault_with_altered_response() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let vault_mock = server
+        .mock(GET, VAULT)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[42]))
+        .with_body([41])
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_vault();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "The response signature doesn't match."),
+        _ => panic!("Test result should be: 'ExchangeFailed' error."),
+    }
+
+    vault_mock.assert();
+}
+
+#[test]
+fn should_get_records() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let records_mock = server
+        .mock(GET, VAULT_RECORDS)
+        .with_status(200)
+        .with_header(ETAG, "\"6\"")
+        .with_header_from_request(X_SIGNATURE, signed_response(&client_login_finish_result.session_key, StatusCode::OK, etag("\"6\""), RECORDS_BODY))
+        .with_body(RECORDS_BODY)
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_records();
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), (records(), Some("\"6\"".to_string())));
+
+    records_mock.assert();
+}
+
+#[test]
+fn should_not_get_records_with_malformed_response() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let records_mock = server
+        .mock(GET, VAULT_RECORDS)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[0, 0, 0, 5, 0, 0, 0, 9, 1]))
+        .with_body([0, 0, 0, 5, 0, 0, 0, 9, 1])
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_records();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "Malformed vault records response."),
+        _ => panic!("Test result should be: 'ExchangeFailed' error."),
+    }
+
+    records_mock.assert();
+}
+
+#[test]
+fn should_save_records() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let records_mock = server
+        .mock(POST, VAULT_RECORDS)
+        .match_body(RECORDS_BODY.to_vec())
+        .match_header("Authorization", mockito::Matcher::Any)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.save_records(records(), None);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    records_mock.assert();
+}
+
+#[test]
+fn should_save_records_with_revision_they_were_based_on() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let records_mock = server
+        .mock(POST, VAULT_RECORDS)
+        .match_header(IF_MATCH, "\"6\"")
+        .with_status(200)
+        .with_header(ETAG, "\"7\"")
+        .with_header_from_request(X_SIGNATURE, signed_response(&client_login_finish_result.session_key, StatusCode::OK, etag("\"7\""), &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.save_records(records(), Some("\"6\""));
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().as_deref(), Some("\"7\""));
+
+    records_mock.assert();
+}
+
+#[test]
+fn should_be_conflict_error_on_outdated_revision() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let records_mock = server
+        .mock(POST, VAULT_RECORDS)
+        .match_header(IF_MATCH, "\"6\"")
+        .with_status(412)
+        .with_header_from_request(X_SIGNATURE, signed_response(&client_login_finish_result.session_key, StatusCode::PRECONDITION_FAILED, HeaderMap::new(), &[]))
+        .expect(1)
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.save_records(records(), Some("\"6\""));
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::Conflict) => {}
+        _ => panic!("Test result should be Err(VaultError::Conflict)."),
+    }
+
+    records_mock.assert();
+}
+
+#[test]
+fn should_get_wrapped_key() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let expected = vec![42; 72];
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let wrapped_key_mock = server
+        .mock(GET, VAULT_KEY)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &expected))
+        .with_header("Content-Type", "application/octet-stream")
+        .with_body(&expected)
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_wrapped_key();
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(result.unwrap(), expected);
+
+    wrapped_key_mock.assert();
+}
+
+#[test]
+fn should_save_wrapped_key() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let wrapped_key = vec![42; 72];
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let wrapped_key_mock = server
+        .mock(POST, VAULT_KEY)
+        .match_body(wrapped_key.clone())
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.save_wrapped_key(wrapped_key);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    wrapped_key_mock.assert();
+}
+
+#[test]
+fn should_start_password_change() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_setup = ServerSetup::<StandardCipherSuite>::new(&mut server_rng);
+
+    let client_registration_start_result = start_client_registration(client_rng, NEW_PASSWORD);
+
+    let server_registration_start_result_bytes =
+        start_server_registration(USERNAME, &server_setup, &client_registration_start_result)
+            .message
+            .serialize();
+
+    let expected_server_response =
+        RegistrationResponse::deserialize(&server_registration_start_result_bytes).unwrap();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let password_change_mock = server
+        .mock(POST, OPAQUE_PASSWORD_CHANGE_START)
+        .match_body(client_registration_start_result.message.serialize().to_vec())
+        .match_header("Authorization", mockito::Matcher::Any)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &server_registration_start_result_bytes))
+        .with_header("Content-Type", "application/octet-stream")
+        .with_body(server_registration_start_result_bytes)
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_password_change(&client_registration_start_result);
+
+    // A-ssert
+
+    assert_eq!(result.unwrap(), expected_server_response);
+
+    password_change_mock.assert();
+}
+
+#[test]
+fn should_finish_password_change_with_wrapped_key() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_setup = ServerSetup::<StandardCipherSuite>::new(&mut server_rng);
+
+    let client_registration_start_result = start_client_registration(client_rng, NEW_PASSWORD);
+
+    let server_registration_response = RegistrationResponse::deserialize(
+        &start_server_registration(USERNAME, &server_setup, &client_registration_start_result)
+            .message
+            .serialize(),
+    )
+    .unwrap();
+
+    let client_registration_finish_result = finish_client_registration(
+        client_registration_start_result,
+        client_rng,
+        NEW_PASSWORD,
+        server_registration_response,
+    );
+
+    let wrapped_key = vec![42; 72];
+
+    let registration_upload = client_registration_finish_result.message.serialize().to_vec();
+
+    let mut expected_body = (registration_upload.len() as u32).to_be_bytes().to_vec();
+    expected_body.extend_from_slice(&registration_upload);
+    expected_body.extend_from_slice(&wrapped_key);
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let password_change_mock = server
+        .mock(POST, OPAQUE_PASSWORD_CHANGE_FINISH)
+        .match_body(expected_body)
+        .match_header(X_KEY_STRETCHING, "m=8192,t=1,p=1")
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.finish_password_change(
+        &client_registration_finish_result,
+        wrapped_key,
+        &KeyStretching::new(8192, 1, 1),
+    );
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    password_change_mock.assert();
+}
+
+#[test]
+fn should_not_start_password_change_if_not_logged_in() {
+
+    // A-rrange
+
+    let client_registration_start_result = start_client_registration(OsRng, NEW_PASSWORD);
+
+    let opaque_api = OpaqueApi::new("http://localhost".to_string()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_password_change(&client_registration_start_result);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::NotLoggedIn(_)) => {}
+        _ => panic!("Test result should be: 'NotLoggedIn' error."),
+    }
+}
+
+#[test]
+fn should_save_recovery_key() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let wrapped_key = vec![42; 72];
+
+    let mut expected_body = (RECOVERY_TOKEN.len() as u32).to_be_bytes().to_vec();
+    expected_body.extend_from_slice(RECOVERY_TOKEN);
+    expected_body.extend_from_slice(&wrapped_key);
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let recovery_mock = server
+        .mock(POST, VAULT_RECOVERY)
+        .match_body(expected_body)
+        .match_header("Authorization", mockito::Matcher::Any)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.save_recovery_key(RECOVERY_TOKEN.to_vec(), wrapped_key);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    recovery_mock.assert();
+}
+
+#[test]
+fn should_start_recovery() {
+
+    // A-rrange
+
+    let client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_setup = ServerSetup::<StandardCipherSuite>::new(&mut server_rng);
+
+    let client_registration_start_result = start_client_registration(client_rng, NEW_PASSWORD);
+
+    let server_registration_start_result_bytes =
+        start_server_registration(USERNAME, &server_setup, &client_registration_start_result)
+            .message
+            .serialize();
+
+    let expected_server_response =
+        RegistrationResponse::deserialize(&server_registration_start_result_bytes).unwrap();
+
+    let recovery_wrapped_key = vec![42; 72];
+
+    let mut response_body = (server_registration_start_result_bytes.len() as u32).to_be_bytes().to_vec();
+    response_body.extend_from_slice(&server_registration_start_result_bytes);
+    response_body.extend_from_slice(&recovery_wrapped_key);
+
+    let mut server = Server::new();
+
+    let recovery_mock = server
+        .mock(POST, OPAQUE_RECOVERY_START)
+        .match_body(client_registration_start_result.message.serialize().to_vec())
+        .match_header("X-Username", USERNAME)
+        .match_header("X-Recovery-Token", hex::encode(RECOVERY_TOKEN).as_str())
+        .with_status(200)
+        .with_header("Content-Type", "application/octet-stream")
+        .with_body(response_body)
+        .create();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_recovery(USERNAME, RECOVERY_TOKEN, &client_registration_start_result);
+
+    // A-ssert
+
+    let (server_response, wrapped_key) = result.unwrap();
+
+    assert_eq!(server_response, expected_server_response);
+    assert_eq!(wrapped_key, recovery_wrapped_key);
+
+    recovery_mock.assert();
+}
+
+#[test]
+fn should_not_start_recovery_with_malformed_response() {
+
+    // A-rrange
+
+    let client_registration_start_result = start_client_registration(OsRng, NEW_PASSWORD);
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_RECOVERY_START)
+        .with_status(200)
+        .with_body([0, 0, 1, 0, 42])
+        .create();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_recovery(USERNAME, RECOVERY_TOKEN, &client_registration_start_result);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "Malformed recovery response."),
+        _ => panic!("Test result should be: 'ExchangeFailed' error."),
+    }
+}
+
+#[test]
+fn should_finish_recovery_with_wrapped_key() {
+
+    // A-rrange
+
+    let client_rng = OsRng;
+    let mut server_rng = OsRng;
+    let server_setup = ServerSetup::<StandardCipherSuite>::new(&mut server_rng);
+
+    let client_registration_start_result = start_client_registration(client_rng, NEW_PASSWORD);
+
+    let server_registration_response = RegistrationResponse::deserialize(
+        &start_server_registration(USERNAME, &server_setup, &client_registration_start_result)
+            .message
+            .serialize(),
+    )
+    .unwrap();
+
+    let client_registration_finish_result = finish_client_registration(
+        client_registration_start_result,
+        client_rng,
+        NEW_PASSWORD,
+        server_registration_response,
+    );
+
+    let wrapped_key = vec![42; 72];
+
+    let registration_upload = client_registration_finish_result.message.serialize().to_vec();
+
+    let mut expected_body = (registration_upload.len() as u32).to_be_bytes().to_vec();
+    expected_body.extend_from_slice(&registration_upload);
+    expected_body.extend_from_slice(&wrapped_key);
+
+    let mut server = Server::new();
+
+    let recovery_mock = server
+        .mock(POST, OPAQUE_RECOVERY_FINISH)
+        .match_body(expected_body)
+        .match_header("X-Recovery-Token", hex::encode(RECOVERY_TOKEN).as_str())
+        .match_header(X_KEY_STRETCHING, "m=8192,t=1,p=1")
+        .with_status(200)
+        .create();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.finish_recovery(
+        USERNAME,
+        RECOVERY_TOKEN,
+        &client_registration_finish_result,
+        wrapped_key,
+        &KeyStretching::new(8192, 1, 1),
+    );
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    recovery_mock.assert();
+}
+
+#[test]
+fn should_save_public_key() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let public_key = vec![1; 32];
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let public_key_mock = server
+        .mock(POST, SHARING_KEY)
+        .match_body(public_key.clone())
+        .match_header("Authorization", mockito::Matcher::Any)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.save_public_key(public_key);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    public_key_mock.assert();
+}
+
+#[test]
+fn should_not_get_unpublished_public_key() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let lookup_mock = server
+        .mock(POST, SHARING_KEY_LOOKUP)
+        .match_body("bob")
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_public_key("bob");
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::NotFound) => {}
+        _ => panic!("Test result should be: 'NotFound' error."),
+    }
+
+    lookup_mock.assert();
+}
+
+#[test]
+fn should_not_get_shares_with_malformed_inbox() {
+
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .create();
+
+    let inbox_mock = server
+        .mock(GET, SHARING_INBOX)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[0, 0, 0, 42, 1]))
+        .with_body([0, 0, 0, 42, 1])
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_shares();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "Malformed share inbox response."),
+        _ => panic!("Test result should be: 'ExchangeFailed' error."),
+    }
+
+    inbox_mock.assert();
+}
+
+#[test]
+fn should_share_entry_between_two_users() {
+
+    // A-rrange
+
+    let bob_key_pair = sharing::generate_key_pair();
+
+    let entry = SharedEntry {
+        title: "title".to_string(),
+        username: "username".to_string(),
+        password: "password".to_string(),
+    };
+
+    let alice_login = login();
+    let bob_login = login();
+
+    let sent_body = Arc::new(Mutex::new(vec![]));
+    let captured_body = Arc::clone(&sent_body);
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_FINISH)
+        .with_status(200)
+        .expect(2)
+        .create();
+
+    let lookup_mock = server
+        .mock(POST, SHARING_KEY_LOOKUP)
+        .match_body("bob")
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&alice_login.session_key, &bob_key_pair.public_key))
+        .with_body(&bob_key_pair.public_key)
+        .create();
+
+    let send_mock = server
+        .mock(POST, SHARING_INBOX)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&alice_login.session_key, &[]))
+        .with_body_from_request(move |request| {
+            *captured_body.lock().unwrap() = request.body().unwrap().clone();
+            vec![]
+        })
+        .create();
+
+    let mut alice_api = OpaqueApi::new(server.url()).unwrap();
+    let mut bob_api = OpaqueApi::new(server.url()).unwrap();
+
+    alice_api.finish_server_login("alice", &alice_login).unwrap();
+    bob_api.finish_server_login("bob", &bob_login).unwrap();
+
+    // A-ct
+
+    let public_key = alice_api.get_public_key("bob").unwrap();
+    let sealed_entry = sharing::seal(&public_key, &entry.as_bytes().unwrap()).unwrap();
+
+    alice_api.send_share("bob", sealed_entry).unwrap();
+
+    // The server stores the share under the recipient and adds the sender and an id
+    let sent_body = sent_body.lock().unwrap().clone();
+    let (recipient, sealed_entry) = sent_body[4..].split_at(3);
+
+    let mut share = 7u64.to_be_bytes().to_vec();
+    share.extend_from_slice(&5u32.to_be_bytes());
+    share.extend_from_slice(b"alice");
+    share.extend_from_slice(sealed_entry);
+
+    let mut inbox = (share.len() as u32).to_be_bytes().to_vec();
+    inbox.extend_from_slice(&share);
+
+    let inbox_mock = server
+        .mock(GET, SHARING_INBOX)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&bob_login.session_key, &inbox))
+        .with_body(inbox)
+        .create();
+
+    let delete_mock = server
+        .mock(POST, SHARING_INBOX_DELETE)
+        .match_body(7u64.to_be_bytes().to_vec())
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&bob_login.session_key, &[]))
+        .create();
+
+    let shares = bob_api.get_shares().unwrap();
+    let opened_entry = sharing::open(&bob_key_pair.private_key, &shares[0].sealed_entry).unwrap();
+
+    bob_api.delete_share(shares[0].id).unwrap();
+
+    // A-ssert
+
+    assert_eq!(&sent_body[..4], &3u32.to_be_bytes());
+    assert_eq!(recipient, b"bob");
+    assert_eq!(shares.len(), 1);
+    assert_eq!(shares[0].id, 7);
+    assert_eq!(shares[0].sender, "alice");
+    assert_eq!(SharedEntry::decode(&opened_entry).unwrap(), entry);
+
+    lookup_mock.assert();
+    send_mock.assert();
+    inbox_mock.assert();
+    delete_mock.assert();
+}
+
+#[test]
+fn should_be_exchange_failed_error() {
+
+    // A-rrange
+
+    let client_rng = OsRng;
+
+    let client_registration_start_result = start_client_registration(client_rng, PASSWORD);
+
+    let server = Server::new();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_server_registration(USERNAME, &client_registration_start_result);
+
+    // A-ssert
+
+    assert!(result.is_err());
+    
+    match result {
+        Err(VaultError::ExchangeFailed(_)) => assert!(true),
+        _ => panic!("Test result should be Err(VaultError::ExchangeFailed).")
+    }
+}
+
+#[test]
+fn should_be_invalid_credentials_error_on_rejected_login() {
+    // A-rrange
+
+    let client_login_start_result = start_client_login(OsRng, PASSWORD);
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_START).with_status(401).create();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_server_login(USERNAME, &client_login_start_result);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::InvalidCredentials) => {}
+        _ => panic!("Test result should be Err(VaultError::InvalidCredentials)."),
+    }
+}
+
+#[test]
+fn should_be_user_exists_error_on_conflicting_registration() {
+    // A-rrange
+
+    let client_registration_start_result = start_client_registration(OsRng, PASSWORD);
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_REGISTRATION_START).with_status(409).create();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_server_registration(USERNAME, &client_registration_start_result);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::UserExists) => {}
+        _ => panic!("Test result should be Err(VaultError::UserExists)."),
+    }
+}
+
+#[test]
+fn should_be_session_expired_error_on_rejected_vault_request() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+    let _ = server
+        .mock(GET, VAULT)
+        .with_status(401)
+        .with_header_from_request(X_SIGNATURE, signed_response(&client_login_finish_result.session_key, StatusCode::UNAUTHORIZED, HeaderMap::new(), &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_vault();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::SessionExpired) => {}
+        _ => panic!("Test result should be Err(VaultError::SessionExpired)."),
+    }
+}
+
+#[test]
+fn should_be_conflict_error_on_conflicting_authenticated_request() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+    let _ = server
+        .mock(POST, VAULT_RECORDS)
+        .with_status(409)
+        .with_header_from_request(X_SIGNATURE, signed_response(&client_login_finish_result.session_key, StatusCode::CONFLICT, HeaderMap::new(), &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.save_records(records(), Some("\"6\""));
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::Conflict) => {}
+        _ => panic!("Test result should be Err(VaultError::Conflict)."),
+    }
+}
+
+#[test]
+fn should_not_trust_unsigned_error_body() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+    let _ = server.mock(GET, VAULT).with_status(401).with_body("session_expired").create();
+    let _ = server.mock(POST, VAULT_RECORDS).with_status(412).with_body("conflict").create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let vault_result = opaque_api.get_vault();
+    let records_result = opaque_api.save_records(records(), Some("\"6\""));
+
+    // A-ssert
+
+    match (vault_result, records_result) {
+        (Err(VaultError::ExchangeFailed(_)), Err(VaultError::ExchangeFailed(_))) => {}
+        _ => panic!("Test result should be Err(VaultError::ExchangeFailed)."),
+    }
+}
+
+#[test]
+fn should_not_trust_error_signed_with_another_status() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+    let _ = server
+        .mock(POST, VAULT_RECORDS)
+        .with_status(412)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &[]))
+        .create();
+
+    let mut opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.save_records(records(), Some("\"6\""));
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "The response signature doesn't match."),
+        _ => panic!("Test result should be Err(VaultError::ExchangeFailed)."),
+    }
+}
+
+#[test]
+fn should_be_rate_limited_error_with_retry_after() {
+    // A-rrange
+
+    let client_login_start_result = start_client_login(OsRng, PASSWORD);
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_LOGIN_START)
+        .with_status(429)
+        .with_header("Retry-After", "30")
+        .create();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_server_login(USERNAME, &client_login_start_result);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::RateLimited(retry_after)) => assert_eq!(retry_after, Some(30)),
+        _ => panic!("Test result should be Err(VaultError::RateLimited)."),
+    }
+}
+
+#[test]
+fn should_be_server_unavailable_error() {
+    // A-rrange
+
+    let client_login_start_result = start_client_login(OsRng, PASSWORD);
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_START).with_status(503).create();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_server_login(USERNAME, &client_login_start_result);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ServerUnavailable(_)) => {}
+        _ => panic!("Test result should be Err(VaultError::ServerUnavailable)."),
+    }
+}
+
+#[test]
+fn should_be_server_unavailable_error_if_unreachable() {
+    // A-rrange
+
+    let client_login_start_result = start_client_login(OsRng, PASSWORD);
+
+    // Nothing listens on the port 1
+    let opaque_api = OpaqueApi::new("http://127.0.0.1:1".to_string()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_server_login(USERNAME, &client_login_start_result);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ServerUnavailable(_)) => {}
+        _ => panic!("Test result should be Err(VaultError::ServerUnavailable)."),
+    }
+}
+
+#[test]
+fn should_retry_get_vault_while_server_unavailable() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let expected = vec![42];
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+
+    let unavailable_mock = server.mock(GET, VAULT).with_status(503).expect(2).create();
+
+    let vault_mock = server
+        .mock(GET, VAULT)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &expected))
+        .with_body(&expected)
+        .create();
+
+    let mut opaque_api = OpaqueApi::with_request_policy(server.url(), fast_request_policy()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_vault();
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().0, expected);
+
+    unavailable_mock.assert();
+    vault_mock.assert();
+}
+
+#[test]
+fn should_give_up_get_vault_once_retries_exhausted() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+
+    // The first attempt and the two retries
+    let unavailable_mock = server.mock(GET, VAULT).with_status(503).expect(3).create();
+
+    let mut opaque_api = OpaqueApi::with_request_policy(server.url(), fast_request_policy()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.get_vault();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ServerUnavailable(_)) => {}
+        _ => panic!("Test result should be Err(VaultError::ServerUnavailable)."),
+    }
+
+    unavailable_mock.assert();
+}
+
+#[test]
+fn should_not_retry_save_vault() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+
+    let unavailable_mock = server.mock(POST, VAULT).with_status(503).expect(1).create();
+
+    let mut opaque_api = OpaqueApi::with_request_policy(server.url(), fast_request_policy()).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.save_vault(vec![42], None);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ServerUnavailable(_)) => {}
+        _ => panic!("Test result should be Err(VaultError::ServerUnavailable)."),
+    }
+
+    unavailable_mock.assert();
+}
+
+#[test]
+fn should_wait_retry_after_before_getting_vault_again() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let expected = vec![42];
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+
+    let rate_limited_mock = server
+        .mock(GET, VAULT)
+        .with_status(429)
+        .with_header("Retry-After", "1")
+        .expect(1)
+        .create();
+
+    let vault_mock = server
+        .mock(GET, VAULT)
+        .with_status(200)
+        .with_header_from_request(X_SIGNATURE, signed_with(&client_login_finish_result.session_key, &expected))
+        .with_body(&expected)
+        .create();
+
+    let request_policy = RequestPolicy {
+        max_delay: Duration::from_secs(2),
+        ..fast_request_policy()
+    };
+
+    let mut opaque_api = OpaqueApi::with_request_policy(server.url(), request_policy).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    let started = Instant::now();
+
+    // A-ct
+
+    let result = opaque_api.get_vault();
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().0, expected);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+
+    rate_limited_mock.assert();
+    vault_mock.assert();
+}
+
+#[test]
+fn should_time_out_on_stalled_server() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+
+    let _ = server
+        .mock(GET, VAULT)
+        .with_status(200)
+        .with_body_from_request(|_| {
+            thread::sleep(Duration::from_secs(2));
+            vec![42]
+        })
+        .create();
+
+    let request_policy = RequestPolicy {
+        max_retries: 0,
+        ..fast_request_policy()
+    };
+
+    let mut opaque_api = OpaqueApi::with_request_policy(server.url(), request_policy).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    let started = Instant::now();
+
+    // A-ct
+
+    let result = opaque_api.get_vault();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ServerUnavailable(_)) => assert!(started.elapsed() < Duration::from_secs(2)),
+        _ => panic!("Test result should be Err(VaultError::ServerUnavailable)."),
+    }
+}
+
+#[test]
+fn should_time_out_on_trickling_server() {
+    // A-rrange
+
+    let client_login_finish_result = login();
+
+    let mut server = Server::new();
+
+    let _ = server.mock(POST, OPAQUE_LOGIN_FINISH).with_status(200).create();
+
+    let _ = server
+        .mock(GET, VAULT)
+        .with_status(200)
+        .with_chunked_body(|writer| {
+            for _ in 0..30 {
+                writer.write_all(&[42])?;
+                writer.flush()?;
+                thread::sleep(Duration::from_millis(100));
+            }
+            Ok(())
+        })
+        .create();
+
+    let request_policy = RequestPolicy {
+        max_retries: 0,
+        ..fast_request_policy()
+    };
+
+    let mut opaque_api = OpaqueApi::with_request_policy(server.url(), request_policy).unwrap();
+
+    opaque_api.finish_server_login(USERNAME, &client_login_finish_result).unwrap();
+
+    let started = Instant::now();
+
+    // A-ct
+
+    let result = opaque_api.get_vault();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ServerUnavailable(_)) => assert!(started.elapsed() < Duration::from_secs(2)),
+        _ => panic!("Test result should be Err(VaultError::ServerUnavailable)."),
+    }
+}
+
+#[test]
+fn should_prefer_error_code_of_the_body() {
+    // A-rrange
+
+    let client_registration_start_result = start_client_registration(OsRng, PASSWORD);
+
+    let mut server = Server::new();
+
+    let _ = server
+        .mock(POST, OPAQUE_REGISTRATION_START)
+        .with_status(400)
+        .with_body("user_exists")
+        .create();
+
+    let opaque_api = OpaqueApi::new(server.url()).unwrap();
+
+    // A-ct
+
+    let result = opaque_api.start_server_registration(USERNAME, &client_registration_start_result);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::UserExists) => {}
+        _ => panic!("Test result should be Err(VaultError::UserExists)."),
+    }
+}
+
+/// `entry/0` with a content and `entry/1` deleted
+const RECORDS_BODY: &[u8] = &[
+    0, 0, 0, 13, 0, 0, 0, 7, b'e', b'n', b't', b'r', b'y', b'/', b'0', 1, 2,
+    0, 0, 0, 11, 0, 0, 0, 7, b'e', b'n', b't', b'r', b'y', b'/', b'1',
+];
+
+fn records() -> Vec<CryptedRecord> {
+    vec![
+        CryptedRecord::new("entry/0".to_string(), vec![1, 2]),
+        CryptedRecord::deleted("entry/1".to_string()),
+    ]
+}
+
+fn etag(revision: &'static str) -> HeaderMap {
+    HeaderMap::from_iter([(reqwest::header::ETAG, HeaderValue::from_static(revision))])
+}
+
+fn session_lifetime(lifetime: &'static str) -> HeaderMap {
+    HeaderMap::from_iter([(reqwest::header::HeaderName::from_static("x-session-lifetime"), HeaderValue::from_static(lifetime))])
+}
+
+/// Short delays and timeouts so the retries don't slow the tests down
+pub(super) fn fast_request_policy() -> RequestPolicy {
+    RequestPolicy {
+        connect_timeout: Duration::from_secs(1),
+        read_timeout: Duration::from_millis(200),
+        timeout: Duration::from_secs(1),
+        max_retries: 2,
+        base_delay: Duration::from_millis(1),
+        max_delay: Duration::from_millis(10),
+    }
+}
+
+/// Signs a successful response like the server does, with the nonce of the request
+pub(super) fn signed_with(session_key: &[u8], body: &[u8]) -> impl Fn(&Request) -> String + Send + Sync + 'static {
+    signed_response(session_key, StatusCode::OK, HeaderMap::new(), body)
+}
+
+/// The headers are the signed ones the mock answers with, `ETag` or the session lifetime
+pub(super) fn signed_response(
+    session_key: &[u8],
+    status: StatusCode,
+    headers: HeaderMap,
+    body: &[u8],
+) -> impl Fn(&Request) -> String + Send + Sync + 'static {
+    let session_key = session_key.to_vec();
+    let body = body.to_vec();
+
+    move |request| {
+        let nonce = request.header(X_NONCE)[0].to_str().unwrap().to_string();
+
+        let mut mac = HmacSha512::new_from_slice(&session_key).unwrap();
+        mac.update(get_canonical_response(&nonce, status, &headers, &body).as_bytes());
+
+        hex::encode(mac.finalize().into_bytes())
+    }
+}
+
+fn start_client_registration(
+    mut client_rng: OsRng,
+    password: &str,
+) -> ClientRegistrationStartResult<StandardCipherSuite> {
+    ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes()).unwrap()
+}
+
+fn start_server_registration(
+    username: &str,
+    server_setup: &ServerSetup<StandardCipherSuite>,
+    client_registration_start_result: &ClientRegistrationStartResult<StandardCipherSuite>,
+) -> ServerRegistrationStartResult<StandardCipherSuite> {
+    ServerRegistration::<StandardCipherSuite>::start(
+        server_setup,
+        client_registration_start_result.message.clone(),
+        username.as_bytes(),
+    )
+    .unwrap()
+}
+
+fn finish_client_registration(
+    client_registration_start_result: ClientRegistrationStartResult<StandardCipherSuite>,
+    mut client_rng: OsRng,
+    password: &str,
+    server_registration_response: RegistrationResponse<StandardCipherSuite>,
+) -> ClientRegistrationFinishResult<StandardCipherSuite> {
+    client_registration_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            password.as_bytes(),
+            server_registration_response,
+            ClientRegistrationFinishParameters::default(),
+        )
+        .unwrap()
+}
+
+fn finish_server_registration(
+    client_registration_finish_result: ClientRegistrationFinishResult<StandardCipherSuite>,
+) -> ServerRegistration<StandardCipherSuite> {
+    ServerRegistration::<StandardCipherSuite>::finish(
         client_registration_finish_result.message.clone(),
     )
 }
@@ -387,7 +2167,20 @@ fn finish_client_login(
         .unwrap()
 }
 
-fn login() -> ClientLoginFinishResult<StandardCipherSuite> {
+/// Answer of the server to the login start of a registered user
+fn credential_response_bytes(client_login_start_result: &ClientLoginStartResult<StandardCipherSuite>) -> Vec<u8> {
+    let mut server_rng = OsRng;
+    let server_setup = ServerSetup::<StandardCipherSuite>::new(&mut server_rng);
+
+    let password_file = register_user(USERNAME, PASSWORD, OsRng, &server_setup);
+
+    start_server_login(server_rng, &server_setup, password_file, client_login_start_result, USERNAME)
+        .message
+        .serialize()
+        .to_vec()
+}
+
+pub(super) fn login() -> ClientLoginFinishResult<StandardCipherSuite> {
 
     let client_rng = OsRng;
     let mut server_rng = OsRng;
@@ -1,138 +1,903 @@
 use app_core::ports::vault_manager::VaultManager;
+use app_core::vault::crypted_record::CryptedRecord;
+use app_core::vault::share::SealedShare;
 use app_core::vault::vault_error::VaultError;
-use std::cell::RefCell;
-use std::collections::HashMap;
+use futures_executor::block_on;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, Mutex};
 
-use crate::opaque_api::Api;
+use crate::key_stretching::KeyStretching;
+use crate::async_opaque_api::AsyncApi;
 use crate::opaque_vault_manager::{OpaqueVaultManager, StandardCipherSuite};
 use opaque_ke::generic_array::GenericArray;
 use opaque_ke::{
-    ClientRegistration, ClientRegistrationFinishParameters, ServerLogin, ServerLoginParameters,
+    ClientRegistration, ClientRegistrationFinishParameters, Identifiers, ServerLogin, ServerLoginParameters,
     ServerLoginStartResult, ServerRegistration, ServerRegistrationLen, ServerSetup,
 };
 
 use opaque_ke::rand::rngs::OsRng;
 
+const RECOVERY_TOKEN: &[u8] = &[9; 32];
+const SERVER_URL: &str = "http://localhost:8080";
+
+#[test]
+fn should_create_vault() {
+    // A-rrange
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(false));
+    let username = "username";
+    let password = "password";
+
+    // A-ct
+
+    let vault = opaque_vault_manager.create(username, password).unwrap();
+
+    // A-ssert
+
+    assert_eq!(vault.content, vec![42]);
+    assert!(vault.wrapped_key.is_empty());
+    assert!(vault.encryption_key.as_bytes().len() != 0);
+}
+
+#[test]
+fn should_not_create_vault_if_logged_in() {
+    // A-rrange
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(true));
+    let username = "username";
+    let password = "password";
+
+    // A-ct
+
+    let vault = opaque_vault_manager.create(username, password);
+
+    // A-ssert
+
+    match vault {
+        Err(VaultError::AlreadyLoggedIn(error)) => {
+            assert_eq!(error, "Cannot save a vault if you are not logged in.")
+        }
+        _ => panic!("Test result should be: 'AlreadyLoggedIn' error."),
+    }
+}
+
+#[test]
+fn should_retrieve_vault() {
+    // A-rrange
+
+    let username = "username";
+    let password = "password";
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+
+    let mut client_rng = OsRng;
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
+            .unwrap();
+
+    let server_registration_response =
+        block_on(mock_opaque_api.start_server_registration(username, &client_registration_start_result)).unwrap();
+
+    let client_registration_finish_result = client_registration_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            password.as_bytes(),
+            server_registration_response,
+            ClientRegistrationFinishParameters::new(identifiers(username, SERVER_URL), None),
+        )
+        .unwrap();
+
+    block_on(mock_opaque_api.finish_server_registration(
+        username,
+        &client_registration_finish_result,
+        &KeyStretching::default(),
+    ))
+    .unwrap();
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // A-ct
+
+    let vault = opaque_vault_manager.retrieve(username, password).unwrap();
+
+    // A-ssert
+
+    assert_eq!(vault.content, vec![42]);
+    assert_eq!(vault.records, vec![CryptedRecord::new("manifest".to_string(), vec![3])]);
+    assert_eq!(vault.wrapped_key, vec![7]);
+    assert_eq!(vault.revision.as_deref(), Some("1"));
+    assert!(vault.encryption_key.as_bytes().len() != 0);
+}
+
+#[test]
+fn should_pin_server_key_on_registration() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+    let server_key = mock_opaque_api.server_state.lock().unwrap().server_key();
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // A-ct
+
+    let vault = opaque_vault_manager.create("username", "password").unwrap();
+
+    // A-ssert
+
+    assert_eq!(vault.server_key, server_key);
+}
+
+#[test]
+fn should_retrieve_vault_with_pinned_server_key() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+    let server_key = mock_opaque_api.server_state.lock().unwrap().server_key();
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+    opaque_vault_manager.pin_server_key(Some(server_key.clone()));
+
+    // A-ct
+
+    let vault = opaque_vault_manager.retrieve("username", "password").unwrap();
+
+    // A-ssert
+
+    assert_eq!(vault.server_key, server_key);
+}
+
+#[test]
+fn should_not_retrieve_vault_if_server_key_changed() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+    opaque_vault_manager.pin_server_key(Some(vec![1; 32]));
+
+    // A-ct
+
+    let result = opaque_vault_manager.retrieve("username", "password");
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ServerKeyChanged) => {}
+        _ => panic!("Test result should be: 'ServerKeyChanged' error."),
+    }
+
+    // The login was never finished with the server
+    assert!(server_state.lock().unwrap().server_login_start_result.is_some());
+}
+
+#[test]
+fn should_not_retrieve_vault_registered_for_another_server() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+
+    register_user_for(&mock_opaque_api, "username", "password", identifiers("username", "http://other:8080"));
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // A-ct
+
+    let result = opaque_vault_manager.retrieve("username", "password");
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::InvalidCredentials) => {}
+        _ => panic!("Test result should be: 'InvalidCredentials' error."),
+    }
+}
+
+#[test]
+fn should_retrieve_vault_registered_without_identifiers() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+
+    register_legacy_user(&mock_opaque_api, "username", "password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // A-ct
+
+    let vault = opaque_vault_manager.retrieve("username", "password");
+
+    // A-ssert
+
+    assert_eq!(vault.unwrap().wrapped_key, vec![7]);
+    assert!(opaque_vault_manager.needs_reregistration());
+    assert!(!opaque_vault_manager.identifiers_bound());
+}
+
+#[test]
+fn should_not_retrieve_vault_without_identifiers_once_bound() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+
+    register_legacy_user(&mock_opaque_api, "username", "password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+    opaque_vault_manager.require_bound_identifiers(true);
+
+    // A-ct
+
+    let result = opaque_vault_manager.retrieve("username", "password");
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::InvalidCredentials) => {}
+        _ => panic!("Test result should be: 'InvalidCredentials' error."),
+    }
+}
+
+#[test]
+fn should_bind_identifiers_by_registering_legacy_account_again() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(true);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+
+    register_legacy_user(&mock_opaque_api, "username", "password");
+    log_in_as(&server_state, "username");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    opaque_vault_manager.retrieve("username", "password").unwrap();
+
+    // A-ct
+
+    let result = opaque_vault_manager.change_password("username", "password", "password", &|export_key| {
+        Ok(export_key.to_vec())
+    });
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(!opaque_vault_manager.needs_reregistration());
+    assert!(!server_state.lock().unwrap().legacy_users.contains("username"));
+
+    opaque_vault_manager.retrieve("username", "password").unwrap();
+
+    assert!(!opaque_vault_manager.needs_reregistration());
+    assert!(opaque_vault_manager.identifiers_bound());
+}
+
+#[cfg(feature = "p256")]
+#[test]
+fn should_register_and_log_in_with_p256() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+
+    let mut registering_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+    let created_vault = registering_vault_manager.create("username", "password").unwrap();
+
+    let mock_opaque_api = MockOpaqueClient {
+        server_state: Arc::clone(&server_state),
+        is_logged_in: false,
+    };
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // A-ct
+
+    let vault = opaque_vault_manager.retrieve("username", "password").unwrap();
+
+    // A-ssert
+
+    // SEC1 compressed point of the server and SHA-256 sized export key
+    assert_eq!(vault.server_key.len(), 33);
+    assert_eq!(vault.server_key, server_state.lock().unwrap().server_key());
+    assert_eq!(vault.encryption_key.as_bytes().len(), 32);
+    assert_eq!(vault.encryption_key.as_bytes(), created_vault.encryption_key.as_bytes());
+}
+
+#[test]
+fn should_record_key_stretching_on_registration() {
+    // A-rrange
+
+    let key_stretching = KeyStretching::new(8192, 1, 1);
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+
+    let mut opaque_vault_manager = OpaqueVaultManager::with_key_stretching(mock_opaque_api, key_stretching).unwrap();
+
+    // A-ct
+
+    let result = opaque_vault_manager.create("username", "password");
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(server_state.lock().unwrap().key_stretchings["username"], key_stretching);
+    assert!(!opaque_vault_manager.needs_reregistration());
+}
+
+#[test]
+fn should_retrieve_vault_with_recorded_key_stretching() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+
+    let mut registering_vault_manager =
+        OpaqueVaultManager::with_key_stretching(mock_opaque_api, KeyStretching::new(8192, 1, 1)).unwrap();
+    registering_vault_manager.create("username", "password").unwrap();
+
+    let mock_opaque_api = MockOpaqueClient {
+        server_state,
+        is_logged_in: false,
+    };
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // A-ct
+
+    let result = opaque_vault_manager.retrieve("username", "password");
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    // Weaker than the default parameters of this client
+    assert!(opaque_vault_manager.needs_reregistration());
+}
+
+#[test]
+fn should_upgrade_key_stretching_by_registering_again() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(true);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+
+    register_user(&mock_opaque_api, "username", "password");
+    server_state.lock().unwrap().key_stretchings.remove("username");
+
+    let key_stretching = KeyStretching::new(32768, 2, 1);
+    let mut opaque_vault_manager = OpaqueVaultManager::with_key_stretching(mock_opaque_api, key_stretching).unwrap();
+
+    opaque_vault_manager.retrieve("username", "password").unwrap();
+    assert!(opaque_vault_manager.needs_reregistration());
+
+    // A-ct
+
+    let result = opaque_vault_manager.change_password("username", "password", "password", &|export_key| {
+        Ok(export_key.to_vec())
+    });
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert!(!opaque_vault_manager.needs_reregistration());
+    assert_eq!(server_state.lock().unwrap().key_stretchings["username"], key_stretching);
+
+    let vault = opaque_vault_manager.retrieve("username", "password").unwrap();
+
+    assert_eq!(vault.wrapped_key, vault.encryption_key.as_bytes());
+}
+
+#[test]
+fn should_not_retrieve_vault_with_exhausting_key_stretching() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    mock_opaque_api
+        .server_state
+        .lock().unwrap()
+        .key_stretchings
+        .insert("username".to_string(), KeyStretching::new(u32::MAX, 1, 1));
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // A-ct
+
+    let result = opaque_vault_manager.retrieve("username", "password");
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ExchangeFailed(error)) => assert_eq!(error, "Invalid key stretching parameters."),
+        _ => panic!("Test result should be: 'ExchangeFailed' error."),
+    }
+}
+
+#[test]
+fn should_reauthenticate() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(true);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+    let server_key = server_state.lock().unwrap().server_key();
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+    opaque_vault_manager.pin_server_key(Some(server_key));
+
+    // A-ct
+
+    let result = opaque_vault_manager.reauthenticate("username", "password");
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(server_state.lock().unwrap().logged_in_username.as_deref(), Some("username"));
+}
+
+#[test]
+fn should_not_reauthenticate_with_wrong_password() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(true);
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // A-ct
+
+    let result = opaque_vault_manager.reauthenticate("username", "wrong password");
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::InvalidCredentials) => {}
+        _ => panic!("Test result should be: 'InvalidCredentials' error."),
+    }
+}
+
+#[test]
+fn should_reauthenticate_if_never_logged_in() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // A-ct
+
+    let result = opaque_vault_manager.reauthenticate("username", "password");
+
+    // A-ssert
+
+    assert!(result.is_ok());
+    assert_eq!(server_state.lock().unwrap().logged_in_username.as_deref(), Some("username"));
+}
+
+#[test]
+fn should_not_save_vault_after_logout() {
+    // A-rrange
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(true));
+
+    // A-ct
+
+    let logout_result = opaque_vault_manager.logout();
+    let result = opaque_vault_manager.save(vec![], None);
+
+    // A-ssert
+
+    assert!(logout_result.is_ok());
+
+    match result {
+        Err(VaultError::NotLoggedIn(_)) => {}
+        _ => panic!("Test result should be: 'NotLoggedIn' error."),
+    }
+}
+
+#[test]
+fn should_save_vault() {
+    // A-rrange
+
+    let opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(true));
+
+    let vault_bytes = vec![];
+
+    // A-ct
+
+    let result = opaque_vault_manager.save(vault_bytes, None);
+
+    // A-ssert
+    assert!(result.is_ok());
+}
+
+#[test]
+fn should_not_save_vault_if_not_logged_in() {
+    // A-rrange
+
+    let opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(false));
+
+    let vault_bytes = vec![];
+
+    // A-ct
+
+    let result = opaque_vault_manager.save(vault_bytes, None);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::NotLoggedIn(error)) => assert_eq!(
+            error,
+            "Cannot create a new vault if you are already logged in."
+        ),
+        _ => panic!("Test result should be: 'NotLoggedIn' error."),
+    }
+}
+
+#[test]
+fn should_save_records() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(true);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+    let opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    let records = vec![
+        CryptedRecord::new("manifest".to_string(), vec![4]),
+        CryptedRecord::new("entry/0".to_string(), vec![1]),
+    ];
+
+    // A-ct
+
+    let result = opaque_vault_manager.save_records(records.clone(), Some("1")).and_then(|revision| {
+        opaque_vault_manager.save_records(vec![CryptedRecord::deleted("entry/0".to_string())], revision.as_deref())
+    });
+
+    // A-ssert
+
+    assert_eq!(result.unwrap().as_deref(), Some("3"));
+    assert_eq!(server_state.lock().unwrap().records, vec![records[0].clone()]);
+}
+
+#[test]
+fn should_not_save_records_based_on_outdated_revision() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(true);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+    let opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    let records = vec![CryptedRecord::new("entry/0".to_string(), vec![1])];
+
+    opaque_vault_manager.save_records(records.clone(), Some("1")).unwrap();
+
+    // A-ct
+
+    let result = opaque_vault_manager.save_records(records, Some("1"));
+    let fetched = opaque_vault_manager.fetch_records().unwrap();
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::Conflict) => {}
+        _ => panic!("Test result should be: 'Conflict' error."),
+    }
+
+    assert_eq!(fetched, (server_state.lock().unwrap().records.clone(), Some("2".to_string())));
+}
+
+#[test]
+fn should_not_save_records_if_not_logged_in() {
+    // A-rrange
+
+    let opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(false));
+
+    // A-ct
+
+    let result = opaque_vault_manager.save_records(vec![], None);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::NotLoggedIn(_)) => {}
+        _ => panic!("Test result should be: 'NotLoggedIn' error."),
+    }
+}
+
+#[test]
+fn should_not_save_wrapped_key_if_not_logged_in() {
+    // A-rrange
+
+    let opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(false));
+
+    // A-ct
+
+    let result = opaque_vault_manager.save_wrapped_key(vec![7]);
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::NotLoggedIn(_)) => {}
+        _ => panic!("Test result should be: 'NotLoggedIn' error."),
+    }
+}
+
+#[test]
+fn should_change_password() {
+    // A-rrange
+
+    let username = "username";
+    let password = "password";
+    let new_password = "new password";
+
+    let mock_opaque_api = MockOpaqueClient::new(true);
+
+    register_user(&mock_opaque_api, username, password);
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // Wrapping with the identity, so the stored key is the new export key
+    let wrap_data_key = |export_key: &[u8]| Ok(export_key.to_vec());
+
+    // A-ct
+
+    let result = opaque_vault_manager.change_password(username, password, new_password, &wrap_data_key);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let vault = opaque_vault_manager.retrieve(username, new_password).unwrap();
+
+    assert_eq!(vault.wrapped_key, vault.encryption_key.as_bytes());
+}
+
+#[test]
+fn should_not_change_password_with_wrong_old_password() {
+    // A-rrange
+
+    let username = "username";
+
+    let mock_opaque_api = MockOpaqueClient::new(true);
+
+    register_user(&mock_opaque_api, username, "password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // A-ct
+
+    let result = opaque_vault_manager.change_password(username, "wrong password", "new password", &|_| {
+        panic!("The data key shouldn't be wrapped with a wrong old password.")
+    });
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::InvalidCredentials) => {}
+        _ => panic!("Test result should be: 'InvalidCredentials' error."),
+    }
+}
+
+#[test]
+fn should_not_change_password_if_not_logged_in() {
+    // A-rrange
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(false));
+
+    // A-ct
+
+    let result = opaque_vault_manager.change_password("username", "password", "new password", &|_| Ok(vec![]));
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::NotLoggedIn(error)) => {
+            assert_eq!(error, "Cannot change the password if you are not logged in.")
+        }
+        _ => panic!("Test result should be: 'NotLoggedIn' error."),
+    }
+}
+
+#[test]
+fn should_recover_vault() {
+    // A-rrange
+
+    let username = "username";
+    let new_password = "new password";
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+
+    register_user(&mock_opaque_api, username, "forgotten password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+
+    // Rewrapping with the identity, so the stored key is the new export key
+    let rewrap_data_key = |recovery_wrapped_key: &[u8], export_key: &[u8]| {
+        assert_eq!(recovery_wrapped_key, [5]);
+        Ok(export_key.to_vec())
+    };
+
+    // A-ct
+
+    let result = opaque_vault_manager.recover(username, RECOVERY_TOKEN, new_password, &rewrap_data_key);
+
+    // A-ssert
+
+    assert!(result.is_ok());
+
+    let vault = opaque_vault_manager.retrieve(username, new_password).unwrap();
+
+    assert_eq!(vault.wrapped_key, vault.encryption_key.as_bytes());
+}
+
 #[test]
-fn should_create_vault() {
+fn should_not_recover_vault_with_wrong_recovery_token() {
     // A-rrange
 
-    let mut opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(false));
-    let username = "username";
-    let password = "password";
+    let mock_opaque_api = MockOpaqueClient::new(false);
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
 
     // A-ct
 
-    let vault = opaque_vault_manager.create(username, password).unwrap();
+    let result = opaque_vault_manager.recover("username", &[10; 32], "new password", &|_, _| {
+        panic!("The data key shouldn't be rewrapped with a wrong recovery token.")
+    });
 
     // A-ssert
 
-    assert_eq!(vault.content, vec![42]);
-    assert!(vault.encryption_key.as_bytes().len() != 0);
+    match result {
+        Err(VaultError::InvalidCredentials) => {}
+        _ => panic!("Test result should be: 'InvalidCredentials' error."),
+    }
 }
 
 #[test]
-fn should_not_create_vault_if_logged_in() {
+fn should_not_recover_vault_if_server_key_changed() {
+    // A-rrange
+
+    let mock_opaque_api = MockOpaqueClient::new(false);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+
+    register_user(&mock_opaque_api, "username", "password");
+
+    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+    opaque_vault_manager.pin_server_key(Some(vec![1; 32]));
+
+    // A-ct
+
+    let result = opaque_vault_manager.recover("username", RECOVERY_TOKEN, "new password", &|_, _| {
+        panic!("The data key shouldn't be rewrapped for another server.")
+    });
+
+    // A-ssert
+
+    match result {
+        Err(VaultError::ServerKeyChanged) => {}
+        _ => panic!("Test result should be: 'ServerKeyChanged' error."),
+    }
+
+    assert_eq!(server_state.lock().unwrap().wrapped_key, vec![7]);
+}
+
+#[test]
+fn should_not_recover_vault_if_logged_in() {
     // A-rrange
 
     let mut opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(true));
-    let username = "username";
-    let password = "password";
 
     // A-ct
 
-    let vault = opaque_vault_manager.create(username, password);
+    let result = opaque_vault_manager.recover("username", RECOVERY_TOKEN, "new password", &|_, _| Ok(vec![]));
 
     // A-ssert
 
-    match vault {
+    match result {
         Err(VaultError::AlreadyLoggedIn(error)) => {
-            assert_eq!(error, "Cannot save a vault if you are not logged in.")
+            assert_eq!(error, "Cannot recover a vault if you are already logged in.")
         }
         _ => panic!("Test result should be: 'AlreadyLoggedIn' error."),
     }
 }
 
 #[test]
-fn should_retrieve_vault() {
+fn should_not_save_recovery_key_if_not_logged_in() {
     // A-rrange
 
-    let username = "username";
-    let password = "password";
+    let opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(false));
 
-    let mock_opaque_api = MockOpaqueClient::new(false);
+    // A-ct
 
-    let mut client_rng = OsRng;
+    let result = opaque_vault_manager.save_recovery_key(RECOVERY_TOKEN.to_vec(), vec![5]);
 
-    let client_registration_start_result =
-        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
-            .unwrap();
+    // A-ssert
 
-    let server_registration_response = mock_opaque_api
-        .start_server_registration(username, &client_registration_start_result)
-        .unwrap();
+    match result {
+        Err(VaultError::NotLoggedIn(_)) => {}
+        _ => panic!("Test result should be: 'NotLoggedIn' error."),
+    }
+}
 
-    let client_registration_finish_result = client_registration_start_result
-        .state
-        .finish(
-            &mut client_rng,
-            password.as_bytes(),
-            server_registration_response,
-            ClientRegistrationFinishParameters::default(),
-        )
-        .unwrap();
+#[test]
+fn should_deliver_share_to_recipient() {
+    // A-rrange
 
-    mock_opaque_api
-        .finish_server_registration(username, &client_registration_finish_result)
-        .unwrap();
+    let mock_opaque_api = MockOpaqueClient::new(true);
+    let server_state = Arc::clone(&mock_opaque_api.server_state);
+    let opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
 
-    let mut opaque_vault_manager = create_opaque_vault_manager(mock_opaque_api);
+    log_in_as(&server_state, "bob");
+    opaque_vault_manager.save_public_key(vec![1; 32]).unwrap();
+
+    log_in_as(&server_state, "alice");
 
     // A-ct
 
-    let vault = opaque_vault_manager.retrieve(username, password).unwrap();
+    let public_key = opaque_vault_manager.get_public_key("bob").unwrap();
+    opaque_vault_manager.send_share("bob", vec![3]).unwrap();
+
+    log_in_as(&server_state, "bob");
+
+    let shares = opaque_vault_manager.get_shares().unwrap();
 
     // A-ssert
 
-    assert_eq!(vault.content, vec![42]);
-    assert!(vault.encryption_key.as_bytes().len() != 0);
+    assert_eq!(public_key, vec![1; 32]);
+    assert_eq!(shares.len(), 1);
+    assert_eq!(shares[0].sender, "alice");
+    assert_eq!(shares[0].sealed_entry, vec![3]);
+
+    opaque_vault_manager.delete_share(shares[0].id).unwrap();
+
+    assert!(opaque_vault_manager.get_shares().unwrap().is_empty());
 }
 
 #[test]
-fn should_save_vault() {
+fn should_not_get_public_key_if_not_published() {
     // A-rrange
 
     let opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(true));
 
-    let vault_bytes = vec![];
-
     // A-ct
 
-    let result = opaque_vault_manager.save(vault_bytes);
+    let result = opaque_vault_manager.get_public_key("bob");
 
     // A-ssert
-    assert!(result.is_ok());
+
+    match result {
+        Err(VaultError::NotFound) => {}
+        _ => panic!("Test result should be: 'NotFound' error."),
+    }
 }
 
 #[test]
-fn should_not_save_vault_if_not_logged_in() {
+fn should_not_send_share_if_not_logged_in() {
     // A-rrange
 
     let opaque_vault_manager = create_opaque_vault_manager(MockOpaqueClient::new(false));
 
-    let vault_bytes = vec![];
-
     // A-ct
 
-    let result = opaque_vault_manager.save(vault_bytes);
+    let result = opaque_vault_manager.send_share("bob", vec![3]);
 
     // A-ssert
 
     match result {
-        Err(VaultError::NotLoggedIn(error)) => assert_eq!(
-            error,
-            "Cannot create a new vault if you are already logged in."
-        ),
+        Err(VaultError::NotLoggedIn(error)) => {
+            assert_eq!(error, "Cannot use the share inbox if you are not logged in.")
+        }
         _ => panic!("Test result should be: 'NotLoggedIn' error."),
     }
 }
@@ -140,7 +905,20 @@ fn should_not_save_vault_if_not_logged_in() {
 struct ServerState {
     server_setup: ServerSetup<StandardCipherSuite>,
     users: HashMap<String, GenericArray<u8, ServerRegistrationLen<StandardCipherSuite>>>,
+    // Missing for the users registered before the parameters were recorded
+    key_stretchings: HashMap<String, KeyStretching>,
     server_login_start_result: Option<ServerLoginStartResult<StandardCipherSuite>>,
+    logged_in_username: Option<String>,
+    // Registered before the identifiers were bound, the server logs them in with the default ones
+    legacy_users: HashSet<String>,
+    records: Vec<CryptedRecord>,
+    // Incremented by every save of the records
+    revision: u64,
+    wrapped_key: Vec<u8>,
+    recovery_token: Vec<u8>,
+    recovery_wrapped_key: Vec<u8>,
+    public_keys: HashMap<String, Vec<u8>>,
+    inbox: Vec<(String, SealedShare)>,
 }
 
 impl ServerState {
@@ -152,6 +930,16 @@ impl ServerState {
             server_login_start_result: None,
             server_setup,
             users: HashMap::new(),
+            key_stretchings: HashMap::new(),
+            logged_in_username: None,
+            legacy_users: HashSet::new(),
+            records: vec![CryptedRecord::new("manifest".to_string(), vec![3])],
+            revision: 1,
+            wrapped_key: vec![7],
+            recovery_token: RECOVERY_TOKEN.to_vec(),
+            recovery_wrapped_key: vec![5],
+            public_keys: HashMap::new(),
+            inbox: vec![],
         }
     }
 
@@ -162,24 +950,38 @@ impl ServerState {
     ) {
         self.users.insert(username, password_file);
     }
+
+    fn login_parameters<'a>(&self, username: &'a str) -> ServerLoginParameters<'static, 'a> {
+        match self.legacy_users.contains(username) {
+            true => ServerLoginParameters::default(),
+            false => ServerLoginParameters {
+                context: None,
+                identifiers: identifiers(username, SERVER_URL),
+            },
+        }
+    }
+
+    fn server_key(&self) -> Vec<u8> {
+        self.server_setup.keypair().public().serialize().to_vec()
+    }
 }
 
-struct MockOpaqueClient {
-    server_state: RefCell<ServerState>,
+pub(super) struct MockOpaqueClient {
+    server_state: Arc<Mutex<ServerState>>,
     is_logged_in: bool,
 }
 
 impl MockOpaqueClient {
-    fn new(is_logged_in: bool) -> Self {
+    pub(super) fn new(is_logged_in: bool) -> Self {
         Self {
-            server_state: RefCell::new(ServerState::new()),
+            server_state: Arc::new(Mutex::new(ServerState::new())),
             is_logged_in,
         }
     }
 }
 
-impl Api for MockOpaqueClient {
-    fn start_server_registration(
+impl AsyncApi for MockOpaqueClient {
+    async fn start_server_registration(
         &self,
         username: &str,
         client_registration_start_result: &opaque_ke::ClientRegistrationStartResult<
@@ -187,7 +989,7 @@ impl Api for MockOpaqueClient {
         >,
     ) -> crate::Result<opaque_ke::RegistrationResponse<StandardCipherSuite>> {
         let server_registration_start_result = ServerRegistration::<StandardCipherSuite>::start(
-            &self.server_state.borrow().server_setup,
+            &self.server_state.lock().unwrap().server_setup,
             client_registration_start_result.message.clone(),
             username.as_bytes(),
         )
@@ -196,30 +998,32 @@ impl Api for MockOpaqueClient {
         Ok(server_registration_start_result.message)
     }
 
-    fn finish_server_registration(
+    async fn finish_server_registration(
         &self,
         username: &str,
         client_registration_finish_result: &opaque_ke::ClientRegistrationFinishResult<
             StandardCipherSuite,
         >,
+        key_stretching: &KeyStretching,
     ) -> crate::Result<()> {
         let password_file = ServerRegistration::<StandardCipherSuite>::finish(
             client_registration_finish_result.message.clone(),
         );
 
-        self.server_state
-            .borrow_mut()
-            .add_user(username.to_string(), password_file.serialize());
+        let mut server_state = self.server_state.lock().unwrap();
+
+        server_state.add_user(username.to_string(), password_file.serialize());
+        server_state.key_stretchings.insert(username.to_string(), *key_stretching);
 
         Ok(())
     }
 
-    fn start_server_login(
+    async fn start_server_login(
         &self,
         username: &str,
         client_login_start_result: &opaque_ke::ClientLoginStartResult<StandardCipherSuite>,
-    ) -> crate::Result<opaque_ke::CredentialResponse<StandardCipherSuite>> {
-        let mut server_state = self.server_state.borrow_mut();
+    ) -> crate::Result<(opaque_ke::CredentialResponse<StandardCipherSuite>, KeyStretching)> {
+        let mut server_state = self.server_state.lock().unwrap();
 
         let password_file_bytes = server_state.users.get(username).unwrap();
 
@@ -234,51 +1038,315 @@ impl Api for MockOpaqueClient {
             Some(password_file),
             client_login_start_result.message.clone(),
             username.as_bytes(),
-            ServerLoginParameters::default(),
+            server_state.login_parameters(username),
         )
         .unwrap();
 
         server_state.server_login_start_result = Some(server_login_start_result.clone());
+        server_state.logged_in_username = Some(username.to_string());
 
-        Ok(server_login_start_result.message)
+        let key_stretching = server_state.key_stretchings.get(username).copied().unwrap_or_default();
+
+        Ok((server_login_start_result.message, key_stretching))
     }
 
-    fn finish_server_login(
+    async fn finish_server_login(
         &mut self,
-        _: &str,
+        username: &str,
         client_login_finish_result: &opaque_ke::ClientLoginFinishResult<StandardCipherSuite>,
     ) -> crate::Result<()> {
-        let _ = self
-            .server_state
-            .borrow_mut()
+        let mut server_state = self.server_state.lock().unwrap();
+
+        let login_parameters = server_state.login_parameters(username);
+
+        server_state
             .server_login_start_result
             .take()
             .unwrap()
             .state
-            .finish(
-                client_login_finish_result.message.clone(),
-                ServerLoginParameters::default(),
-            )
-            .unwrap();
+            .finish(client_login_finish_result.message.clone(), login_parameters)
+            .map_err(|_| VaultError::InvalidCredentials)?;
+
+        Ok(())
+    }
+
+    async fn logout(&mut self) -> crate::Result<()> {
+        self.is_logged_in = false;
+
+        Ok(())
+    }
+
+    async fn get_vault(&self) -> crate::Result<(Vec<u8>, Option<String>)> {
+        Ok((vec![42], None))
+    }
+
+    async fn save_vault(&self, _: Vec<u8>, revision: Option<&str>) -> crate::Result<Option<String>> {
+        Ok(revision.map(str::to_string))
+    }
+
+    async fn get_records(&self) -> crate::Result<(Vec<CryptedRecord>, Option<String>)> {
+        let server_state = self.server_state.lock().unwrap();
+
+        Ok((server_state.records.clone(), Some(server_state.revision.to_string())))
+    }
+
+    async fn save_records(&self, records: Vec<CryptedRecord>, revision: Option<&str>) -> crate::Result<Option<String>> {
+        let mut server_state = self.server_state.lock().unwrap();
+
+        if revision.is_some_and(|revision| revision != server_state.revision.to_string()) {
+            return Err(VaultError::Conflict);
+        }
+
+        server_state.revision += 1;
+
+        let stored_records = &mut server_state.records;
+
+        for record in records {
+            stored_records.retain(|stored_record| stored_record.id != record.id);
+
+            if !record.is_deleted() {
+                stored_records.push(record);
+            }
+        }
+
+        Ok(Some(server_state.revision.to_string()))
+    }
+
+    async fn start_password_change(
+        &self,
+        client_registration_start_result: &opaque_ke::ClientRegistrationStartResult<
+            StandardCipherSuite,
+        >,
+    ) -> crate::Result<opaque_ke::RegistrationResponse<StandardCipherSuite>> {
+        let server_state = self.server_state.lock().unwrap();
+
+        let username = server_state.logged_in_username.as_ref().unwrap();
+
+        let server_registration_start_result = ServerRegistration::<StandardCipherSuite>::start(
+            &server_state.server_setup,
+            client_registration_start_result.message.clone(),
+            username.as_bytes(),
+        )
+        .unwrap();
+
+        Ok(server_registration_start_result.message)
+    }
+
+    async fn finish_password_change(
+        &self,
+        client_registration_finish_result: &opaque_ke::ClientRegistrationFinishResult<
+            StandardCipherSuite,
+        >,
+        wrapped_key: Vec<u8>,
+        key_stretching: &KeyStretching,
+    ) -> crate::Result<()> {
+        let password_file = ServerRegistration::<StandardCipherSuite>::finish(
+            client_registration_finish_result.message.clone(),
+        );
+
+        let mut server_state = self.server_state.lock().unwrap();
+
+        let username = server_state.logged_in_username.clone().unwrap();
+
+        // The new registration binds the identifiers
+        server_state.legacy_users.remove(&username);
+        server_state.add_user(username.clone(), password_file.serialize());
+        server_state.key_stretchings.insert(username, *key_stretching);
+        server_state.wrapped_key = wrapped_key;
+
+        Ok(())
+    }
+
+    async fn get_wrapped_key(&self) -> crate::Result<Vec<u8>> {
+        Ok(self.server_state.lock().unwrap().wrapped_key.clone())
+    }
+
+    async fn save_wrapped_key(&self, _: Vec<u8>) -> crate::Result<()> {
+        Ok(())
+    }
+
+    async fn save_recovery_key(&self, recovery_token: Vec<u8>, wrapped_key: Vec<u8>) -> crate::Result<()> {
+        let mut server_state = self.server_state.lock().unwrap();
+
+        server_state.recovery_token = recovery_token;
+        server_state.recovery_wrapped_key = wrapped_key;
+
+        Ok(())
+    }
+
+    async fn start_recovery(
+        &self,
+        username: &str,
+        recovery_token: &[u8],
+        client_registration_start_result: &opaque_ke::ClientRegistrationStartResult<
+            StandardCipherSuite,
+        >,
+    ) -> crate::Result<(opaque_ke::RegistrationResponse<StandardCipherSuite>, Vec<u8>)> {
+        let server_state = self.server_state.lock().unwrap();
+
+        if server_state.recovery_token != recovery_token {
+            return Err(VaultError::InvalidCredentials);
+        }
+
+        let server_registration_start_result = ServerRegistration::<StandardCipherSuite>::start(
+            &server_state.server_setup,
+            client_registration_start_result.message.clone(),
+            username.as_bytes(),
+        )
+        .unwrap();
+
+        Ok((
+            server_registration_start_result.message,
+            server_state.recovery_wrapped_key.clone(),
+        ))
+    }
+
+    async fn finish_recovery(
+        &self,
+        username: &str,
+        recovery_token: &[u8],
+        client_registration_finish_result: &opaque_ke::ClientRegistrationFinishResult<
+            StandardCipherSuite,
+        >,
+        wrapped_key: Vec<u8>,
+        key_stretching: &KeyStretching,
+    ) -> crate::Result<()> {
+        let mut server_state = self.server_state.lock().unwrap();
+
+        if server_state.recovery_token != recovery_token {
+            return Err(VaultError::InvalidCredentials);
+        }
+
+        let password_file = ServerRegistration::<StandardCipherSuite>::finish(
+            client_registration_finish_result.message.clone(),
+        );
+
+        server_state.add_user(username.to_string(), password_file.serialize());
+        server_state.key_stretchings.insert(username.to_string(), *key_stretching);
+        server_state.wrapped_key = wrapped_key;
+
+        Ok(())
+    }
+
+    async fn save_public_key(&self, public_key: Vec<u8>) -> crate::Result<()> {
+        let mut server_state = self.server_state.lock().unwrap();
+
+        let username = server_state.logged_in_username.clone().unwrap();
+
+        server_state.public_keys.insert(username, public_key);
+
+        Ok(())
+    }
+
+    async fn get_public_key(&self, username: &str) -> crate::Result<Vec<u8>> {
+        self.server_state
+            .lock().unwrap()
+            .public_keys
+            .get(username)
+            .cloned()
+            .ok_or(VaultError::NotFound)
+    }
+
+    async fn send_share(&self, recipient: &str, sealed_entry: Vec<u8>) -> crate::Result<()> {
+        let mut server_state = self.server_state.lock().unwrap();
+
+        let share = SealedShare {
+            id: server_state.inbox.len() as u64,
+            sender: server_state.logged_in_username.clone().unwrap(),
+            sealed_entry,
+        };
+
+        server_state.inbox.push((recipient.to_string(), share));
 
         Ok(())
     }
 
-    fn get_vault(&self) -> crate::Result<Vec<u8>> {
-        Ok(vec![42])
+    async fn get_shares(&self) -> crate::Result<Vec<SealedShare>> {
+        let server_state = self.server_state.lock().unwrap();
+
+        let username = server_state.logged_in_username.as_ref().unwrap();
+
+        Ok(server_state
+            .inbox
+            .iter()
+            .filter(|(recipient, _)| recipient == username)
+            .map(|(_, share)| share.clone())
+            .collect())
     }
 
-    fn save_vault(&self, _: Vec<u8>) -> crate::Result<()> {
+    async fn delete_share(&self, id: u64) -> crate::Result<()> {
+        self.server_state
+            .lock().unwrap()
+            .inbox
+            .retain(|(_, share)| share.id != id);
+
         Ok(())
     }
 
     fn is_logged_in(&self) -> bool {
         self.is_logged_in
     }
+
+    fn get_server_identifier(&self) -> &str {
+        SERVER_URL
+    }
 }
 
 fn create_opaque_vault_manager(
     mock_opaque_client: MockOpaqueClient,
 ) -> OpaqueVaultManager<MockOpaqueClient> {
-    OpaqueVaultManager::new(mock_opaque_client)
+    OpaqueVaultManager::new(mock_opaque_client).unwrap()
+}
+
+pub(super) fn register_user(mock_opaque_api: &MockOpaqueClient, username: &str, password: &str) {
+    register_user_for(mock_opaque_api, username, password, identifiers(username, SERVER_URL));
+}
+
+/// Registration made before the identifiers were bound
+fn register_legacy_user(mock_opaque_api: &MockOpaqueClient, username: &str, password: &str) {
+    register_user_for(mock_opaque_api, username, password, Identifiers::default());
+
+    let mut server_state = mock_opaque_api.server_state.lock().unwrap();
+
+    server_state.legacy_users.insert(username.to_string());
+    server_state.key_stretchings.remove(username);
+}
+
+fn register_user_for(mock_opaque_api: &MockOpaqueClient, username: &str, password: &str, identifiers: Identifiers<'_>) {
+    let mut client_rng = OsRng;
+
+    let client_registration_start_result =
+        ClientRegistration::<StandardCipherSuite>::start(&mut client_rng, password.as_bytes())
+            .unwrap();
+
+    let server_registration_response =
+        block_on(mock_opaque_api.start_server_registration(username, &client_registration_start_result)).unwrap();
+
+    let client_registration_finish_result = client_registration_start_result
+        .state
+        .finish(
+            &mut client_rng,
+            password.as_bytes(),
+            server_registration_response,
+            ClientRegistrationFinishParameters::new(identifiers, None),
+        )
+        .unwrap();
+
+    block_on(mock_opaque_api.finish_server_registration(
+        username,
+        &client_registration_finish_result,
+        &KeyStretching::default(),
+    ))
+    .unwrap();
+}
+
+fn identifiers<'a>(username: &'a str, server: &'a str) -> Identifiers<'a> {
+    Identifiers {
+        client: Some(username.as_bytes()),
+        server: Some(server.as_bytes()),
+    }
+}
+
+fn log_in_as(server_state: &Mutex<ServerState>, username: &str) {
+    server_state.lock().unwrap().logged_in_username = Some(username.to_string());
 }
@@ -0,0 +1,95 @@
+use std::time::Duration;
+
+use app_core::vault::vault_error::VaultError;
+
+use crate::request_policy::RequestPolicy;
+
+#[test]
+fn should_back_off_exponentially_with_jitter() {
+    // A-rrange
+
+    let request_policy = RequestPolicy::default();
+    let error = VaultError::ServerUnavailable("503 Service Unavailable".to_string());
+
+    // A-ct
+
+    let delays: Vec<_> = (0..3)
+        .map(|attempt| request_policy.retry_delay(&error, attempt).unwrap())
+        .collect();
+
+    // A-ssert
+
+    assert!(delays[0] >= Duration::from_millis(125) && delays[0] <= Duration::from_millis(250));
+    assert!(delays[1] >= Duration::from_millis(250) && delays[1] <= Duration::from_millis(500));
+    assert!(delays[2] >= Duration::from_millis(500) && delays[2] <= Duration::from_millis(1000));
+}
+
+#[test]
+fn should_not_back_off_longer_than_max_delay() {
+    // A-rrange
+
+    let request_policy = RequestPolicy {
+        max_retries: 20,
+        ..RequestPolicy::default()
+    };
+
+    // A-ct
+
+    let delay = request_policy.retry_delay(&VaultError::RateLimited(None), 19);
+
+    // A-ssert
+
+    assert!(delay.unwrap() <= request_policy.max_delay);
+}
+
+#[test]
+fn should_wait_retry_after_of_the_server() {
+    // A-rrange
+
+    let request_policy = RequestPolicy::default();
+
+    // A-ct
+
+    let delay = request_policy.retry_delay(&VaultError::RateLimited(Some(2)), 0);
+    let too_long_delay = request_policy.retry_delay(&VaultError::RateLimited(Some(60)), 0);
+
+    // A-ssert
+
+    assert_eq!(delay, Some(Duration::from_secs(2)));
+    assert_eq!(too_long_delay, None);
+}
+
+#[test]
+fn should_not_retry_once_retries_exhausted() {
+    // A-rrange
+
+    let request_policy = RequestPolicy::default();
+    let error = VaultError::ServerUnavailable("503 Service Unavailable".to_string());
+
+    // A-ct
+
+    let delay = request_policy.retry_delay(&error, request_policy.max_retries);
+
+    // A-ssert
+
+    assert_eq!(delay, None);
+}
+
+#[test]
+fn should_not_retry_errors_that_are_not_transient() {
+    // A-rrange
+
+    let request_policy = RequestPolicy::default();
+
+    // A-ct
+
+    let delays = [
+        request_policy.retry_delay(&VaultError::InvalidCredentials, 0),
+        request_policy.retry_delay(&VaultError::SessionExpired, 0),
+        request_policy.retry_delay(&VaultError::ExchangeFailed("400 Bad Request".to_string()), 0),
+    ];
+
+    // A-ssert
+
+    assert!(delays.iter().all(Option::is_none));
+}